@@ -0,0 +1,25 @@
+//! Decides whether variables are read through `proc_macro::tracked_env`: always with the
+//! `tracked` feature, and with `tracked-env` only when the compiler is a nightly one.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(const_env_tracked)");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+    let tracked = std::env::var_os("CARGO_FEATURE_TRACKED").is_some();
+    let tracked_env = std::env::var_os("CARGO_FEATURE_TRACKED_ENV").is_some();
+    if tracked || (tracked_env && is_nightly()) {
+        println!("cargo:rustc-cfg=const_env_tracked");
+    }
+}
+
+/// Whether the compiler accepts unstable features, judging by its version string.
+fn is_nightly() -> bool {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .is_some_and(|version| version.contains("nightly") || version.contains("-dev"))
+}
@@ -1,45 +1,138 @@
 #![cfg_attr(
-    feature = "tracked",
+    const_env_tracked,
     feature(proc_macro_tracked_env)
 )]
 
 extern crate proc_macro;
 
+use std::borrow::Cow;
+use std::ffi::OsStr;
+
+use const_env_impl::EnvError;
 use proc_macro::TokenStream;
 
 /// Configure a `const` or `static` item from an environment variable.
 #[proc_macro_attribute]
 pub fn env_item(attr: TokenStream, item: TokenStream) -> TokenStream {
-    #[cfg(not(feature = "tracked"))]
+    #[cfg(not(const_env_tracked))]
     let read_env = StableEnv {};
-    #[cfg(feature = "tracked")]
+    #[cfg(const_env_tracked)]
     let read_env = TrackedEnv {};
     const_env_impl::from_env(attr.into(), item.into(), read_env).into()
 }
 
 #[proc_macro]
 pub fn env_lit(tokens: TokenStream) -> TokenStream {
-    #[cfg(not(feature = "tracked"))]
+    #[cfg(not(const_env_tracked))]
     let read_env = StableEnv {};
-    #[cfg(feature = "tracked")]
+    #[cfg(const_env_tracked)]
     let read_env = TrackedEnv {};
     const_env_impl::env_lit(tokens.into(), read_env).into()
 }
 
-#[cfg(feature = "tracked")]
+/// Configure every `const` and `static` item of the block, as `env_item` does. An item's
+/// own `#[from_env(...)]` attribute gives its arguments.
+#[proc_macro]
+pub fn from_env(tokens: TokenStream) -> TokenStream {
+    #[cfg(not(const_env_tracked))]
+    let read_env = StableEnv {};
+    #[cfg(const_env_tracked)]
+    let read_env = TrackedEnv {};
+    const_env_impl::from_env_items(tokens.into(), read_env).into()
+}
+
+/// Generate a `const` of the given type for each environment variable starting with a
+/// prefix, named after the rest of the variable's name.
+#[proc_macro]
+pub fn consts_from_prefix(tokens: TokenStream) -> TokenStream {
+    #[cfg(not(const_env_tracked))]
+    let read_env = StableEnv {};
+    #[cfg(const_env_tracked)]
+    let read_env = TrackedEnv {};
+    const_env_impl::consts_from_prefix(tokens.into(), read_env).into()
+}
+
+/// An environment variable's value as a literal of the same kind as the default, or the
+/// default if the variable is unset.
+#[proc_macro]
+pub fn env_or(tokens: TokenStream) -> TokenStream {
+    #[cfg(not(const_env_tracked))]
+    let read_env = StableEnv {};
+    #[cfg(const_env_tracked)]
+    let read_env = TrackedEnv {};
+    const_env_impl::env_or(tokens.into(), read_env).into()
+}
+
+/// Derive `Default` for a struct, with each `#[from_env(default = ...)]` field configured
+/// from an environment variable as `env_item` configures an item.
+#[proc_macro_derive(FromEnvDefaults, attributes(from_env))]
+pub fn from_env_defaults(input: TokenStream) -> TokenStream {
+    #[cfg(not(const_env_tracked))]
+    let read_env = StableEnv {};
+    #[cfg(const_env_tracked)]
+    let read_env = TrackedEnv {};
+    const_env_impl::from_env_defaults(input.into(), read_env).into()
+}
+
+/// Derive an associated `pub const` for each field of a struct, named after the field
+/// and configured from an environment variable as `env_item` configures an item.
+#[proc_macro_derive(ConstEnv, attributes(from_env))]
+pub fn const_env(input: TokenStream) -> TokenStream {
+    #[cfg(not(const_env_tracked))]
+    let read_env = StableEnv {};
+    #[cfg(const_env_tracked)]
+    let read_env = TrackedEnv {};
+    const_env_impl::const_env_derive(input.into(), read_env).into()
+}
+
+/// List the items marked `register` as a `&'static [EnvConstInfo]`.
+#[proc_macro]
+pub fn registry(tokens: TokenStream) -> TokenStream {
+    const_env_impl::registry(tokens.into(), StableEnv {}).into()
+}
+
+/// Hash the values of the configured items of the crate as a `u64` literal.
+#[proc_macro]
+pub fn fingerprint(tokens: TokenStream) -> TokenStream {
+    const_env_impl::fingerprint(tokens.into(), StableEnv {}).into()
+}
+
+#[cfg(const_env_tracked)]
 struct TrackedEnv;
 
-#[cfg(feature = "tracked")]
+#[cfg(const_env_tracked)]
 impl const_env_impl::ReadEnv for TrackedEnv {
-    fn read_env(&self, var_name: &String) -> Option<String> {
-        proc_macro::tracked_env::var(var_name).ok()
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        match proc_macro::tracked_env::var(var_name) {
+            Ok(value) => Ok(Some(Cow::Owned(value.into()))),
+            Err(std::env::VarError::NotUnicode(value)) => Ok(Some(Cow::Owned(value))),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+        }
+    }
+
+    // The expansion is kept identical to the stable one, whose `option_env!` references
+    // are redundant but harmless here.
+    fn needs_rebuild_tracking(&self) -> bool {
+        true
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        std::env::vars_os().filter_map(|(name, _)| name.into_string().ok()).collect()
     }
 }
 
 struct StableEnv;
 
 impl const_env_impl::ReadEnv for StableEnv {
-    fn read_env(&self, var_name: &String) -> Option<String> {
-        std::env::var(var_name).ok()
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(std::env::var_os(var_name).map(Cow::Owned))
     }
-}
\ No newline at end of file
+
+    fn needs_rebuild_tracking(&self) -> bool {
+        true
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        std::env::vars_os().filter_map(|(name, _)| name.into_string().ok()).collect()
+    }
+}
@@ -0,0 +1,9 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(assert = "ASSERT_BUF.is_power_of_two()")]
+//~^ ERROR `ASSERT_BUF.is_power_of_two()` does not hold for `ASSERT_BUF`; set `ASSERT_BUF` to a value for which it does
+const ASSERT_BUF: usize = 1024;
+
+fn main() {}
@@ -0,0 +1,17 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item]
+const INVALID_ARRAY: [u8; 2] = [1, 2];
+//~^ ERROR Failed to parse environment variable `INVALID_ARRAY` as an array
+
+#[env_item("INVALID_STR")]
+const NAME: &str = "name";
+//~^ ERROR Failed to parse environment variable `INVALID_STR` as a string
+
+#[env_item]
+const INVALID_PORT: u16 = 80;
+//~^ ERROR Failed to parse environment variable `INVALID_PORT` as an integer
+
+fn main() {}
@@ -0,0 +1,17 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(123)]
+//~^ ERROR The variable name must be a string literal
+const NUMBER_NAME: u32 = 0;
+
+#[env_item("A" "B")]
+//~^ ERROR Expected `,` between attribute arguments
+const TWO_NAMES: u32 = 0;
+
+#[env_item(= "X")]
+//~^ ERROR Expected an attribute argument
+const MISSING_KEY: u32 = 0;
+
+fn main() {}
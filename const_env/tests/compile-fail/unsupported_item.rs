@@ -0,0 +1,9 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item]
+fn not_a_const() {}
+//~^ ERROR `env_item` can only be applied to `const` and `static` items
+
+fn main() {}
@@ -0,0 +1,10 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(assert = "ASSERT_WORKERS > 0", assert = "ASSERT_WORKERS <= 64")]
+const ASSERT_WORKERS: u32 = 4;
+
+fn main() {
+    assert_eq!(16, ASSERT_WORKERS);
+}
@@ -0,0 +1,14 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item("CAPTURE_RUSTC_VERSION", capture = r"^rustc (\d+\.\d+)")]
+const CAPTURE_VERSION: &str = "0.0";
+
+#[env_item("CAPTURE_RUSTC_VERSION", capture = r"\d+\.(?P<minor>\d+)", capture_group = "minor")]
+const CAPTURE_MINOR: u32 = 0;
+
+fn main() {
+    assert_eq!("1.79", CAPTURE_VERSION);
+    assert_eq!(79, CAPTURE_MINOR);
+}
@@ -0,0 +1,12 @@
+extern crate const_env;
+
+mod limits {
+    const_env::consts_from_prefix!(prefix = "PREFIX_CONSTS_", ty = u32, vis = pub);
+}
+
+const_env::consts_from_prefix!(prefix = "PREFIX_CONSTS_UNSET_", ty = &str);
+
+fn main() {
+    assert_eq!(10, limits::UPLOADS);
+    assert_eq!(500, limits::USERS);
+}
@@ -0,0 +1,21 @@
+extern crate const_env;
+
+use const_env::ConstEnv;
+
+#[derive(ConstEnv)]
+#[from_env(prefix = "LIMITS_")]
+#[allow(dead_code)]
+struct Limits {
+    #[from_env(default = 4)]
+    max_conn: u32,
+    #[from_env("LIMITS_LABEL", default = "limits")]
+    name: &'static str,
+}
+
+static SLOTS: [u8; Limits::MAX_CONN as usize] = [0; Limits::MAX_CONN as usize];
+
+fn main() {
+    assert_eq!(16, Limits::MAX_CONN);
+    assert_eq!(16, SLOTS.len());
+    assert_eq!("limits", Limits::NAME);
+}
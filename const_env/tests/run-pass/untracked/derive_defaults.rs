@@ -0,0 +1,22 @@
+extern crate const_env;
+
+use const_env::FromEnvDefaults;
+
+#[derive(Debug, FromEnvDefaults)]
+struct Config {
+    #[from_env("DERIVE_PORT", default = 80)]
+    port: u16,
+    #[from_env(default = "localhost")]
+    derive_host: String,
+    #[from_env(default = &[1, 2])]
+    weights: &'static [u8],
+    verbose: bool,
+}
+
+fn main() {
+    let config = Config::default();
+    assert_eq!(8080, config.port);
+    assert_eq!("example.com", config.derive_host);
+    assert_eq!(&[1, 2], config.weights);
+    assert!(!config.verbose);
+}
@@ -0,0 +1,16 @@
+extern crate const_env;
+
+use std::time::Duration;
+
+use const_env::env_item;
+
+#[env_item]
+const DURATION_POLL: Duration = Duration::from_millis(500);
+
+#[env_item]
+static DURATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn main() {
+    assert_eq!(Duration::from_secs(90), DURATION_POLL);
+    assert_eq!(Duration::new(1, 500_000_000), DURATION_TIMEOUT);
+}
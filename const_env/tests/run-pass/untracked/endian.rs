@@ -0,0 +1,15 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(endian = "big")]
+const ENDIAN_MAGIC: [u8; 4] = [0; 4];
+
+#[env_item(endian = "little")]
+const ENDIAN_VERSION: [u8; 2] = *b"\0\0";
+
+fn main() {
+    assert_eq!([0xDE, 0xAD, 0xBE, 0xEF], ENDIAN_MAGIC);
+    assert_eq!(0xDEADBEEF, u32::from_be_bytes(ENDIAN_MAGIC));
+    assert_eq!(258, u16::from_le_bytes(ENDIAN_VERSION));
+}
@@ -0,0 +1,22 @@
+extern crate const_env;
+
+use const_env::env_or;
+
+const RETRIES: u32 = env_or!("ENV_OR_RETRIES", 3u32);
+
+fn classify(code: i16) -> &'static str {
+    match code {
+        env_or!("ENV_OR_CODE", 0i16) => "configured",
+        _ => "other",
+    }
+}
+
+fn main() {
+    assert_eq!(5, RETRIES);
+    let timeout = env_or!("ENV_OR_TIMEOUT", 30u64);
+    assert_eq!(30, timeout);
+    let greeting = env_or!("ENV_OR_GREETING", "hi");
+    assert_eq!("hello world", greeting);
+    assert_eq!("configured", classify(-7));
+    assert_eq!("other", classify(0));
+}
@@ -0,0 +1,25 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+mod config {
+    use const_env::env_item;
+
+    #[env_item("FINGERPRINT_PORT")]
+    pub const PORT: u16 = 80;
+}
+
+#[env_item]
+const FINGERPRINT_RETRIES: u32 = 3;
+
+// Invoked after the items, so that it covers them.
+const FINGERPRINT: u64 = const_env::fingerprint!();
+
+/// The fingerprint of a crate without configured items.
+const EMPTY: u64 = 0xe3b0c44298fc1c14;
+
+fn main() {
+    assert_eq!(8080, config::PORT);
+    assert_eq!(3, FINGERPRINT_RETRIES);
+    assert_ne!(EMPTY, FINGERPRINT);
+}
@@ -0,0 +1,18 @@
+extern crate const_env;
+
+use const_env::from_env;
+
+from_env! {
+    pub const BLOCK_A: u32 = 1;
+    static BLOCK_B: &'static str = "x";
+    #[from_env("BLOCK_CUSTOM")]
+    const BLOCK_C: bool = false;
+    const BLOCK_D: u8 = 4;
+}
+
+fn main() {
+    assert_eq!(2, BLOCK_A);
+    assert_eq!("y", BLOCK_B);
+    assert!(BLOCK_C);
+    assert_eq!(4, BLOCK_D);
+}
@@ -0,0 +1,14 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item("INCLUDE_PATH", include)]
+static INCLUDED_STR: &'static str = "";
+
+#[env_item("INCLUDE_PATH", include)]
+static INCLUDED_BYTES: &'static [u8] = b"";
+
+fn main() {
+    assert_eq!("included\n", INCLUDED_STR);
+    assert_eq!(b"included\n", INCLUDED_BYTES);
+}
@@ -0,0 +1,16 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+struct Tuning {
+    gain: u32,
+    taps: [i16; 3],
+}
+
+#[env_item("INCLUDE_EXPR_TUNING", include_expr)]
+const TUNING: Tuning = Tuning { gain: 1, taps: [0; 3] };
+
+fn main() {
+    assert_eq!(4, TUNING.gain);
+    assert_eq!([-1, 2, -1], TUNING.taps);
+}
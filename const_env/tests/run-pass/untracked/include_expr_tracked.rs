@@ -0,0 +1,19 @@
+extern crate const_env;
+
+use const_env::{env_item, FromEnvDefaults};
+
+#[derive(FromEnvDefaults)]
+struct Filter {
+    #[from_env("INCLUDE_EXPR_FIELD", include_expr, default = [0; 3])]
+    taps: [u8; 3],
+}
+
+fn main() {
+    // The item that has rustc track the file is emitted next to each expansion, which
+    // must compile in a function body and next to a derived impl alike.
+    #[env_item("INCLUDE_EXPR_LOCAL", include_expr)]
+    const TAPS: [u8; 3] = [0; 3];
+
+    assert_eq!([3, 5, 7], TAPS);
+    assert_eq!([3, 5, 7], Filter::default().taps);
+}
@@ -0,0 +1,18 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item("POINTER_CONFIG", json_pointer = "/server/port")]
+const POINTER_PORT: u16 = 8080;
+
+#[env_item("POINTER_CONFIG", json_pointer = "/hosts/1")]
+static POINTER_HOST: &str = "localhost";
+
+#[env_item("POINTER_CONFIG", json_pointer = "/server/tls")]
+const POINTER_TLS: bool = false;
+
+fn main() {
+    assert_eq!(9000, POINTER_PORT);
+    assert_eq!("b.example.com", POINTER_HOST);
+    assert!(POINTER_TLS);
+}
@@ -0,0 +1,32 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[derive(Debug, PartialEq)]
+pub struct Tls {
+    enabled: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NetCfg {
+    port: u16,
+    backlog: u32,
+    tls: Tls,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Limits {
+    rate: f64,
+    burst: u32,
+}
+
+#[env_item]
+const JSON_NET: NetCfg = NetCfg { port: 8080, backlog: 64, tls: Tls { enabled: false } };
+
+#[env_item(partial)]
+static JSON_LIMITS: Limits = Limits { rate: 1.5, burst: 4 };
+
+fn main() {
+    assert_eq!(NetCfg { port: 9000, backlog: 256, tls: Tls { enabled: true } }, JSON_NET);
+    assert_eq!(Limits { rate: 1.5, burst: 12 }, JSON_LIMITS);
+}
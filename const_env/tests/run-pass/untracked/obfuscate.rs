@@ -0,0 +1,16 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(obfuscate)]
+const OBFUSCATED_TOKEN: &str = "";
+
+#[env_item(obfuscate)]
+static OBFUSCATED_BYTES: &[u8] = b"";
+
+fn main() {
+    assert_eq!("s3cr3t-t0ken", obfuscated_token_deobfuscated());
+    assert_eq!(*b"s3cr3t-t0ken", obfuscated_bytes_deobfuscated());
+    assert_ne!(OBFUSCATED_TOKEN, OBFUSCATED_BYTES);
+    assert_eq!(12, OBFUSCATED_TOKEN_KEY.len());
+}
@@ -0,0 +1,27 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+mod hash {
+    pub fn fast_hash(bytes: &[u8]) -> u64 {
+        bytes.len() as u64
+    }
+
+    pub fn sum_hash(bytes: &[u8]) -> u64 {
+        bytes.iter().map(|byte| *byte as u64).sum()
+    }
+}
+
+const LOW: u32 = 1;
+const HIGH: u32 = 100;
+
+#[env_item("OPTIONS_HASH_IMPL", options(fast = hash::fast_hash, sum = hash::sum_hash))]
+static HASHER: fn(&[u8]) -> u64 = hash::fast_hash;
+
+#[env_item(options(low = LOW, high = HIGH))]
+const OPTIONS_LEVEL: u32 = LOW;
+
+fn main() {
+    assert_eq!(6, HASHER(&[1, 2, 3]));
+    assert_eq!(100, OPTIONS_LEVEL);
+}
@@ -0,0 +1,14 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(pad = 0)]
+const PAD_DEVICE_NAME: [u8; 16] = *b"unnamed\0\0\0\0\0\0\0\0\0";
+
+#[env_item(pad = b' ')]
+static PAD_SERIAL: [u8; 6] = [0; 6];
+
+fn main() {
+    assert_eq!(*b"sensor-01\0\0\0\0\0\0\0", PAD_DEVICE_NAME);
+    assert_eq!(*b"A1    ", PAD_SERIAL);
+}
@@ -0,0 +1,10 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(split = "path_list", path_sep = ":")]
+static PATH_LIST_PLUGINS: &[&str] = &["/usr/lib/plugins"];
+
+fn main() {
+    assert_eq!(&["/opt/a", "/opt/b"], PATH_LIST_PLUGINS);
+}
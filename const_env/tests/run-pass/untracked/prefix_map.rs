@@ -0,0 +1,14 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(prefix_map = "PREFIX_MAP_")]
+static PREFIX_MAP_FEATURES: &[(&str, &str)] = &[];
+
+#[env_item(prefix_map = "PREFIX_MAP_UNSET_")]
+static PREFIX_MAP_NONE: &[(&str, &str)] = &[("default", "dropped")];
+
+fn main() {
+    assert_eq!(&[("BETA", "2"), ("LOGGING", "on \"verbose\"")], PREFIX_MAP_FEATURES);
+    assert!(PREFIX_MAP_NONE.is_empty());
+}
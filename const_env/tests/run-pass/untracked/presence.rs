@@ -0,0 +1,22 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(any_set("PRESENCE_CI_UNSET", "PRESENCE_CI_GITHUB"))]
+const IS_CI: bool = false;
+
+#[env_item(all_set("PRESENCE_CI_GITHUB", "PRESENCE_RELEASE_UNSET"))]
+const IS_RELEASE_CI: bool = false;
+
+#[env_item(any_set("PRESENCE_NO_COLOR"), invert)]
+const COLOR: bool = true;
+
+#[env_item(not(any_set("PRESENCE_LOCAL_UNSET")))]
+const REMOTE: bool = false;
+
+fn main() {
+    assert!(IS_CI);
+    assert!(!IS_RELEASE_CI);
+    assert!(!COLOR);
+    assert!(REMOTE);
+}
@@ -0,0 +1,19 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item("PROFILE_BUF", debug_var = "PROFILE_BUF_DEBUG", release_var = "PROFILE_BUF_RELEASE")]
+const PROFILE_BUF: usize = 0;
+
+#[env_item("PROFILE_NAME", debug_var = "PROFILE_NAME_DEBUG")]
+static PROFILE_NAME: &'static str = "default";
+
+fn main() {
+    if cfg!(debug_assertions) {
+        assert_eq!(4096, PROFILE_BUF);
+        assert_eq!("debug", PROFILE_NAME);
+    } else {
+        assert_eq!(256, PROFILE_BUF);
+        assert_eq!("default", PROFILE_NAME);
+    }
+}
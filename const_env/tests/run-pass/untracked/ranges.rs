@@ -0,0 +1,14 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(ranges, max = 15)]
+const RANGES_CORES: &'static [u16] = &[0];
+
+#[env_item(ranges, dedup)]
+const RANGES_LANES: [u8; 4] = [0, 1, 2, 3];
+
+fn main() {
+    assert_eq!(&[0, 1, 2, 3, 8, 10, 11], RANGES_CORES);
+    assert_eq!([7, 6, 5, 4], RANGES_LANES);
+}
@@ -0,0 +1,18 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item("FIELD_BUILD_INFO", field = 0, field_sep = "|")]
+const FIELD_VERSION: &str = "0.0.0";
+
+#[env_item("FIELD_BUILD_INFO", field = 2, field_sep = "|")]
+static FIELD_PROFILE: &str = "debug";
+
+#[env_item("FIELD_LIMITS", field = 1)]
+const FIELD_BURST: u32 = 1;
+
+fn main() {
+    assert_eq!("1.4.2", FIELD_VERSION);
+    assert_eq!("release", FIELD_PROFILE);
+    assert_eq!(12, FIELD_BURST);
+}
@@ -0,0 +1,26 @@
+extern crate const_env;
+
+use const_env::{env_item, registry};
+
+#[env_item(register)]
+const REGISTRY_PORT: u16 = 80;
+
+#[env_item]
+const REGISTRY_UNLISTED: u8 = 0;
+
+#[env_item(register, sensitive)]
+const REGISTRY_TOKEN: &'static str = "dev";
+
+fn main() {
+    let registry = registry!();
+    assert_eq!(2, registry.len());
+    assert_eq!("REGISTRY_PORT", registry[0].const_name);
+    assert_eq!("u16", registry[0].declared_type);
+    assert_eq!("80", registry[0].default_str);
+    assert_eq!("8080", registry[0].value_str);
+    assert!(registry[0].overridden);
+    assert_eq!(8080, REGISTRY_PORT);
+    assert_eq!("<redacted>", registry[1].value_str);
+    assert_eq!("secret", REGISTRY_TOKEN);
+    assert_eq!(0, REGISTRY_UNLISTED);
+}
@@ -0,0 +1,34 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(runtime_fallback)]
+static FALLBACK_PORT: u16 = 80;
+
+#[env_item(runtime_fallback)]
+static FALLBACK_RETRIES: u32 = 3;
+
+#[env_item(runtime_fallback)]
+static FALLBACK_WORKERS: u32 = 4;
+
+#[env_item(runtime_fallback)]
+static FALLBACK_MASK: u32 = 0;
+
+#[env_item(runtime_fallback)]
+static FALLBACK_LIMIT: u32 = 0;
+
+fn main() {
+    // Set at compile time and at run time.
+    assert_eq!(8080, *FALLBACK_PORT);
+    // Set at run time only.
+    std::env::set_var("FALLBACK_RETRIES", "5");
+    assert_eq!(5, *FALLBACK_RETRIES);
+    // A malformed value at run time falls back to the compile-time one.
+    std::env::set_var("FALLBACK_WORKERS", "many");
+    assert_eq!(4, *FALLBACK_WORKERS);
+    // Radix prefixes and underscores are accepted, as at compile time.
+    std::env::set_var("FALLBACK_MASK", "0x10");
+    assert_eq!(16, *FALLBACK_MASK);
+    std::env::set_var("FALLBACK_LIMIT", "1_000");
+    assert_eq!(1000, *FALLBACK_LIMIT);
+}
@@ -0,0 +1,27 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(emit_runtime_getter)]
+const GETTER_PORT: u16 = 80;
+
+#[env_item(emit_runtime_getter)]
+const GETTER_MASK: u32 = 0;
+
+#[env_item(emit_runtime_getter, invert)]
+const GETTER_QUIET: bool = true;
+
+#[env_item(emit_runtime_getter)]
+const GETTER_RATIO: f64 = 0.5;
+
+fn main() {
+    assert_eq!(Some(GETTER_PORT), getter_port_from_env());
+    assert_eq!(8080, GETTER_PORT);
+    assert_eq!(Some(0xff), getter_mask_from_env());
+    assert_eq!(Some(false), getter_quiet_from_env());
+    assert_eq!(None, getter_ratio_from_env());
+    std::env::set_var("GETTER_RATIO", "1_000.5");
+    assert_eq!(Some(1000.5), getter_ratio_from_env());
+    std::env::set_var("GETTER_PORT", "eighty");
+    assert_eq!(None, getter_port_from_env());
+}
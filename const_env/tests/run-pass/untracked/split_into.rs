@@ -0,0 +1,18 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item(split_into(HOST = 0, PORT = 1, USER = 2), split_sep = ":")]
+mod split_endpoint {
+    pub const HOST: &str = "localhost";
+    pub const PORT: u16 = 5432;
+    pub const USER: &str = "postgres";
+    pub const SCHEME: &str = "tcp";
+}
+
+fn main() {
+    assert_eq!("db.internal", split_endpoint::HOST);
+    assert_eq!(6432, split_endpoint::PORT);
+    assert_eq!("postgres", split_endpoint::USER);
+    assert_eq!("tcp", split_endpoint::SCHEME);
+}
@@ -0,0 +1,23 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+// Each warning is emitted as a scoped `#[deprecated]` shim, which must neither clash
+// with other items nor with the shims of other items.
+#[env_item(warn_redundant)]
+const WARN_PORT: u16 = 8080;
+
+#[env_item(warn_redundant)]
+static WARN_RETRIES: u8 = 3;
+
+#[env_item(warn_redundant, allow_warnings)]
+const WARN_QUIET: u8 = 1;
+
+#[allow(dead_code, non_upper_case_globals)]
+const warning: () = ();
+
+fn main() {
+    assert_eq!(8080, WARN_PORT);
+    assert_eq!(3, WARN_RETRIES);
+    assert_eq!(1, WARN_QUIET);
+}
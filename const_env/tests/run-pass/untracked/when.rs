@@ -0,0 +1,14 @@
+extern crate const_env;
+
+use const_env::env_item;
+
+#[env_item("WHEN_TICK_HZ", when(any(unix, windows)))]
+const TICK_HZ_HOSTED: u32 = 100;
+
+#[env_item("WHEN_TICK_HZ", when(not(any(unix, windows))))]
+const TICK_HZ_BARE: u32 = 100;
+
+fn main() {
+    assert_eq!(1000, TICK_HZ_HOSTED);
+    assert_eq!(100, TICK_HZ_BARE);
+}
@@ -33,9 +33,73 @@ fn smoke() {
     set_var("STRING_ARRAY", "[\"bar\"]");
     set_var("TUPLE_ARRAY", r#"[("goodbye", false), ("planet", true)]"#);
     set_var("ORIGIN", "Vec2 { x: 1., y: 2.}");
+    set_var("PROFILE_BUF_DEBUG", "4096");
+    set_var("PROFILE_BUF_RELEASE", "256");
+    set_var("PROFILE_NAME_DEBUG", "debug");
+    set_var("INCLUDE_PATH", "tests/run-pass/untracked/include.txt");
+    set_var("INCLUDE_EXPR_TUNING", "tests/run-pass/untracked/include_expr.txt");
+    set_var("INCLUDE_EXPR_LOCAL", "tests/run-pass/untracked/include_expr_tracked.txt");
+    set_var("INCLUDE_EXPR_FIELD", "tests/run-pass/untracked/include_expr_tracked.txt");
+    set_var("RANGES_CORES", "0-3,8,10-11");
+    set_var("RANGES_LANES", "7-4");
+    set_var("WHEN_TICK_HZ", "1000");
+    set_var("REGISTRY_PORT", "8080");
+    set_var("REGISTRY_TOKEN", "secret");
+    set_var("FALLBACK_PORT", "8080");
+    set_var("ASSERT_WORKERS", "16");
+    set_var("ENV_OR_RETRIES", "5");
+    set_var("ENV_OR_CODE", "-7");
+    set_var("ENV_OR_GREETING", "hello world");
+    set_var("BLOCK_A", "2");
+    set_var("BLOCK_B", "y");
+    set_var("BLOCK_CUSTOM", "true");
+    set_var("DERIVE_PORT", "8080");
+    set_var("DERIVE_HOST", "example.com");
+    set_var("LIMITS_MAX_CONN", "16");
+    set_var("GETTER_PORT", "8080");
+    set_var("GETTER_MASK", "0xff");
+    set_var("GETTER_QUIET", "true");
+    set_var("DURATION_POLL", "1m30s");
+    set_var("DURATION_TIMEOUT", "1.5s");
+    set_var("PAD_DEVICE_NAME", "sensor-01");
+    set_var("PAD_SERIAL", "A1");
+    set_var("OPTIONS_HASH_IMPL", "sum");
+    set_var("OPTIONS_LEVEL", "high");
+    set_var("PATH_LIST_PLUGINS", "/opt/a::/opt/b");
+    set_var("JSON_NET", r#"{"port": 9000, "backlog": 256, "tls": {"enabled": true}}"#);
+    set_var("JSON_LIMITS", r#"{"burst": 12}"#);
+    set_var("WARN_PORT", "8080");
+    set_var("WARN_RETRIES", "3");
+    set_var("WARN_QUIET", "1");
+    set_var("POINTER_CONFIG", r#"{"server": {"port": 9000, "tls": true}, "hosts": ["a.example.com", "b.example.com"]}"#);
+    set_var("FIELD_BUILD_INFO", "1.4.2|2024-06-01|release");
+    set_var("FIELD_LIMITS", "4, 12");
+    set_var("CAPTURE_RUSTC_VERSION", "rustc 1.79.0 (129f3b996 2024-06-10)");
+    set_var("PREFIX_MAP_LOGGING", "on \"verbose\"");
+    set_var("PREFIX_MAP_BETA", "2");
+    set_var("OBFUSCATED_TOKEN", "s3cr3t-t0ken");
+    set_var("OBFUSCATED_BYTES", "s3cr3t-t0ken");
+    set_var("PRESENCE_CI_GITHUB", "");
+    set_var("PRESENCE_NO_COLOR", "0");
+    set_var("SPLIT_ENDPOINT", "db.internal:6432");
+    set_var("ENDIAN_MAGIC", "0xDEADBEEF");
+    set_var("ENDIAN_VERSION", "258");
+    set_var("PREFIX_CONSTS_UPLOADS", "10");
+    set_var("PREFIX_CONSTS_USERS", "500");
+    set_var("CONST_ENV_FINGERPRINT", "1");
+    set_var("FINGERPRINT_PORT", "8080");
     run_test("run-pass", "run-pass/untracked", |_| {});
 }
 
+#[test]
+fn compile_fail() {
+    set_var("INVALID_ARRAY", "[1, 2");
+    set_var("INVALID_STR", "trailing \\");
+    set_var("INVALID_PORT", "eighty");
+    set_var("ASSERT_BUF", "1000");
+    run_test("compile-fail", "compile-fail", |_| {});
+}
+
 #[cfg(feature = "tracked")]
 #[test]
 fn tracked() {
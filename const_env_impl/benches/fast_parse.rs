@@ -0,0 +1,66 @@
+//! Times the expansion of a synthetic crate of small items with and without the
+//! `fast-parse` path. Run with `cargo bench --bench fast_parse --features fast-parse`.
+
+use std::time::Instant;
+
+use const_env_impl::{from_env, TestEnv, TestEnvBuilder};
+
+use proc_macro2::TokenStream;
+
+const ITERATIONS: u32 = 20;
+const ITEMS: usize = 500;
+
+/// A distinct name for the `i`th item, far enough from the others that no lookup warns
+/// about a similarly named variable.
+fn item_name(i: usize) -> String {
+    let mut state = (i as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    (0..12).map(|_| {
+        state ^= state >> 29;
+        state = state.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        (b'A' + (state % 26) as u8) as char
+    }).collect()
+}
+
+/// The items of a crate configuring `ITEMS` constants and statics of the common shapes,
+/// with the variables that override every one of them.
+fn synthetic_crate() -> (Vec<TokenStream>, Vec<(String, String)>) {
+    let shapes = [
+        ("u16", "80", "8080"),
+        ("i64", "-1", "-42"),
+        ("f64", "0.5", "2.25"),
+        ("bool", "false", "true"),
+        ("&str", "\"localhost\"", "example.com"),
+    ];
+    let mut items = Vec::new();
+    let mut vars = Vec::new();
+    for i in 0..ITEMS {
+        let (ty, default, value) = shapes[i % shapes.len()];
+        let keyword = if i % 2 == 0 { "const" } else { "static" };
+        let name = item_name(i);
+        let item = format!("/// Item {}.\npub {} {}: {} = {};", i, keyword, name, ty, default);
+        items.push(item.parse().unwrap());
+        vars.push((name, value.to_string()));
+    }
+    (items, vars)
+}
+
+fn time(name: &str, items: &[TokenStream], env: impl Fn() -> TestEnvBuilder) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for item in items {
+            std::hint::black_box(from_env(TokenStream::new(), item.clone(), env().build()));
+        }
+    }
+    println!("{:<24} {:>10.1?} per crate", name, start.elapsed() / ITERATIONS);
+}
+
+fn main() {
+    let (items, vars) = synthetic_crate();
+    // Claims are kept in a file, whose locking would dominate the timings.
+    let unset = || TestEnv::builder().set("CONST_ENV_DUPLICATES", "off");
+    let set = || unset().extend(vars.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+    time("unset, full parse", &items, || unset().set("CONST_ENV_FAST_PARSE", "0"));
+    time("unset, fast parse", &items, unset);
+    time("set, full parse", &items, || set().set("CONST_ENV_FAST_PARSE", "0"));
+    time("set, fast parse", &items, set);
+}
@@ -0,0 +1,39 @@
+//! Times the expansion of large items against parsing them alone, which bounds the cost
+//! of parsing the item. Run with `cargo bench --bench parse_item`.
+
+use std::time::Instant;
+
+use const_env_impl::{from_env, TestEnv};
+
+use proc_macro2::TokenStream;
+
+const ITERATIONS: u32 = 200;
+
+/// An item whose initializer is an array of `len` integers.
+fn synthetic_item(keyword: &str, len: usize) -> TokenStream {
+    let elements: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+    format!("#[doc = \"data\"] pub {} DATA: [u32; {}] = [{}];", keyword, len, elements.join(", "))
+        .parse()
+        .unwrap()
+}
+
+fn time<T>(name: &str, mut run: impl FnMut() -> T) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        std::hint::black_box(run());
+    }
+    println!("{:<24} {:>10.1?}", name, start.elapsed() / ITERATIONS);
+}
+
+fn main() {
+    let unset = || TestEnv::builder().build();
+    let set = || TestEnv::builder().set("DATA", "[1, 2, 3]").build();
+    let item_const = synthetic_item("const", 10_000);
+    let item_static = synthetic_item("static", 10_000);
+    let item_fn: TokenStream = "fn data() -> u32 { 0 }".parse().unwrap();
+    time("parse only", || syn::parse2::<syn::Item>(item_static.clone()).unwrap());
+    time("const, unset", || from_env(TokenStream::new(), item_const.clone(), unset()));
+    time("static, unset", || from_env(TokenStream::new(), item_static.clone(), unset()));
+    time("static, set", || from_env(TokenStream::new(), item_static.clone(), set()));
+    time("fn (unsupported)", || from_env(TokenStream::new(), item_fn.clone(), unset()));
+}
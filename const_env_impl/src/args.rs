@@ -0,0 +1,1277 @@
+use proc_macro2::{Span, TokenStream};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Expr, ExprLit, Lit, LitStr, Token};
+
+use crate::expand::MissingRef;
+use crate::value;
+
+/// Arguments accepted by the `env_item` attribute, e.g.
+/// `#[env_item("VAR_NAME", case = "lower")]`.
+#[derive(Default)]
+pub(crate) struct ItemArgs {
+    /// Explicit environment variable name. Defaults to the item's identifier.
+    pub var_name: Option<LitStr>,
+    /// How the default variable name is derived from the item's identifier.
+    pub rename_all: RenameRule,
+    /// Case transformation applied to string values, with the span of the `case` key.
+    pub case: Option<(Case, Span)>,
+    /// Template assembling the value from several variables, e.g. `"{HOST}:{PORT}"`.
+    pub format: Option<LitStr>,
+    /// Replace the `{}` in the default string with the value instead of replacing the
+    /// whole string, with the span of the flag.
+    pub placeholder: Option<Span>,
+    /// Expand `${NAME}` references inside values, and how to treat unset references.
+    pub expand: Option<MissingRef>,
+    /// Try `<NAME>_<TARGET>` before `<NAME>`, where `TARGET` is the target triple, with
+    /// the span of the flag.
+    pub per_target: Option<Span>,
+    /// File relative to `OUT_DIR` whose trimmed contents are used instead of a variable.
+    pub out_file: Option<LitStr>,
+    /// Command whose output is used when the variable is unset. Only available with the
+    /// `command-source` feature.
+    pub command: Option<LitStr>,
+    /// Variable consulted instead of the main one in debug builds.
+    pub debug_var: Option<LitStr>,
+    /// Variable consulted instead of the main one in release builds.
+    pub release_var: Option<LitStr>,
+    /// Parse the value as an arbitrary expression replacing the whole initializer,
+    /// with the span of the flag.
+    pub expr: Option<Span>,
+    /// Make a boolean item `true` whenever the variable is set, without parsing its
+    /// value, with the span of the flag.
+    pub exists: Option<Span>,
+    /// Negate the boolean produced from the variable, with the span of the flag.
+    pub invert: Option<Span>,
+    /// Make a boolean item `true` whenever a predicate over which variables are set
+    /// holds, from `any_set(...)`, `all_set(...)` or `not(...)`, with the span of the key.
+    pub presence: Option<(crate::presence::Presence, Span)>,
+    /// Treat the value as a path to a file whose contents are the actual value.
+    pub file: bool,
+    /// Embed the file named by the value with `include_bytes!` or `include_str!`, with
+    /// the span of the flag.
+    pub include: Option<Span>,
+    /// Replace the whole initializer with the contents of the file named by the value,
+    /// parsed as an expression, with the span of the flag.
+    pub include_expr: Option<Span>,
+    /// If the variable is unset, read the file named by `<NAME>_FILE` instead.
+    pub file_fallback: bool,
+    /// Remove a single trailing newline from file contents.
+    pub trim_newline: bool,
+    /// If the variable is unset, look it up in `[package.metadata.const_env]` of the
+    /// crate's manifest.
+    pub cargo_metadata: bool,
+    /// Do not reference the variables with `option_env!` for rebuild tracking.
+    pub untracked: bool,
+    /// Also generate a `<IDENT>_ENV_VAR` constant holding the variable name, with the
+    /// span of the flag.
+    pub emit_var_name: Option<Span>,
+    /// Also generate a `<IDENT>_DEFAULT` constant holding the written default.
+    pub emit_default: bool,
+    /// With `emit_default`, generate the constant only if the default was replaced.
+    pub only_when_overridden: bool,
+    /// Also generate a `<IDENT>_LEN` constant holding the length of the value, with the
+    /// span of the flag.
+    pub emit_len: Option<Span>,
+    /// Do not append a doc line naming the variables to the item.
+    pub no_doc: bool,
+    /// Also note in the appended doc line whether this build overrode the default.
+    pub doc_override: bool,
+    /// Wrap a `static` in a `LazyLock` that reads the variable again at run time, with
+    /// the span of the flag.
+    pub runtime_fallback: Option<Span>,
+    /// Also generate a `<ident>_from_env()` function that reads the variable again at run
+    /// time, with the span of the flag.
+    pub emit_runtime_getter: Option<Span>,
+    /// Record the item in the crate's registry, listed by `registry!()`.
+    pub register: bool,
+    /// Fall back to a case-insensitive search of the environment.
+    pub ignore_case: bool,
+    /// Smallest value accepted, checked against every element of a list.
+    pub min: Option<Expr>,
+    /// Largest value accepted, checked against every element of a list.
+    pub max: Option<Expr>,
+    /// The default of a field of `#[derive(FromEnvDefaults)]`, with the span of the key.
+    pub default: Option<(Expr, Span)>,
+    /// Predicates on the item checked by const evaluation, each with the literal it was
+    /// written in.
+    pub asserts: Vec<(Expr, LitStr)>,
+    /// Sort the elements of a list, with the span of the flag.
+    pub sort: Option<Span>,
+    /// Remove duplicate elements from a list, with the span of the flag.
+    pub dedup: Option<Span>,
+    /// Expand a list of integers and inclusive ranges such as `0-3,8,10-11` into an
+    /// integer array or slice, with the span of the flag.
+    pub ranges: Option<Span>,
+    /// Zero-based field of a delimited record taken as the value, from `field = N`, with
+    /// the span of the key.
+    pub field: Option<(usize, Span)>,
+    /// Separator of the fields of `field`, with the span of the key.
+    pub field_sep: Option<(char, Span)>,
+    /// Items of the module, each with the field of the value it takes, from
+    /// `split_into(HOST = 0, PORT = 1)`, with the span of the key.
+    pub split_into: Option<(Vec<(syn::Ident, usize)>, Span)>,
+    /// Separator of the fields of `split_into`, with the span of the key.
+    pub split_sep: Option<(char, Span)>,
+    /// Reject values with more fields than `split_into` names, with the span of the flag.
+    pub strict_fields: Option<Span>,
+    /// Reject float values with more digits than the item's float type keeps, with the
+    /// span of the flag.
+    pub strict_precision: Option<Span>,
+    /// Set on the items of a `split_into` module rather than by an argument: the number
+    /// of fields named, and whether more are an error. A missing field keeps the default.
+    pub split_fields: Option<(usize, bool)>,
+    /// Pattern whose match in the value supplies the text that is parsed, from
+    /// `capture = "..."`, with the span of the key.
+    pub capture: Option<(crate::regex::Regex, Span)>,
+    /// The group of `capture` taken, by index or name, with the span of the key.
+    pub capture_group: Option<(crate::regex::CaptureGroup, Span)>,
+    /// Smallest number of elements accepted in a list, with the span of the value.
+    pub min_items: Option<(usize, Span)>,
+    /// Largest number of elements accepted in a list, with the span of the value.
+    pub max_items: Option<(usize, Span)>,
+    /// Expected SHA-256 digest of the value, as lowercase hex, with the span of the value.
+    pub sha256: Option<(String, Span)>,
+    /// Check the written default against `sha256` too when the variable is unset.
+    pub verify_default: bool,
+    /// Values accepted, checked against every element of a list, with the span of the
+    /// `one_of` key.
+    pub one_of: Option<(Vec<Expr>, Span)>,
+    /// Number of fractional digits of a decimal value parsed exactly into an integer, e.g.
+    /// `19.99` into `1999` with a scale of 2, with the span of the key.
+    pub fixed_point: Option<(u32, Span)>,
+    /// How a `fixed_point` value with more fractional digits than the scale is rounded,
+    /// with the span of the key. Without it, such values are an error.
+    pub round: Option<(Rounding, Span)>,
+    /// Expressions, usually paths, selected by name by the value to replace the
+    /// initializer, with the span of the `options` key.
+    pub options: Option<(Vec<(String, Expr)>, Span)>,
+    /// Split the value into a slice of strings on the path list separator of the target,
+    /// `:` or `;` on Windows, from `split = "path_list"`, with the span of the key.
+    pub path_list: Option<Span>,
+    /// Separator of `split = "path_list"` used instead of the target's.
+    pub path_sep: Option<(char, Span)>,
+    /// Keep the empty segments of a path list instead of dropping them.
+    pub keep_empty: bool,
+    /// Read a single comma in a float value as the decimal point, with the span of the
+    /// flag.
+    pub decimal_comma: Option<Span>,
+    /// The type that values are parsed as, from `ty = "..."`, for items declared with an
+    /// alias of it, with the span of the key.
+    pub ty: Option<(syn::Type, Span)>,
+    /// JSON pointer selecting the value within a JSON document, from `json_pointer = "..."`,
+    /// with the span of the key.
+    pub json_pointer: Option<(String, Span)>,
+    /// Prefix of the variables collected into a slice of `(suffix, value)` pairs, from
+    /// `prefix_map = "..."`, with the span of the key.
+    pub prefix_map: Option<(String, Span)>,
+    /// Let a JSON object filling a struct literal default leave out fields, which keep
+    /// their defaults, with the span of the flag.
+    pub partial: Option<Span>,
+    /// Byte that right-pads the value to the length of a fixed-size byte array default,
+    /// with the span of the key.
+    pub pad: Option<(u8, Span)>,
+    /// Byte order in which an integer value fills a fixed-size byte array default, with
+    /// the span of the key.
+    pub endian: Option<(Endian, Span)>,
+    /// Names of bits OR'd together from a comma-separated value, with the span of the
+    /// `flags` key.
+    pub flags: Option<(Vec<(String, u128)>, Span)>,
+    /// `cfg` predicate that must hold for the substitution to apply.
+    pub when: Option<syn::NestedMeta>,
+    /// Hide the value in diagnostics and any other output.
+    pub sensitive: bool,
+    /// Store the value XOR'd with a random key, restored at run time by a generated
+    /// helper, with the span of the flag. Implies `sensitive`.
+    pub obfuscate: Option<Span>,
+    /// Do not warn when the variable is a well-known system variable.
+    pub allow_system_var: bool,
+    /// Do not emit the item's warnings.
+    pub allow_warnings: bool,
+    /// Warn when the value equals the written default.
+    pub warn_redundant: bool,
+    /// Fail the build instead of keeping the default when a variable is unset.
+    pub required: bool,
+    /// Keep the default when a variable is unset even in strict mode.
+    pub optional: bool,
+}
+
+/// ASCII case transformation for string values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Case {
+    Lower,
+    Upper,
+}
+
+impl Case {
+    pub fn apply(self, value: &str) -> String {
+        match self {
+            Case::Lower => value.to_ascii_lowercase(),
+            Case::Upper => value.to_ascii_uppercase(),
+        }
+    }
+
+    pub fn apply_bytes(self, value: &[u8]) -> Vec<u8> {
+        match self {
+            Case::Lower => value.to_ascii_lowercase(),
+            Case::Upper => value.to_ascii_uppercase(),
+        }
+    }
+}
+
+/// Rounding of `fixed_point` values with more fractional digits than the scale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Rounding {
+    /// Drop the extra digits, rounding toward zero.
+    Down,
+    /// Round to the nearest value, and halves away from zero.
+    HalfUp,
+    /// Round to the nearest value, and halves to the even neighbour.
+    HalfEven,
+}
+
+/// Byte order of `endian`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Endian {
+    /// The most significant byte first.
+    Big,
+    /// The least significant byte first.
+    Little,
+}
+
+/// How the default variable name is derived from the item's identifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub(crate) enum RenameRule {
+    /// Use the identifier as written.
+    #[default]
+    Verbatim,
+    /// `HTTPServerPort` becomes `HTTP_SERVER_PORT`.
+    ScreamingSnakeCase,
+}
+
+impl RenameRule {
+    pub fn apply(self, ident: &syn::Ident) -> String {
+        let name = ident.to_string();
+        let name = name.strip_prefix("r#").unwrap_or(&name);
+        match self {
+            RenameRule::Verbatim => name.to_string(),
+            RenameRule::ScreamingSnakeCase => screaming_snake_case(name),
+        }
+    }
+}
+
+/// Splits an identifier into words at underscores, lowercase-to-uppercase transitions
+/// and the end of acronyms, then joins the uppercased words with underscores.
+fn screaming_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::with_capacity(name.len() + 4);
+    for (index, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !result.is_empty() && !result.ends_with('_') {
+                result.push('_');
+            }
+            continue;
+        }
+        if c.is_uppercase() && index > 0 && !result.ends_with('_') {
+            let previous = chars[index - 1];
+            let next_is_lower = chars.get(index + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase() || previous.is_ascii_digit() || (previous.is_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_uppercase());
+    }
+    while result.ends_with('_') {
+        result.pop();
+    }
+    result
+}
+
+/// Arguments written as `key = value`.
+const KEY_VALUE_ARGS: &[&str] = &[
+    "case", "format", "rename_all", "debug_var", "release_var", "expand_missing", "out_file",
+    "command", "min", "max", "min_items", "max_items",
+    "sha256", "assert", "default", "fixed_point", "round", "pad", "split", "path_sep", "ty",
+    "json_pointer", "field", "field_sep", "capture", "capture_group",
+    "prefix_map", "split_sep", "endian",
+];
+
+/// Arguments that may be given more than once.
+const REPEATABLE_ARGS: &[&str] = &["assert"];
+
+/// Arguments written as `key(...)`.
+const LIST_ARGS: &[&str] = &["when", "one_of", "flags", "options", "any_set", "all_set", "not", "split_into"];
+
+/// Arguments written as a bare identifier.
+const FLAG_ARGS: &[&str] = &[
+    "required", "optional", "placeholder", "expand", "per_target", "expr", "ignore_case", "sensitive", "warn_redundant",
+    "exists", "file", "file_fallback", "include", "include_expr", "trim_newline", "invert",
+    "sort", "dedup", "verify_default", "allow_system_var", "cargo_metadata", "untracked", "emit_var_name", "emit_default",
+    "emit_len", "only_when_overridden", "no_doc", "doc_override", "register", "runtime_fallback",
+    "emit_runtime_getter", "decimal_comma", "keep_empty", "partial", "allow_warnings",
+    "obfuscate", "strict_fields", "strict_precision", "ranges",
+];
+
+/// The accepted attribute forms, quoted in errors about malformed arguments.
+const ACCEPTED_FORMS: &str = "expected `#[env_item]`, `#[env_item(\"VAR_NAME\")]` or `#[env_item(\"VAR_NAME\", key = value, flag, ...)]`";
+
+/// A single comma-separated attribute argument.
+enum Arg {
+    /// A bare string literal, which names the environment variable.
+    Name(LitStr),
+    /// `key = value`.
+    KeyValue(syn::Ident, Box<Expr>),
+    /// A bare identifier such as `required`.
+    Flag(syn::Ident),
+    /// `key(...)`, with the tokens inside the parentheses.
+    List(syn::Ident, TokenStream),
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Arg::Name(input.parse()?));
+        }
+        if input.peek(Lit) {
+            return Err(input.error(format!("The variable name must be a string literal; {}", ACCEPTED_FORMS)));
+        }
+        if !input.peek(syn::Ident) {
+            return Err(input.error(format!("Expected an attribute argument; {}", ACCEPTED_FORMS)));
+        }
+        let key: syn::Ident = input.parse()?;
+        if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            return Ok(Arg::List(key, content.parse()?));
+        }
+        if !input.peek(Token![=]) {
+            return Ok(Arg::Flag(key));
+        }
+        input.parse::<Token![=]>()?;
+        if input.is_empty() || input.peek(Token![,]) {
+            return Err(input.error(format!("Expected a value after `{} =`", key)));
+        }
+        let value: Expr = input.parse()?;
+        Ok(Arg::KeyValue(key, Box::new(value)))
+    }
+}
+
+impl Parse for ItemArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Older callers pass the attribute arguments wrapped in parentheses.
+        if input.peek(syn::token::Paren) {
+            let fork = input.fork();
+            let _content;
+            syn::parenthesized!(_content in fork);
+            if fork.is_empty() {
+                let content;
+                syn::parenthesized!(content in input);
+                return content.parse();
+            }
+        }
+
+        let mut args = ItemArgs::default();
+        let mut expand = false;
+        let mut expand_missing = None;
+        let mut expand_missing_span = Span::call_site();
+        let mut trim_newline_span = Span::call_site();
+        let mut only_when_overridden_span = Span::call_site();
+        let mut doc_override_span = Span::call_site();
+        let mut verify_default_span = Span::call_site();
+        let mut keep_empty_span = Span::call_site();
+        let mut optional_span = Span::call_site();
+        let mut seen: Vec<(String, Span)> = Vec::new();
+        let mut parsed = Vec::new();
+        while !input.is_empty() {
+            parsed.push(input.parse::<Arg>()?);
+            if input.is_empty() {
+                break;
+            }
+            if !input.peek(Token![,]) {
+                return Err(input.error(format!("Expected `,` between attribute arguments; {}", ACCEPTED_FORMS)));
+            }
+            input.parse::<Token![,]>()?;
+        }
+        for (index, arg) in parsed.into_iter().enumerate() {
+            let (key, known) = match &arg {
+                Arg::Name(_) => (None, &[][..]),
+                Arg::KeyValue(key, _) => (Some(key), KEY_VALUE_ARGS),
+                Arg::List(key, _) => (Some(key), LIST_ARGS),
+                Arg::Flag(key) => (Some(key), FLAG_ARGS),
+            };
+            if let Some(key) = key {
+                let name = key.to_string();
+                if !known.contains(&name.as_str()) {
+                    return Err(unknown_argument(key, known));
+                }
+                let repeatable = REPEATABLE_ARGS.contains(&name.as_str());
+                if let Some((_, first)) = seen.iter().find(|(seen_name, _)| *seen_name == name && !repeatable) {
+                    let mut error = syn::Error::new(key.span(), format!("Duplicate attribute argument `{}`", name));
+                    error.combine(syn::Error::new(*first, format!("`{}` is first specified here", name)));
+                    return Err(error);
+                }
+                seen.push((name, key.span()));
+            }
+            match arg {
+                Arg::Name(name) if index == 0 => args.var_name = Some(name),
+                Arg::Name(name) => {
+                    return Err(syn::Error::new(name.span(), "The variable name must be the first attribute argument"));
+                }
+                Arg::KeyValue(key, value) => match key.to_string().as_str() {
+                    "case" => {
+                        let case = match string_lit(&value)?.value().as_str() {
+                            "lower" => Case::Lower,
+                            "upper" => Case::Upper,
+                            _ => return Err(syn::Error::new(value.span(), "Expected `case` to be \"lower\" or \"upper\"")),
+                        };
+                        args.case = Some((case, key.span()));
+                    }
+                    "format" => args.format = Some(string_lit(&value)?),
+                    "rename_all" => {
+                        args.rename_all = match string_lit(&value)?.value().as_str() {
+                            "verbatim" => RenameRule::Verbatim,
+                            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnakeCase,
+                            _ => return Err(syn::Error::new(value.span(), "Expected `rename_all` to be \"verbatim\" or \"SCREAMING_SNAKE_CASE\"")),
+                        };
+                    }
+                    "out_file" => args.out_file = Some(string_lit(&value)?),
+                    "min" => args.min = Some(number_lit(&value)?),
+                    "max" => args.max = Some(number_lit(&value)?),
+                    "sha256" => {
+                        let digest = string_lit(&value)?;
+                        let hex = digest.value().to_ascii_lowercase();
+                        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                            return Err(syn::Error::new(digest.span(), "Expected a SHA-256 digest of 64 hexadecimal digits"));
+                        }
+                        args.sha256 = Some((hex, digest.span()));
+                    }
+                    "field" => args.field = Some((count_lit(&value)?, key.span())),
+                    "field_sep" => args.field_sep = Some((separator_lit(&key, &value)?, key.span())),
+                    "split_sep" => args.split_sep = Some((separator_lit(&key, &value)?, key.span())),
+                    "capture" => {
+                        let pattern = string_lit(&value)?;
+                        let regex = crate::regex::Regex::new(&pattern.value())
+                            .map_err(|message| syn::Error::new(pattern.span(), format!("Invalid `capture` pattern: {}", message)))?;
+                        args.capture = Some((regex, key.span()));
+                    }
+                    "capture_group" => {
+                        let group = match &*value {
+                            Expr::Lit(ExprLit { lit: Lit::Str(name), .. }) => crate::regex::CaptureGroup::Name(name.value()),
+                            Expr::Lit(ExprLit { lit: Lit::Int(index), .. }) => crate::regex::CaptureGroup::Index(index.base10_parse()?),
+                            other => return Err(syn::Error::new(other.span(), "Expected `capture_group` to be a group index or a string naming a group")),
+                        };
+                        args.capture_group = Some((group, value.span()));
+                    }
+                    "min_items" => args.min_items = Some((count_lit(&value)?, value.span())),
+                    "max_items" => args.max_items = Some((count_lit(&value)?, value.span())),
+                    "default" => args.default = Some((*value, key.span())),
+                    "fixed_point" => {
+                        let scale = count_lit(&value)?;
+                        // 10^38 is the largest power of ten that fits in a `u128`.
+                        if scale > 38 {
+                            return Err(syn::Error::new(value.span(), "Expected `fixed_point` to be at most 38"));
+                        }
+                        args.fixed_point = Some((scale as u32, key.span()));
+                    }
+                    "pad" => {
+                        let pad = match &*value {
+                            Expr::Lit(ExprLit { lit: Lit::Byte(byte), .. }) => byte.value(),
+                            Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse::<u8>()?,
+                            _ => return Err(syn::Error::new(value.span(), "Expected `pad` to be a byte literal such as `b' '` or an integer from 0 to 255")),
+                        };
+                        args.pad = Some((pad, key.span()));
+                    }
+                    "split" => match string_lit(&value)?.value().as_str() {
+                        "path_list" => args.path_list = Some(key.span()),
+                        _ => return Err(syn::Error::new(value.span(), "Expected `split` to be \"path_list\"")),
+                    },
+                    "path_sep" => {
+                        let separator = string_lit(&value)?;
+                        let value = separator.value();
+                        let mut chars = value.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => args.path_sep = Some((c, separator.span())),
+                            _ => return Err(syn::Error::new(separator.span(), "Expected `path_sep` to be a single character")),
+                        }
+                    }
+                    "ty" => {
+                        let hint = string_lit(&value)?;
+                        let ty = hint.parse::<syn::Type>()
+                            .map_err(|err| syn::Error::new(hint.span(), format!("Invalid `ty`: {}", err)))?;
+                        if crate::declared::DeclaredType::from_type(&ty).is_none() {
+                            return Err(syn::Error::new(hint.span(),
+                                "Expected `ty` to name a number, `bool`, `char`, `&str` or `&CStr` type, or an array, slice or reference to an array of those"));
+                        }
+                        args.ty = Some((ty, key.span()));
+                    }
+                    "json_pointer" => {
+                        let pointer = string_lit(&value)?;
+                        crate::json::pointer_tokens(&pointer.value())
+                            .map_err(|message| syn::Error::new(pointer.span(), format!("Invalid `json_pointer`: {}", message)))?;
+                        args.json_pointer = Some((pointer.value(), key.span()));
+                    }
+                    "prefix_map" => {
+                        let prefix = string_lit(&value)?;
+                        if prefix.value().is_empty() {
+                            return Err(syn::Error::new(prefix.span(), "`prefix_map` cannot be empty"));
+                        }
+                        args.prefix_map = Some((prefix.value(), key.span()));
+                    }
+                    "endian" => {
+                        let endian = match string_lit(&value)?.value().as_str() {
+                            "big" => Endian::Big,
+                            "little" => Endian::Little,
+                            _ => return Err(syn::Error::new(value.span(), "Expected `endian` to be \"big\" or \"little\"")),
+                        };
+                        args.endian = Some((endian, key.span()));
+                    }
+                    "round" => {
+                        let rounding = match string_lit(&value)?.value().as_str() {
+                            "down" => Rounding::Down,
+                            "half_up" => Rounding::HalfUp,
+                            "half_even" => Rounding::HalfEven,
+                            _ => return Err(syn::Error::new(value.span(), "Expected `round` to be \"down\", \"half_up\" or \"half_even\"")),
+                        };
+                        args.round = Some((rounding, key.span()));
+                    }
+                    "assert" => {
+                        let predicate = string_lit(&value)?;
+                        let expr = predicate.parse::<Expr>()
+                            .map_err(|err| syn::Error::new(predicate.span(), format!("Invalid `assert` predicate: {}", err)))?;
+                        args.asserts.push((expr, predicate));
+                    }
+                    "command" => {
+                        let command = string_lit(&value)?;
+                        if !cfg!(feature = "command-source") {
+                            return Err(syn::Error::new(command.span(), "`command` requires the `command-source` feature of `const_env`"));
+                        }
+                        args.command = Some(command);
+                    }
+                    "debug_var" => args.debug_var = Some(string_lit(&value)?),
+                    "release_var" => args.release_var = Some(string_lit(&value)?),
+                    "expand_missing" => {
+                        expand_missing = Some(match string_lit(&value)?.value().as_str() {
+                            "error" => MissingRef::Error,
+                            "empty" => MissingRef::Empty,
+                            _ => return Err(syn::Error::new(value.span(), "Expected `expand_missing` to be \"error\" or \"empty\"")),
+                        });
+                        expand_missing_span = key.span();
+                    }
+                    _ => unreachable!("argument is listed as known but not handled"),
+                },
+                Arg::List(key, tokens) => match key.to_string().as_str() {
+                    "when" => args.when = Some(syn::parse2(tokens)?),
+                    "one_of" => {
+                        let values = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(tokens)?;
+                        if values.is_empty() {
+                            return Err(syn::Error::new(key.span(), "`one_of` needs at least one value"));
+                        }
+                        args.one_of = Some((values.into_iter().collect(), key.span()));
+                    }
+                    "flags" => {
+                        let definitions = Punctuated::<FlagDef, Token![,]>::parse_terminated.parse2(tokens)?;
+                        if definitions.is_empty() {
+                            return Err(syn::Error::new(key.span(), "`flags` needs at least one `name = value` pair"));
+                        }
+                        let mut flags: Vec<(String, u128)> = Vec::new();
+                        for FlagDef { name, bits } in definitions {
+                            let lower = name.to_string().to_lowercase();
+                            if flags.iter().any(|(existing, _)| existing.to_lowercase() == lower) {
+                                return Err(syn::Error::new(name.span(), format!("Duplicate flag `{}`; names are matched case-insensitively", name)));
+                            }
+                            flags.push((name.to_string(), bits));
+                        }
+                        args.flags = Some((flags, key.span()));
+                    }
+                    "options" => {
+                        let definitions = Punctuated::<OptionDef, Token![,]>::parse_terminated.parse2(tokens)?;
+                        if definitions.is_empty() {
+                            return Err(syn::Error::new(key.span(), "`options` needs at least one `name = expression` pair"));
+                        }
+                        let mut options: Vec<(String, Expr)> = Vec::new();
+                        for OptionDef { name, value } in definitions {
+                            let lower = name.to_string().to_lowercase();
+                            if options.iter().any(|(existing, _)| existing.to_lowercase() == lower) {
+                                return Err(syn::Error::new(name.span(), format!("Duplicate option `{}`; names are matched case-insensitively", name)));
+                            }
+                            options.push((name.to_string(), value));
+                        }
+                        args.options = Some((options, key.span()));
+                    }
+                    "split_into" => {
+                        let definitions = Punctuated::<SplitDef, Token![,]>::parse_terminated.parse2(tokens)?;
+                        if definitions.is_empty() {
+                            return Err(syn::Error::new(key.span(), "`split_into` needs at least one `NAME = field` pair"));
+                        }
+                        let mut members: Vec<(syn::Ident, usize)> = Vec::new();
+                        for SplitDef { name, index } in definitions {
+                            if members.iter().any(|(existing, _)| *existing == name) {
+                                return Err(syn::Error::new(name.span(), format!("`{}` is named more than once in `split_into`", name)));
+                            }
+                            members.push((name, index));
+                        }
+                        args.split_into = Some((members, key.span()));
+                    }
+                    "any_set" | "all_set" | "not" => {
+                        if let Some((presence, _)) = &args.presence {
+                            return Err(syn::Error::new(key.span(), format!("`{}` cannot be combined with `{}`; nest one inside the other instead", key, presence.keyword())));
+                        }
+                        args.presence = Some((crate::presence::Presence::parse_list(&key, tokens)?, key.span()));
+                    }
+                    _ => unreachable!("argument is listed as known but not handled"),
+                },
+                Arg::Flag(flag) => match flag.to_string().as_str() {
+                    "required" => args.required = true,
+                    "optional" => {
+                        args.optional = true;
+                        optional_span = flag.span();
+                    }
+                    "placeholder" => args.placeholder = Some(flag.span()),
+                    "expand" => expand = true,
+                    "per_target" => args.per_target = Some(flag.span()),
+                    "expr" => args.expr = Some(flag.span()),
+                    "ignore_case" => args.ignore_case = true,
+                    "sensitive" => args.sensitive = true,
+                    "warn_redundant" => args.warn_redundant = true,
+                    "exists" => args.exists = Some(flag.span()),
+                    "file" => args.file = true,
+                    "file_fallback" => args.file_fallback = true,
+                    "include" => args.include = Some(flag.span()),
+                    "include_expr" => args.include_expr = Some(flag.span()),
+                    "sort" => args.sort = Some(flag.span()),
+                    "allow_system_var" => args.allow_system_var = true,
+                    "allow_warnings" => args.allow_warnings = true,
+                    "cargo_metadata" => args.cargo_metadata = true,
+                    "untracked" => args.untracked = true,
+                    "emit_var_name" => args.emit_var_name = Some(flag.span()),
+                    "emit_default" => args.emit_default = true,
+                    "emit_len" => args.emit_len = Some(flag.span()),
+                    "only_when_overridden" => {
+                        args.only_when_overridden = true;
+                        only_when_overridden_span = flag.span();
+                    }
+                    "no_doc" => args.no_doc = true,
+                    "register" => args.register = true,
+                    "runtime_fallback" => args.runtime_fallback = Some(flag.span()),
+                    "emit_runtime_getter" => args.emit_runtime_getter = Some(flag.span()),
+                    "decimal_comma" => args.decimal_comma = Some(flag.span()),
+                    "partial" => args.partial = Some(flag.span()),
+                    "keep_empty" => {
+                        args.keep_empty = true;
+                        keep_empty_span = flag.span();
+                    }
+                    "doc_override" => {
+                        args.doc_override = true;
+                        doc_override_span = flag.span();
+                    }
+                    "verify_default" => {
+                        args.verify_default = true;
+                        verify_default_span = flag.span();
+                    }
+                    "dedup" => args.dedup = Some(flag.span()),
+                    "trim_newline" => {
+                        args.trim_newline = true;
+                        trim_newline_span = flag.span();
+                    }
+                    "invert" => args.invert = Some(flag.span()),
+                    "strict_fields" => args.strict_fields = Some(flag.span()),
+                    "strict_precision" => args.strict_precision = Some(flag.span()),
+                    "ranges" => args.ranges = Some(flag.span()),
+                    "obfuscate" => {
+                        args.obfuscate = Some(flag.span());
+                        args.sensitive = true;
+                    }
+                    _ => unreachable!("argument is listed as known but not handled"),
+                },
+            }
+        }
+        match (expand, expand_missing) {
+            (true, missing) => args.expand = Some(missing.unwrap_or(MissingRef::Error)),
+            (false, Some(_)) => return Err(syn::Error::new(expand_missing_span, "`expand_missing` requires the `expand` flag")),
+            (false, None) => {}
+        }
+        if args.doc_override && args.no_doc {
+            return Err(syn::Error::new(doc_override_span, "`doc_override` cannot be combined with `no_doc`"));
+        }
+        if args.only_when_overridden && !args.emit_default {
+            return Err(syn::Error::new(only_when_overridden_span, "`only_when_overridden` requires the `emit_default` flag"));
+        }
+        if args.trim_newline && !args.file && !args.file_fallback {
+            return Err(syn::Error::new(trim_newline_span, "`trim_newline` requires the `file` or `file_fallback` flag"));
+        }
+        if let Some(out_file) = &args.out_file {
+            let conflicts = [
+                ("a variable name", args.var_name.is_some()),
+                ("`format`", args.format.is_some()),
+                ("`debug_var`", args.debug_var.is_some()),
+                ("`release_var`", args.release_var.is_some()),
+                ("`per_target`", args.per_target.is_some()),
+                ("`file`", args.file),
+                ("`file_fallback`", args.file_fallback),
+                ("`command`", args.command.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(out_file.span(), format!("`out_file` cannot be combined with {}", conflict)));
+            }
+        }
+        if let (Some(command), Some(_)) = (&args.command, &args.format) {
+            return Err(syn::Error::new(command.span(), "`command` cannot be combined with `format`"));
+        }
+        if let (Some(var_name), Some(_)) = (&args.var_name, &args.format) {
+            return Err(syn::Error::new(var_name.span(), "A variable name cannot be combined with `format`"));
+        }
+        if let (Some(placeholder), Some(_)) = (args.placeholder, &args.format) {
+            return Err(syn::Error::new(placeholder, "`placeholder` cannot be combined with `format`"));
+        }
+        if let (Some(profile_var), Some(_)) = (args.debug_var.as_ref().or(args.release_var.as_ref()), &args.format) {
+            return Err(syn::Error::new(profile_var.span(), "`debug_var` and `release_var` cannot be combined with `format`"));
+        }
+        if let Some(expr) = args.expr {
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("case", args.case.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(expr, format!("`expr` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(include) = args.include {
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("case", args.case.is_some()),
+                ("expr", args.expr.is_some()),
+                ("file", args.file),
+                ("file_fallback", args.file_fallback),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(include, format!("`include` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(include_expr) = args.include_expr {
+            // The file holds the whole initializer, which no other argument shapes.
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("out_file", args.out_file.is_some()),
+                ("prefix_map", args.prefix_map.is_some()),
+                ("any_set", matches!(&args.presence, Some((crate::presence::Presence::Any(_), _)))),
+                ("all_set", matches!(&args.presence, Some((crate::presence::Presence::All(_), _)))),
+                ("not", matches!(&args.presence, Some((crate::presence::Presence::Not(_), _)))),
+                ("split_into", args.split_into.is_some()),
+                ("file", args.file),
+                ("file_fallback", args.file_fallback),
+                ("include", args.include.is_some()),
+                ("expr", args.expr.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("case", args.case.is_some()),
+                ("flags", args.flags.is_some()),
+                ("options", args.options.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("pad", args.pad.is_some()),
+                ("endian", args.endian.is_some()),
+                ("split", args.path_list.is_some()),
+                ("decimal_comma", args.decimal_comma.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+                ("json_pointer", args.json_pointer.is_some()),
+                ("field", args.field.is_some()),
+                ("capture", args.capture.is_some()),
+                ("obfuscate", args.obfuscate.is_some()),
+                ("emit_runtime_getter", args.emit_runtime_getter.is_some()),
+                ("runtime_fallback", args.runtime_fallback.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(include_expr, format!("`include_expr` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(emit_var_name) = args.emit_var_name {
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("out_file", args.out_file.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(emit_var_name, format!("`emit_var_name` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(runtime_fallback) = args.runtime_fallback {
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("out_file", args.out_file.is_some()),
+                ("file", args.file),
+                ("file_fallback", args.file_fallback),
+                ("assert", !args.asserts.is_empty()),
+                ("emit_len", args.emit_len.is_some()),
+                // The variable read at run time is the item's own, not one chosen for the
+                // target or profile.
+                ("per_target", args.per_target.is_some()),
+                ("debug_var", args.debug_var.is_some()),
+                ("release_var", args.release_var.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(runtime_fallback, format!("`runtime_fallback` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(emit_runtime_getter) = args.emit_runtime_getter {
+            // Sources other than the variable itself have no run time equivalent.
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("out_file", args.out_file.is_some()),
+                ("command", args.command.is_some()),
+                ("debug_var", args.debug_var.is_some()),
+                ("release_var", args.release_var.is_some()),
+                ("file", args.file),
+                ("file_fallback", args.file_fallback),
+                ("include", args.include.is_some()),
+                ("expr", args.expr.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("expand", args.expand.is_some()),
+                ("flags", args.flags.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(emit_runtime_getter, format!("`emit_runtime_getter` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let (Some(per_target), Some(_)) = (args.per_target, &args.format) {
+            return Err(syn::Error::new(per_target, "`per_target` cannot be combined with `format`"));
+        }
+        if args.optional && args.required {
+            return Err(syn::Error::new(optional_span, "`optional` cannot be combined with `required`"));
+        }
+        if let (Some((_, round)), None) = (args.round, args.fixed_point) {
+            return Err(syn::Error::new(round, "`round` requires `fixed_point`"));
+        }
+        if let Some((_, fixed_point)) = args.fixed_point {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("expr", args.expr.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(fixed_point, format!("`fixed_point` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some((_, pad)) = args.pad {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("expr", args.expr.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(pad, format!("`pad` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some((_, endian)) = args.endian {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("options", args.options.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("pad", args.pad.is_some()),
+                ("case", args.case.is_some()),
+                ("decimal_comma", args.decimal_comma.is_some()),
+                ("expr", args.expr.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(endian, format!("`endian` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(ranges) = args.ranges {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("options", args.options.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("pad", args.pad.is_some()),
+                ("endian", args.endian.is_some()),
+                ("case", args.case.is_some()),
+                ("split", args.path_list.is_some()),
+                ("decimal_comma", args.decimal_comma.is_some()),
+                ("expr", args.expr.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("include", args.include.is_some()),
+                ("include_expr", args.include_expr.is_some()),
+                ("json_pointer", args.json_pointer.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(ranges, format!("`ranges` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some((_, options)) = args.options {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("pad", args.pad.is_some()),
+                ("case", args.case.is_some()),
+                ("expr", args.expr.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(options, format!("`options` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if args.path_list.is_none() {
+            if let Some((_, path_sep)) = args.path_sep {
+                return Err(syn::Error::new(path_sep, "`path_sep` requires `split = \"path_list\"`"));
+            }
+            if args.keep_empty {
+                return Err(syn::Error::new(keep_empty_span, "`keep_empty` requires `split = \"path_list\"`"));
+            }
+        }
+        if let Some(path_list) = args.path_list {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("options", args.options.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("pad", args.pad.is_some()),
+                ("decimal_comma", args.decimal_comma.is_some()),
+                ("expr", args.expr.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(path_list, format!("`split` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let (Some((_, field_sep)), None) = (args.field_sep, args.field) {
+            return Err(syn::Error::new(field_sep, "`field_sep` requires `field`"));
+        }
+        if let Some((_, field)) = args.field {
+            let conflicts = [
+                ("split", args.path_list.is_some()),
+                ("json_pointer", args.json_pointer.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(field, format!("`field` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if args.split_into.is_none() {
+            if let Some((_, split_sep)) = args.split_sep {
+                return Err(syn::Error::new(split_sep, "`split_sep` requires `split_into`"));
+            }
+            if let Some(strict_fields) = args.strict_fields {
+                return Err(syn::Error::new(strict_fields, "`strict_fields` requires `split_into`"));
+            }
+        }
+        if let Some((_, split_into)) = args.split_into {
+            // Each item reads its own field, as if it had `field = N`.
+            let conflicts = [
+                ("format", args.format.is_some()),
+                ("out_file", args.out_file.is_some()),
+                ("prefix_map", args.prefix_map.is_some()),
+                ("field", args.field.is_some()),
+                ("field_sep", args.field_sep.is_some()),
+                ("split", args.path_list.is_some()),
+                ("json_pointer", args.json_pointer.is_some()),
+                ("include", args.include.is_some()),
+                ("obfuscate", args.obfuscate.is_some()),
+                ("emit_runtime_getter", args.emit_runtime_getter.is_some()),
+                ("runtime_fallback", args.runtime_fallback.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(split_into, format!("`split_into` cannot be combined with `{}`", conflict)));
+            }
+        }
+        match (&args.capture, &args.capture_group) {
+            (None, Some((_, capture_group))) => return Err(syn::Error::new(*capture_group, "`capture_group` requires `capture`")),
+            (Some((regex, _)), Some((group, span))) if regex.group_index(group).is_none() => {
+                let message = match group {
+                    crate::regex::CaptureGroup::Name(name) => format!("The `capture` pattern has no group named `{}`", name),
+                    crate::regex::CaptureGroup::Index(index) => format!("The `capture` pattern has no group {}; it has {} groups", index, regex.group_count()),
+                };
+                return Err(syn::Error::new(*span, message));
+            }
+            _ => {}
+        }
+        if let Some((_, capture)) = args.capture {
+            let conflicts = [
+                ("split", args.path_list.is_some()),
+                ("json_pointer", args.json_pointer.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(capture, format!("`capture` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some((_, json_pointer)) = args.json_pointer {
+            let conflicts = [
+                ("flags", args.flags.is_some()),
+                ("options", args.options.is_some()),
+                ("fixed_point", args.fixed_point.is_some()),
+                ("pad", args.pad.is_some()),
+                ("split", args.path_list.is_some()),
+                ("decimal_comma", args.decimal_comma.is_some()),
+                ("expr", args.expr.is_some()),
+                ("exists", args.exists.is_some()),
+                ("invert", args.invert.is_some()),
+                ("placeholder", args.placeholder.is_some()),
+                ("include", args.include.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(json_pointer, format!("`json_pointer` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some(obfuscate) = args.obfuscate {
+            // The plaintext would appear in the expansion or in the registry, and values
+            // chosen by `cfg!` would need obfuscating separately.
+            let conflicts = [
+                ("emit_default", args.emit_default),
+                ("register", args.register),
+                ("emit_runtime_getter", args.emit_runtime_getter.is_some()),
+                ("runtime_fallback", args.runtime_fallback.is_some()),
+                ("assert", !args.asserts.is_empty()),
+                ("when", args.when.is_some()),
+                ("debug_var", args.debug_var.is_some()),
+                ("release_var", args.release_var.is_some()),
+                ("expr", args.expr.is_some()),
+                ("include", args.include.is_some()),
+                ("split", args.path_list.is_some()),
+                ("pad", args.pad.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(obfuscate, format!("`obfuscate` cannot be combined with `{}`", conflict)));
+            }
+        }
+        if let Some((_, prefix_map)) = args.prefix_map {
+            // The pairs are taken as they are, from every variable under the prefix.
+            let conflicts = [
+                ("a variable name", args.var_name.is_some()),
+                ("`format`", args.format.is_some()),
+                ("`out_file`", args.out_file.is_some()),
+                ("`command`", args.command.is_some()),
+                ("`debug_var`", args.debug_var.is_some()),
+                ("`release_var`", args.release_var.is_some()),
+                ("`per_target`", args.per_target.is_some()),
+                ("`file`", args.file),
+                ("`file_fallback`", args.file_fallback),
+                ("`include`", args.include.is_some()),
+                ("`cargo_metadata`", args.cargo_metadata),
+                ("`ignore_case`", args.ignore_case),
+                ("`expand`", args.expand.is_some()),
+                ("`expr`", args.expr.is_some()),
+                ("`placeholder`", args.placeholder.is_some()),
+                ("`case`", args.case.is_some()),
+                ("`flags`", args.flags.is_some()),
+                ("`options`", args.options.is_some()),
+                ("`fixed_point`", args.fixed_point.is_some()),
+                ("`pad`", args.pad.is_some()),
+                ("`split`", args.path_list.is_some()),
+                ("`decimal_comma`", args.decimal_comma.is_some()),
+                ("`exists`", args.exists.is_some()),
+                ("`invert`", args.invert.is_some()),
+                ("`json_pointer`", args.json_pointer.is_some()),
+                ("`field`", args.field.is_some()),
+                ("`capture`", args.capture.is_some()),
+                ("`one_of`", args.one_of.is_some()),
+                ("`min`", args.min.is_some()),
+                ("`max`", args.max.is_some()),
+                ("`sort`", args.sort.is_some()),
+                ("`dedup`", args.dedup.is_some()),
+                ("`required`", args.required),
+                ("`emit_var_name`", args.emit_var_name.is_some()),
+                ("`emit_runtime_getter`", args.emit_runtime_getter.is_some()),
+                ("`runtime_fallback`", args.runtime_fallback.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(prefix_map, format!("`prefix_map` cannot be combined with {}", conflict)));
+            }
+        }
+        if let Some((presence, presence_span)) = &args.presence {
+            // Only whether the listed variables are set is read, never a value.
+            let conflicts = [
+                ("a variable name", args.var_name.is_some()),
+                ("`format`", args.format.is_some()),
+                ("`out_file`", args.out_file.is_some()),
+                ("`command`", args.command.is_some()),
+                ("`prefix_map`", args.prefix_map.is_some()),
+                ("`debug_var`", args.debug_var.is_some()),
+                ("`release_var`", args.release_var.is_some()),
+                ("`per_target`", args.per_target.is_some()),
+                ("`file`", args.file),
+                ("`file_fallback`", args.file_fallback),
+                ("`include`", args.include.is_some()),
+                ("`cargo_metadata`", args.cargo_metadata),
+                ("`expand`", args.expand.is_some()),
+                ("`exists`", args.exists.is_some()),
+                ("`expr`", args.expr.is_some()),
+                ("`placeholder`", args.placeholder.is_some()),
+                ("`case`", args.case.is_some()),
+                ("`flags`", args.flags.is_some()),
+                ("`options`", args.options.is_some()),
+                ("`json_pointer`", args.json_pointer.is_some()),
+                ("`field`", args.field.is_some()),
+                ("`capture`", args.capture.is_some()),
+                ("`one_of`", args.one_of.is_some()),
+                ("`required`", args.required),
+                ("`emit_runtime_getter`", args.emit_runtime_getter.is_some()),
+                ("`runtime_fallback`", args.runtime_fallback.is_some()),
+                ("`obfuscate`", args.obfuscate.is_some()),
+            ];
+            if let Some((conflict, _)) = conflicts.iter().find(|(_, present)| *present) {
+                return Err(syn::Error::new(*presence_span, format!("`{}` cannot be combined with {}", presence.keyword(), conflict)));
+            }
+        }
+        if args.verify_default && args.sha256.is_none() {
+            return Err(syn::Error::new(verify_default_span, "`verify_default` requires `sha256`"));
+        }
+        if let (Some((min_items, span)), Some((max_items, _))) = (args.min_items, args.max_items) {
+            if min_items > max_items {
+                return Err(syn::Error::new(span, "`min_items` cannot be greater than `max_items`"));
+            }
+        }
+        if let (Some(min), Some(max)) = (&args.min, &args.max) {
+            if value::compare(min, max) == Some(std::cmp::Ordering::Greater) {
+                return Err(syn::Error::new(min.span(), "`min` cannot be greater than `max`"));
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Builds the error for an argument that is not in `known`, the list of arguments
+/// accepted in the form it was written in.
+fn unknown_argument(key: &syn::Ident, known: &[&str]) -> syn::Error {
+    let name = key.to_string();
+    let usage = if KEY_VALUE_ARGS.contains(&name.as_str()) {
+        Some(format!("`{}` expects a value: `{} = ...`", name, name))
+    } else if LIST_ARGS.contains(&name.as_str()) {
+        Some(format!("`{}` expects arguments: `{}(...)`", name, name))
+    } else if FLAG_ARGS.contains(&name.as_str()) {
+        Some(format!("`{}` is a flag and does not take a value", name))
+    } else {
+        None
+    };
+    if let Some(usage) = usage {
+        return syn::Error::new(key.span(), usage);
+    }
+    let suggestion = KEY_VALUE_ARGS.iter()
+        .chain(LIST_ARGS)
+        .chain(FLAG_ARGS)
+        .map(|candidate| (edit_distance(&name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= std::cmp::max(1, name.len() / 3))
+        .min_by_key(|(distance, _)| *distance);
+    let message = match suggestion {
+        Some((_, candidate)) => format!("Unknown attribute argument `{}`, did you mean `{}`?", name, candidate),
+        None => {
+            let expected: Vec<String> = known.iter().map(|candidate| format!("`{}`", candidate)).collect();
+            format!("Unknown attribute argument `{}`, expected one of {}", name, expected.join(", "))
+        }
+    };
+    syn::Error::new(key.span(), message)
+}
+
+/// Levenshtein distance between two strings.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A `name = bits` pair of `flags(...)`.
+struct FlagDef {
+    name: syn::Ident,
+    bits: u128,
+}
+
+impl Parse for FlagDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let bits: syn::LitInt = input.parse()?;
+        Ok(FlagDef { name, bits: bits.base10_parse()? })
+    }
+}
+
+/// A `name = expression` pair of `options(...)`.
+struct OptionDef {
+    name: syn::Ident,
+    value: Expr,
+}
+
+impl Parse for OptionDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        Ok(OptionDef { name, value: input.parse()? })
+    }
+}
+
+/// A `NAME = field` pair of `split_into(...)`.
+struct SplitDef {
+    name: syn::Ident,
+    index: usize,
+}
+
+impl Parse for SplitDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let index: syn::LitInt = input.parse()?;
+        Ok(SplitDef { name, index: index.base10_parse()? })
+    }
+}
+
+fn count_lit(expr: &Expr) -> syn::Result<usize> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(lit_int), .. }) => lit_int.base10_parse(),
+        otherwise => Err(syn::Error::new(otherwise.span(), "Expected an integer literal")),
+    }
+}
+
+/// The single character of a separator such as `field_sep = "|"`, which cannot be the
+/// backslash that escapes it.
+fn separator_lit(key: &syn::Ident, expr: &Expr) -> syn::Result<char> {
+    let separator = string_lit(expr)?;
+    let value = separator.value();
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some('\\'), None) => Err(syn::Error::new(separator.span(), format!("`{}` cannot be a backslash, which escapes the separator", key))),
+        (Some(c), None) => Ok(c),
+        _ => Err(syn::Error::new(separator.span(), format!("Expected `{}` to be a single character", key))),
+    }
+}
+
+fn number_lit(expr: &Expr) -> syn::Result<Expr> {
+    if value::is_number(expr) {
+        Ok(expr.clone())
+    } else {
+        Err(syn::Error::new(expr.span(), "Expected a number literal"))
+    }
+}
+
+fn string_lit(expr: &Expr) -> syn::Result<LitStr> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit_str), .. }) => Ok(lit_str.clone()),
+        otherwise => Err(syn::Error::new(otherwise.span(), "Expected a string literal")),
+    }
+}
+
+pub(crate) fn parse_item_args(attr: TokenStream) -> syn::Result<ItemArgs> {
+    if attr.is_empty() {
+        return Ok(ItemArgs::default());
+    }
+    syn::parse2(attr)
+}
@@ -0,0 +1,52 @@
+//! Lists every item of a source tree that is configured by the environment, with the
+//! variables it reads and its default.
+//!
+//! ```text
+//! const-env-scan [--json] [DIR]
+//! ```
+//!
+//! Exits with status 1 if an attribute's variables cannot be named without building the
+//! crate, so that CI can flag it, and with status 2 on other errors.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: const-env-scan [--json] [DIR]\n\nLists the items under DIR, or the current directory, that are configured by environment variables.";
+
+fn main() -> ExitCode {
+    let mut json = false;
+    let mut root = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') || root.is_some() => {
+                eprintln!("Unexpected argument `{}`\n\n{}", arg, USAGE);
+                return ExitCode::from(2);
+            }
+            _ => root = Some(PathBuf::from(arg)),
+        }
+    }
+    let root = root.unwrap_or_else(|| PathBuf::from("."));
+    let scan = match const_env_impl::scan_dir(&root) {
+        Ok(scan) => scan,
+        Err(err) => {
+            eprintln!("Failed to scan `{}`: {}", root.display(), err);
+            return ExitCode::from(2);
+        }
+    };
+    match json {
+        true => println!("{}", scan.to_json()),
+        false => print!("{}", scan.to_table()),
+    }
+    for unresolved in &scan.unresolved {
+        eprintln!("{}:{}: {}", unresolved.file.display(), unresolved.line, unresolved.message);
+    }
+    match scan.unresolved.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
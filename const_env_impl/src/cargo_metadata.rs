@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::toml::{self, TomlError};
+use crate::{EnvError, ReadEnv};
+
+/// Variables read from the `[package.metadata.const_env]` table of a crate's manifest,
+/// falling back to the `[workspace.metadata.const_env]` table of its workspace.
+///
+/// Keys are variable names, and keys of nested tables are joined with their table names
+/// by `_`. Values are converted as by `TomlEnv`. Only these tables are parsed, so the
+/// rest of the manifest may use any TOML syntax.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CargoMetadataEnv {
+    package: HashMap<String, String>,
+    workspace: HashMap<String, String>,
+}
+
+impl CargoMetadataEnv {
+    /// Reads the metadata of the crate whose manifest is in `manifest_dir`, and of the
+    /// workspace found by searching it and its parents for a manifest with a
+    /// `[workspace]` table.
+    pub fn from_manifest_dir(manifest_dir: impl AsRef<Path>) -> Result<Self, TomlError> {
+        let manifest_dir = manifest_dir.as_ref();
+        let manifest = manifest_dir.join("Cargo.toml");
+        let source = read(&manifest)?;
+        let package = section(&manifest, &source, &["package", "metadata", "const_env"])?;
+        let mut workspace = HashMap::new();
+        for dir in manifest_dir.ancestors() {
+            let candidate = dir.join("Cargo.toml");
+            let source = match dir == manifest_dir {
+                true => source.clone(),
+                false if candidate.is_file() => read(&candidate)?,
+                false => continue,
+            };
+            if source.lines().any(|line| line.trim() == "[workspace]") {
+                workspace = section(&candidate, &source, &["workspace", "metadata", "const_env"])?;
+                break;
+            }
+        }
+        Ok(CargoMetadataEnv { package, workspace })
+    }
+
+    /// Reads the `[package.metadata.const_env]` table of the manifest at `path`, with no
+    /// workspace fallback.
+    pub fn from_manifest(path: impl AsRef<Path>) -> Result<Self, TomlError> {
+        let path = path.as_ref();
+        let package = section(path, &read(path)?, &["package", "metadata", "const_env"])?;
+        Ok(CargoMetadataEnv { package, workspace: HashMap::new() })
+    }
+}
+
+fn read(path: &Path) -> Result<String, TomlError> {
+    std::fs::read_to_string(path).map_err(|error| TomlError::Io { path: path.to_path_buf(), error })
+}
+
+fn section(path: &Path, source: &str, name: &[&str]) -> Result<HashMap<String, String>, TomlError> {
+    let entries = toml::parse_section(source, name).map_err(|err| TomlError::Parse {
+        path: Some(path.to_path_buf()),
+        line: err.line,
+        key: err.key,
+        message: err.message,
+    })?;
+    Ok(entries.into_iter().map(|entry| (entry.path.join("_"), entry.value.to_env_string())).collect())
+}
+
+impl ReadEnv for CargoMetadataEnv {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.package.get(var_name).or_else(|| self.workspace.get(var_name)).map(|value| Cow::Borrowed(OsStr::new(value))))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.package.keys().chain(self.workspace.keys()).cloned().collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// The metadata of the crate being compiled, loaded on first use so that the manifest is
+/// read at most once per macro invocation.
+pub(crate) struct CargoMetadata {
+    loaded: OnceCell<Result<CargoMetadataEnv, String>>,
+}
+
+impl CargoMetadata {
+    pub(crate) fn new() -> Self {
+        CargoMetadata { loaded: OnceCell::new() }
+    }
+
+    /// Looks `key` up in the metadata of the crate named by `CARGO_MANIFEST_DIR`, or
+    /// returns `None` if that variable is unset.
+    pub(crate) fn get(&self, key: &str, read_env: &impl ReadEnv) -> Result<Option<String>, String> {
+        let metadata = self.loaded.get_or_init(|| match read_env.read_env("CARGO_MANIFEST_DIR").map_err(|err| err.to_string())? {
+            Some(manifest_dir) => CargoMetadataEnv::from_manifest_dir(PathBuf::from(&*manifest_dir)).map_err(|err| err.to_string()),
+            None => Ok(CargoMetadataEnv::default()),
+        });
+        match metadata {
+            Ok(metadata) => metadata.read_env_utf8(key).map_err(|err| err.to_string()),
+            Err(message) => Err(message.clone()),
+        }
+    }
+}
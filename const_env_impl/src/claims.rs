@@ -0,0 +1,109 @@
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+
+use crate::registry::{escape, unescape};
+use crate::ReadEnv;
+
+/// An item reading a variable, identified by where it is declared so that items of the
+/// same name in different modules are told apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Claim {
+    file: String,
+    line: usize,
+    ident: String,
+    var_name: String,
+    /// The modification time of `file` when the item was expanded, or `-` if unknown.
+    modified: String,
+}
+
+impl Claim {
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\n", escape(&self.file), self.line, escape(&self.ident), escape(&self.var_name), self.modified)
+    }
+
+    fn from_line(line: &str) -> Option<Claim> {
+        match line.split('\t').collect::<Vec<&str>>().as_slice() {
+            [file, line, ident, var_name, modified] => Some(Claim {
+                file: unescape(file),
+                line: line.parse().ok()?,
+                ident: unescape(ident),
+                var_name: unescape(var_name),
+                modified: modified.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn same_item(&self, other: &Claim) -> bool {
+        (&self.file, self.line, &self.ident) == (&other.file, other.line, &other.ident)
+    }
+}
+
+/// Records that `ident`, declared at `file:line`, reads `var_name`, returning the item
+/// and location of another item that already claimed the variable. Outside cargo, which
+/// sets `CARGO_CRATE_NAME`, invocations cannot be told apart by crate, so nothing is
+/// recorded.
+///
+/// Macro invocations cannot see each other, so claims are kept in a file shared by all
+/// invocations for the crate, and by every target of the package, which expand the same
+/// items. A long-lived compiler process such as rust-analyzer expands items again as
+/// they are edited, so a claim is dropped once its source file has changed or is gone:
+/// the items still in the file claim their variables again when they are re-expanded.
+pub(crate) fn claim(var_name: &str, ident: &str, file: &str, line: usize, read_env: &impl ReadEnv) -> Result<Option<String>, String> {
+    let path = match claims_path(read_env)? {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let mut handle = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)
+        .map_err(|err| format!("Failed to open `{}`: {}", path.display(), err))?;
+    handle.lock().map_err(|err| format!("Failed to lock `{}`: {}", path.display(), err))?;
+
+    let mut contents = String::new();
+    handle.read_to_string(&mut contents)
+        .map_err(|err| format!("Failed to read `{}`: {}", path.display(), err))?;
+    let claim = Claim {
+        file: file.to_string(),
+        line,
+        ident: ident.to_string(),
+        var_name: var_name.to_string(),
+        modified: crate::file::modified_time(file),
+    };
+    let mut current: Vec<(String, String)> = vec![(claim.file.clone(), claim.modified.clone())];
+    let mut claims = Vec::new();
+    for existing in contents.lines().filter_map(Claim::from_line) {
+        let modified = match current.iter().find(|(file, _)| *file == existing.file) {
+            Some((_, modified)) => modified.clone(),
+            None => {
+                let modified = crate::file::modified_time(&existing.file);
+                current.push((existing.file.clone(), modified.clone()));
+                modified
+            }
+        };
+        if existing.modified == modified && !existing.same_item(&claim) {
+            claims.push(existing);
+        }
+    }
+    let previous = claims.iter()
+        .find(|existing| existing.var_name == claim.var_name)
+        .map(|existing| format!("`{}` at {}:{}", existing.ident, existing.file, existing.line));
+    claims.push(claim);
+
+    let contents: String = claims.iter().map(Claim::to_line).collect();
+    handle.set_len(0)
+        .and_then(|_| handle.rewind())
+        .and_then(|_| handle.write_all(contents.as_bytes()))
+        .map_err(|err| format!("Failed to write `{}`: {}", path.display(), err))?;
+    Ok(previous)
+}
+
+/// The claims file for the crate being compiled, in its `OUT_DIR` if it has one, or
+/// `None` outside cargo.
+fn claims_path(read_env: &impl ReadEnv) -> Result<Option<PathBuf>, String> {
+    let crate_name = match read_env.read_env_utf8("CARGO_CRATE_NAME").map_err(|err| err.to_string())? {
+        Some(crate_name) => crate_name,
+        None => return Ok(None),
+    };
+    let dir = read_env.read_env("OUT_DIR").map_err(|err| err.to_string())?
+        .map_or_else(std::env::temp_dir, |dir| PathBuf::from(&*dir));
+    Ok(Some(dir.join(format!("const_env_claims_{}.tsv", crate_name))))
+}
@@ -0,0 +1,25 @@
+use std::process::Command;
+
+use crate::{EnvError, ReadEnv};
+
+/// Runs `command` in the directory of the crate being compiled and returns its
+/// standard output with trailing whitespace removed, or `None` if the command could
+/// not be started or exited unsuccessfully. The command line is split on whitespace
+/// and run directly, without a shell. Fails only if the crate's directory cannot be
+/// read from the environment.
+pub(crate) fn run_command(command: &str, read_env: &impl ReadEnv) -> Result<Option<String>, EnvError> {
+    let mut words = command.split_whitespace();
+    let mut process = match words.next() {
+        Some(program) => Command::new(program),
+        None => return Ok(None),
+    };
+    process.args(words);
+    if let Some(manifest_dir) = read_env.read_env("CARGO_MANIFEST_DIR")? {
+        process.current_dir(&*manifest_dir);
+    }
+    let output = match process.output() {
+        Ok(output) if output.status.success() => output,
+        _ => return Ok(None),
+    };
+    Ok(String::from_utf8(output.stdout).ok().map(|stdout| stdout.trim_end().to_string()))
+}
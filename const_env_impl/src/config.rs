@@ -0,0 +1,105 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use syn::{Expr, ExprLit, ExprUnary, Lit};
+
+use crate::toml::{self, TomlValue};
+use crate::ReadEnv;
+
+/// Variable naming a TOML file of defaults consulted when a variable is unset.
+pub(crate) const CONFIG_VAR: &str = "CONST_ENV_CONFIG";
+
+/// Renders a value of the defaults file the way it would be written in an environment
+/// variable for an item whose default is `expr`, or returns `None` if the kinds do not
+/// match.
+fn to_env_value(value: &TomlValue, expr: &Expr) -> Option<String> {
+    let lit = match expr {
+        Expr::Lit(ExprLit { lit, .. }) => lit,
+        Expr::Unary(ExprUnary { expr, .. }) => match &**expr {
+            Expr::Lit(ExprLit { lit, .. }) => lit,
+            _ => return None,
+        },
+        // Anything else, such as an array, is written out as a string.
+        _ => return match value {
+            TomlValue::String(value) => Some(value.clone()),
+            _ => None,
+        },
+    };
+    match (value, lit) {
+        (TomlValue::Integer(value), Lit::Int(_)) | (TomlValue::Float(value), Lit::Float(_)) => Some(value.clone()),
+        // An integer written for a float default needs a fractional part to remain a
+        // float literal.
+        (TomlValue::Integer(value), Lit::Float(_)) if !value.contains(['x', 'o', 'b']) => Some(format!("{}.0", value)),
+        (TomlValue::Bool(value), Lit::Bool(_)) => Some(value.to_string()),
+        (TomlValue::String(value), Lit::Str(_) | Lit::ByteStr(_) | Lit::Char(_) | Lit::Byte(_) | Lit::Verbatim(_)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// The defaults file named by `CONST_ENV_CONFIG`, loaded on first use so that it is read
+/// at most once per macro invocation.
+pub(crate) struct ConfigFile {
+    loaded: OnceCell<Result<Option<Config>, String>>,
+}
+
+struct Config {
+    path: PathBuf,
+    values: HashMap<String, TomlValue>,
+}
+
+impl ConfigFile {
+    pub(crate) fn new() -> Self {
+        ConfigFile { loaded: OnceCell::new() }
+    }
+
+    /// Looks up `key` in the defaults file, returning its value as it would appear in an
+    /// environment variable. Fails if the file cannot be read or parsed, or if the value
+    /// is of a different kind than the default `expr`.
+    pub(crate) fn get(&self, key: &str, expr: &Expr, read_env: &impl ReadEnv) -> Result<Option<String>, String> {
+        let config = match self.loaded.get_or_init(|| load(read_env)) {
+            Ok(Some(config)) => config,
+            Ok(None) => return Ok(None),
+            Err(message) => return Err(message.clone()),
+        };
+        match config.values.get(key) {
+            Some(value) => to_env_value(value, expr).map(Some).ok_or_else(|| format!(
+                "Key `{}` in `{}` is {}, which does not match the type of the default", key, config.path.display(), value.kind())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads the defaults file, having it and `CONST_ENV_CONFIG` tracked for rebuilds, so that
+/// the expansion that consulted them is redone when either changes.
+fn load(read_env: &impl ReadEnv) -> Result<Option<Config>, String> {
+    crate::file::track_var(CONFIG_VAR, read_env);
+    let path = match read_env.read_env(CONFIG_VAR).map_err(|err| err.to_string())? {
+        Some(path) => PathBuf::from(&*path),
+        None => return Ok(None),
+    };
+    let resolved = crate::file::resolve_path(&path, read_env)?;
+    let source = std::fs::read_to_string(&resolved)
+        .map_err(|err| format!("Failed to read `{}` named by `{}`: {}", resolved.display(), CONFIG_VAR, err))?;
+    crate::file::track_file(crate::file::include_path(&path.to_string_lossy(), &resolved, read_env)?);
+    let values = parse_config(&source)
+        .map_err(|message| format!("Failed to parse `{}`: {}", path.display(), message))?;
+    Ok(Some(Config { path, values }))
+}
+
+/// Parses a TOML document consisting of top-level `key = value` pairs whose values are
+/// strings, integers, floats or booleans.
+fn parse_config(source: &str) -> Result<HashMap<String, TomlValue>, String> {
+    let mut values = HashMap::new();
+    for entry in toml::parse_document(source).map_err(|err| err.to_string())? {
+        let key = entry.path.join(".");
+        if entry.path.len() > 1 {
+            return Err(format!("line {}: tables are not supported, keys must be at the top level", entry.line));
+        }
+        if let TomlValue::Array(_) = entry.value {
+            return Err(format!("line {}, key `{}`: arrays and tables are not supported", entry.line, key));
+        }
+        values.insert(key, entry.value);
+    }
+    Ok(values)
+}
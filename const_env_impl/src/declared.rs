@@ -0,0 +1,389 @@
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::{explain, FromEnvError};
+
+const INT_TYPES: &[&str] = &["u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize"];
+const FLOAT_TYPES: &[&str] = &["f32", "f64"];
+
+/// A declared type whose literals are known, which decides how values are parsed
+/// instead of the kind of the default.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DeclaredType {
+    Int(String),
+    Float(String),
+    Bool,
+    Char,
+    Str,
+    CStr,
+    /// `[T; N]`, with `N` if it is a literal.
+    Array(Box<DeclaredType>, Option<usize>),
+    /// `&[T]`, or `&[T; N]` with `N` if it is a literal.
+    Slice(Box<DeclaredType>, Option<usize>),
+    /// `(T, U, ...)`, with at least one element.
+    Tuple(Vec<DeclaredType>),
+}
+
+/// A default or value that does not match the declared type.
+pub(crate) struct Mismatch {
+    expected: String,
+    found: String,
+    /// The index of the mismatched element, such as `[1]` or `.0` in a tuple, or empty for
+    /// the whole value.
+    at: String,
+    /// The index of the element of the whole value that holds the mismatch, if any.
+    pub(crate) element: Option<usize>,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.at.chars().next() {
+            None => write!(f, "expected {}, found {}", self.expected, self.found),
+            Some('.') => write!(f, "expected {}, found {} at field {}", self.expected, self.found, self.at),
+            Some(_) => write!(f, "expected {}, found {} at index {}", self.expected, self.found, self.at),
+        }
+    }
+}
+
+impl DeclaredType {
+    /// Recognizes the integer, float, `bool`, `char`, `&str` and `&CStr` types, and
+    /// arrays, slices, references to arrays and tuples of them.
+    pub(crate) fn from_type(ty: &syn::Type) -> Option<DeclaredType> {
+        match ty {
+            syn::Type::Paren(paren) => DeclaredType::from_type(&paren.elem),
+            syn::Type::Group(group) => DeclaredType::from_type(&group.elem),
+            syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                let name = path.get_ident()?.to_string();
+                match name.as_str() {
+                    name if INT_TYPES.contains(&name) => Some(DeclaredType::Int(name.to_string())),
+                    name if FLOAT_TYPES.contains(&name) => Some(DeclaredType::Float(name.to_string())),
+                    "bool" => Some(DeclaredType::Bool),
+                    "char" => Some(DeclaredType::Char),
+                    _ => None,
+                }
+            }
+            syn::Type::Array(array) => Some(DeclaredType::Array(Box::new(DeclaredType::from_type(&array.elem)?), array_len(&array.len))),
+            syn::Type::Tuple(tuple) if !tuple.elems.is_empty() => Some(DeclaredType::Tuple(tuple.elems.iter().map(DeclaredType::from_type).collect::<Option<_>>()?)),
+            syn::Type::Reference(syn::TypeReference { mutability: None, elem, .. }) => match &**elem {
+                syn::Type::Path(syn::TypePath { qself: None, path }) if path.is_ident("str") => Some(DeclaredType::Str),
+                syn::Type::Path(syn::TypePath { qself: None, path })
+                    if path.segments.last().is_some_and(|segment| segment.ident == "CStr" && segment.arguments.is_empty()) => Some(DeclaredType::CStr),
+                syn::Type::Slice(slice) => Some(DeclaredType::Slice(Box::new(DeclaredType::from_type(&slice.elem)?), None)),
+                syn::Type::Array(array) => Some(DeclaredType::Slice(Box::new(DeclaredType::from_type(&array.elem)?), array_len(&array.len))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            DeclaredType::Int(name) => format!("an integer that fits in `{}`", name),
+            DeclaredType::Float(name) => format!("a float that fits in `{}`", name),
+            DeclaredType::Bool => "a boolean".to_string(),
+            DeclaredType::Char => "a character".to_string(),
+            DeclaredType::Str => "a string".to_string(),
+            DeclaredType::CStr => "a C string".to_string(),
+            DeclaredType::Array(_, Some(len)) | DeclaredType::Slice(_, Some(len)) => format!("an array of {} elements", len),
+            DeclaredType::Array(..) | DeclaredType::Slice(..) => "an array".to_string(),
+            DeclaredType::Tuple(elems) => format!("a tuple of {} elements", elems.len()),
+        }
+    }
+
+    /// The kind of value expected for an item whose default is `expr`, for parse errors.
+    fn kind(&self, expr: &Expr) -> &'static str {
+        match self {
+            DeclaredType::Int(_) => "an integer",
+            DeclaredType::Float(_) => "a float",
+            DeclaredType::Bool => "a boolean",
+            DeclaredType::Char => "a character",
+            DeclaredType::Str => "a string",
+            DeclaredType::CStr => "a C string",
+            DeclaredType::Slice(elem, _) if **elem == DeclaredType::Int("u8".to_string()) => match expr {
+                Expr::Lit(ExprLit { lit: Lit::ByteStr(_), .. }) => "a byte string",
+                _ => "a list of `u8`",
+            },
+            DeclaredType::Array(..) | DeclaredType::Slice(..) => "an array",
+            DeclaredType::Tuple(_) => "a tuple",
+        }
+    }
+
+    /// Checks that the default `expr` is a literal, or an array of literals, of this type.
+    /// Other expressions, such as paths and calls, are left to the compiler. Every
+    /// mismatched element is reported.
+    pub(crate) fn check(&self, expr: &Expr) -> Result<(), Vec<Mismatch>> {
+        self.check_expr(expr, false)
+    }
+
+    /// Checks a value parsed from a variable, which unlike a default must be made of
+    /// literals: a name such as `abc` where a number is expected is a typo, not a
+    /// constant.
+    pub(crate) fn check_value(&self, expr: &Expr) -> Result<(), Vec<Mismatch>> {
+        self.check_expr(expr, true)
+    }
+
+    fn check_expr(&self, expr: &Expr, value: bool) -> Result<(), Vec<Mismatch>> {
+        let mismatch = |found: String| Err(vec![Mismatch { expected: self.describe(), found, at: String::new(), element: None }]);
+        let not_literal = || match value {
+            true => mismatch(format!("`{}`", crate::pretty::to_pretty_string(expr.to_token_stream()))),
+            false => Ok(()),
+        };
+        let (negative, lit) = match expr {
+            Expr::Lit(ExprLit { lit, .. }) => (false, lit),
+            Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => match &**expr {
+                Expr::Lit(ExprLit { lit, .. }) => (true, lit),
+                _ => return not_literal(),
+            },
+            Expr::Paren(paren) => return self.check_expr(&paren.expr, value),
+            Expr::Array(array) => return self.check_elements(array.elems.iter(), false, value),
+            Expr::Tuple(tuple) => return self.check_fields(tuple, value),
+            Expr::Repeat(repeat) => return match self {
+                DeclaredType::Array(elem, len) => {
+                    elem.check_expr(&repeat.expr, value).map_err(|mismatches| element(mismatches, 0))?;
+                    match (len, array_len(&repeat.len)) {
+                        (Some(len), Some(found)) if *len != found => mismatch(format!("an array of {} elements", found)),
+                        _ => Ok(()),
+                    }
+                }
+                _ => mismatch("an array".to_string()),
+            },
+            Expr::Reference(syn::ExprReference { mutability: None, expr, .. }) => return match &**expr {
+                Expr::Array(array) => self.check_elements(array.elems.iter(), true, value),
+                _ => not_literal(),
+            },
+            _ => return not_literal(),
+        };
+        if let Lit::Verbatim(verbatim) = lit {
+            let text = verbatim.to_string();
+            return match (self, text.starts_with("c\"") || text.starts_with("cr")) {
+                (DeclaredType::CStr, true) | (_, false) => Ok(()),
+                (_, true) => mismatch("a C string".to_string()),
+            };
+        }
+        let found = || match negative {
+            true => format!("`-{}`", lit.to_token_stream()),
+            false => format!("`{}`", lit.to_token_stream()),
+        };
+        match (self, lit) {
+            (DeclaredType::Int(name), Lit::Int(int)) if int.suffix().is_empty() || int.suffix() == name => {
+                let (min, max) = int_range(name).expect("integer types have a range");
+                match int.base10_parse::<u128>() {
+                    Ok(magnitude) if (negative && magnitude <= min) || (!negative && magnitude <= max) => Ok(()),
+                    _ => mismatch(found()),
+                }
+            }
+            (DeclaredType::Int(name), Lit::Byte(_)) if name == "u8" && !negative => Ok(()),
+            (DeclaredType::Float(name), Lit::Float(float)) if float.suffix().is_empty() || float.suffix() == name => {
+                match crate::float::overflows(float.base10_digits(), name) {
+                    true => mismatch(found()),
+                    false => Ok(()),
+                }
+            }
+            // `1f32` is lexed as an integer with a float suffix.
+            (DeclaredType::Float(name), Lit::Int(int)) if int.suffix() == name => Ok(()),
+            (DeclaredType::Bool, Lit::Bool(_)) | (DeclaredType::Char, Lit::Char(_)) | (DeclaredType::Str, Lit::Str(_)) if !negative => Ok(()),
+            (DeclaredType::Slice(elem, len), Lit::ByteStr(bytes)) if **elem == DeclaredType::Int("u8".to_string()) => match len {
+                Some(len) if *len != bytes.value().len() => mismatch(format!("a byte string of {} bytes", bytes.value().len())),
+                _ => Ok(()),
+            },
+            (_, Lit::Int(int)) if !int.suffix().is_empty() => mismatch(format!("an integer of type `{}`", int.suffix())),
+            (_, Lit::Float(float)) if !float.suffix().is_empty() => mismatch(format!("a float of type `{}`", float.suffix())),
+            (_, lit) => mismatch(lit_kind(lit).to_string()),
+        }
+    }
+
+    fn check_elements<'a>(&self, elems: impl ExactSizeIterator<Item = &'a Expr>, reference: bool, value: bool) -> Result<(), Vec<Mismatch>> {
+        let mismatch = |expected: String, found: String| Err(vec![Mismatch { expected, found, at: String::new(), element: None }]);
+        let (elem, len) = match (self, reference) {
+            (DeclaredType::Array(elem, len), false) | (DeclaredType::Slice(elem, len), true) => (elem, len),
+            (DeclaredType::Array(..), true) => return mismatch(self.describe(), "a reference to an array".to_string()),
+            (DeclaredType::Slice(..), false) => return mismatch(format!("a reference to {}", self.describe()), "an array".to_string()),
+            _ => return mismatch(self.describe(), "an array".to_string()),
+        };
+        let mut mismatches = Vec::new();
+        if let Some(len) = len {
+            if elems.len() != *len {
+                mismatches.push(Mismatch { expected: self.describe(), found: format!("an array of {} elements", elems.len()), at: String::new(), element: None });
+            }
+        }
+        for (index, expr) in elems.enumerate() {
+            if let Err(element_mismatches) = elem.check_expr(expr, value) {
+                mismatches.extend(element(element_mismatches, index));
+            }
+        }
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(mismatches),
+        }
+    }
+
+    fn check_fields(&self, tuple: &syn::ExprTuple, value: bool) -> Result<(), Vec<Mismatch>> {
+        let elems = match self {
+            DeclaredType::Tuple(elems) => elems,
+            _ => return Err(vec![Mismatch { expected: self.describe(), found: "a tuple".to_string(), at: String::new(), element: None }]),
+        };
+        if tuple.elems.len() != elems.len() {
+            return Err(vec![Mismatch { expected: self.describe(), found: format!("a tuple of {} elements", tuple.elems.len()), at: String::new(), element: None }]);
+        }
+        let mismatches: Vec<Mismatch> = elems.iter().zip(&tuple.elems).enumerate()
+            .filter_map(|(index, (elem, expr))| elem.check_expr(expr, value).err().map(|mismatches| (index, mismatches)))
+            .flat_map(|(index, mismatches)| mismatches.into_iter().map(move |mut mismatch| {
+                mismatch.at = format!(".{}{}", index, mismatch.at);
+                mismatch
+            }))
+            .collect();
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(mismatches),
+        }
+    }
+
+    /// A literal of this type to parse values like in place of `expr`, if `expr` is not
+    /// a literal of this type itself.
+    pub(crate) fn template_for(&self, expr: &Expr) -> Option<Expr> {
+        // Defaults such as paths, which the compiler checks, have no kind to parse values
+        // like.
+        let literal_default = match expr {
+            Expr::Lit(ExprLit { lit, .. }) => !matches!(lit, Lit::Verbatim(_)),
+            Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => matches!(&**expr, Expr::Lit(_)),
+            Expr::Array(_) | Expr::Tuple(_) => true,
+            Expr::Reference(reference) => matches!(&*reference.expr, Expr::Array(_)),
+            _ => false,
+        };
+        match literal_default && self.check(expr).is_ok() {
+            true => None,
+            false => Some(self.template(expr.span())),
+        }
+    }
+
+    /// A literal of this type that values are parsed like, for defaults that are not
+    /// literals themselves.
+    pub(crate) fn template(&self, span: Span) -> Expr {
+        let lit = match self {
+            DeclaredType::Int(_) => Lit::Int(syn::LitInt::new("0", span)),
+            DeclaredType::Float(_) => Lit::Float(syn::LitFloat::new("0.0", span)),
+            DeclaredType::Bool => Lit::Bool(syn::LitBool::new(false, span)),
+            DeclaredType::Char => Lit::Char(syn::LitChar::new(' ', span)),
+            DeclaredType::Str | DeclaredType::CStr => Lit::Str(syn::LitStr::new("", span)),
+            DeclaredType::Slice(elem, _) if **elem == DeclaredType::Int("u8".to_string()) => Lit::ByteStr(syn::LitByteStr::new(b"", span)),
+            DeclaredType::Array(..) => return syn::parse_quote_spanned!(span => []),
+            DeclaredType::Slice(..) => return syn::parse_quote_spanned!(span => &[]),
+            DeclaredType::Tuple(elems) => {
+                let elems = elems.iter().map(|elem| elem.template(span));
+                // The trailing comma keeps a tuple of one element from reading as parentheses.
+                return syn::parse_quote_spanned!(span => (#(#elems,)*));
+            }
+        };
+        Expr::Lit(ExprLit { attrs: Vec::new(), lit })
+    }
+}
+
+/// Places `mismatches` in the element at `index` of an enclosing array.
+fn element(mismatches: Vec<Mismatch>, index: usize) -> Vec<Mismatch> {
+    mismatches.into_iter().map(|mut mismatch| {
+        mismatch.at = format!("[{}]{}", index, mismatch.at);
+        mismatch.element = Some(index);
+        mismatch
+    }).collect()
+}
+
+/// The declared type of an item: the one named by `ty`, for aliases, or the written one.
+pub(crate) fn declared_type(args: &ItemArgs, ty: &syn::Type) -> Option<DeclaredType> {
+    match &args.ty {
+        Some((hint, _)) => DeclaredType::from_type(hint),
+        None => DeclaredType::from_type(ty),
+    }
+}
+
+/// Reports a default that does not match the item's declared type, which would
+/// otherwise fail later with a type error that does not mention the variable.
+pub(crate) fn check_default(declared: &DeclaredType, ident: &syn::Ident, ty: &syn::Type, args: &ItemArgs, expr: &Expr) -> syn::Result<()> {
+    declared.check(expr).map_err(|mismatches| {
+        let ty = match &args.ty {
+            Some((hint, _)) => hint.to_token_stream(),
+            None => ty.to_token_stream(),
+        };
+        let ty = crate::pretty::to_pretty_string(ty);
+        explain::combine(mismatches.iter().map(|mismatch| explain::error(explain::TYPE_MISMATCH, expr.span(), format!(
+            "The default of `{}` does not match its declared type `{}`: {}", ident, ty, mismatch))))
+            .expect("a mismatch is reported with at least one problem")
+    })
+}
+
+/// Parses a value for an item whose declared type is known. A default of the declared
+/// type's kind is parsed like as before, and other defaults like a literal of the type.
+/// The result must then fit in the type. Every element that does not is reported, along
+/// with the validation failures of the elements that do.
+pub(crate) fn typed_value(args: &ItemArgs, declared: &DeclaredType, var_name: &str, value: &str, expr: &Expr) -> Result<Expr, FromEnvError> {
+    let parse_error = |reason: String| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: crate::display_value(args, value),
+        kind: declared.kind(expr),
+        reason: Some(reason),
+        span: expr.span(),
+    };
+    if *declared == DeclaredType::CStr {
+        let string: syn::LitStr = syn::parse_str(&format!("\"{}\"", value)).map_err(|err| parse_error(err.to_string()))?;
+        let c_string = std::ffi::CString::new(string.value())
+            .map_err(|err| parse_error(format!("contains a NUL byte at offset {}", err.nul_position())))?;
+        let mut literal = proc_macro2::Literal::c_string(&c_string);
+        literal.set_span(expr.span());
+        return Ok(Expr::Lit(ExprLit { attrs: Vec::new(), lit: Lit::Verbatim(literal) }));
+    }
+    let template = declared.template_for(expr);
+    let new_expr = crate::value_to_literal(args, var_name, value, template.as_ref().unwrap_or(expr))?;
+    if let Err(mismatches) = declared.check_value(&new_expr) {
+        let mismatched: Vec<usize> = mismatches.iter().filter_map(|mismatch| mismatch.element).collect();
+        // A mismatch of the whole value leaves no elements to validate.
+        let failures = match mismatched.len() == mismatches.len() {
+            true => crate::validate::failures(args, var_name, &new_expr, &mismatched, expr.span()),
+            false => Vec::new(),
+        };
+        let errors = mismatches.iter().map(|mismatch| parse_error(mismatch.to_string()))
+            .chain(failures.into_iter().map(FromEnvError::Other))
+            .collect();
+        return Err(crate::combine_failures(errors));
+    }
+    Ok(new_expr)
+}
+
+/// The bounds of an integer type, as the largest magnitudes of its negative and positive
+/// values. `isize` and `usize` are taken as 64-bit, the widest pointer size they can have.
+pub(crate) fn int_range(name: &str) -> Option<(u128, u128)> {
+    Some(match name {
+        "i8" => (1 << 7, i8::MAX as u128),
+        "i16" => (1 << 15, i16::MAX as u128),
+        "i32" => (1 << 31, i32::MAX as u128),
+        "i64" | "isize" => (1 << 63, i64::MAX as u128),
+        "i128" => (1 << 127, i128::MAX as u128),
+        "u8" => (0, u8::MAX as u128),
+        "u16" => (0, u16::MAX as u128),
+        "u32" => (0, u32::MAX as u128),
+        "u64" | "usize" => (0, u64::MAX as u128),
+        "u128" => (0, u128::MAX),
+        _ => return None,
+    })
+}
+
+fn array_len(len: &Expr) -> Option<usize> {
+    match len {
+        Expr::Lit(ExprLit { lit: Lit::Int(len), .. }) => len.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+fn lit_kind(lit: &Lit) -> &'static str {
+    match lit {
+        Lit::Str(_) => "a string",
+        Lit::ByteStr(_) => "a byte string",
+        Lit::Byte(_) => "a byte",
+        Lit::Char(_) => "a character",
+        Lit::Bool(_) => "a boolean",
+        Lit::Int(_) => "an integer",
+        Lit::Float(_) => "a float",
+        Lit::Verbatim(_) => "a literal",
+    }
+}
@@ -0,0 +1,281 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+
+use crate::args::{self, ItemArgs, RenameRule};
+use crate::{explain, FromEnvError, ReadEnv};
+
+/// Primitive types whose defaults are literals that the environment can replace.
+const PRIMITIVES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    "f32", "f64", "bool", "char",
+];
+
+/// Arguments that only make sense on items, which have a name and visibility of their own.
+const ITEM_ONLY_ARGS: &[&str] = &[
+    "emit_var_name", "emit_default", "emit_len", "register", "runtime_fallback", "assert", "doc_override",
+    "emit_runtime_getter", "obfuscate", "split_into",
+];
+
+/// Which trait is derived, and so which field types can be configured.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Derive {
+    FromEnvDefaults,
+    ConstEnv,
+}
+
+impl Derive {
+    fn name(self) -> &'static str {
+        match self {
+            Derive::FromEnvDefaults => "FromEnvDefaults",
+            Derive::ConstEnv => "ConstEnv",
+        }
+    }
+}
+
+/// A field configured by its `#[from_env(...)]` attribute.
+struct Configured<'a> {
+    field: &'a syn::Field,
+    ident: &'a syn::Ident,
+    value: syn::Expr,
+}
+
+/// Generates `impl Default` for a struct, with `Default::default()` for its fields except
+/// those marked `#[from_env(...)]`, whose `default = ...` is replaced from the
+/// environment as the attribute replaces an item's initializer.
+pub(crate) fn default_impl(input: TokenStream, read_env: &impl ReadEnv) -> syn::Result<TokenStream> {
+    let input: syn::DeriveInput = syn::parse2(input)?;
+    let (configured, tracking) = configure(&input, Derive::FromEnvDefaults, read_env)?;
+    let fields = named_fields(&input, Derive::FromEnvDefaults)?;
+    let inits = fields.iter().map(|field| {
+        let ident = &field.ident;
+        match configured.iter().find(|configured| configured.field == field) {
+            Some(Configured { value, .. }) if is_string(&field.ty) => {
+                quote_spanned!(field.span() => #ident: ::std::string::String::from(#value))
+            }
+            Some(Configured { value, .. }) => quote_spanned!(field.span() => #ident: #value),
+            None => quote_spanned!(field.span() => #ident: ::std::default::Default::default()),
+        }
+    });
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::std::default::Default for #name #ty_generics #where_clause {
+            fn default() -> Self {
+                #name {
+                    #(#inits,)*
+                }
+            }
+        }
+        #tracking
+    })
+}
+
+/// Generates an inherent `pub const` for each field of a struct, named after the field in
+/// SCREAMING_SNAKE_CASE, with the field's `default = ...` replaced from the environment.
+pub(crate) fn const_impl(input: TokenStream, read_env: &impl ReadEnv) -> syn::Result<TokenStream> {
+    let input: syn::DeriveInput = syn::parse2(input)?;
+    // Clashing names are reported along with the errors of the fields' values.
+    let mut errors: Option<syn::Error> = None;
+    let mut names: Vec<(String, &syn::Ident)> = Vec::new();
+    for ident in named_fields(&input, Derive::ConstEnv)?.iter().filter_map(|field| field.ident.as_ref()) {
+        let const_name = RenameRule::ScreamingSnakeCase.apply(ident);
+        match names.iter().find(|(name, _)| *name == const_name) {
+            Some((_, first)) => push_error(&mut errors, explain::error(explain::INVALID_ARGUMENT, ident.span(), format!(
+                "`{}` and `{}` would both generate the constant `{}`",
+                syn::ext::IdentExt::unraw(*first), syn::ext::IdentExt::unraw(ident), const_name))),
+            None => names.push((const_name, ident)),
+        }
+    }
+    let (configured, tracking) = match (configure(&input, Derive::ConstEnv, read_env), errors) {
+        (Ok(configured), None) => configured,
+        (Ok(_), Some(errors)) => return Err(errors),
+        (Err(err), None) => return Err(err),
+        (Err(err), Some(mut errors)) => {
+            errors.combine(err);
+            return Err(errors);
+        }
+    };
+    let consts = configured.iter().map(|Configured { field, ident, value }| {
+        let const_ident = syn::Ident::new(&RenameRule::ScreamingSnakeCase.apply(ident), ident.span());
+        let ty = &field.ty;
+        quote_spanned!(field.span() => pub const #const_ident: #ty = #value;)
+    });
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#consts)*
+        }
+        #tracking
+    })
+}
+
+/// The named fields of the struct being derived for.
+fn named_fields(input: &syn::DeriveInput, derive: Derive) -> syn::Result<&syn::punctuated::Punctuated<syn::Field, syn::Token![,]>> {
+    match &input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) => Ok(&fields.named),
+        _ => Err(explain::error(explain::UNSUPPORTED_ITEM, input.ident.span(),
+            format!("`{}` can only be derived for structs with named fields", derive.name()))),
+    }
+}
+
+/// Substitutes the default of every configured field, reporting the errors of all fields
+/// together, and returns the fields with the rebuild tracking of the variables they read
+/// and files they read, and the fields' warnings.
+fn configure<'a>(input: &'a syn::DeriveInput, derive: Derive, read_env: &impl ReadEnv) -> syn::Result<(Vec<Configured<'a>>, TokenStream)> {
+    let fields = named_fields(input, derive)?;
+    let prefix = struct_prefix(input)?;
+    let strict = crate::is_enabled(crate::STRICT_VAR, Span::call_site(), read_env)?;
+    let mut errors: Option<syn::Error> = None;
+    let mut configured = Vec::new();
+    let mut tracked = Vec::new();
+    let mut warnings = TokenStream::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named fields have names");
+        match field_value(field, ident, derive, prefix.as_deref(), strict, read_env) {
+            Ok(Some((value, names, field_extra))) => {
+                configured.push(Configured { field, ident, value });
+                tracked.extend(names);
+                warnings.extend(field_extra);
+            }
+            Ok(None) => {}
+            Err(err) => push_error(&mut errors, err.into()),
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+    tracked.sort();
+    tracked.dedup();
+    let mut tracking = match read_env.needs_rebuild_tracking() {
+        true => crate::rebuild_tracking(&tracked, input.ident.span()),
+        false => TokenStream::new(),
+    };
+    tracking.extend(warnings);
+    Ok((configured, tracking))
+}
+
+/// The `prefix = "..."` of the struct's own `#[from_env(...)]` attribute, prepended to the
+/// variable names derived from its fields.
+pub(crate) fn struct_prefix(input: &syn::DeriveInput) -> syn::Result<Option<String>> {
+    let mut prefix = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("from_env")) {
+        let value: syn::LitStr = attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let key: syn::Ident = input.parse()?;
+            if key != "prefix" {
+                return Err(syn::Error::new(key.span(), "A struct's `from_env` attribute only accepts `prefix = \"...\"`"));
+            }
+            input.parse::<syn::Token![=]>()?;
+            input.parse()
+        }).map_err(|err| explain::with_code(explain::INVALID_ARGUMENT, err))?;
+        if prefix.is_some() {
+            return Err(explain::error(explain::INVALID_ARGUMENT, attr.span(), "A struct takes at most one `from_env` attribute"));
+        }
+        prefix = Some(value.value());
+    }
+    Ok(prefix)
+}
+
+/// The value of a field marked `#[from_env(...)]`, after substitution, the variables it
+/// reads, and its warnings with the tracking of the files it reads. Fields without the
+/// attribute are `None`, except for `ConstEnv`, which configures every field.
+fn field_value(field: &syn::Field, ident: &syn::Ident, derive: Derive, prefix: Option<&str>, strict: bool, read_env: &impl ReadEnv) -> Result<Option<(syn::Expr, Vec<String>, TokenStream)>, FromEnvError> {
+    let mut own = field.attrs.iter().filter(|attr| attr.path.is_ident("from_env"));
+    let attr = match (own.next(), own.next()) {
+        (None, _) if derive == Derive::ConstEnv => return Err(explain::error(explain::INVALID_ARGUMENT, ident.span(), format!(
+            "Every field of a `ConstEnv` struct needs a `#[from_env(default = ...)]` attribute; `{}` has none", ident)).into()),
+        (None, _) => return Ok(None),
+        (Some(_), Some(second)) => return Err(explain::error(explain::INVALID_ARGUMENT, second.span(),
+            "A field takes at most one `from_env` attribute").into()),
+        (Some(attr), None) => attr,
+    };
+    let mut args: ItemArgs = args::parse_item_args(attr.tokens.clone())
+        .map_err(|err| FromEnvError::BadAttribute(explain::with_code(explain::INVALID_ARGUMENT, err)))?;
+    let (default, _) = args.default.clone().ok_or_else(|| FromEnvError::BadAttribute(explain::error(
+        explain::INVALID_ARGUMENT, attr.span(), format!("The `from_env` attribute of `{}` needs a `default = ...` literal", ident))))?;
+    if let Some((name, span)) = item_only_arg(attr.tokens.clone()) {
+        return Err(FromEnvError::BadAttribute(explain::error(explain::INVALID_ARGUMENT, span,
+            format!("`{}` cannot be used on fields of `#[derive({})]`", name, derive.name()))));
+    }
+    if !supported_type(&field.ty) || (derive == Derive::ConstEnv && is_string(&field.ty)) {
+        let supported = match derive {
+            Derive::FromEnvDefaults => "a primitive, `String`, `&str`, `&[u8]` or an array",
+            Derive::ConstEnv => "a primitive, `&str`, `&[u8]` or an array",
+        };
+        return Err(explain::error(explain::UNSUPPORTED_DEFAULT, field.ty.span(), format!(
+            "`{}` cannot configure a field of type `{}`; use {}", derive.name(),
+            crate::pretty::to_pretty_string(field.ty.to_token_stream()), supported)).into());
+    }
+    if args.var_name.is_none() {
+        args.var_name = Some(syn::LitStr::new(&field_var_name(prefix, ident), ident.span()));
+    }
+    args.required |= strict && !args.optional;
+    let ((value, tracked_files), warnings) = crate::diagnostics::collect_warnings(|| crate::file::collect_tracked_files(|| crate::substitute(&args, ident, &field.ty, &default, read_env)));
+    let value = value?.unwrap_or(default);
+    let names = match args.untracked {
+        true => Vec::new(),
+        false => crate::tracked_names(&args, ident, read_env)?,
+    };
+    let mut extra = match args.allow_warnings {
+        true => TokenStream::new(),
+        false => crate::diagnostics::emit_warnings(&warnings),
+    };
+    extra.extend(tracked_files);
+    Ok(Some((value, names, extra)))
+}
+
+/// The variable read by a field without a name of its own: the field's name in
+/// SCREAMING_SNAKE_CASE after the struct's prefix.
+pub(crate) fn field_var_name(prefix: Option<&str>, ident: &syn::Ident) -> String {
+    format!("{}{}", prefix.unwrap_or(""), RenameRule::ScreamingSnakeCase.apply(ident))
+}
+
+fn push_error(errors: &mut Option<syn::Error>, err: syn::Error) {
+    match errors {
+        Some(errors) => errors.combine(err),
+        None => *errors = Some(err),
+    }
+}
+
+/// The first argument in `tokens` that only applies to items, with its span.
+fn item_only_arg(tokens: TokenStream) -> Option<(String, Span)> {
+    fn find(tokens: TokenStream) -> Option<(String, Span)> {
+        for token in tokens {
+            match token {
+                proc_macro2::TokenTree::Ident(ident) if ITEM_ONLY_ARGS.contains(&ident.to_string().as_str()) => {
+                    return Some((ident.to_string(), ident.span()));
+                }
+                // Only the outer parentheses are searched, not the values of arguments.
+                proc_macro2::TokenTree::Group(group) if group.delimiter() == proc_macro2::Delimiter::Parenthesis => {
+                    return find(group.stream());
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+    find(tokens)
+}
+
+fn supported_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.qself.is_none()
+            && path.path.segments.last().is_some_and(|segment| {
+                segment.arguments.is_empty() && PRIMITIVES.contains(&segment.ident.to_string().as_str())
+            }) || is_string(ty),
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Path(path) => path.path.is_ident("str"),
+            syn::Type::Slice(slice) => supported_type(&slice.elem),
+            _ => false,
+        },
+        syn::Type::Array(array) => supported_type(&array.elem),
+        syn::Type::Paren(paren) => supported_type(&paren.elem),
+        syn::Type::Group(group) => supported_type(&group.elem),
+        _ => false,
+    }
+}
+
+fn is_string(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(path) if path.path.segments.last().is_some_and(|segment| segment.ident == "String"))
+}
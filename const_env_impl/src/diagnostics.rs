@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::Path;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote_spanned;
+
+thread_local! {
+    /// Warnings reported while expanding the current item, emitted with its output.
+    static WARNINGS: RefCell<Vec<(Span, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Reports a warning about the item at `span`. The warning is queued and emitted with
+/// the item's output by `emit_warnings`, unless the item has `allow_warnings`.
+pub(crate) fn emit_warning(span: Span, message: &str) {
+    WARNINGS.with(|warnings| warnings.borrow_mut().push((span, message.to_string())));
+}
+
+/// Runs `f`, returning its result and the warnings it reported.
+pub(crate) fn collect_warnings<R>(f: impl FnOnce() -> R) -> (R, Vec<(Span, String)>) {
+    let outer = WARNINGS.with(|warnings| warnings.take());
+    let result = f();
+    let collected = WARNINGS.with(|warnings| warnings.replace(outer));
+    (result, collected)
+}
+
+/// Emits the warnings collected for an item. With the `nightly-diagnostics` feature they
+/// are reported through the unstable diagnostic API, so that they read like the
+/// compiler's own warnings, and otherwise, or outside a macro invocation such as in
+/// tests, as shims.
+pub(crate) fn emit_warnings(warnings: &[(Span, String)]) -> TokenStream {
+    #[cfg(feature = "nightly-diagnostics")]
+    if proc_macro::is_available() {
+        for (span, message) in warnings {
+            proc_macro::Diagnostic::spanned(span.unwrap(), proc_macro::Level::Warning, message.as_str()).emit();
+        }
+        return TokenStream::new();
+    }
+    warning_shims(warnings)
+}
+
+/// Emits each warning as a `#[deprecated]` constant and a use of it, attached to the
+/// warning's span, since stable proc macros cannot emit warnings. Each pair is scoped in
+/// a `const _` so that it adds no names to the module.
+fn warning_shims(warnings: &[(Span, String)]) -> TokenStream {
+    warnings.iter().map(|(span, message)| {
+        let warning = syn::Ident::new("warning", *span);
+        quote_spanned! {*span=>
+            const _: () = {
+                #[deprecated(note = #message)]
+                #[allow(non_upper_case_globals)]
+                const #warning: () = ();
+                let _ = #warning;
+            };
+        }
+    }).collect()
+}
+
+/// Prints a line of `CONST_ENV_DEBUG` output to stderr.
+pub(crate) fn trace(message: &str) {
+    eprintln!("const_env: {}", message);
+}
+
+/// Records a line of `CONST_ENV_LIST` output, appending it to `file` if given and
+/// printing it to stderr otherwise.
+pub(crate) fn list(line: &str, file: Option<&Path>) -> Result<(), String> {
+    match file {
+        Some(path) => {
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|err| format!("Failed to open `{}` named by `CONST_ENV_LIST_FILE`: {}", path.display(), err))?;
+            // A single write keeps lines from concurrent invocations intact.
+            file.write_all(format!("{}\n", line).as_bytes())
+                .map_err(|err| format!("Failed to write to `{}` named by `CONST_ENV_LIST_FILE`: {}", path.display(), err))
+        }
+        None => {
+            eprintln!("const_env list: {}", line);
+            Ok(())
+        }
+    }
+}
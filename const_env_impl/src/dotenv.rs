@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::{EnvError, ReadEnv};
+
+/// Variable naming a `.env` file consulted after the environment.
+pub(crate) const DOTENV_VAR: &str = "CONST_ENV_DOTENV";
+
+/// Variables read from a `.env` file.
+///
+/// Each line is blank, a `#` comment, or `KEY=value`, optionally preceded by `export`.
+/// Values are taken verbatim up to an unquoted ` #` comment, or may be quoted: single
+/// quotes keep their contents as is, while double quotes also process the escapes `\n`,
+/// `\r`, `\t`, `\"` and `\\`. References to other variables are not expanded. If a key
+/// appears more than once, the last value wins.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DotenvEnv {
+    vars: HashMap<String, String>
+}
+
+/// An error loading a `.env` file.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DotenvError {
+    /// The file could not be read.
+    Io { path: PathBuf, error: std::io::Error },
+    /// The line with the given number, counting from 1, is malformed.
+    Parse { path: Option<PathBuf>, line: usize, message: String },
+}
+
+impl std::fmt::Display for DotenvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DotenvError::Io { path, error } => write!(f, "Failed to read `{}`: {}", path.display(), error),
+            DotenvError::Parse { path: Some(path), line, message } => {
+                write!(f, "Failed to parse `{}`: line {}: {}", path.display(), line, message)
+            }
+            DotenvError::Parse { path: None, line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for DotenvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DotenvError::Io { error, .. } => Some(error),
+            DotenvError::Parse { .. } => None,
+        }
+    }
+}
+
+impl DotenvEnv {
+    /// Reads and parses the file at `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, DotenvError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| DotenvError::Io { path: path.to_path_buf(), error })?;
+        source.parse().map_err(|err| match err {
+            DotenvError::Parse { line, message, .. } => DotenvError::Parse { path: Some(path.to_path_buf()), line, message },
+            err => err,
+        })
+    }
+}
+
+impl std::str::FromStr for DotenvEnv {
+    type Err = DotenvError;
+
+    fn from_str(source: &str) -> Result<Self, DotenvError> {
+        let mut vars = HashMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = parse_line(line)
+                .map_err(|message| DotenvError::Parse { path: None, line: index + 1, message })?;
+            vars.insert(key.to_string(), value);
+        }
+        Ok(DotenvEnv { vars })
+    }
+}
+
+impl ReadEnv for DotenvEnv {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.vars.get(var_name).map(|value| Cow::Borrowed(OsStr::new(value))))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.vars.keys().cloned().collect()
+    }
+}
+
+fn parse_line(line: &str) -> Result<(&str, String), String> {
+    let line = match line.strip_prefix("export") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim_start(),
+        _ => line,
+    };
+    let (key, value) = line.split_once('=').ok_or_else(|| "expected `KEY=value`".to_string())?;
+    let key = key.trim_end();
+    let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !valid_key {
+        return Err(format!("invalid key `{}`", key));
+    }
+    let value = value.trim_start();
+    let value = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => parse_quoted(value, quote)?,
+        _ => {
+            // A `#` starts a comment only after whitespace, so that values such as
+            // URLs with fragments are kept intact.
+            let end = value.find(" #").or_else(|| value.find("\t#")).unwrap_or(value.len());
+            value[..end].trim_end().to_string()
+        }
+    };
+    Ok((key, value))
+}
+
+/// Parses a value starting with `quote`, which must be closed on the same line and
+/// may only be followed by a comment.
+fn parse_quoted(text: &str, quote: char) -> Result<String, String> {
+    let mut value = String::new();
+    let mut chars = text[1..].char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c == quote => {
+                let rest = text[1 + index + 1..].trim_start();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err(format!("unexpected `{}` after quoted value", rest));
+                }
+                return Ok(value);
+            }
+            '\\' if quote == '"' => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some('t') => value.push('\t'),
+                Some(c @ ('"' | '\\')) => value.push(c),
+                Some(other) => return Err(format!("invalid escape `\\{}`", other)),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated quoted value".to_string())
+}
+
+/// Loads the `.env` file named by `CONST_ENV_DOTENV`, resolved against the crate
+/// directory, or an empty one if the variable is unset. The file and the variable are
+/// tracked for rebuilds, as `CONST_ENV_CONFIG` is.
+pub(crate) fn load(read_env: &impl ReadEnv) -> Result<DotenvEnv, String> {
+    crate::file::track_var(DOTENV_VAR, read_env);
+    match read_env.read_env(DOTENV_VAR).map_err(|err| err.to_string())? {
+        Some(path) => {
+            let resolved = crate::file::resolve_path(&*path, read_env)?;
+            let dotenv = DotenvEnv::from_path(&resolved).map_err(|err| format!("{} (named by `{}`)", err, DOTENV_VAR))?;
+            crate::file::track_file(crate::file::include_path(&Path::new(&*path).to_string_lossy(), &resolved, read_env)?);
+            Ok(dotenv)
+        }
+        None => Ok(DotenvEnv::default()),
+    }
+}
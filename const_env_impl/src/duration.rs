@@ -0,0 +1,141 @@
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::FromEnvError;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Units accepted in duration values, with their length in nanoseconds.
+const UNITS: &[(&str, u128)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("s", NANOS_PER_SEC),
+    ("m", 60 * NANOS_PER_SEC),
+    ("h", 3_600 * NANOS_PER_SEC),
+    ("d", 86_400 * NANOS_PER_SEC),
+];
+
+/// A `Duration` constructor written as an item's default, such as
+/// `Duration::from_millis(500)`.
+pub(crate) struct Constructor<'a> {
+    call: &'a syn::ExprCall,
+    path: &'a syn::Path,
+    /// The nanoseconds in one unit of the constructor's argument, or `None` for
+    /// `Duration::new`, whose first argument is in seconds.
+    unit: Option<u128>,
+}
+
+/// Recognizes `Duration::from_secs`, `from_millis`, `from_micros`, `from_nanos` and `new`
+/// with integer literal arguments, under any path ending in `Duration`.
+pub(crate) fn constructor(expr: &Expr) -> Option<Constructor<'_>> {
+    let call = match expr {
+        Expr::Call(call) => call,
+        _ => return None,
+    };
+    let path = match &*call.func {
+        Expr::Path(syn::ExprPath { qself: None, path, .. }) => path,
+        _ => return None,
+    };
+    let mut segments = path.segments.iter().rev();
+    let (function, ty) = (segments.next()?, segments.next()?);
+    if ty.ident != "Duration" {
+        return None;
+    }
+    let (unit, arity) = match function.ident.to_string().as_str() {
+        "from_secs" => (Some(NANOS_PER_SEC), 1),
+        "from_millis" => (Some(1_000_000), 1),
+        "from_micros" => (Some(1_000), 1),
+        "from_nanos" => (Some(1), 1),
+        "new" => (None, 2),
+        _ => return None,
+    };
+    let literal_args = call.args.iter().all(|arg| matches!(arg, Expr::Lit(ExprLit { lit: Lit::Int(_), .. })));
+    match call.args.len() == arity && literal_args {
+        true => Some(Constructor { call, path, unit }),
+        false => None,
+    }
+}
+
+/// Converts a value such as `2s`, `750ms` or `1m30s` into a call of the constructor that
+/// states it exactly, keeping the written one when it can. A plain integer is in the unit
+/// of the written constructor, or in seconds for `Duration::new`.
+pub(crate) fn duration_value(args: &ItemArgs, constructor: &Constructor, var_name: &str, value: &str) -> Result<Expr, FromEnvError> {
+    // Replacements take the span of the written arguments.
+    let span = syn::spanned::Spanned::span(&constructor.call.args);
+    let parse_error = |reason: String| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: crate::display_value(args, value),
+        kind: "a duration",
+        reason: Some(reason),
+        span,
+    };
+    let nanos = parse_nanos(value, constructor.unit.unwrap_or(NANOS_PER_SEC)).map_err(parse_error)?;
+    if nanos / NANOS_PER_SEC > u64::MAX as u128 {
+        return Err(parse_error("the duration is too long to be represented".to_string()));
+    }
+    let call = |function: &str, values: &[u128]| {
+        let mut path = constructor.path.clone();
+        let last = path.segments.last_mut().expect("the path has a `Duration` segment");
+        last.ident = syn::Ident::new(function, last.ident.span());
+        let values = values.iter().map(|value| syn::LitInt::new(&value.to_string(), span));
+        syn::parse_quote!(#path(#(#values),*))
+    };
+    let function = constructor.path.segments.last().expect("the path has a `Duration` segment").ident.to_string();
+    match constructor.unit {
+        Some(unit) if nanos % unit == 0 && nanos / unit <= u64::MAX as u128 => Ok(call(&function, &[nanos / unit])),
+        _ => Ok(call("new", &[nanos / NANOS_PER_SEC, nanos % NANOS_PER_SEC])),
+    }
+}
+
+/// The nanoseconds in `value`, a plain integer in `plain_unit` or a sequence of numbers
+/// each followed by a unit, optionally separated by spaces.
+fn parse_nanos(value: &str, plain_unit: u128) -> Result<u128, String> {
+    let value = value.trim().replace('_', "");
+    if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return value.parse::<u128>().ok()
+            .and_then(|count| count.checked_mul(plain_unit))
+            .ok_or_else(|| "the duration is too long to be represented".to_string());
+    }
+    let mut rest = value.as_str();
+    let mut total: u128 = 0;
+    if rest.is_empty() {
+        return Err("expected a number followed by a unit, such as `2s` or `750ms`".to_string());
+    }
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let unit_len = rest[number_len..].find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len() - number_len);
+        let (number, unit) = (&rest[..number_len], rest[number_len..number_len + unit_len].trim());
+        rest = rest[number_len + unit_len..].trim_start();
+        let unit_nanos = match UNITS.iter().find(|(name, _)| *name == unit) {
+            Some((_, nanos)) => *nanos,
+            None if unit.is_empty() => return Err(format!("`{}` needs a unit, such as `s` or `ms`", number)),
+            None => return Err(format!("unknown unit `{}`; expected `ns`, `us`, `ms`, `s`, `m`, `h` or `d`", unit)),
+        };
+        let nanos = component_nanos(number, unit, unit_nanos)?;
+        total = total.checked_add(nanos).ok_or_else(|| "the duration is too long to be represented".to_string())?;
+    }
+    Ok(total)
+}
+
+/// The nanoseconds in `number` units of `unit_nanos` each, if they are a whole number.
+fn component_nanos(number: &str, unit: &str, unit_nanos: u128) -> Result<u128, String> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() || fraction.contains('.') || (number.contains('.') && fraction.is_empty()) {
+        return Err(format!("`{}` is not a number", number));
+    }
+    let too_long = || "the duration is too long to be represented".to_string();
+    let scale = 10u128.checked_pow(fraction.len() as u32).ok_or_else(too_long)?;
+    let fraction_nanos = match fraction.is_empty() {
+        true => 0,
+        false => fraction.parse::<u128>().ok().and_then(|fraction| fraction.checked_mul(unit_nanos)).ok_or_else(too_long)?,
+    };
+    if fraction_nanos % scale != 0 {
+        return Err(format!("`{}{}` is finer than a nanosecond", number, unit));
+    }
+    whole.parse::<u128>().ok()
+        .and_then(|whole| whole.checked_mul(unit_nanos))
+        .and_then(|nanos| nanos.checked_add(fraction_nanos / scale))
+        .ok_or_else(too_long)
+}
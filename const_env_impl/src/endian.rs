@@ -0,0 +1,65 @@
+use syn::spanned::Spanned;
+use syn::Expr;
+
+use crate::args::{Endian, ItemArgs};
+use crate::declared::DeclaredType;
+use crate::{explain, pad, FromEnvError};
+
+/// Parses `value` as a non-negative integer, in decimal or with a `0x`, `0o` or `0b`
+/// prefix and with underscores, and stores it in the bytes of a fixed-size byte array
+/// default in the order of `endian`. An integer that needs more bytes than the array
+/// has is an error.
+pub(crate) fn endian_value(args: &ItemArgs, endian: Endian, var_name: &str, value: &str, declared: Option<&DeclaredType>, expr: &Expr, span: proc_macro2::Span) -> Result<Expr, FromEnvError> {
+    let is_byte_array = match declared {
+        Some(DeclaredType::Array(element, _)) => matches!(&**element, DeclaredType::Int(name) if name == "u8"),
+        Some(_) => false,
+        None => true,
+    };
+    let len = match (is_byte_array, pad::array_len(expr)) {
+        (true, Some(len)) => len,
+        _ => return Err(explain::error(explain::UNSUPPORTED_DEFAULT, span,
+            "`endian` can only be used on items with a `*b\"...\"` or fixed-size byte array default").into()),
+    };
+    let digits = value.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") | Some("0X") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, &digits[..]),
+    };
+    // `from_str_radix` accepts a leading `+`, which is not an integer literal.
+    let integer = match digits.starts_with('+') {
+        true => None,
+        false => u128::from_str_radix(digits, radix).ok(),
+    };
+    let integer = integer.ok_or_else(|| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: crate::display_value(args, value),
+        kind: "a non-negative integer",
+        reason: None,
+        span,
+    })?;
+    let needed = (128 - integer.leading_zeros() as usize).div_ceil(8);
+    if needed > len {
+        return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+            "Environment variable `{}` needs {} bytes, more than the {} bytes of the default\n  value: {}",
+            var_name, needed, len, crate::display_value(args, value))).into());
+    }
+    let mut bytes = vec![0; len];
+    for (index, byte) in integer.to_le_bytes().iter().take(needed).enumerate() {
+        bytes[index] = *byte;
+    }
+    if endian == Endian::Big {
+        bytes.reverse();
+    }
+    match expr {
+        Expr::Unary(_) => {
+            let lit = syn::LitByteStr::new(&bytes, expr.span());
+            Ok(syn::parse_quote!(*#lit))
+        }
+        _ => {
+            let elements = bytes.iter().map(|byte| syn::LitInt::new(&format!("{:#04x}", byte), expr.span()));
+            Ok(syn::parse_quote!([#(#elements),*]))
+        }
+    }
+}
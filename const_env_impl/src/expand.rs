@@ -0,0 +1,88 @@
+use crate::ReadEnv;
+
+/// How many levels of nested references are followed before giving up.
+const MAX_DEPTH: usize = 8;
+
+/// What to do when a `${NAME}` reference names an unset variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MissingRef {
+    Error,
+    Empty,
+}
+
+/// Expands `${NAME}` and `$NAME` references in the value of `var_name` using
+/// `read_env`. Referenced values are expanded recursively; `$$` produces a literal `$`.
+pub(crate) fn expand_value(var_name: &str, value: &str, missing: MissingRef, read_env: &impl ReadEnv) -> Result<String, String> {
+    let mut stack = vec![var_name.to_string()];
+    expand_recursive(value, missing, read_env, &mut stack)
+}
+
+fn expand_recursive(value: &str, missing: MissingRef, read_env: &impl ReadEnv, stack: &mut Vec<String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let name = match chars.peek() {
+            Some('$') => {
+                chars.next();
+                expanded.push('$');
+                continue;
+            }
+            Some('{') => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unclosed reference `${{{}` in value", name)),
+                    }
+                }
+                if name.is_empty() {
+                    return Err("Empty reference `${}` in value".to_string());
+                }
+                name
+            }
+            Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || *c == '_' {
+                        name.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                name
+            }
+            // A `$` that does not start a reference is kept as-is.
+            _ => {
+                expanded.push('$');
+                continue;
+            }
+        };
+        if stack.contains(&name) {
+            stack.push(name);
+            return Err(format!("Cyclic variable reference: {}", stack.join(" -> ")));
+        }
+        if stack.len() > MAX_DEPTH {
+            return Err(format!("Variable references nested more than {} levels deep at `{}`", MAX_DEPTH, name));
+        }
+        match read_env.read_env_utf8(&name).map_err(|err| err.to_string())? {
+            Some(referenced) => {
+                stack.push(name);
+                let referenced = expand_recursive(&referenced, missing, read_env, stack)?;
+                stack.pop();
+                expanded.push_str(&referenced);
+            }
+            None => match missing {
+                MissingRef::Empty => {}
+                MissingRef::Error => return Err(format!("Referenced environment variable `{}` is not set", name)),
+            },
+        }
+    }
+    Ok(expanded)
+}
@@ -0,0 +1,286 @@
+//! Stable codes for the errors reported by the macros. Every error message starts with
+//! its code in brackets, such as `[CE0001]`, so that build logs can be searched and
+//! classified. Codes are never renumbered or reused: a new kind of error gets a new code.
+
+use proc_macro2::Span;
+
+/// A required variable, or a variable referenced by a required `format`, is not set.
+pub(crate) const MISSING_REQUIRED: &str = "CE0001";
+/// A variable's value cannot be parsed as the kind of the default.
+pub(crate) const PARSE_FAILURE: &str = "CE0002";
+/// The attribute is applied to something other than a `const` or `static` item.
+pub(crate) const UNSUPPORTED_ITEM: &str = "CE0003";
+/// The attribute arguments are malformed, unknown or conflicting.
+pub(crate) const INVALID_ARGUMENT: &str = "CE0004";
+/// An argument cannot be used with the kind of item or default it is applied to.
+pub(crate) const UNSUPPORTED_DEFAULT: &str = "CE0005";
+/// A value violates `min`, `max`, `one_of`, `min_items`, `max_items`, `sort` or `dedup`.
+pub(crate) const VALIDATION_FAILURE: &str = "CE0006";
+/// A value does not match its `sha256` digest.
+pub(crate) const DIGEST_MISMATCH: &str = "CE0007";
+/// A file named by a variable, by `out_file`, by `CONST_ENV_LIST_FILE`, by
+/// `CONST_ENV_MANIFEST`, by `CONST_ENV_REPORT` or by `CONST_ENV_LOCK` cannot be used.
+pub(crate) const FILE_ERROR: &str = "CE0008";
+/// A reference inside a variable's value cannot be expanded.
+pub(crate) const EXPANSION_FAILURE: &str = "CE0009";
+/// A variable's value is not valid UTF-8.
+pub(crate) const INVALID_UTF8: &str = "CE0010";
+/// A case-insensitive lookup matches several variables.
+pub(crate) const AMBIGUOUS_VARIABLE: &str = "CE0011";
+/// The defaults file named by `CONST_ENV_CONFIG` cannot be read or used.
+pub(crate) const CONFIG_ERROR: &str = "CE0012";
+/// Several items read the same variable and `CONST_ENV_DUPLICATES` is `error`.
+pub(crate) const DUPLICATE_VARIABLE: &str = "CE0013";
+/// A crate-wide `CONST_ENV_*` setting has an invalid value.
+pub(crate) const INVALID_SETTING: &str = "CE0014";
+/// A `format` template is malformed.
+pub(crate) const INVALID_TEMPLATE: &str = "CE0015";
+/// The environment source failed to look a variable up.
+pub(crate) const LOOKUP_FAILURE: &str = "CE0016";
+/// The `.env` file named by `CONST_ENV_DOTENV` cannot be read or parsed.
+pub(crate) const DOTENV_ERROR: &str = "CE0017";
+/// The crate's manifest cannot be read for `cargo_metadata`.
+pub(crate) const METADATA_ERROR: &str = "CE0018";
+/// A value differs from, or is missing in, the lockfile named by `CONST_ENV_LOCK`.
+pub(crate) const LOCK_MISMATCH: &str = "CE0019";
+/// A default does not match the item's declared type.
+pub(crate) const TYPE_MISMATCH: &str = "CE0020";
+/// An item is expanded after `fingerprint!()`, which does not include it.
+pub(crate) const FINGERPRINT_ORDER: &str = "CE0021";
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (MISSING_REQUIRED, "\
+A required environment variable is not set.
+
+Items marked `required`, and every item when `CONST_ENV_STRICT=1` unless it is marked
+`optional`, fail to compile when none of their variables is set:
+
+    #[env_item(required)]
+    const API_URL: &str = \"http://localhost\";
+
+Set the variable, remove `required`, or mark the item `optional`. For `format`, every
+referenced variable must be set, and for `out_file` the file must exist in `OUT_DIR`."),
+    (PARSE_FAILURE, "\
+The value of an environment variable cannot be parsed as the kind of the default.
+
+The value replaces the default, so it must be a literal of the same kind:
+
+    #[env_item]
+    const PORT: u16 = 8080;
+
+fails with `PORT=eighty`. Integer defaults need integers, boolean defaults `true` or
+`false`, and array defaults a bracketed list such as `[1, 2]`. With the `expr` flag the
+value must be a valid Rust expression."),
+    (UNSUPPORTED_ITEM, "\
+The attribute is applied to an item that is not a `const` or `static`.
+
+    #[env_item]
+    fn port() -> u16 { 8080 }
+
+Only `const` and `static` items with an initializer can take their value from the
+environment. An item that does not parse, such as `const PORT: u16 = ;`, is reported
+with this code and the parser's message."),
+    (INVALID_ARGUMENT, "\
+The attribute arguments are malformed, unknown, repeated or conflicting.
+
+    #[env_item(\"PORT\", requried)]
+    const PORT: u16 = 8080;
+
+The variable name, if given, must be the first argument and a string literal; the
+other arguments are flags such as `required`, `key = value` pairs such as
+`case = \"lower\"`, or lists such as `one_of(1, 2)`. Some arguments cannot be combined,
+such as `format` with a variable name, and some require another, such as `trim_newline`
+with `file`."),
+    (UNSUPPORTED_DEFAULT, "\
+An argument cannot be used with the kind of default the item has.
+
+    #[env_item(case = \"lower\")]
+    const RETRIES: u32 = 3;
+
+`case`, `format`, `include` and `verify_default` need a string or byte string default,
+`placeholder` a string containing exactly one `{}`, `exists` and `invert` a boolean,
+`flags` an integer, `emit_len` a string, byte string, array or slice, and `min_items`,
+`max_items`, `sort` and `dedup` an array or slice.
+Defaults that are not literals, arrays or negative numbers need the `expr` flag.
+`runtime_fallback` needs a `static` item whose type implements `FromStr`, such as `u16`,
+rather than a reference, array or tuple."),
+    (VALIDATION_FAILURE, "\
+A value is outside the bounds given by the item's arguments.
+
+    #[env_item(min = 1, max = 100)]
+    const WORKERS: u32 = 4;
+
+fails with `WORKERS=500`. Lists are checked element by element and their length against
+`min_items` and `max_items`. `sort` needs elements that can be ordered, and `dedup` on
+an array, whose length is fixed, cannot remove elements; use a slice instead."),
+    (DIGEST_MISMATCH, "\
+A value does not match the SHA-256 digest given by `sha256`.
+
+    #[env_item(sha256 = \"...\")]
+    const PUBLIC_KEY: &str = \"\";
+
+The digest of the value, or of the default with `verify_default`, must equal the given
+one. The error shows both digests but never the value."),
+    (FILE_ERROR, "\
+A file cannot be read or written.
+
+This covers files named by variables read with `file`, `file_fallback` or `include`,
+files in `OUT_DIR` named by `out_file`, and the files named by `CONST_ENV_LIST_FILE`,
+`CONST_ENV_MANIFEST`, `CONST_ENV_REPORT` and `CONST_ENV_LOCK`.
+Relative paths are resolved against `CARGO_MANIFEST_DIR`."),
+    (EXPANSION_FAILURE, "\
+A `${NAME}` reference inside a variable's value cannot be expanded.
+
+With the `expand` flag, `DATABASE_URL=postgres://${DB_HOST}/app` fails if `DB_HOST` is
+not set, unless `expand_missing = \"empty\"` is given, and references that form a cycle
+always fail. Use `$$` for a literal `$`."),
+    (INVALID_UTF8, "\
+A variable's value is not valid UTF-8.
+
+Only byte string defaults such as `b\"\"` accept arbitrary bytes; for every other kind
+of default the value must be valid UTF-8."),
+    (AMBIGUOUS_VARIABLE, "\
+A case-insensitive lookup matches several variables.
+
+With `ignore_case` or `CONST_ENV_IGNORE_CASE=1`, `Port` and `PORT` both match an item
+reading `PORT` when the exact name is not set. Unset all but one of them."),
+    (CONFIG_ERROR, "\
+The defaults file named by `CONST_ENV_CONFIG` cannot be used.
+
+The file must exist and consist of top-level `key = value` pairs whose values are
+strings, integers, floats or booleans:
+
+    PORT = 8080
+    HOST = \"localhost\"
+
+A value must also match the kind of the item's default."),
+    (DUPLICATE_VARIABLE, "\
+Several items of the crate read the same variable while `CONST_ENV_DUPLICATES=error`.
+
+Give the items different variable names, or set `CONST_ENV_DUPLICATES` to `warn` or
+`off` if sharing the variable is intended."),
+    (INVALID_SETTING, "\
+A crate-wide setting has an invalid value.
+
+`CONST_ENV_DUPLICATES` must be `warn`, `error` or `off`."),
+    (INVALID_TEMPLATE, "\
+A `format` template is malformed.
+
+    #[env_item(format = \"postgres://{PGHOST}:{PGPORT}/app\")]
+    const DATABASE_URL: &str = \"\";
+
+Placeholders must name a variable as `{VAR_NAME}`, and literal braces are written
+`{{` and `}}`."),
+    (LOOKUP_FAILURE, "\
+The environment source failed to look a variable up.
+
+The process environment never fails, but sources that embed `const_env_impl`, such as
+ones reading a configuration service, report their errors this way. The message names
+the variable and gives the source's reason."),
+    (DOTENV_ERROR, "\
+The `.env` file named by `CONST_ENV_DOTENV` cannot be read or parsed.
+
+Each line must be blank, a `#` comment, or `KEY=value`, optionally preceded by `export`:
+
+    # local overrides
+    export PORT=8080
+    GREETING=\"hello world\"
+
+Quoted values must be closed on the same line. The error names the offending line."),
+    (METADATA_ERROR, "\
+The crate's manifest cannot be read for `cargo_metadata` or `CONST_ENV_CARGO_METADATA=1`.
+
+Defaults are read from the `[package.metadata.const_env]` table of the manifest in
+`CARGO_MANIFEST_DIR`, and from the `[workspace.metadata.const_env]` table of the
+workspace root:
+
+    [package.metadata.const_env]
+    PORT = 8080
+    log.level = \"info\"
+
+Keys of nested tables are joined with `_`, so the second entry sets `log_level`. Values
+must be strings, integers, floats, booleans or single-line arrays."),
+    (LOCK_MISMATCH, "\
+A value differs from the one recorded in the lockfile named by `CONST_ENV_LOCK`, or the
+item has no entry in it.
+
+The lockfile records the value of every item under a table named after its crate, with
+the SHA-256 digest of the value for `sensitive` items:
+
+    [my_crate]
+    PORT = \"8080\"
+    API_KEY = \"sha256:...\"
+
+If the change is expected, build once with `CONST_ENV_LOCK_WRITE=1` to update the
+lockfile, and check it in."),
+    (TYPE_MISMATCH, "\
+The default of an item does not match its declared type.
+
+Values are parsed as the declared type when it is a number, `bool`, `char`, `&str`,
+`&CStr`, or an array, slice or reference to an array of those, so the default must be a
+literal of that type:
+
+    #[env_item]
+    const RATIO: u32 = 0.5;
+
+Fix the default or the type. For a type alias, name the aliased type with `ty`, such as
+`#[env_item(ty = \"u16\")]`, so that values are parsed as it."),
+    (FINGERPRINT_ORDER, "\
+An item is expanded after `fingerprint!()`, whose fingerprint does not include its value.
+
+Macro invocations cannot see each other, so `fingerprint!()` covers the items that
+recorded their values before it in the same build, with `CONST_ENV_FINGERPRINT=1`. Items
+are expanded in the order of the source, so an item in a module declared after the
+invocation is missed:
+
+    const FINGERPRINT: u64 = const_env::fingerprint!();
+    mod config;
+
+Invoke `fingerprint!()` after every configured item, such as at the end of the crate
+root."),
+];
+
+/// Returns a description, with examples, of the error with the given code, such as
+/// `"CE0001"`, or `None` if no such code exists.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS.iter().find(|(known, _)| *known == code).map(|(_, explanation)| *explanation)
+}
+
+/// The code at the start of `message`, if it is a known one.
+pub(crate) fn code_of(message: &str) -> Option<&'static str> {
+    EXPLANATIONS.iter()
+        .map(|(code, _)| *code)
+        .find(|code| message.strip_prefix('[').is_some_and(|rest| rest.starts_with(code) && rest[code.len()..].starts_with(']')))
+}
+
+/// Creates an error whose message is prefixed with `code`.
+pub(crate) fn error(code: &str, span: Span, message: impl std::fmt::Display) -> syn::Error {
+    syn::Error::new(span, format!("[{}] {}", code, message))
+}
+
+/// Prefixes every message of `err` that does not already carry a code with `code`.
+pub(crate) fn with_code(code: &str, err: syn::Error) -> syn::Error {
+    err.into_iter()
+        .map(|err| {
+            let message = err.to_string();
+            if message.starts_with("[CE") {
+                err
+            } else {
+                error(code, err.span(), message)
+            }
+        })
+        .reduce(|mut combined, err| {
+            combined.combine(err);
+            combined
+        })
+        .expect("a `syn::Error` holds at least one message")
+}
+
+/// Combines `errors` into one error that reports each of them, or `None` if there are
+/// none.
+pub(crate) fn combine(errors: impl IntoIterator<Item = syn::Error>) -> Option<syn::Error> {
+    errors.into_iter().reduce(|mut combined, err| {
+        combined.combine(err);
+        combined
+    })
+}
@@ -0,0 +1,172 @@
+//! A parser for the common shape of configurable items, `const NAME: Type = literal;` and
+//! its `static` equivalents, that works on the item's tokens directly instead of parsing
+//! a whole `syn::Item`. Anything else is left to `syn`.
+
+use proc_macro2::{Delimiter, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote_spanned, ToTokens};
+use syn::{Expr, ExprLit, Lit};
+
+/// Keywords, which `syn` does not accept as item names.
+const KEYWORDS: &[&str] = &[
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+    "trait", "true", "try", "type", "typeof", "union", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// An item of the common shape, split into the parts that `expand` needs.
+pub(crate) struct FastItem {
+    tokens: Vec<TokenTree>,
+    /// The index of the first token after the item's attributes.
+    attrs_end: usize,
+    /// Whether one of the item's attributes is documentation.
+    documented: bool,
+    /// The `const` or `static` keyword, and `mut` for a `static mut`.
+    keyword: Vec<TokenTree>,
+    colon: Span,
+    eq: Span,
+    semi: Span,
+    span: Span,
+    pub(crate) vis: syn::Visibility,
+    pub(crate) ident: syn::Ident,
+    pub(crate) ty: syn::Type,
+    pub(crate) expr: Expr,
+}
+
+impl FastItem {
+    /// Splits `item` if it has the common shape: outer attributes, a visibility, `const`,
+    /// `static` or `static mut`, a name, a type and an initializer that is a literal,
+    /// possibly negated, followed by nothing but the semicolon.
+    pub(crate) fn parse(item: &TokenStream) -> Option<FastItem> {
+        let tokens: Vec<TokenTree> = item.clone().into_iter().collect();
+        let mut pos = 0;
+        let mut documented = false;
+        while let (Some(TokenTree::Punct(pound)), Some(TokenTree::Group(group))) = (tokens.get(pos), tokens.get(pos + 1)) {
+            if pound.as_char() != '#' || group.delimiter() != Delimiter::Bracket {
+                return None;
+            }
+            let mut inner = group.stream().into_iter();
+            let is_doc = matches!(inner.next(), Some(TokenTree::Ident(ident)) if ident == "doc");
+            documented |= is_doc && !matches!(inner.next(), Some(TokenTree::Punct(punct)) if punct.as_char() == ':');
+            pos += 2;
+        }
+        let attrs_end = pos;
+
+        let vis = match tokens.get(pos) {
+            Some(TokenTree::Ident(ident)) if ident == "pub" => match tokens.get(pos + 1) {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                    pos += 2;
+                    syn::parse2(tokens[pos - 2..pos].iter().cloned().collect()).ok()?
+                }
+                _ => {
+                    pos += 1;
+                    syn::Visibility::Public(syn::VisPublic { pub_token: syn::token::Pub(ident.span()) })
+                }
+            },
+            Some(TokenTree::Ident(ident)) if ident == "crate" => return None,
+            _ => syn::Visibility::Inherited,
+        };
+
+        let keyword_start = pos;
+        match tokens.get(pos) {
+            Some(TokenTree::Ident(ident)) if ident == "const" => pos += 1,
+            Some(TokenTree::Ident(ident)) if ident == "static" => {
+                pos += 1;
+                if matches!(tokens.get(pos), Some(TokenTree::Ident(ident)) if ident == "mut") {
+                    pos += 1;
+                }
+            }
+            _ => return None,
+        }
+        let keyword = tokens[keyword_start..pos].to_vec();
+
+        let ident = match tokens.get(pos) {
+            Some(TokenTree::Ident(ident)) if !KEYWORDS.contains(&&*ident.to_string()) => ident.clone(),
+            _ => return None,
+        };
+        let colon = match tokens.get(pos + 1) {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ':' && punct.spacing() == Spacing::Alone => punct.span(),
+            _ => return None,
+        };
+        let ty_start = pos + 2;
+
+        // The initializer is found from the end: a semicolon, a literal, an optional minus
+        // and the equals sign.
+        let semi = match tokens.last() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ';' => punct.span(),
+            _ => return None,
+        };
+        let lit_pos = tokens.len().checked_sub(2)?;
+        let lit = match &tokens[lit_pos] {
+            TokenTree::Literal(literal) => match Lit::new(literal.clone()) {
+                // Literals that `syn` does not recognize are left to the full parse.
+                Lit::Verbatim(_) => return None,
+                lit => lit,
+            },
+            TokenTree::Ident(ident) if ident == "true" || ident == "false" => Lit::Bool(syn::LitBool::new(ident == "true", ident.span())),
+            _ => return None,
+        };
+        let mut expr = Expr::Lit(ExprLit { attrs: Vec::new(), lit });
+        let mut init_start = lit_pos;
+        if let Some(TokenTree::Punct(minus)) = tokens.get(init_start.checked_sub(1)?) {
+            if minus.as_char() == '-' {
+                // Only numbers are negated in the common shape.
+                if matches!(&expr, Expr::Lit(ExprLit { lit: Lit::Int(_) | Lit::Float(_), .. })) {
+                    expr = Expr::Unary(syn::ExprUnary { attrs: Vec::new(), op: syn::UnOp::Neg(syn::token::Sub(minus.span())), expr: Box::new(expr) });
+                    init_start -= 1;
+                } else {
+                    return None;
+                }
+            }
+        }
+        let eq_pos = init_start.checked_sub(1)?;
+        let eq = match &tokens[eq_pos] {
+            TokenTree::Punct(punct) if punct.as_char() == '=' => punct.span(),
+            _ => return None,
+        };
+        if eq_pos <= ty_start {
+            return None;
+        }
+        let ty = match &tokens[ty_start..eq_pos] {
+            [TokenTree::Ident(ty)] if !KEYWORDS.contains(&&*ty.to_string()) => syn::Type::Path(syn::TypePath { qself: None, path: ty.clone().into() }),
+            ty => syn::parse2(ty.iter().cloned().collect()).ok()?,
+        };
+
+        let span = join_spans(&tokens);
+        Some(FastItem { tokens, attrs_end, documented, keyword, colon, eq, semi, span, vis, ident, ty, expr })
+    }
+
+    /// The tokens of the item with `new_expr` as its initializer and the `doc` lines
+    /// appended to its attributes, followed by `extra`, as `expand` would emit them.
+    pub(crate) fn into_tokens(self, new_expr: Option<Expr>, doc: Vec<String>, extra: TokenStream) -> TokenStream {
+        if doc.is_empty() && new_expr.is_none() {
+            let mut tokens: TokenStream = self.tokens.into_iter().collect();
+            tokens.extend(extra);
+            return tokens;
+        }
+        let span = self.span;
+        let mut tokens: TokenStream = self.tokens[..self.attrs_end].iter().cloned().collect();
+        // A blank line separates the generated text from the item's own documentation, so
+        // that it forms a paragraph of its own.
+        let blank = (self.documented && !doc.is_empty()).then(String::new);
+        for line in blank.into_iter().chain(doc) {
+            tokens.extend(quote_spanned!(span => #[doc = #line]));
+        }
+        self.vis.to_tokens(&mut tokens);
+        tokens.extend(self.keyword);
+        self.ident.to_tokens(&mut tokens);
+        syn::token::Colon(self.colon).to_tokens(&mut tokens);
+        self.ty.to_tokens(&mut tokens);
+        syn::token::Eq(self.eq).to_tokens(&mut tokens);
+        new_expr.unwrap_or(self.expr).to_tokens(&mut tokens);
+        syn::token::Semi(self.semi).to_tokens(&mut tokens);
+        tokens.extend(extra);
+        tokens
+    }
+}
+
+/// The span of the whole item, joined from its first and last tokens as `syn` does.
+fn join_spans(tokens: &[TokenTree]) -> Span {
+    let first = tokens.first().map_or_else(Span::call_site, TokenTree::span);
+    tokens.last().and_then(|last| first.join(last.span())).unwrap_or(first)
+}
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+
+use crate::ReadEnv;
+
+thread_local! {
+    /// Items that make rustc track the files and variables read while expanding the
+    /// current item, emitted with its output.
+    static TRACKED_FILES: RefCell<Vec<TokenStream>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Has the file at `path_expr`, an expression for its path, tracked for rebuilds by
+/// emitting `include_bytes!` of it with the item's output, as files read by the macro
+/// itself are invisible to rustc.
+pub(crate) fn track_file(path_expr: TokenStream) {
+    TRACKED_FILES.with(|tracked| tracked.borrow_mut().push(quote::quote! {
+        const _: &[u8] = ::core::include_bytes!(#path_expr);
+    }));
+}
+
+/// Has the variable `name`, read by the macro for itself rather than for an item, tracked
+/// for rebuilds along with the files, if `read_env` needs it.
+pub(crate) fn track_var(name: &str, read_env: &impl ReadEnv) {
+    if read_env.needs_rebuild_tracking() {
+        let tracking = crate::rebuild_tracking(&[name.to_string()], proc_macro2::Span::call_site());
+        TRACKED_FILES.with(|tracked| tracked.borrow_mut().push(tracking));
+    }
+}
+
+/// Runs `f`, returning its result and the tracking items of the files it read.
+pub(crate) fn collect_tracked_files<R>(f: impl FnOnce() -> R) -> (R, TokenStream) {
+    let outer = TRACKED_FILES.with(|tracked| tracked.take());
+    let result = f();
+    let collected = TRACKED_FILES.with(|tracked| tracked.replace(outer));
+    (result, collected.into_iter().collect())
+}
+
+/// Resolves a path taken from the environment. Relative paths are resolved against
+/// the directory of the crate being compiled.
+pub(crate) fn resolve_path(path: impl AsRef<Path>, read_env: &impl ReadEnv) -> Result<PathBuf, String> {
+    let path = path.as_ref();
+    if path.is_relative() {
+        if let Some(manifest_dir) = read_env.read_env("CARGO_MANIFEST_DIR").map_err(|err| err.to_string())? {
+            return Ok(Path::new(&manifest_dir).join(path));
+        }
+    }
+    Ok(path.to_path_buf())
+}
+
+/// The path argument of an `include_*!` invocation for `path`, resolved to `resolved`.
+/// A relative path is written against `CARGO_MANIFEST_DIR`, so that the expansion does
+/// not depend on where the macro ran.
+pub(crate) fn include_path(path: &str, resolved: &Path, read_env: &impl ReadEnv) -> Result<TokenStream, String> {
+    if Path::new(path).is_relative() && read_env.read_env("CARGO_MANIFEST_DIR").map_err(|err| err.to_string())?.is_some() {
+        let relative = path.replace('\\', "/");
+        Ok(quote::quote!(::core::concat!(::core::env!("CARGO_MANIFEST_DIR"), "/", #relative)))
+    } else {
+        let absolute = resolved.to_string_lossy();
+        Ok(quote::quote!(#absolute))
+    }
+}
+
+/// Reads the file at `path` to use its contents as a value, optionally removing a
+/// single trailing newline, and has rustc track the file so that a changed value, such
+/// as a rotated secret, rebuilds the crate.
+pub(crate) fn read_value_file(path: &str, trim_newline: bool, read_env: &impl ReadEnv) -> Result<String, String> {
+    let resolved = resolve_path(path, read_env)?;
+    let mut contents = std::fs::read_to_string(&resolved)
+        .map_err(|err| format!("Failed to read file `{}`: {}", resolved.display(), err))?;
+    track_file(include_path(path, &resolved, read_env)?);
+    if trim_newline {
+        if contents.ends_with("\r\n") {
+            contents.truncate(contents.len() - 2);
+        } else if contents.ends_with('\n') {
+            contents.truncate(contents.len() - 1);
+        }
+    }
+    Ok(contents)
+}
+
+/// Reads a file written by the crate's build script into `OUT_DIR`, returning its
+/// trimmed contents or `None` if the file does not exist.
+pub(crate) fn read_out_file(path: &str, read_env: &impl ReadEnv) -> Result<Option<String>, String> {
+    let out_dir = read_env.read_env("OUT_DIR")
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("Cannot read `{}`: `OUT_DIR` is not set, does the crate have a build script?", path))?;
+    let resolved = Path::new(&out_dir).join(path);
+    match std::fs::read(&resolved) {
+        Ok(bytes) => String::from_utf8(bytes)
+            .map(|contents| Some(contents.trim().to_string()))
+            .map_err(|err| format!("File `{}` is not valid UTF-8: {}", resolved.display(), err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("Failed to read file `{}`: {}", resolved.display(), err)),
+    }
+}
+
+/// The modification time of the source file `file`, in nanoseconds, or `-` if it cannot
+/// be read, as for the tokens of tests.
+pub(crate) fn modified_time(file: &str) -> String {
+    std::fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or_else(|| "-".to_string(), |time| time.as_nanos().to_string())
+}
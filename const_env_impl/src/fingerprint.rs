@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::registry::crate_key;
+use crate::{sha256, EnvError, ReadEnv};
+
+/// An item's contribution to the fingerprint: its value is only kept as a hash, so that
+/// the value of a `sensitive` item is never held.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    vars: String,
+    hash: String,
+}
+
+/// The items recorded for a crate, keyed by their file and name, and whether
+/// `fingerprint!()` was expanded.
+#[derive(Default)]
+struct Recorded {
+    entries: BTreeMap<(String, String), Entry>,
+    taken: bool,
+}
+
+/// The items recorded by this compiler process, per crate.
+///
+/// As with the registry, the fingerprint only covers the items of the current build, so
+/// it is kept in the macro's memory, which every invocation of a compiler process shares,
+/// instead of a file.
+static RECORDED: Mutex<BTreeMap<String, Recorded>> = Mutex::new(BTreeMap::new());
+
+/// Records the value of `item`, declared in `file` and read from `vars`. Returns `false`
+/// if `fingerprint!()` was already expanded without the item, which it then misses.
+///
+/// An item expanded again, as rust-analyzer does when it is edited, was seen by the
+/// fingerprint, so only items expanded for the first time count as missed.
+pub(crate) fn record(item: &str, file: &str, vars: &[String], value: &str, read_env: &impl ReadEnv) -> Result<bool, EnvError> {
+    let entry = Entry {
+        vars: vars.join(","),
+        hash: sha256::hex_digest(value.as_bytes())[..16].to_string(),
+    };
+    let mut recorded = RECORDED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let recorded = recorded.entry(crate_key(read_env)?).or_default();
+    let previous = recorded.entries.insert((file.to_string(), item.to_string()), entry);
+    Ok(!recorded.taken || previous.is_some())
+}
+
+/// The fingerprint of the items recorded so far for the crate being compiled, marking
+/// that it was taken so that items recorded later are reported.
+pub(crate) fn fingerprint(read_env: &impl ReadEnv) -> Result<u64, EnvError> {
+    let mut recorded = RECORDED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let recorded = recorded.entry(crate_key(read_env)?).or_default();
+    recorded.taken = true;
+    // Sorted, so that the fingerprint does not depend on the order of expansion, and
+    // without files, so that it does not change when an item moves.
+    let mut lines: Vec<String> = recorded.entries.iter()
+        .map(|((_, item), entry)| format!("{}\t{}\t{}\n", item, entry.vars, entry.hash))
+        .collect();
+    lines.sort();
+    let digest = sha256::hex_digest(lines.concat().as_bytes());
+    Ok(u64::from_str_radix(&digest[..16], 16).expect("digests are hexadecimal"))
+}
@@ -0,0 +1,109 @@
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::{ItemArgs, Rounding};
+use crate::{explain, FromEnvError};
+
+/// Parses a decimal such as `-19.99` exactly into the integer `-1999` for a scale of 2,
+/// without passing through a float. Missing fractional digits are zeros, and extra ones
+/// are an error unless `round` is given. The result keeps the default's suffix.
+pub(crate) fn fixed_point_value(args: &ItemArgs, scale: u32, var_name: &str, value: &str, expr: &Expr) -> Result<Expr, FromEnvError> {
+    let default = match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(default), .. }) => default,
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => match &**expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(default), .. }) => default,
+            _ => return Err(unsupported_default(args)),
+        },
+        _ => return Err(unsupported_default(args)),
+    };
+    let parse_error = |reason: String| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: crate::display_value(args, value),
+        kind: "a decimal number",
+        reason: Some(reason),
+        span: default.span(),
+    };
+    let (negative, magnitude) = parse_decimal(value, scale, args.round.map(|(rounding, _)| rounding)).map_err(parse_error)?;
+    let lit = syn::LitInt::new(&format!("{}{}", magnitude, default.suffix()), default.span());
+    match negative && magnitude != 0 {
+        true => Ok(syn::parse_quote!(-#lit)),
+        false => Ok(syn::parse_quote!(#lit)),
+    }
+}
+
+/// Checks that a `fixed_point` value fits in the item's integer type, which the scaled
+/// value can exceed even when the written decimal looks small.
+pub(crate) fn check_range(args: &ItemArgs, ident: &syn::Ident, ty: &syn::Type, expr: &Expr) -> syn::Result<()> {
+    let (negative, lit) = match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => (false, lit),
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => match &**expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => (true, lit),
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    let type_name = match lit.suffix() {
+        "" => match ty {
+            syn::Type::Path(path) => path.path.get_ident().map(ToString::to_string).unwrap_or_default(),
+            _ => String::new(),
+        },
+        suffix => suffix.to_string(),
+    };
+    let (min, max) = match crate::declared::int_range(&type_name) {
+        Some(range) => range,
+        None => return Ok(()),
+    };
+    let magnitude = lit.base10_parse::<u128>()?;
+    if (negative && magnitude > min) || (!negative && magnitude > max) {
+        let value = match args.sensitive {
+            true => "<redacted>".to_string(),
+            false => format!("{}{}", if negative { "-" } else { "" }, magnitude),
+        };
+        return Err(explain::error(explain::PARSE_FAILURE, lit.span(), format!(
+            "The `fixed_point` value {} of `{}` does not fit in `{}`", value, ident, type_name)));
+    }
+    Ok(())
+}
+
+fn unsupported_default(args: &ItemArgs) -> FromEnvError {
+    let span = args.fixed_point.map_or_else(proc_macro2::Span::call_site, |(_, span)| span);
+    explain::error(explain::UNSUPPORTED_DEFAULT, span, "`fixed_point` can only be used on items with an integer default").into()
+}
+
+/// The sign and the magnitude scaled by `10^scale` of a decimal with an optional sign.
+fn parse_decimal(value: &str, scale: u32, rounding: Option<Rounding>) -> Result<(bool, u128), String> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    // Either side of the point may be empty, as in `.5` or `5.`, but not both.
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err("expected digits with an optional sign and decimal point, such as `-19.99`, `.5` or `5.`".to_string());
+    }
+    let scale = scale as usize;
+    let (kept, extra) = fraction.split_at(fraction.len().min(scale));
+    if !extra.is_empty() && rounding.is_none() && extra.bytes().any(|byte| byte != b'0') {
+        return Err(format!("more than {} decimal places; add `round = \"half_up\"` or another policy to round", scale));
+    }
+    let too_large = || "the value is too large".to_string();
+    let scaled = format!("{}{}{}", whole, kept, "0".repeat(scale - kept.len()));
+    let mut magnitude = match scaled.trim_start_matches('0') {
+        "" => 0,
+        scaled => scaled.parse::<u128>().map_err(|_| too_large())?,
+    };
+    let round_up = match (rounding, extra.as_bytes().first()) {
+        (Some(Rounding::HalfUp), Some(first)) => *first >= b'5',
+        (Some(Rounding::HalfEven), Some(first)) => match first.cmp(&b'5') {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Less => false,
+            std::cmp::Ordering::Equal => extra[1..].bytes().any(|byte| byte != b'0') || magnitude % 2 == 1,
+        },
+        _ => false,
+    };
+    if round_up {
+        magnitude = magnitude.checked_add(1).ok_or_else(too_large)?;
+    }
+    Ok((negative, magnitude))
+}
@@ -0,0 +1,59 @@
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::declared::{self, DeclaredType};
+use crate::{explain, FromEnvError};
+
+/// ORs together the bits of the comma-separated flag names in `value`, matched
+/// case-insensitively. An empty value sets no bits, and a leading `+` adds the bits to
+/// those of the default. The result keeps the default's suffix, in hexadecimal, and must
+/// fit in the declared type, or the type the suffix names.
+pub(crate) fn flags_value(args: &ItemArgs, flags: &[(String, u128)], var_name: &str, value: &str, declared: Option<&DeclaredType>, expr: &Expr, span: proc_macro2::Span) -> Result<Expr, FromEnvError> {
+    let default = match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(default), .. }) => default,
+        _ => return Err(explain::error(explain::UNSUPPORTED_DEFAULT, span, "`flags` can only be used on items with an integer default").into()),
+    };
+    let (mut bits, names) = match value.trim().strip_prefix('+') {
+        Some(names) => (default.base10_parse::<u128>()?, names),
+        None => (0, value),
+    };
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        match flags.iter().find(|(flag, _)| flag.eq_ignore_ascii_case(name)) {
+            Some((_, flag_bits)) => bits |= flag_bits,
+            None => {
+                let valid: Vec<String> = flags.iter().map(|(flag, _)| format!("`{}`", flag)).collect();
+                return Err(explain::error(explain::PARSE_FAILURE, span, format!(
+                    "Unknown flag `{}` in environment variable `{}`; expected a comma-separated list of {}\n  value: {}",
+                    name, var_name, valid.join(", "), crate::display_value(args, value))).into());
+            }
+        }
+    }
+    let ty = match declared {
+        Some(DeclaredType::Int(name)) => Some(name.as_str()),
+        _ => Some(default.suffix()).filter(|suffix| !suffix.is_empty()),
+    };
+    if let Some((ty, (_, max))) = ty.and_then(|ty| declared::int_range(ty).map(|range| (ty, range))) {
+        if bits > max {
+            return Err(explain::error(explain::PARSE_FAILURE, span, format!(
+                "Environment variable `{}` sets the bits {:#x}, which do not fit in `{}`\n  value: {}",
+                var_name, bits, ty, crate::display_value(args, value))).into());
+        }
+    }
+    let lit = syn::LitInt::new(&format!("{:#x}{}", bits, default.suffix()), default.span());
+    Ok(syn::parse_quote!(#lit))
+}
+
+/// The expression of the option named by `value`, matched case-insensitively. Unknown
+/// names are an error listing the valid ones.
+pub(crate) fn option_value(args: &ItemArgs, options: &[(String, Expr)], var_name: &str, value: &str, span: proc_macro2::Span) -> Result<Expr, FromEnvError> {
+    let name = value.trim();
+    match options.iter().find(|(option, _)| option.eq_ignore_ascii_case(name)) {
+        Some((_, expr)) => Ok(expr.clone()),
+        None => {
+            let valid: Vec<String> = options.iter().map(|(option, _)| format!("`{}`", option)).collect();
+            Err(explain::error(explain::PARSE_FAILURE, span, format!(
+                "Unknown option `{}` in environment variable `{}`; expected one of {}\n  value: {}",
+                name, var_name, valid.join(", "), crate::display_value(args, value))).into())
+        }
+    }
+}
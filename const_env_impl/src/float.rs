@@ -0,0 +1,131 @@
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::declared::DeclaredType;
+use crate::list::list_elements;
+use crate::{explain, FromEnvError};
+
+/// The digits of the float literal `expr`, without its sign, underscores or suffix, and
+/// its suffix. `1f32` counts, since it is lexed as an integer with a float suffix.
+fn float_literal(expr: &Expr) -> Option<(&str, &str)> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Float(float), .. }) => Some((float.base10_digits(), float.suffix())),
+        Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) if matches!(int.suffix(), "f32" | "f64") => Some((int.base10_digits(), int.suffix())),
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => float_literal(expr),
+        Expr::Paren(paren) => float_literal(&paren.expr),
+        _ => None,
+    }
+}
+
+/// The float type of the item with default `expr`, or of its elements: the declared one,
+/// or the suffix of the default, if either names one.
+fn float_type<'a>(declared: Option<&'a DeclaredType>, expr: &'a Expr) -> Option<&'a str> {
+    let declared = match declared {
+        Some(DeclaredType::Array(elem, _)) | Some(DeclaredType::Slice(elem, _)) => Some(&**elem),
+        declared => declared,
+    };
+    if let Some(DeclaredType::Float(name)) = declared {
+        return Some(name);
+    }
+    let default = match list_elements(expr) {
+        Some(elements) => elements.first().copied()?,
+        None => expr,
+    };
+    float_literal(default).map(|(_, suffix)| suffix).filter(|suffix| !suffix.is_empty())
+}
+
+/// Whether the item with default `expr` holds floats, so `strict_precision` applies.
+pub(crate) fn is_float_item(declared: Option<&DeclaredType>, expr: &Expr) -> bool {
+    let default = match list_elements(expr) {
+        Some(elements) => elements.first().copied(),
+        None => Some(expr),
+    };
+    float_type(declared, expr).is_some() || default.and_then(float_literal).is_some()
+}
+
+/// Whether the finite decimal `digits` rounds to infinity as `ty`.
+pub(crate) fn overflows(digits: &str, ty: &str) -> bool {
+    match ty {
+        "f32" => digits.parse::<f32>().is_ok_and(f32::is_infinite),
+        _ => digits.parse::<f64>().is_ok_and(f64::is_infinite),
+    }
+}
+
+/// The value `ty` holds for the decimal `digits`, written in the fewest digits that
+/// read back as it, if that is a different number than the written one.
+fn rounded(digits: &str, ty: &str) -> Option<String> {
+    let (nearest, shortest) = match ty {
+        "f32" => digits.parse::<f32>().ok().map(|value| (format!("{:?}", value), format!("{:e}", value)))?,
+        _ => digits.parse::<f64>().ok().map(|value| (format!("{:?}", value), format!("{:e}", value)))?,
+    };
+    match canonical(digits)? == canonical(&shortest)? {
+        true => None,
+        false => Some(nearest),
+    }
+}
+
+/// The significant digits and power of ten of a decimal such as `0.10e1`, as `("1", 0)`.
+fn canonical(decimal: &str) -> Option<(String, i64)> {
+    let (mantissa, exponent) = match decimal.find(['e', 'E']) {
+        Some(index) => (&decimal[..index], decimal[index + 1..].parse::<i64>().ok()?),
+        None => (decimal, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    if significant.is_empty() {
+        return Some(("0".to_string(), 0));
+    }
+    let trailing = (digits.len() - digits.trim_end_matches('0').len()) as i64;
+    Some((significant.to_string(), exponent - frac.len() as i64 + trailing))
+}
+
+/// Checks the floats of `new_expr`, the value of `var_name`, against the float type of
+/// the item. A value that overflows to infinity is an error, and with
+/// `strict_precision`, so is one with more digits than the type keeps, which would
+/// round to a different number. Values in a list are checked one by one.
+pub(crate) fn check(args: &ItemArgs, var_name: &str, value: &str, new_expr: &Expr, declared: Option<&DeclaredType>, expr: &Expr, span: proc_macro2::Span) -> Result<(), FromEnvError> {
+    let default_type = float_type(declared, expr);
+    let elements = list_elements(new_expr);
+    let is_list = elements.is_some();
+    let mut errors = Vec::new();
+    for (index, element) in elements.unwrap_or_else(|| vec![new_expr]).into_iter().enumerate() {
+        let (digits, suffix) = match float_literal(element) {
+            Some(literal) => literal,
+            None => continue,
+        };
+        let ty = match (suffix, default_type) {
+            ("", Some(ty)) => ty,
+            ("", None) => continue,
+            (suffix, _) => suffix,
+        };
+        if overflows(digits, ty) {
+            errors.push(FromEnvError::ParseFailure {
+                var: var_name.to_string(),
+                value: crate::display_value(args, value),
+                kind: "a float",
+                reason: Some(match args.sensitive {
+                    true => format!("expected a float that fits in `{}`", ty),
+                    false => format!("expected a float that fits in `{}`, found `{}`", ty, digits),
+                }),
+                span,
+            });
+        } else if let (Some(_), Some(nearest)) = (args.strict_precision, rounded(digits, ty)) {
+            let subject = match is_list {
+                true => format!("Element {} of environment variable `{}`", index, var_name),
+                false => format!("Environment variable `{}`", var_name),
+            };
+            let sign = if matches!(element, Expr::Unary(_)) { "-" } else { "" };
+            let message = match args.sensitive {
+                true => format!("{} has more digits than `{}` keeps", subject, ty),
+                false => format!("{} is `{}{}`, which has more digits than `{}` keeps and would round to `{}{}`",
+                    subject, sign, digits, ty, sign, nearest),
+            };
+            errors.push(explain::error(explain::VALIDATION_FAILURE, span, message).into());
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(crate::combine_failures(errors)),
+    }
+}
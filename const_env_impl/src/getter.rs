@@ -0,0 +1,115 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::{ItemArgs, RenameRule};
+use crate::explain;
+
+/// How the run time value is parsed, from the kind of the written default.
+enum Kind {
+    Integer,
+    Float,
+    Bool,
+    Char,
+}
+
+/// A function named after the item, e.g. `app_port_from_env()` for `APP_PORT`, that
+/// looks up `names` again at run time and parses the first one set as the attribute
+/// would, returning `None` if none is set or the value does not parse or validate.
+///
+/// Integers accept underscores and the `0x`, `0o` and `0b` prefixes, but not suffixes.
+pub(crate) fn runtime_getter(args: &ItemArgs, vis: &syn::Visibility, ident: &syn::Ident, ty: &syn::Type, expr: &Expr, names: &[String], flag_span: Span) -> syn::Result<TokenStream> {
+    if !matches!(ty, syn::Type::Path(_) | syn::Type::Paren(_) | syn::Type::Group(_)) {
+        return Err(explain::error(explain::UNSUPPORTED_DEFAULT, flag_span,
+            "`emit_runtime_getter` needs a type that implements `FromStr`, such as a number, `bool` or `char`"));
+    }
+    if kind(expr).is_none() {
+        return Err(no_equivalent(flag_span));
+    }
+    let fn_ident = syn::Ident::new(&format!("{}_from_env", RenameRule::ScreamingSnakeCase.apply(ident).to_lowercase()), ident.span());
+    let names: Vec<syn::LitStr> = names.iter().map(|name| syn::LitStr::new(name, ident.span())).collect();
+    if args.exists.is_some() {
+        let set = args.invert.is_none();
+        return Ok(quote_spanned! {ident.span() =>
+            #vis fn #fn_ident() -> Option<#ty> {
+                match [#(#names),*].iter().any(|name| ::std::env::var_os(name).is_some()) {
+                    true => Some(#set),
+                    false => None,
+                }
+            }
+        });
+    }
+    let parse = parse_value(args, ty, expr);
+    Ok(quote_spanned! {ident.span() =>
+        #vis fn #fn_ident() -> Option<#ty> {
+            let value = [#(#names),*].iter().find_map(|name| ::std::env::var(name).ok())?;
+            #parse
+            Some(value)
+        }
+    })
+}
+
+/// The kind of the written default, or `None` if the getter has no run time equivalent.
+fn kind(expr: &Expr) -> Option<Kind> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(_), .. }) => Some(Kind::Integer),
+        Expr::Lit(ExprLit { lit: Lit::Float(_), .. }) => Some(Kind::Float),
+        Expr::Lit(ExprLit { lit: Lit::Bool(_), .. }) => Some(Kind::Bool),
+        Expr::Lit(ExprLit { lit: Lit::Char(_), .. }) => Some(Kind::Char),
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => match &**expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(_), .. }) => Some(Kind::Integer),
+            Expr::Lit(ExprLit { lit: Lit::Float(_), .. }) => Some(Kind::Float),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Statements parsing the `String` named `value` into a `#ty` named `value`, as the
+/// attribute would for an item whose default is `expr`, with `?` failing when the value
+/// does not parse or validate. Defaults of other kinds are parsed with `FromStr`.
+pub(crate) fn parse_value(args: &ItemArgs, ty: &syn::Type, expr: &Expr) -> TokenStream {
+    let mut parse = match kind(expr) {
+        Some(Kind::Integer) => quote! {
+            let value = value.trim().replace('_', "");
+            let (sign, digits) = match value.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", &value[..]),
+            };
+            let (radix, digits) = match digits.get(..2) {
+                Some("0x") => (16, &digits[2..]),
+                Some("0o") => (8, &digits[2..]),
+                Some("0b") => (2, &digits[2..]),
+                _ => (10, digits),
+            };
+            let value = <#ty>::from_str_radix(&format!("{}{}", sign, digits), radix).ok()?;
+        },
+        Some(Kind::Float) => quote! {
+            let value = value.trim().replace('_', "").parse::<#ty>().ok()?;
+        },
+        Some(Kind::Bool) if args.invert.is_some() => quote! {
+            let value = !value.trim().parse::<#ty>().ok()?;
+        },
+        Some(Kind::Bool) => quote! {
+            let value = value.trim().parse::<#ty>().ok()?;
+        },
+        Some(Kind::Char) | None => quote! {
+            let value = value.parse::<#ty>().ok()?;
+        },
+    };
+    if let Some(min) = &args.min {
+        parse.extend(quote!(if value < #min { return None; }));
+    }
+    if let Some(max) = &args.max {
+        parse.extend(quote!(if value > #max { return None; }));
+    }
+    if let Some((allowed, _)) = &args.one_of {
+        parse.extend(quote!(if ![#(#allowed),*].contains(&value) { return None; }));
+    }
+    parse
+}
+
+fn no_equivalent(flag_span: Span) -> syn::Error {
+    explain::error(explain::UNSUPPORTED_DEFAULT, flag_span,
+        "`emit_runtime_getter` can only be used on items with a number, `bool` or `char` default; other literals have no run time equivalent")
+}
@@ -0,0 +1,481 @@
+use syn::spanned::Spanned;
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::declared::DeclaredType;
+use crate::FromEnvError;
+
+/// A parsed JSON value. Numbers keep their text, so that they convert to literals
+/// without passing through a float.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "`null`",
+            Json::Bool(_) => "a boolean",
+            Json::Number(_) => "a number",
+            Json::String(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+}
+
+/// Fills a struct literal default, such as `NetCfg { port: 8080, backlog: 64 }`, from
+/// a JSON object whose keys name its fields. Each field keeps the kind of literal it
+/// was written with, and nested objects fill nested struct literals. Unknown keys are an
+/// error, and so are missing fields unless `partial` is given.
+pub(crate) fn struct_value(args: &ItemArgs, var_name: &str, value: &str, expr: &Expr) -> Result<Expr, FromEnvError> {
+    let parse_error = |reason: String| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: crate::display_value(args, value),
+        kind: "a JSON object",
+        reason: Some(reason),
+        span: expr.span(),
+    };
+    let json = Parser { input: value, pos: 0 }.parse_document().map_err(parse_error)?;
+    fill(args, expr, &json, "").map_err(|problems| crate::combine_failures(problems.into_iter().map(parse_error).collect()))
+}
+
+/// Converts the value found at `pointer`, an RFC 6901 JSON pointer such as
+/// `/server/port`, in the JSON document `value` to the kind of literal of the default,
+/// or of the declared type for defaults that are not literals.
+pub(crate) fn pointer_value(args: &ItemArgs, pointer: &str, var_name: &str, value: &str, declared: Option<&DeclaredType>, expr: &Expr) -> Result<Expr, FromEnvError> {
+    let parse_error = |reason: String| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: crate::display_value(args, value),
+        kind: "JSON",
+        reason: Some(reason),
+        span: expr.span(),
+    };
+    let json = Parser { input: value, pos: 0 }.parse_document().map_err(parse_error)?;
+    let found = resolve(&json, pointer).map_err(parse_error)?;
+    let template = declared.and_then(|declared| declared.template_for(expr));
+    let new_expr = fill(args, template.as_ref().unwrap_or(expr), found, pointer)
+        .map_err(|problems| crate::combine_failures(problems.into_iter().map(parse_error).collect()))?;
+    if let Some(declared) = declared {
+        declared.check_value(&new_expr).map_err(|mismatches| crate::combine_failures(mismatches.iter()
+            .map(|mismatch| parse_error(format!("{} at `{}`", mismatch, pointer)))
+            .collect()))?;
+    }
+    Ok(new_expr)
+}
+
+/// The reference tokens of a JSON pointer, with `~1` and `~0` unescaped to `/` and `~`.
+pub(crate) fn pointer_tokens(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/').ok_or("a JSON pointer must be empty or start with `/`")?;
+    rest.split('/')
+        .map(|token| {
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    unescaped.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => unescaped.push('~'),
+                    Some('1') => unescaped.push('/'),
+                    _ => return Err(format!("`~` in `{}` must be followed by `0` or `1`", token)),
+                }
+            }
+            Ok(unescaped)
+        })
+        .collect()
+}
+
+/// The value that `pointer` refers to in `json`.
+fn resolve<'a>(json: &'a Json, pointer: &str) -> Result<&'a Json, String> {
+    let tokens = pointer_tokens(pointer)?;
+    let raw_tokens = pointer.split('/').skip(1);
+    let mut current = json;
+    let mut at = String::new();
+    for (token, raw_token) in tokens.iter().zip(raw_tokens) {
+        let location = match at.is_empty() {
+            true => "the root".to_string(),
+            false => format!("`{}`", at),
+        };
+        current = match current {
+            Json::Object(entries) => entries.iter().rev()
+                .find(|(key, _)| key == token)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("no key `{}` in the object at {}", token, location))?,
+            Json::Array(elements) => {
+                // Indices are decimal, without a sign or leading zeros.
+                let canonical = token.bytes().all(|b| b.is_ascii_digit()) && (token == "0" || !token.starts_with('0'));
+                let index = match token.parse::<usize>() {
+                    Ok(index) if canonical => index,
+                    _ => return Err(format!("`{}` is not an index of the array at {}", token, location)),
+                };
+                elements.get(index).ok_or_else(|| format!(
+                    "index {} is out of bounds of the array of {} elements at {}", index, elements.len(), location))?
+            }
+            other => return Err(format!("expected an object or array at {}, found {}", location, other.kind())),
+        };
+        at.push('/');
+        at.push_str(raw_token);
+    }
+    Ok(current)
+}
+
+/// The replacement for `template` from `json`, with `path` naming the field in errors.
+/// The problems of every field and element are reported, not only the first.
+fn fill(args: &ItemArgs, template: &Expr, json: &Json, path: &str) -> Result<Expr, Vec<String>> {
+    let at = || match path {
+        "" => String::new(),
+        path => format!(" at `{}`", path),
+    };
+    let mismatch = |expected: &str| vec![format!("expected {}{}, found {}", expected, at(), json.kind())];
+    match (template, json) {
+        (Expr::Struct(template), Json::Object(entries)) => {
+            let mut filled = template.clone();
+            let mut problems = Vec::new();
+            for (index, (key, _)) in entries.iter().enumerate() {
+                if entries[..index].iter().any(|(earlier, _)| earlier == key) {
+                    problems.push(format!("duplicate key `{}`{}", key, at()));
+                }
+            }
+            for (key, value) in entries {
+                let field = filled.fields.iter_mut().find(|field| matches!(&field.member, syn::Member::Named(name) if syn::ext::IdentExt::unraw(name) == key));
+                let field = match field {
+                    Some(field) => field,
+                    None => {
+                        problems.push(format!("unknown key `{}`{}", key, at()));
+                        continue;
+                    }
+                };
+                let field_path = match path {
+                    "" => key.clone(),
+                    path => format!("{}.{}", path, key),
+                };
+                match fill(args, &field.expr, value, &field_path) {
+                    Ok(expr) => field.expr = expr,
+                    Err(field_problems) => problems.extend(field_problems),
+                }
+                // A shorthand field such as `Point { x }` needs its value spelled out.
+                field.colon_token.get_or_insert_with(Default::default);
+            }
+            if args.partial.is_none() {
+                let missing: Vec<String> = filled.fields.iter()
+                    .filter_map(|field| match &field.member {
+                        syn::Member::Named(name) => Some(syn::ext::IdentExt::unraw(name).to_string()),
+                        syn::Member::Unnamed(_) => None,
+                    })
+                    .filter(|name| !entries.iter().any(|(key, _)| key == name))
+                    .map(|name| format!("`{}`", name))
+                    .collect();
+                if !missing.is_empty() {
+                    problems.push(format!("missing {}{}; add `partial` to keep the defaults of missing fields", missing.join(", "), at()));
+                }
+            }
+            match problems.is_empty() {
+                true => Ok(Expr::Struct(filled)),
+                false => Err(problems),
+            }
+        }
+        (Expr::Struct(_), _) => Err(mismatch("an object")),
+        (Expr::Array(template), Json::Array(elements)) => {
+            let first = template.elems.first().ok_or_else(|| vec![format!("cannot infer the element kind of the empty array{}", at())])?;
+            let mut filled = template.clone();
+            filled.elems.clear();
+            let mut problems = Vec::new();
+            for (index, element) in elements.iter().enumerate() {
+                match fill(args, first, element, &format!("{}[{}]", path, index)) {
+                    Ok(expr) => filled.elems.push(expr),
+                    Err(element_problems) => problems.extend(element_problems),
+                }
+            }
+            match problems.is_empty() {
+                true => Ok(Expr::Array(filled)),
+                false => Err(problems),
+            }
+        }
+        (Expr::Array(_), _) => Err(mismatch("an array")),
+        (Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }), _) => fill(args, expr, json, path),
+        (Expr::Paren(paren), _) => fill(args, &paren.expr, json, path),
+        (Expr::Lit(ExprLit { lit, .. }), json) => {
+            let new_expr = literal(lit, json).ok_or_else(|| mismatch(lit_kind(lit)))?;
+            // The suffix of the default names the type of the field, which the value must
+            // fit in, as for an item of that type.
+            // The value is checked without the suffix, so that errors show it as written.
+            let suffix_type = match lit {
+                Lit::Int(int) if crate::declared::int_range(int.suffix()).is_some() => {
+                    Some((DeclaredType::Int(int.suffix().to_string()), Lit::Int(syn::LitInt::new(int.base10_digits(), int.span()))))
+                }
+                Lit::Float(float) if matches!(float.suffix(), "f32" | "f64") => {
+                    Some((DeclaredType::Float(float.suffix().to_string()), Lit::Float(syn::LitFloat::new(float.base10_digits(), float.span()))))
+                }
+                _ => None,
+            };
+            let checked = suffix_type.and_then(|(declared, unsuffixed)| literal(&unsuffixed, json).map(|expr| declared.check_value(&expr)));
+            match checked {
+                Some(Err(mismatches)) => Err(mismatches.iter().map(|mismatch| format!("{}{}", mismatch, at())).collect()),
+                _ => Ok(new_expr),
+            }
+        }
+        _ => Err(vec![format!("the default{} is not a literal, array or struct literal", at())]),
+    }
+}
+
+/// The literal of the same kind as `template` holding `json`, if they agree.
+fn literal(template: &Lit, json: &Json) -> Option<Expr> {
+    let span = template.span();
+    let lit = match (template, json) {
+        (Lit::Str(_), Json::String(value)) => Lit::Str(syn::LitStr::new(value, span)),
+        (Lit::ByteStr(_), Json::String(value)) => Lit::ByteStr(syn::LitByteStr::new(value.as_bytes(), span)),
+        (Lit::Char(_), Json::String(value)) => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Lit::Char(syn::LitChar::new(c, span)),
+                _ => return None,
+            }
+        }
+        (Lit::Bool(_), Json::Bool(value)) => Lit::Bool(syn::LitBool::new(*value, span)),
+        (Lit::Int(int), Json::Number(number)) if !number.contains(['.', 'e', 'E']) => {
+            let (negative, digits) = split_sign(number);
+            let lit = syn::LitInt::new(&format!("{}{}", digits, int.suffix()), span);
+            return Some(negate(negative, Lit::Int(lit)));
+        }
+        (Lit::Float(float), Json::Number(number)) => {
+            let (negative, digits) = split_sign(number);
+            // A JSON integer gets a point so that it reads as a float literal.
+            let digits = match digits.contains(['.', 'e', 'E']) {
+                true => digits.to_string(),
+                false => format!("{}.0", digits),
+            };
+            let lit = syn::LitFloat::new(&format!("{}{}", digits, float.suffix()), span);
+            return Some(negate(negative, Lit::Float(lit)));
+        }
+        _ => return None,
+    };
+    Some(Expr::Lit(ExprLit { attrs: Vec::new(), lit }))
+}
+
+fn split_sign(number: &str) -> (bool, &str) {
+    match number.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, number),
+    }
+}
+
+fn negate(negative: bool, lit: Lit) -> Expr {
+    match negative {
+        true => syn::parse_quote!(-#lit),
+        false => syn::parse_quote!(#lit),
+    }
+}
+
+fn lit_kind(lit: &Lit) -> &'static str {
+    match lit {
+        Lit::Str(_) | Lit::ByteStr(_) => "a string",
+        Lit::Char(_) => "a string of one character",
+        Lit::Bool(_) => "a boolean",
+        Lit::Int(_) => "an integer",
+        Lit::Float(_) => "a number",
+        _ => "a value this literal can hold",
+    }
+}
+
+/// A recursive descent parser for the JSON grammar of RFC 8259.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse_document(&mut self) -> Result<Json, String> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        match self.pos == self.input.len() {
+            true => Ok(value),
+            false => Err(self.error("unexpected characters after the value")),
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at offset {}", message, self.pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            _ => Err(self.error(&format!("expected `{}`", expected))),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        for (keyword, value) in [("null", Json::Null), ("true", Json::Bool(true)), ("false", Json::Bool(false))] {
+            if rest.starts_with(keyword) {
+                self.pos += keyword.len();
+                return Ok(value);
+            }
+        }
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('-' | '0'..='9') => self.parse_number(),
+            _ => Err(self.error("expected a JSON value")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(entries));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(elements));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += escape.len_utf8();
+                    match escape {
+                        '"' | '\\' | '/' => value.push(escape),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{c}'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        'u' => value.push(self.parse_unicode_escape()?),
+                        _ => return Err(self.error(&format!("invalid escape `\\{}`", escape))),
+                    }
+                }
+                c if c < ' ' => return Err(self.error("control character in string")),
+                c => value.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\uXXXX` escape, combining a surrogate pair.
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.parse_hex4()?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.input[self.pos..].starts_with("\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.pos += 2;
+                let low = self.parse_hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let hex = self.input.get(self.pos..self.pos + 4).ok_or_else(|| self.error("expected four hex digits"))?;
+        let code = u32::from_str_radix(hex, 16).map_err(|_| self.error("expected four hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let rest = &self.input[self.pos..];
+        let len = rest.find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')).unwrap_or(rest.len());
+        let number = &rest[..len];
+        if !is_json_number(number) {
+            return Err(self.error(&format!("invalid number `{}`", number)));
+        }
+        self.pos += len;
+        Ok(Json::Number(number.to_string()))
+    }
+}
+
+/// Whether `number` follows the JSON number grammar: an optional minus, an integer part
+/// without leading zeros, then an optional fraction and exponent.
+fn is_json_number(number: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|byte| byte.is_ascii_digit());
+    let unsigned = number.strip_prefix('-').unwrap_or(number);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(index) => (&unsigned[..index], Some(&unsigned[index + 1..])),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let exponent_ok = exponent.is_none_or(|exponent| digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent)));
+    digits(whole) && (whole == "0" || !whole.starts_with('0')) && fraction.is_none_or(digits) && exponent_ok
+}
@@ -1,16 +1,435 @@
-use std::collections::HashMap;
+#![cfg_attr(feature = "nightly-diagnostics", feature(proc_macro_diagnostic))]
+
+extern crate proc_macro;
+
+mod args;
+mod cargo_metadata;
+mod claims;
+#[cfg(feature = "command-source")]
+mod command;
+mod config;
+mod declared;
+mod derive;
+mod diagnostics;
+mod dotenv;
+mod duration;
+mod endian;
+mod expand;
+mod explain;
+#[cfg(feature = "fast-parse")]
+mod fast_parse;
+mod file;
+mod fingerprint;
+mod fixed_point;
+mod float;
+mod flags;
+mod getter;
+mod json;
+mod list;
+mod locate;
+mod lockfile;
+mod obfuscate;
+mod pad;
+mod prefix_map;
+mod presence;
+mod pretty;
+mod ranges;
+mod regex;
+mod registry;
+mod report;
+#[cfg(feature = "cli")]
+mod scan;
+mod sha256;
+mod split;
+mod system_vars;
+mod template;
+mod toml;
+mod validate;
+mod value;
+mod var_manifest;
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
 
 use proc_macro2::TokenStream;
-use quote::{ToTokens, quote_spanned};
+use quote::{ToTokens, quote, quote_spanned};
 use syn::{Expr, ExprLit, Lit};
 use syn::spanned::Spanned;
 
+use args::{Case, ItemArgs};
+use cargo_metadata::CargoMetadata;
+use config::ConfigFile;
+use declared::DeclaredType;
+use template::Segment;
+
+pub use cargo_metadata::CargoMetadataEnv;
+pub use dotenv::{DotenvEnv, DotenvError};
+pub use explain::explain;
+#[cfg(feature = "cli")]
+pub use scan::{scan_dir, scan_source, Scan, ScannedItem, Unresolved};
+pub use toml::{TomlEnv, TomlEnvBuilder, TomlError};
+pub use var_manifest::{consumed_vars, emit_rerun_directives};
+
 pub trait ReadEnv {
-    fn read_env(&self, var_name: &String) -> Option<String>;
+    /// Reads a variable, returning `None` if it is unset. Values are returned as stored
+    /// by the source and need not be valid UTF-8.
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError>;
+
+    /// Reads a variable whose value must be valid UTF-8, failing with
+    /// `EnvError::NotUnicode` if it is not.
+    fn read_env_utf8(&self, var_name: &str) -> Result<Option<String>, EnvError> {
+        match self.read_env(var_name)? {
+            Some(value) => value.into_owned().into_string().map(Some).map_err(|value| EnvError::NotUnicode {
+                name: var_name.to_string(),
+                value
+            }),
+            None => Ok(None)
+        }
+    }
+
+    /// Lists the names of all variables in the environment. Sources that cannot be
+    /// enumerated return an empty list.
+    fn var_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether expansions should reference every variable they read with `option_env!`,
+    /// so that the compiler rebuilds the crate when one changes. Sources that read the
+    /// compiler's environment without telling it, as the stable macros do, return `true`.
+    fn needs_rebuild_tracking(&self) -> bool {
+        false
+    }
+
+    /// Layers `next` below this source, so that variables unset here are looked up
+    /// there.
+    fn chain<B: ReadEnv>(self, next: B) -> ChainEnv<Self, B> where Self: Sized {
+        ChainEnv { first: self, second: next }
+    }
+}
+
+/// An error reading a variable from a `ReadEnv` source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EnvError {
+    /// The variable is set, but its value is not valid UTF-8.
+    NotUnicode { name: String, value: OsString },
+    /// The source failed to look the variable up.
+    Lookup { name: String, message: String },
+}
+
+impl std::fmt::Display for EnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvError::NotUnicode { name, value } => {
+                let bytes = os_str_bytes(value);
+                let offset = std::str::from_utf8(&bytes).err().map_or(bytes.len(), |err| err.valid_up_to());
+                write!(f, "Environment variable `{}` is not valid UTF-8 (invalid byte at offset {})", name, offset)
+            }
+            EnvError::Lookup { name, message } => write!(f, "Failed to read environment variable `{}`: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+/// An error expanding `from_env`, as returned by `try_from_env`. Its `Display` output is
+/// the message the macro reports, starting with the error's code.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum FromEnvError {
+    /// The attribute is applied to something other than a `const` or `static` item.
+    UnsupportedItem { span: proc_macro2::Span },
+    /// The attribute arguments are malformed, unknown or conflicting.
+    BadAttribute(syn::Error),
+    /// The item is `required`, but none of the variables it reads is set. `suggestion`
+    /// names a similarly named variable that is set, if any.
+    MissingVariable { names: Vec<String>, suggestion: Option<String>, span: proc_macro2::Span },
+    /// The value of `var` cannot be parsed as `kind`, the kind of the default. `value` is
+    /// shown as in diagnostics, so it is redacted for `sensitive` items.
+    ParseFailure { var: String, value: String, kind: &'static str, reason: Option<String>, span: proc_macro2::Span },
+    /// Any other error, such as a failed validation or an unreadable file.
+    Other(syn::Error),
+}
+
+impl FromEnvError {
+    /// The code of the error, such as `"CE0001"`, which `explain` describes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FromEnvError::UnsupportedItem { .. } => explain::UNSUPPORTED_ITEM,
+            FromEnvError::BadAttribute(_) => explain::INVALID_ARGUMENT,
+            FromEnvError::MissingVariable { .. } => explain::MISSING_REQUIRED,
+            FromEnvError::ParseFailure { .. } => explain::PARSE_FAILURE,
+            FromEnvError::Other(err) => explain::code_of(&err.to_string()).unwrap_or(explain::INVALID_ARGUMENT),
+        }
+    }
+
+    /// The location the error is reported at.
+    pub fn span(&self) -> proc_macro2::Span {
+        match self {
+            FromEnvError::UnsupportedItem { span }
+            | FromEnvError::MissingVariable { span, .. }
+            | FromEnvError::ParseFailure { span, .. } => *span,
+            FromEnvError::BadAttribute(err) | FromEnvError::Other(err) => err.span(),
+        }
+    }
+
+    /// Converts the error into `compile_error!` invocations, as `from_env` reports it.
+    pub fn to_compile_error(&self) -> TokenStream {
+        syn::Error::from(self.clone()).to_compile_error()
+    }
+}
+
+impl std::fmt::Display for FromEnvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] ", self.code())?;
+        match self {
+            FromEnvError::UnsupportedItem { .. } => write!(f, "`env_item` can only be applied to `const` and `static` items"),
+            FromEnvError::MissingVariable { names, suggestion, .. } => {
+                let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+                write!(f, "Environment variable {} is required but not set", names.join(" or "))?;
+                match suggestion {
+                    Some(suggestion) => write!(f, "; {}", suggestion),
+                    None => Ok(()),
+                }
+            }
+            FromEnvError::ParseFailure { var, value, kind, reason, .. } => {
+                let reason = reason.as_ref().map(|reason| format!(": {}", reason)).unwrap_or_default();
+                write!(f, "Failed to parse environment variable `{}` as {}{}\n  value: {}", var, kind, reason, value)
+            }
+            // These already carry their code, and hold one message per diagnostic.
+            FromEnvError::BadAttribute(err) | FromEnvError::Other(err) => {
+                let messages: Vec<String> = err.clone().into_iter().map(|err| err.to_string()).collect();
+                let messages = messages.join("\n");
+                f.write_str(messages.strip_prefix(&format!("[{}] ", self.code())).unwrap_or(&messages))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromEnvError {}
+
+impl From<syn::Error> for FromEnvError {
+    fn from(err: syn::Error) -> Self {
+        FromEnvError::Other(err)
+    }
+}
+
+impl From<FromEnvError> for syn::Error {
+    fn from(err: FromEnvError) -> Self {
+        match err {
+            FromEnvError::BadAttribute(err) | FromEnvError::Other(err) => err,
+            err => syn::Error::new(err.span(), err.to_string()),
+        }
+    }
+}
+
+/// Reports every error of `errors`, which holds at least one, in a single error. A lone
+/// error is returned as it is.
+pub(crate) fn combine_failures(mut errors: Vec<FromEnvError>) -> FromEnvError {
+    match errors.len() {
+        1 => errors.remove(0),
+        _ => FromEnvError::Other(explain::combine(errors.into_iter().map(syn::Error::from)).expect("at least one error is combined")),
+    }
+}
+
+/// Converts a value from the environment into bytes. Outside Unix, values that are not
+/// valid Unicode cannot be represented exactly and are converted lossily.
+fn os_str_bytes(value: &OsStr) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        Cow::Borrowed(std::os::unix::ffi::OsStrExt::as_bytes(value))
+    }
+    #[cfg(not(unix))]
+    {
+        match value.to_string_lossy() {
+            Cow::Borrowed(value) => Cow::Borrowed(value.as_bytes()),
+            Cow::Owned(value) => Cow::Owned(value.into_bytes()),
+        }
+    }
+}
+
+/// Looks variables up by calling the closure, which returns `None` for unset variables.
+impl<F: Fn(&str) -> Option<String>> ReadEnv for F {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self(var_name).map(|value| Cow::Owned(value.into())))
+    }
+}
+
+impl ReadEnv for HashMap<String, String> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.get(var_name).map(|value| Cow::Borrowed(OsStr::new(value))))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+impl ReadEnv for BTreeMap<String, String> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.get(var_name).map(|value| Cow::Borrowed(OsStr::new(value))))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+/// Looks variables up in a list of `(name, value)` pairs. If a name appears more than
+/// once, the first pair wins.
+impl ReadEnv for &[(&str, &str)] {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.iter().find(|(name, _)| *name == var_name).map(|(_, value)| Cow::Borrowed(OsStr::new(*value))))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.iter().map(|(name, _)| name.to_string()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Looks variables up in `first` and then in `second`, created by `ReadEnv::chain`. A
+/// variable unset in `first` is looked up in `second`, but an error from `first` is
+/// returned as is rather than hidden by a value from `second`.
+pub struct ChainEnv<A, B> {
+    first: A,
+    second: B
+}
+
+impl<A: ReadEnv, B: ReadEnv> ReadEnv for ChainEnv<A, B> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        match self.first.read_env(var_name)? {
+            Some(value) => Ok(Some(value)),
+            None => self.second.read_env(var_name)
+        }
+    }
+
+    fn needs_rebuild_tracking(&self) -> bool {
+        self.first.needs_rebuild_tracking() || self.second.needs_rebuild_tracking()
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        let mut names = self.first.var_names();
+        names.extend(self.second.var_names());
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Wraps a source and records every variable looked up through it, in order, along
+/// with whether it was set. Pass it to the macros by reference to inspect the lookups
+/// afterwards.
+pub struct RecordingEnv<R> {
+    inner: R,
+    queries: RefCell<Vec<(String, bool)>>
+}
+
+impl<R: ReadEnv> RecordingEnv<R> {
+    pub fn new(inner: R) -> Self {
+        RecordingEnv { inner, queries: RefCell::new(Vec::new()) }
+    }
+
+    /// The variables looked up so far, in order, each with whether it was set. A lookup
+    /// that failed counts as unset.
+    pub fn queries(&self) -> Vec<(String, bool)> {
+        self.queries.borrow().clone()
+    }
+
+    /// Panics unless exactly the variables in `expected` were looked up, in order.
+    #[track_caller]
+    pub fn assert_read(&self, expected: &[&str]) {
+        let queries = self.queries.borrow();
+        let actual: Vec<&str> = queries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(actual, expected, "unexpected environment variable lookups");
+    }
+}
+
+impl<R: ReadEnv> ReadEnv for RecordingEnv<R> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        let value = self.inner.read_env(var_name);
+        self.queries.borrow_mut().push((var_name.to_string(), matches!(value, Ok(Some(_)))));
+        value
+    }
+
+    fn needs_rebuild_tracking(&self) -> bool {
+        self.inner.needs_rebuild_tracking()
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.inner.var_names()
+    }
+}
+
+impl<R: ReadEnv> ReadEnv for &RecordingEnv<R> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        (**self).read_env(var_name)
+    }
+
+    fn needs_rebuild_tracking(&self) -> bool {
+        (**self).needs_rebuild_tracking()
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        (**self).var_names()
+    }
+}
+
+/// Wraps a source and looks every variable up under a prefix, so that reading `PORT`
+/// through `PrefixedEnv::new("MYAPP_", inner)` reads `MYAPP_PORT` from `inner`. The
+/// macros' own settings, such as `CONST_ENV_STRICT`, are looked up under the prefix too.
+/// Since expansions would track the unprefixed names, rebuild tracking is never requested.
+pub struct PrefixedEnv<R> {
+    prefix: String,
+    inner: R,
+    fallthrough: bool
+}
+
+impl<R: ReadEnv> PrefixedEnv<R> {
+    pub fn new(prefix: impl Into<String>, inner: R) -> Self {
+        PrefixedEnv { prefix: prefix.into(), inner, fallthrough: false }
+    }
+
+    /// Also looks up the unprefixed name when the prefixed one is unset.
+    pub fn fallthrough(mut self) -> Self {
+        self.fallthrough = true;
+        self
+    }
+}
+
+impl<R: ReadEnv> ReadEnv for PrefixedEnv<R> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        match self.inner.read_env(&format!("{}{}", self.prefix, var_name))? {
+            Some(value) => Ok(Some(value)),
+            None if self.fallthrough => self.inner.read_env(var_name),
+            None => Ok(None)
+        }
+    }
+
+    /// The names under the prefix with the prefix removed, and with `fallthrough` also
+    /// every name of the inner source.
+    fn var_names(&self) -> Vec<String> {
+        let inner_names = self.inner.var_names();
+        let mut names: Vec<String> = inner_names.iter()
+            .filter_map(|name| name.strip_prefix(self.prefix.as_str()))
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        if self.fallthrough {
+            names.extend(inner_names);
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
 }
 
 pub struct TestEnv {
-    env_vars: HashMap<String, String>
+    env_vars: HashMap<String, OsString>
 }
 
 impl TestEnv {
@@ -19,21 +438,86 @@ impl TestEnv {
             env_vars: HashMap::new()
         }
     }
+
+    /// Captures the environment of the current process, so that later changes to it do
+    /// not affect the test.
+    pub fn snapshot() -> TestEnv {
+        TestEnvBuilder::from_snapshot().build()
+    }
+
+    /// The number of variables that are set.
+    pub fn len(&self) -> usize {
+        self.env_vars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.env_vars.is_empty()
+    }
+
+    /// Whether the variable is set, whatever its value.
+    pub fn contains(&self, name: &str) -> bool {
+        self.env_vars.contains_key(name)
+    }
+}
+
+impl FromIterator<(String, String)> for TestEnv {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(vars: I) -> Self {
+        TestEnv::builder().extend(vars).build()
+    }
 }
 
 impl ReadEnv for TestEnv {
-    fn read_env(&self, var_name: &String) -> Option<String> {
-        self.env_vars.get(var_name).cloned()
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.env_vars.get(var_name).map(|value| Cow::Borrowed(value.as_os_str())))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.env_vars.keys().cloned().collect()
     }
 }
 
 pub struct TestEnvBuilder {
-    env_vars: HashMap<String, String>
+    env_vars: HashMap<String, OsString>
 }
 
 impl TestEnvBuilder {
+    /// Starts from the environment of the current process. Values are kept as they are,
+    /// including ones that are not valid UTF-8, while variables whose names are not
+    /// valid UTF-8 are left out.
+    pub fn from_snapshot() -> Self {
+        let env_vars = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .collect();
+        TestEnvBuilder { env_vars }
+    }
+
     pub fn set(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
-        self.env_vars.insert(name.into(), value.into());
+        self.env_vars.insert(name.into(), value.into().into());
+        self
+    }
+
+    /// Sets every variable in `vars`, replacing earlier values of the same names.
+    pub fn extend<N: Into<String>, V: Into<String>>(mut self, vars: impl IntoIterator<Item = (N, V)>) -> Self {
+        for (name, value) in vars {
+            self = self.set(name, value);
+        }
+        self
+    }
+
+    /// Unsets a variable, such as one set by an earlier call or taken from a snapshot.
+    pub fn remove(mut self, name: &str) -> Self {
+        self.env_vars.remove(name);
+        self
+    }
+
+    /// Sets a variable to raw bytes, which may not be valid UTF-8, in which case
+    /// `read_env_utf8` fails for it. Outside Unix, such values are converted lossily.
+    pub fn set_bytes(mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> Self {
+        #[cfg(unix)]
+        let value: OsString = std::os::unix::ffi::OsStringExt::from_vec(value.into());
+        #[cfg(not(unix))]
+        let value: OsString = String::from_utf8_lossy(&value.into()).into_owned().into();
+        self.env_vars.insert(name.into(), value);
         self
     }
 
@@ -53,13 +537,13 @@ impl syn::parse::Parse for MacroInput {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let args = syn::punctuated::Punctuated::<syn::Expr, syn::token::Comma>::parse_terminated(input)?;
         if args.len() != 2 {
-            return Err(syn::Error::new(input.span(), "Exactly 2 arguments expected"));
+            return Err(explain::error(explain::INVALID_ARGUMENT, input.span(), "Exactly 2 arguments expected"));
         }
         let env_var_name = match args.first().unwrap() {
             Expr::Lit(ExprLit { lit: syn::Lit::Str(lit_str), .. }) => {
                 lit_str.clone()
             },
-            otherwise => return Err(syn::Error::new(otherwise.span(), "Expected first argument to be a string literal"))
+            otherwise => return Err(explain::error(explain::INVALID_ARGUMENT, otherwise.span(), "Expected first argument to be a string literal"))
         };
         let default_value = args.last().unwrap().clone();
         Ok(Self {
@@ -73,15 +557,22 @@ impl syn::parse::Parse for MacroInput {
 pub fn env_lit(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
     let input: MacroInput = match syn::parse2(tokens) {
         Ok(input) => input,
+        Err(err) => return explain::with_code(explain::INVALID_ARGUMENT, err).to_compile_error()
+    };
+    // A literal may stand in a pattern, where no item can, so the `.env` file is not
+    // tracked for rebuilds.
+    let read_env = match file::collect_tracked_files(|| with_dotenv(read_env)).0 {
+        Ok(read_env) => read_env,
         Err(err) => return err.to_compile_error()
     };
-    let env_var_value = match read_env.read_env(&input.env_var_name.value()) {
-        Some(env_var_value) => env_var_value,
-        None => return input.default_value.into_token_stream()
+    let env_var_value = match read_env.read_env_utf8(&input.env_var_name.value()) {
+        Ok(Some(env_var_value)) => env_var_value,
+        Ok(None) => return input.default_value.into_token_stream(),
+        Err(err) => return env_error(err, input.env_var_name.span()).to_compile_error()
     };
     let env_var_value_tokens = match env_var_value.parse::<TokenStream>() {
         Ok(tokens) => tokens,
-        Err(err) => return syn::Error::new(input.env_var_name.span(), format!("{}", err)).to_compile_error()
+        Err(err) => return explain::error(explain::PARSE_FAILURE, input.env_var_name.span(), err).to_compile_error()
     };
     // Special case logic for quoted literals such as strings. We want to allow users not
     // to need to quote their environment variable values for strings, even though this is
@@ -94,142 +585,1634 @@ pub fn env_lit(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
             let quoted = format!("\"{}\"", env_var_value);
             match syn::parse_str::<syn::LitStr>(&quoted) {
                 Ok(literal) => literal.to_token_stream(),
-                Err(err) => syn::Error::new(input.env_var_name.span(), format!("Invalid string literal contents: {}", err)).to_compile_error()
+                Err(err) => explain::error(explain::PARSE_FAILURE, input.env_var_name.span(), format!("Invalid string literal contents: {}", err)).to_compile_error()
             }
         }
         syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::ByteStr(_), ..}) => {
             let quoted = format!("b\"{}\"", env_var_value);
             match syn::parse_str::<syn::LitByteStr>(&quoted) {
                 Ok(literal) => literal.to_token_stream(),
-                Err(err) => syn::Error::new(input.env_var_name.span(), format!("Invalid byte string literal contents: {}", err)).to_compile_error()
+                Err(err) => explain::error(explain::PARSE_FAILURE, input.env_var_name.span(), format!("Invalid byte string literal contents: {}", err)).to_compile_error()
             }
         }
         syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Char(_), ..}) => {
             let quoted = format!("'{}'", env_var_value);
             match syn::parse_str::<syn::LitChar>(&quoted) {
                 Ok(literal) => literal.to_token_stream(),
-                Err(err) => syn::Error::new(input.env_var_name.span(), format!("Invalid char literal contents: {}", err)).to_compile_error()
+                Err(err) => explain::error(explain::PARSE_FAILURE, input.env_var_name.span(), format!("Invalid char literal contents: {}", err)).to_compile_error()
             }
         }
         syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Byte(_), ..}) => {
             let quoted = format!("b'{}'", env_var_value);
             match syn::parse_str::<syn::LitByte>(&quoted) {
                 Ok(literal) => literal.to_token_stream(),
-                Err(err) => syn::Error::new(input.env_var_name.span(), format!("Invalid byte literal contents: {}", err)).to_compile_error()
+                Err(err) => explain::error(explain::PARSE_FAILURE, input.env_var_name.span(), format!("Invalid byte literal contents: {}", err)).to_compile_error()
             }
         }
         _ => env_var_value_tokens
     }
 }
 
+/// Expands to the value of an environment variable as a literal of the same kind as the
+/// default, or to the default if the variable is unset, for use in expressions, const
+/// contexts and patterns.
+pub fn env_or(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    let input: MacroInput = match syn::parse2(tokens) {
+        Ok(input) => input,
+        Err(err) => return explain::with_code(explain::INVALID_ARGUMENT, err).to_compile_error()
+    };
+    // A literal may stand in a pattern, where no item can, so the `.env` file is not
+    // tracked for rebuilds.
+    let read_env = match file::collect_tracked_files(|| with_dotenv(read_env)).0 {
+        Ok(read_env) => read_env,
+        Err(err) => return err.to_compile_error()
+    };
+    let var_name = input.env_var_name.value();
+    let byte_string = matches!(input.default_value, Expr::Lit(ExprLit { lit: Lit::ByteStr(_), .. }));
+    let value = match read_utf8(&var_name, byte_string, input.env_var_name.span(), &read_env) {
+        Ok(Some(value)) => value,
+        Ok(None) => return input.default_value.into_token_stream(),
+        Err(err) => return err.to_compile_error()
+    };
+    match value_to_literal(&ItemArgs::default(), &var_name, &value, &input.default_value) {
+        Ok(mut expr) => {
+            inherit_suffix(&mut expr, &input.default_value);
+            expr.into_token_stream()
+        }
+        Err(err) => err.to_compile_error()
+    }
+}
+
+/// Gives an unsuffixed number the suffix of the default, such as `5` for `3u32`, since no
+/// item type pins down the type of an inline value.
+fn inherit_suffix(expr: &mut Expr, default: &Expr) {
+    fn number(expr: &mut Expr) -> Option<&mut Lit> {
+        match expr {
+            Expr::Lit(ExprLit { lit: lit @ (Lit::Int(_) | Lit::Float(_)), .. }) => Some(lit),
+            Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => number(expr),
+            _ => None,
+        }
+    }
+    fn suffix(expr: &Expr) -> &str {
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => lit.suffix(),
+            Expr::Lit(ExprLit { lit: Lit::Float(lit), .. }) => lit.suffix(),
+            Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => suffix(expr),
+            _ => "",
+        }
+    }
+    let suffix = suffix(default);
+    match number(expr) {
+        Some(Lit::Int(lit)) if lit.suffix().is_empty() && !suffix.is_empty() => {
+            *lit = syn::LitInt::new(&format!("{}{}", lit.base10_digits(), suffix), lit.span());
+        }
+        Some(Lit::Float(lit)) if lit.suffix().is_empty() && !suffix.is_empty() => {
+            *lit = syn::LitFloat::new(&format!("{}{}", lit.base10_digits(), suffix), lit.span());
+        }
+        _ => {}
+    }
+}
+
+/// Inner implementation details of `const_env::consts_from_prefix!`, which generates a
+/// `const` for each environment variable starting with a prefix.
+pub fn consts_from_prefix(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    expand_with_dotenv(read_env, |read_env| syn::parse2::<prefix_map::PrefixConsts>(tokens)
+        .map_err(|err| FromEnvError::BadAttribute(explain::with_code(explain::INVALID_ARGUMENT, err)))
+        .and_then(|input| prefix_map::prefix_consts(input, &read_env)))
+        .unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// Lists the items of the crate marked `register` as a `&'static [EnvConstInfo]`, where
+/// `EnvConstInfo` is a struct defined by the expansion.
+///
+/// Macro invocations cannot see each other, so the list is read from a registry kept in
+/// the compiler's process by the items, and only holds the items expanded before it:
+/// invoke it after the modules declaring them, such as in `main`.
+pub fn registry(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    if let Some(token) = tokens.into_iter().next() {
+        return explain::error(explain::INVALID_ARGUMENT, token.span(), "`registry!` takes no arguments").to_compile_error();
+    }
+    let entries = match registry::entries(&read_env) {
+        Ok(entries) => entries,
+        Err(err) => return env_error(err, proc_macro2::Span::call_site()).to_compile_error(),
+    };
+    let entries = entries.iter().map(|entry| {
+        let registry::Entry { const_name, env_var, declared_type, default_str, value_str, overridden } = entry;
+        quote! {
+            EnvConstInfo {
+                const_name: #const_name,
+                env_var: #env_var,
+                declared_type: #declared_type,
+                default_str: #default_str,
+                value_str: #value_str,
+                overridden: #overridden,
+            }
+        }
+    });
+    quote! {
+        {
+            #[allow(dead_code)]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            struct EnvConstInfo {
+                pub const_name: &'static str,
+                pub env_var: &'static str,
+                pub declared_type: &'static str,
+                pub default_str: &'static str,
+                pub value_str: &'static str,
+                pub overridden: bool,
+            }
+            const REGISTRY: &'static [EnvConstInfo] = &[#(#entries),*];
+            REGISTRY
+        }
+    }
+}
+
+/// Expands to a `u64` literal hashing the values of the items of the crate, for cache
+/// keys and diagnostics. Values are only recorded as hashes, including those of
+/// `sensitive` items.
+///
+/// Items only record their values when the crate sets `CONST_ENV_FINGERPRINT=1`. As with
+/// `registry!()`, the fingerprint covers the items expanded before it, so it must be
+/// invoked after them. An item first expanded later in the same build fails to compile
+/// instead of being silently left out.
+pub fn fingerprint(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    if let Some(token) = tokens.into_iter().next() {
+        return explain::error(explain::INVALID_ARGUMENT, token.span(), "`fingerprint!` takes no arguments").to_compile_error();
+    }
+    let span = proc_macro2::Span::call_site();
+    match is_enabled(FINGERPRINT_VAR, span, &read_env) {
+        Ok(true) => {}
+        Ok(false) => return explain::error(explain::INVALID_SETTING, span, format!(
+            "`fingerprint!()` needs `{}=1`, which makes the items of the crate record their values for it", FINGERPRINT_VAR)).to_compile_error(),
+        Err(err) => return err.to_compile_error(),
+    }
+    match fingerprint::fingerprint(&read_env) {
+        Ok(fingerprint) => syn::LitInt::new(&format!("{:#018x}u64", fingerprint), span).into_token_stream(),
+        Err(err) => env_error(err, span).to_compile_error(),
+    }
+}
+
 /// Inner implementation details of `const_env::from_env`.
 pub fn from_env(attr: TokenStream, item: TokenStream, read_env: impl ReadEnv) -> TokenStream {
-    if let Ok(mut item_const) = syn::parse2::<syn::ItemConst>(item.clone()) {
-        let default_var_name = format!("{}", item_const.ident);
-        let var_name = extract_var_name(attr, default_var_name);
-        let var_value = match read_env.read_env(&var_name) {
-            Some(val) => val,
-            None => return item
-        };
-        let new_expr = value_to_literal(&var_value, &item_const.expr);
-        let span = item_const.span();
-        item_const.expr = Box::new(new_expr);
-        quote_spanned!(span => #item_const)
-    } else if let Ok(mut item_static) = syn::parse2::<syn::ItemStatic>(item.clone()) {
-        let default_var_name = format!("{}", item_static.ident);
-        let var_name = extract_var_name(attr, default_var_name);
-        let var_value = match read_env.read_env(&var_name) {
-            Some(val) => val,
-            None => return item
-        };
-        let new_expr = value_to_literal(&var_value, &item_static.expr);
-        let span = item_static.span();
-        item_static.expr = Box::new(new_expr);
-        quote_spanned!(span => #item_static)
-    } else {
-        panic!("TODO: error reporting");
+    try_from_env(attr, item, read_env).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// Inner implementation details of `const_env::from_env!`, which applies `from_env` to
+/// every item of a block. An item's own `#[from_env(...)]` or `#[env_item(...)]`
+/// attribute gives its arguments. Items that fail are emitted unchanged, so that their
+/// uses do not report further errors, followed by the errors of all items.
+pub fn from_env_items(tokens: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    let items = match syn::parse2::<BlockItems>(tokens) {
+        Ok(BlockItems(items)) => items,
+        Err(err) => return explain::with_code(explain::UNSUPPORTED_ITEM, err).to_compile_error(),
+    };
+    let mut output = TokenStream::new();
+    let mut errors = TokenStream::new();
+    for mut item in items {
+        let attrs = match &mut item {
+            syn::Item::Const(item) => &mut item.attrs,
+            syn::Item::Static(item) => &mut item.attrs,
+            other => {
+                errors.extend(FromEnvError::UnsupportedItem { span: other.span() }.to_compile_error());
+                output.extend(other.to_token_stream());
+                continue;
+            }
+        };
+        let (own, others): (Vec<syn::Attribute>, Vec<syn::Attribute>) = std::mem::take(attrs).into_iter()
+            .partition(|attr| attr.path.is_ident("from_env") || attr.path.is_ident("env_item"));
+        *attrs = others;
+        if let Some(second) = own.get(1) {
+            errors.extend(explain::error(explain::INVALID_ARGUMENT, second.span(), "An item of `from_env!` takes at most one `from_env` attribute").to_compile_error());
+            output.extend(item.to_token_stream());
+            continue;
+        }
+        let args = own.into_iter().next().map_or_else(TokenStream::new, |attr| attr.tokens);
+        match try_from_env(args, item.to_token_stream(), EnvRef(&read_env)) {
+            Ok(tokens) => output.extend(tokens),
+            Err(err) => {
+                errors.extend(err.to_compile_error());
+                output.extend(item.to_token_stream());
+            }
+        }
     }
+    output.extend(errors);
+    output
+}
+
+/// Inner implementation details of `const_env::FromEnvDefaults`, which derives `Default`
+/// for a struct whose `#[from_env(default = ...)]` fields are configurable.
+pub fn from_env_defaults(input: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    expand_with_dotenv(read_env, |read_env| derive::default_impl(input, &read_env))
+        .unwrap_or_else(|err| err.to_compile_error())
 }
 
-fn extract_var_name(attr: TokenStream, default: String) -> String {
-    if attr.is_empty() {
-        return default;
+/// Inner implementation details of `const_env::ConstEnv`, which generates an associated
+/// constant for each field of a struct, configured as `from_env` configures an item.
+pub fn const_env_derive(input: TokenStream, read_env: impl ReadEnv) -> TokenStream {
+    expand_with_dotenv(read_env, |read_env| derive::const_impl(input, &read_env))
+        .unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// The items of a `from_env!` block.
+struct BlockItems(Vec<syn::Item>);
+
+impl syn::parse::Parse for BlockItems {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(BlockItems(items))
     }
-    let expr: Expr = syn::parse2(attr)
-        .expect("Unable to parse attribute args as expression");
-    extract_var_name_from_expr(&expr)
 }
 
-fn extract_var_name_from_expr(expr: &Expr) -> String {
-    match expr {
-        Expr::Lit(literal) => {
-            match &literal.lit {
-                Lit::Str(lit_str) => {
-                    lit_str.value()
-                },
-                _ => panic!("Attribute arguments are not a valid string literal")
-            }
-        },
-        Expr::Paren(paren) => {
-            extract_var_name_from_expr(&paren.expr)
-        },
-        _ => {
-            panic!("Attribute arguments are not a valid string literal expression: {:?}", expr)
+/// Like `from_env`, but returns errors instead of `compile_error!` invocations, for use
+/// by other procedural macros.
+pub fn try_from_env(attr: TokenStream, item: TokenStream, read_env: impl ReadEnv) -> Result<TokenStream, FromEnvError> {
+    expand(attr, item, read_env, None)
+}
+
+/// What `from_env` decided for an item, as reported by `try_from_env_with_substitutions`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Substitution {
+    /// The name of the item.
+    pub item: String,
+    /// Every variable the item may read, as listed by `CONST_ENV_LIST`.
+    pub var_names: Vec<String>,
+    /// Whether a value was found, and so the initializer replaced.
+    pub provided: bool,
+    /// The initializer written in the source.
+    pub original: String,
+    /// The replacement initializer, or `<redacted>` for `sensitive` items.
+    pub replacement: Option<String>,
+}
+
+/// Like `try_from_env`, but also reports the substitutions made, in order.
+pub fn try_from_env_with_substitutions(attr: TokenStream, item: TokenStream, read_env: impl ReadEnv) -> Result<(TokenStream, Vec<Substitution>), FromEnvError> {
+    let mut substitutions = Vec::new();
+    let tokens = expand(attr, item, read_env, Some(&mut substitutions))?;
+    Ok((tokens, substitutions))
+}
+
+fn expand(attr: TokenStream, item: TokenStream, read_env: impl ReadEnv, substitutions: Option<&mut Vec<Substitution>>) -> Result<TokenStream, FromEnvError> {
+    expand_with_dotenv(read_env, |read_env| expand_tokens(attr, item, read_env, substitutions))
+}
+
+fn expand_tokens(attr: TokenStream, item: TokenStream, read_env: impl ReadEnv, substitutions: Option<&mut Vec<Substitution>>) -> Result<TokenStream, FromEnvError> {
+    let args = item_args(attr.clone(), &read_env)?;
+    #[cfg(feature = "fast-parse")]
+    if args.runtime_fallback.is_none() && args.obfuscate.is_none() && args.split_into.is_none() && read_env.read_env_utf8(FAST_PARSE_VAR).map_err(|err| env_error(err, proc_macro2::Span::call_site()))?.as_deref() != Some("0") {
+        if let Some(item) = fast_parse::FastItem::parse(&item) {
+            let (new_expr, doc, extra) = expand_parts(&args, &item.vis, &item.ident, &item.ty, &item.expr, &read_env, substitutions)?;
+            return Ok(item.into_tokens(new_expr, doc, extra));
         }
     }
+    // The item is parsed once, so that an unsupported item is reported with the span
+    // and message of that single parse.
+    let parsed = syn::parse2::<syn::Item>(item.clone())
+        .map_err(|err| explain::with_code(explain::UNSUPPORTED_ITEM, err))?;
+    match (&args.split_into, parsed) {
+        (Some((members, span)), syn::Item::Mod(module)) => split::expand_module(attr, members, *span, module, &read_env, substitutions),
+        (Some((_, span)), _) => Err(explain::error(explain::UNSUPPORTED_ITEM, *span,
+            "`split_into` can only be used on a module, whose `const` and `static` items it names").into()),
+        (None, parsed) => expand_item(&args, item, parsed, &read_env, substitutions),
+    }
 }
 
-fn value_to_literal(value: &str, original_expr: &Expr) -> Expr {
-    match original_expr {
-        Expr::Array(_) => {
-            syn::Expr::Array(syn::parse_str::<syn::ExprArray>(value).expect("Failed to parse environment variable contents as valid array"))
-        },
-        Expr::Unary(_) => {
-            // A unary sign indicates this is a numeric literal which doesn't need any
-            // escaping, so we can parse it directly.
-            let new: Expr = syn::parse_str(value)
-                .expect("Failed to parse environment variable contents as valid expression");
-            return new;
-        },
-        Expr::Lit(literal) => {
-            let new_lit = match &literal.lit {
-                Lit::Str(original) => {
-                    let mut new: syn::LitStr = syn::parse_str(&format!("\"{}\"", value))
-                        .expect("Failed to parse environment variable contents as literal string");
-                    new.set_span(original.span());
-                    Lit::Str(new)
-                },
-                Lit::ByteStr(original) => {
-                    let mut new: syn::LitByteStr = syn::parse_str(&format!("b\"{}\"", value))
-                        .expect("Failed to parse environment variable contents as literal byte string");
-                    new.set_span(original.span());
-                    Lit::ByteStr(new)
-                },
-                Lit::Byte(original) => {
-                    let mut new: syn::LitByte = syn::parse_str(&format!("b'{}'", value))
-                        .expect("Failed to parse environment variable contents as literal byte");
-                    new.set_span(original.span());
-                    Lit::Byte(new)
-                },
-                Lit::Char(original) => {
-                    let mut new: syn::LitChar = syn::parse_str(&format!("'{}'", value))
-                        .expect("Failed to parse environment variable contents as literal character");
-                    new.set_span(original.span());
-                    Lit::Char(new)
-                },
-                // These variants do not need any escaping and can be parsed as an expression
-                // directly.
-                Lit::Bool(_) | Lit::Int(_) | Lit::Float(_) | Lit::Verbatim(_) => {
-                    let new: Expr = syn::parse_str(value)
-                        .expect("Failed to parse environment variable contents as valid expression");
-                    return new;
-                }
-            };
-            ExprLit {
-                attrs: literal.attrs.clone(),
-                lit: new_lit
-            }.into()
-        },
-        _ => panic!("Original const expression was not a recognized literal expression")
+/// Parses the arguments of an item, requiring it to be set if `CONST_ENV_STRICT` is.
+fn item_args(attr: TokenStream, read_env: &impl ReadEnv) -> Result<ItemArgs, FromEnvError> {
+    let mut args = args::parse_item_args(attr)
+        .map_err(|err| FromEnvError::BadAttribute(explain::with_code(explain::INVALID_ARGUMENT, err)))?;
+    if let Some((_, span)) = args.default {
+        return Err(FromEnvError::BadAttribute(explain::error(explain::INVALID_ARGUMENT, span,
+            "`default` is only accepted on fields of `#[derive(FromEnvDefaults)]`; the initializer is the item's default")));
+    }
+    let strict = is_enabled(STRICT_VAR, proc_macro2::Span::call_site(), read_env)?;
+    args.required |= strict && !args.optional;
+    Ok(args)
+}
+
+/// Expands a `const` or `static` item, `item` as written and `parsed`.
+fn expand_item(args: &ItemArgs, item: TokenStream, mut parsed: syn::Item, read_env: &impl ReadEnv, substitutions: Option<&mut Vec<Substitution>>) -> Result<TokenStream, FromEnvError> {
+    let span = parsed.span();
+    let is_const = matches!(parsed, syn::Item::Const(_));
+    let (vis, ident, ty, expr) = match &mut parsed {
+        syn::Item::Const(item) => (&item.vis, &item.ident, &item.ty, &mut item.expr),
+        syn::Item::Static(item) => (&item.vis, &item.ident, &item.ty, &mut item.expr),
+        other => return Err(FromEnvError::UnsupportedItem { span: other.span() }),
+    };
+    if let Some(runtime_fallback) = args.runtime_fallback {
+        check_runtime_fallback(is_const, ty, runtime_fallback)?;
     }
-}
\ No newline at end of file
+    let (new_expr, doc, mut extra) = expand_parts(args, vis, ident, ty, expr, read_env, substitutions)?;
+    let provided = new_expr.is_some();
+    if let Some(new_expr) = new_expr {
+        **expr = new_expr;
+    }
+    if let Some(obfuscate) = args.obfuscate {
+        extra.extend(obfuscate::obfuscate(&mut parsed, obfuscate)?);
+    }
+    if let (Some(_), syn::Item::Static(item)) = (args.runtime_fallback, &mut parsed) {
+        let var_name = args.var_name.as_ref().map_or_else(|| args.rename_all.apply(&item.ident), syn::LitStr::value);
+        runtime_fallback(args, item, &var_name);
+    }
+    if doc.is_empty() && args.runtime_fallback.is_none() && args.obfuscate.is_none() {
+        return match provided {
+            true => Ok(quote_spanned!(span => #parsed #extra)),
+            false => Ok(quote!(#item #extra)),
+        };
+    }
+    let attrs = match &mut parsed {
+        syn::Item::Const(item) => &mut item.attrs,
+        syn::Item::Static(item) => &mut item.attrs,
+        _ => unreachable!(),
+    };
+    // A blank line separates the generated text from the item's own documentation, so
+    // that it forms a paragraph of its own.
+    let documented = attrs.iter().any(|attr| attr.path.is_ident("doc"));
+    let blank = documented.then(String::new);
+    for line in blank.into_iter().chain(doc) {
+        attrs.push(syn::parse_quote_spanned!(span => #[doc = #line]));
+    }
+    Ok(quote_spanned!(span => #parsed #extra))
+}
+
+/// Substitutes the initializer of an item and generates everything that goes with it,
+/// returning the new initializer, if any, the documentation lines to add and the tokens
+/// to emit after the item.
+fn expand_parts(args: &ItemArgs, vis: &syn::Visibility, ident: &syn::Ident, ty: &syn::Type, expr: &Expr, read_env: &impl ReadEnv, substitutions: Option<&mut Vec<Substitution>>) -> Result<(Option<Expr>, Vec<String>, TokenStream), FromEnvError> {
+    if let Some(emit_len) = args.emit_len {
+        literal_len(expr).map_err(|_| explain::error(explain::UNSUPPORTED_DEFAULT, emit_len,
+            "`emit_len` can only be used on items with a string, byte string, array or slice default"))?;
+    }
+    let ((new_expr, tracked_files), warnings) = diagnostics::collect_warnings(|| file::collect_tracked_files(|| substitute(args, ident, ty, expr, read_env)));
+    let new_expr = new_expr?;
+    let mut extra = match args.asserts.is_empty() {
+        true => TokenStream::new(),
+        false => assertions(args, ident, &variable_names(args, ident, read_env)?),
+    };
+    extra.extend(tracked_files);
+    if !args.allow_warnings {
+        extra.extend(diagnostics::emit_warnings(&warnings));
+    }
+    if !args.untracked && read_env.needs_rebuild_tracking() {
+        extra.extend(rebuild_tracking(&tracked_names(args, ident, read_env)?, ident.span()));
+    }
+    if args.emit_var_name.is_some() {
+        // The constant takes the span of the item's name, so that a clash with another
+        // item is reported there.
+        let var_name = args.var_name.as_ref().map_or_else(|| args.rename_all.apply(ident), syn::LitStr::value);
+        let var_name = syn::LitStr::new(&var_name, ident.span());
+        let const_ident = syn::Ident::new(&format!("{}_ENV_VAR", syn::ext::IdentExt::unraw(ident)), ident.span());
+        extra.extend(quote_spanned!(ident.span() => #vis const #const_ident: &str = #var_name;));
+    }
+    if args.emit_default && (new_expr.is_some() || !args.only_when_overridden) {
+        let const_ident = syn::Ident::new(&format!("{}_DEFAULT", syn::ext::IdentExt::unraw(ident)), ident.span());
+        extra.extend(quote_spanned!(ident.span() => #vis const #const_ident: #ty = #expr;));
+    }
+    if args.emit_len.is_some() {
+        let const_ident = syn::Ident::new(&format!("{}_LEN", syn::ext::IdentExt::unraw(ident)), ident.span());
+        let len = match literal_len(new_expr.as_ref().unwrap_or(expr)) {
+            Ok(Some(len)) => syn::LitInt::new(&len.to_string(), ident.span()).into_token_stream(),
+            // Values that are not literals, such as those chosen by `when`, are measured
+            // by const evaluation.
+            _ => quote_spanned!(ident.span() => #ident.len()),
+        };
+        extra.extend(quote_spanned!(ident.span() => #vis const #const_ident: usize = #len;));
+    }
+    if let Some(emit_runtime_getter) = args.emit_runtime_getter {
+        let var_name = args.var_name.as_ref().map_or_else(|| args.rename_all.apply(ident), syn::LitStr::value);
+        let names = candidate_names(args, &var_name, ident.span(), read_env)?;
+        extra.extend(getter::runtime_getter(args, vis, ident, ty, expr, &names, emit_runtime_getter)?);
+    }
+    let doc = match args.no_doc || args.out_file.is_some() {
+        true => Vec::new(),
+        false => doc_lines(args, variable_names(args, ident, read_env)?, new_expr.is_some()),
+    };
+    let manifest = var_manifest::path(read_env).map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))?;
+    if let Some(manifest) = manifest {
+        var_manifest::record(&manifest, &tracked_names(args, ident, read_env)?, &span_location(ident.span()).0)
+            .map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))?;
+    }
+    let report = report::path(read_env).map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))?;
+    if let Some(report) = report {
+        if let Some(obfuscate) = args.obfuscate {
+            return Err(explain::error(explain::INVALID_ARGUMENT, obfuscate, format!(
+                "`obfuscate` cannot be combined with `{}`, which records the value of every item", report::REPORT_VAR)).into());
+        }
+        let crate_name = read_env.read_env_utf8("CARGO_CRATE_NAME")
+            .map_err(|err| env_error(err, ident.span()))?
+            .unwrap_or_else(|| "crate".to_string());
+        let value = match args.sensitive {
+            true => "<redacted>".to_string(),
+            false => pretty::to_pretty_string(new_expr.as_ref().unwrap_or(expr).to_token_stream()),
+        };
+        let (file, line) = span_location(ident.span());
+        let record = report::Record {
+            crate_name: &crate_name,
+            item: &ident.to_string(),
+            vars: &dedup_names(variable_names(args, ident, read_env)?),
+            overridden: new_expr.is_some(),
+            value: &value,
+            file: &file,
+            line,
+        };
+        report::append(&report, &record).map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))?;
+    }
+    let lockfile = lockfile::path(read_env).map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))?;
+    if let Some(lockfile) = lockfile {
+        check_lockfile(args, ident, &lockfile, &pretty::to_pretty_string(new_expr.as_ref().unwrap_or(expr).to_token_stream()), read_env)?;
+    }
+    if args.register {
+        let display = |tokens: TokenStream| match args.sensitive {
+            true => "<redacted>".to_string(),
+            false => pretty::to_pretty_string(tokens),
+        };
+        let entry = registry::Entry {
+            const_name: ident.to_string(),
+            env_var: dedup_names(variable_names(args, ident, read_env)?).join(", "),
+            declared_type: pretty::to_pretty_string(ty.to_token_stream()),
+            default_str: display(expr.to_token_stream()),
+            value_str: display(new_expr.as_ref().unwrap_or(expr).to_token_stream()),
+            overridden: new_expr.is_some(),
+        };
+        registry::register(entry, read_env).map_err(|err| env_error(err, ident.span()))?;
+    }
+    if is_enabled(FINGERPRINT_VAR, ident.span(), read_env)? {
+        let value = pretty::to_pretty_string(new_expr.as_ref().unwrap_or(expr).to_token_stream());
+        let included = fingerprint::record(&ident.to_string(), &span_location(ident.span()).0, &dedup_names(variable_names(args, ident, read_env)?), &value, read_env)
+            .map_err(|err| env_error(err, ident.span()))?;
+        if !included {
+            return Err(explain::error(explain::FINGERPRINT_ORDER, ident.span(), format!(
+                "`{}` is expanded after `fingerprint!()`, which does not include its value; invoke `fingerprint!()` after every configured item, such as at the end of the crate root",
+                ident)).into());
+        }
+    }
+    if let Some(substitutions) = substitutions {
+        let replacement = new_expr.as_ref().map(|new_expr| match args.sensitive {
+            true => "<redacted>".to_string(),
+            false => pretty::to_pretty_string(new_expr.to_token_stream()),
+        });
+        substitutions.push(Substitution {
+            item: ident.to_string(),
+            var_names: variable_names(args, ident, read_env)?,
+            provided: new_expr.is_some(),
+            original: pretty::to_pretty_string(expr.to_token_stream()),
+            replacement,
+        });
+    }
+    Ok((new_expr, doc, extra))
+}
+
+/// Rejects `runtime_fallback` on items that cannot be initialized lazily or whose type
+/// cannot implement `FromStr`.
+fn check_runtime_fallback(is_const: bool, ty: &syn::Type, flag_span: proc_macro2::Span) -> syn::Result<()> {
+    if is_const {
+        return Err(explain::error(explain::UNSUPPORTED_DEFAULT, flag_span,
+            "`runtime_fallback` can only be used on `static` items, since a `const` cannot be initialized lazily"));
+    }
+    match ty {
+        syn::Type::Path(_) | syn::Type::Paren(_) | syn::Type::Group(_) => Ok(()),
+        other => Err(explain::error(explain::UNSUPPORTED_DEFAULT, other.span(), format!(
+            "`runtime_fallback` can only be used on items of a type implementing `FromStr`, not `{}`",
+            pretty::to_pretty_string(other.to_token_stream())))),
+    }
+}
+
+/// Turns `item` into a `LazyLock` that parses `var_name` when first used, as
+/// `emit_runtime_getter` does, falling back to the value given at compile time if it is
+/// unset, malformed or invalid.
+fn runtime_fallback(args: &ItemArgs, item: &mut syn::ItemStatic, var_name: &str) {
+    let span = item.ident.span();
+    let (ty, expr) = (&item.ty, &item.expr);
+    let var_name = syn::LitStr::new(var_name, span);
+    let parse = getter::parse_value(args, ty, expr);
+    let expr: Expr = syn::parse_quote_spanned!(span => ::std::sync::LazyLock::new(|| {
+        let parse = |value: ::std::string::String| -> Option<#ty> {
+            #parse
+            Some(value)
+        };
+        ::std::env::var(#var_name).ok().and_then(parse).unwrap_or(#expr)
+    }));
+    let ty: syn::Type = syn::parse_quote_spanned!(span => ::std::sync::LazyLock<#ty>);
+    *item.ty = ty;
+    *item.expr = expr;
+}
+
+/// The doc lines appended to an item read from `names`, in order of precedence, noting
+/// whether this build overrode the default when `doc_override` is set.
+fn doc_lines(args: &ItemArgs, names: Vec<String>, provided: bool) -> Vec<String> {
+    let quoted: Vec<String> = dedup_names(names).iter().map(|name| format!("`{}`", name)).collect();
+    let mut lines = vec![match (&args.prefix_map, quoted.split_last()) {
+        (None, Some(_)) if args.presence.is_some() => format!(" *Decided at build time by which of the {} environment variables are set.*", quoted.join(", ")),
+        (Some((prefix, _)), _) => format!(" *Collected at build time from the environment variables starting with `{}`.*", prefix),
+        (None, Some((last, []))) => format!(" *Configurable at build time via the {} environment variable.*", last),
+        (None, Some((last, rest))) => format!(" *Configurable at build time via the {} or {} environment variables.*", rest.join(", "), last),
+        (None, None) => return Vec::new(),
+    }];
+    if args.doc_override {
+        lines.push(String::new());
+        lines.push(match provided {
+            true => " *This build overrides the default.*".to_string(),
+            false => " *This build uses the default.*".to_string(),
+        });
+    }
+    lines
+}
+
+/// Removes repeated variable names, keeping the order of precedence.
+pub(crate) fn dedup_names(names: Vec<String>) -> Vec<String> {
+    let mut deduped: Vec<String> = Vec::new();
+    for name in names {
+        if !deduped.contains(&name) {
+            deduped.push(name);
+        }
+    }
+    deduped
+}
+
+/// The environment variables an item may read, sorted, for rebuild tracking and
+/// `CONST_ENV_MANIFEST`. Files in `OUT_DIR` read by `out_file` are not variables.
+fn tracked_names(args: &ItemArgs, ident: &syn::Ident, read_env: &impl ReadEnv) -> syn::Result<Vec<String>> {
+    if args.out_file.is_some() {
+        return Ok(Vec::new());
+    }
+    let mut names = variable_names(args, ident, read_env)?;
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// The byte length of a string or byte string literal, or the element count of an array
+/// or slice, if `expr` is a literal. Fails for literals that have no length.
+fn literal_len(expr: &Expr) -> Result<Option<usize>, ()> {
+    if let Some(elements) = list::list_elements(expr) {
+        return Ok(Some(elements.len()));
+    }
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => Ok(Some(lit.value().len())),
+        Expr::Lit(ExprLit { lit: Lit::ByteStr(lit), .. }) => Ok(Some(lit.value().len())),
+        Expr::Lit(_) | Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), .. }) => Err(()),
+        _ => Ok(None),
+    }
+}
+
+/// An anonymous constant for each `assert` predicate, so that const evaluation checks it
+/// against the item's final value, whatever its source.
+fn assertions(args: &ItemArgs, ident: &syn::Ident, names: &[String]) -> TokenStream {
+    let names: Vec<String> = dedup_names(names.to_vec()).iter().map(|name| format!("`{}`", name)).collect();
+    args.asserts.iter().map(|(predicate, literal)| {
+        // The message is a format string without arguments, so braces are doubled.
+        let message = format!("`{}` does not hold for `{}`; set {} to a value for which it does",
+            literal.value(), ident, names.join(" or ")).replace('{', "{{").replace('}', "}}");
+        quote_spanned!(literal.span() => const _: () = assert!(#predicate, #message);)
+    }).collect()
+}
+
+/// Items referencing each of `names` with `option_env!`, whether set or not, so that the
+/// compiler records them as dependencies of the crate.
+pub(crate) fn rebuild_tracking(names: &[String], span: proc_macro2::Span) -> TokenStream {
+    // The paths take the hygiene of the call site, which finds `::core` in crates of every
+    // edition, located at `span`, whose tokens do not in a 2015 edition crate.
+    let site = proc_macro2::Span::call_site().located_at(span);
+    names.iter().map(|name| {
+        let name = syn::LitStr::new(name, span);
+        quote_spanned!(site => const _: ::core::option::Option<&str> = ::core::option_env!(#name);)
+    }).collect()
+}
+
+/// Expands `from_env` on the attribute arguments and item given as source text, and
+/// formats the result on one line with rustfmt-like spacing, for snapshot tests:
+///
+/// ```
+/// # use const_env_impl::{expand_to_string, TestEnv};
+/// let env = TestEnv::builder().set("PORT", "8080").build();
+/// let expanded = expand_to_string("no_doc", "const PORT: u16 = 80;", &env).unwrap();
+/// assert_eq!(expanded, "const PORT: u16 = 8080;");
+/// ```
+pub fn expand_to_string(attr: &str, item: &str, read_env: &impl ReadEnv) -> Result<String, FromEnvError> {
+    let attr: TokenStream = attr.parse().map_err(|err| FromEnvError::BadAttribute(
+        explain::error(explain::INVALID_ARGUMENT, proc_macro2::Span::call_site(), err)))?;
+    let item: TokenStream = item.parse().map_err(|err| FromEnvError::Other(
+        explain::error(explain::UNSUPPORTED_ITEM, proc_macro2::Span::call_site(), err)))?;
+    try_from_env(attr, item, EnvRef(read_env)).map(pretty::to_pretty_string)
+}
+
+/// Passes a borrowed source where an owned one is expected.
+struct EnvRef<'a, R>(&'a R);
+
+impl<R: ReadEnv> ReadEnv for EnvRef<'_, R> {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        self.0.read_env(var_name)
+    }
+
+    fn needs_rebuild_tracking(&self) -> bool {
+        self.0.needs_rebuild_tracking()
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.0.var_names()
+    }
+}
+
+/// Computes the replacement initializer for an item, or `None` if the item should be
+/// left untouched.
+fn substitute(args: &ItemArgs, ident: &syn::Ident, ty: &syn::Type, expr: &Expr, read_env: &impl ReadEnv) -> Result<Option<Expr>, FromEnvError> {
+    let declared = declared::declared_type(args, ty);
+    if let Some(declared) = &declared {
+        declared::check_default(declared, ident, ty, args, expr)?;
+    }
+    let new_expr = substitute_value(args, ident, declared.as_ref(), expr, read_env)?;
+    if let (Some(_), Some(new_expr)) = (args.fixed_point, &new_expr) {
+        fixed_point::check_range(args, ident, ty, new_expr)?;
+    }
+    if let (Some((expected, span)), None, true) = (&args.sha256, &new_expr, args.verify_default) {
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Str(default), .. }) => check_digest(expected, default.value().as_bytes(), "the default", *span)?,
+            Expr::Lit(ExprLit { lit: Lit::ByteStr(default), .. }) => check_digest(expected, &default.value(), "the default", *span)?,
+            _ => return Err(explain::error(explain::UNSUPPORTED_DEFAULT, *span, "`verify_default` can only be used on items with a string or byte string default").into()),
+        }
+    }
+    if is_enabled(LIST_VAR, ident.span(), read_env)? {
+        let crate_name = read_env.read_env_utf8("CARGO_CRATE_NAME")
+            .map_err(|err| env_error(err, ident.span()))?
+            .unwrap_or_else(|| "crate".to_string());
+        let line = format!(
+            "item={}::{} vars={} type={} set={}",
+            crate_name, ident, variable_names(args, ident, read_env)?.join(","), ty.to_token_stream(), new_expr.is_some());
+        let list_file = read_env.read_env(LIST_FILE_VAR).map_err(|err| env_error(err, ident.span()))?;
+        diagnostics::list(&line, list_file.as_deref().map(std::path::Path::new))
+            .map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))?;
+    }
+    if is_enabled(DEBUG_VAR, ident.span(), read_env)? {
+        let source = match (&args.presence, &args.format, &args.out_file, &args.var_name) {
+            (Some((presence, _)), _, _, _) => presence.to_string(),
+            (_, Some(format), _, _) => format!("{:?}", format.value()),
+            (_, _, Some(out_file), _) => format!("OUT_DIR/{}", out_file.value()),
+            (_, _, _, Some(var_name)) => var_name.value(),
+            _ => args.rename_all.apply(ident),
+        };
+        let result = new_expr.as_ref().unwrap_or(expr);
+        let value = if args.sensitive {
+            "<redacted>".to_string()
+        } else {
+            result.to_token_stream().to_string()
+        };
+        // Which variables decided a presence predicate is not visible in the value.
+        let decided_by = match &args.presence {
+            Some((presence, span)) => format!(" decided_by={}", presence.evaluate(*span, read_env)?.1.join(",")),
+            None => String::new(),
+        };
+        diagnostics::trace(&format!(
+            "item={} var={} overridden={} kind={} value={}{}", ident, source, new_expr.is_some(), kind_name(result), value, decided_by));
+    }
+    // The macro cannot evaluate `cfg` predicates for the crate being compiled, so the
+    // choice is left to `cfg!`.
+    match (&args.when, new_expr) {
+        (Some(predicate), Some(new_expr)) => {
+            Ok(Some(syn::parse_quote!(if ::core::cfg!(#predicate) { #new_expr } else { #expr })))
+        }
+        (_, new_expr) => Ok(new_expr)
+    }
+}
+
+fn substitute_value(args: &ItemArgs, ident: &syn::Ident, declared: Option<&DeclaredType>, expr: &Expr, read_env: &impl ReadEnv) -> Result<Option<Expr>, FromEnvError> {
+    if let Some((_, case_span)) = args.case {
+        // Padded byte arrays and path lists hold strings too.
+        if !is_string_like(expr) && args.pad.is_none() && args.path_list.is_none() {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, case_span, "`case` can only be used on items with a string or byte string default").into());
+        }
+    }
+    if let Some(placeholder_span) = args.placeholder {
+        check_placeholder(placeholder_span, expr)?;
+    }
+    if let Some(strict_precision_span) = args.strict_precision {
+        if !float::is_float_item(declared, expr) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, strict_precision_span, "`strict_precision` can only be used on items with a float type or default").into());
+        }
+    }
+    if let Some(include_span) = args.include {
+        if !is_string_like(expr) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, include_span, "`include` can only be used on items with a string or byte string default").into());
+        }
+    }
+    if let Some(ranges_span) = args.ranges {
+        if ranges::target(declared, expr).is_none() {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, ranges_span, "`ranges` can only be used on items with an integer array or slice type or default").into());
+        }
+    }
+    let list_args = [
+        ("min_items", args.min_items.map(|(_, span)| span)),
+        ("max_items", args.max_items.map(|(_, span)| span)),
+        ("sort", args.sort),
+        ("dedup", args.dedup),
+    ];
+    for (key, arg_span) in list_args {
+        if let Some(arg_span) = arg_span {
+            if list::list_elements(expr).is_none() {
+                return Err(explain::error(explain::UNSUPPORTED_DEFAULT, arg_span, format!("`{}` can only be used on items with an array or slice default", key)).into());
+            }
+        }
+    }
+    if let Some((_, flags_span)) = args.flags {
+        if !matches!(expr, Expr::Lit(ExprLit { lit: Lit::Int(_), .. })) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, flags_span, "`flags` can only be used on items with an integer default").into());
+        }
+    }
+    if let Some((_, fixed_point_span)) = args.fixed_point {
+        let negated = matches!(expr, Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. })
+            if matches!(&**expr, Expr::Lit(ExprLit { lit: Lit::Int(_), .. })));
+        if !negated && !matches!(expr, Expr::Lit(ExprLit { lit: Lit::Int(_), .. })) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, fixed_point_span, "`fixed_point` can only be used on items with an integer default").into());
+        }
+    }
+    if let Some((_, pad_span)) = args.pad {
+        if pad::array_len(expr).is_none() {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, pad_span, "`pad` can only be used on items with a `*b\"...\"` or fixed-size byte array default").into());
+        }
+    }
+    if let Some(path_list_span) = args.path_list {
+        if !list::is_str_slice(expr) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, path_list_span, "`split = \"path_list\"` can only be used on items with a `&[&str]` default").into());
+        }
+    }
+    if let Some(decimal_comma_span) = args.decimal_comma {
+        let float = match expr {
+            Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => matches!(&**expr, Expr::Lit(ExprLit { lit: Lit::Float(_), .. })),
+            other => matches!(other, Expr::Lit(ExprLit { lit: Lit::Float(_), .. })),
+        };
+        if !float && args.fixed_point.is_none() {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, decimal_comma_span, "`decimal_comma` can only be used on items with a float default or with `fixed_point`").into());
+        }
+    }
+    if let Some(partial_span) = args.partial {
+        if !matches!(expr, Expr::Struct(_)) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, partial_span, "`partial` can only be used on items with a struct literal default").into());
+        }
+    }
+    let presence = args.presence.as_ref().map(|(presence, span)| (presence.keyword(), Some(*span)));
+    for (flag, flag_span) in [("exists", args.exists), ("invert", args.invert)].into_iter().chain(presence) {
+        if let Some(flag_span) = flag_span {
+            if !matches!(expr, Expr::Lit(ExprLit { lit: Lit::Bool(_), .. })) {
+                return Err(explain::error(explain::UNSUPPORTED_DEFAULT, flag_span, format!("`{}` can only be used on items with a boolean default", flag)).into());
+            }
+        }
+    }
+    if let Some(format) = &args.format {
+        if !is_string_like(expr) {
+            return Err(explain::error(explain::UNSUPPORTED_DEFAULT, format.span(), "`format` can only be used on items with a string or byte string default").into());
+        }
+        return match format_value(format, args, read_env)? {
+            Some(value) => {
+                if let Some((expected, _)) = &args.sha256 {
+                    check_digest(expected, value.as_bytes(), "`format`", format.span())?;
+                }
+                let mut new_expr = string_to_literal(&value, expr)?;
+                if let Some((case, _)) = args.case {
+                    apply_case(&mut new_expr, case);
+                }
+                validate::validate(args, &format.value(), &new_expr, format.span())?;
+                Ok(Some(new_expr))
+            },
+            None => Ok(None)
+        };
+    }
+    if let Some(out_file) = &args.out_file {
+        let value = match file::read_out_file(&out_file.value(), read_env) {
+            Ok(Some(value)) => value,
+            Ok(None) if args.required => {
+                return Err(explain::error(explain::MISSING_REQUIRED, out_file.span(), format!("File `{}` in `OUT_DIR` is required but does not exist", out_file.value())).into());
+            }
+            Ok(None) => return Ok(None),
+            Err(message) => return Err(explain::error(explain::FILE_ERROR, out_file.span(), message).into())
+        };
+        let source = format!("OUT_DIR/{}", out_file.value());
+        return value_expr(args, &source, &value, declared, expr, out_file.span(), read_env).map(Some);
+    }
+    if let Some((presence, presence_span)) = &args.presence {
+        return presence::presence_value(args, presence, expr, *presence_span, read_env);
+    }
+    if let Some((prefix, prefix_span)) = &args.prefix_map {
+        return prefix_map::prefix_map_value(args, prefix, declared, expr, *prefix_span, read_env).map(Some);
+    }
+    let (var_name, var_span) = match &args.var_name {
+        Some(var_name) => (var_name.value(), var_name.span()),
+        None => (args.rename_all.apply(ident), ident.span())
+    };
+    // Items taking parts of one JSON document, record or captured text all read its variable.
+    if args.json_pointer.is_none() && args.field.is_none() && args.capture.is_none() {
+        check_duplicate(&var_name, ident, var_span, read_env)?;
+    }
+    if !args.allow_system_var {
+        let profile_vars = args.debug_var.iter().chain(&args.release_var).map(syn::LitStr::value);
+        for name in std::iter::once(var_name.clone()).chain(profile_vars) {
+            if system_vars::is_system_var(&name) {
+                diagnostics::emit_warning(var_span, &format!(
+                    "`{}` reads `{}`, a well-known system variable, and may pick up an unrelated value; \
+                     name the variable explicitly, e.g. `#[env_item(\"APP_{}\")]`, or add `allow_system_var`",
+                    ident, name, name));
+            }
+        }
+    }
+    let candidates = candidate_names(args, &var_name, var_span, read_env)?;
+    let config = ConfigFile::new();
+    let metadata = CargoMetadata::new();
+    if args.debug_var.is_none() && args.release_var.is_none() {
+        return match lookup(args, &candidates, expr, &config, &metadata, var_span, read_env)? {
+            Some((name, value)) => {
+                let new_expr = value_expr(args, &name, &value, declared, expr, var_span, read_env)?;
+                if (args.warn_redundant || is_enabled(WARN_REDUNDANT_VAR, var_span, read_env)?)
+                    && value::same_value(&new_expr, expr)
+                {
+                    diagnostics::emit_warning(var_span, &format!(
+                        "`{}` is set to the same value as the default of `{}`", name, ident));
+                }
+                Ok(Some(new_expr))
+            },
+            None => Ok(None)
+        };
+    }
+
+    // Profile-specific variables take precedence over the generic name. The choice
+    // between the two values is left to `cfg!` so that it reflects the profile of the
+    // crate being compiled rather than the profile the macro was built with.
+    let profile_value = |profile_var: &Option<syn::LitStr>| -> Result<Option<Expr>, FromEnvError> {
+        let mut profile_candidates = Vec::new();
+        if let Some(profile_var) = profile_var {
+            profile_candidates.push(profile_var.value());
+        }
+        profile_candidates.extend(candidates.iter().cloned());
+        lookup(args, &profile_candidates, expr, &config, &metadata, var_span, read_env)?
+            .map(|(name, value)| value_expr(args, &name, &value, declared, expr, var_span, read_env))
+            .transpose()
+    };
+    let debug_expr = profile_value(&args.debug_var)?;
+    let release_expr = profile_value(&args.release_var)?;
+    if debug_expr.is_none() && release_expr.is_none() {
+        return Ok(None);
+    }
+    let debug_expr = debug_expr.unwrap_or_else(|| expr.clone());
+    let release_expr = release_expr.unwrap_or_else(|| expr.clone());
+    Ok(Some(syn::parse_quote!(if ::core::cfg!(debug_assertions) { #debug_expr } else { #release_expr })))
+}
+
+/// Reads the first set variable among `candidates`, returning its name and value. If
+/// none is set, the candidates are looked up in the defaults file and then, with
+/// `cargo_metadata`, in the crate's manifest. Fails if no value is found and the item is
+/// `required`.
+fn lookup(args: &ItemArgs, candidates: &[String], expr: &Expr, config: &ConfigFile, metadata: &CargoMetadata, span: proc_macro2::Span, read_env: &impl ReadEnv) -> Result<Option<(String, String)>, FromEnvError> {
+    // Byte string literals can hold any bytes, so values that are not valid UTF-8 are
+    // accepted when the value is parsed as the literal's contents.
+    let byte_string = matches!(expr, Expr::Lit(ExprLit { lit: Lit::ByteStr(_), .. }))
+        && args.include.is_none() && args.include_expr.is_none() && args.expr.is_none() && !args.file;
+    for candidate in candidates {
+        if let Some(value) = read_var(args, candidate, byte_string, span, read_env)? {
+            return Ok(Some((candidate.clone(), value)));
+        }
+    }
+    for candidate in candidates {
+        if let Some(value) = config.get(candidate, expr, read_env).map_err(|message| explain::error(explain::CONFIG_ERROR, span, message))? {
+            return Ok(Some((candidate.clone(), value)));
+        }
+    }
+    if args.cargo_metadata || is_enabled(CARGO_METADATA_VAR, span, read_env)? {
+        for candidate in candidates {
+            if let Some(value) = metadata.get(candidate, read_env).map_err(|message| explain::error(explain::METADATA_ERROR, span, message))? {
+                return Ok(Some((candidate.clone(), value)));
+            }
+        }
+    }
+    #[cfg(feature = "command-source")]
+    if let Some(command) = &args.command {
+        if let Some(value) = command::run_command(&command.value(), read_env).map_err(|err| env_error(err, span))? {
+            return Ok(Some((format!("command `{}`", command.value()), value)));
+        }
+    }
+    // None of the names the item reads is a typo of another, such as its `release_var`
+    // while its `debug_var` is looked up.
+    let own_names: Vec<String> = candidates.iter().cloned()
+        .chain(args.debug_var.iter().chain(&args.release_var).map(syn::LitStr::value))
+        .collect();
+    let suggestion = similar_var(candidates, &own_names, read_env).map(|(found, expected)| format!(
+        "a similarly named variable `{}` is set; did you mean `{}`?", found, expected));
+    if args.required {
+        return Err(FromEnvError::MissingVariable { names: candidates.to_vec(), suggestion, span });
+    }
+    if let Some(suggestion) = suggestion {
+        let names: Vec<String> = candidates.iter().map(|name| format!("`{}`", name)).collect();
+        diagnostics::emit_warning(span, &format!("Environment variable {} is not set, but {}", names.join(" or "), suggestion));
+    }
+    Ok(None)
+}
+
+/// Finds a set variable whose name is within a small edit distance of one of
+/// `candidates`, which is likely a typo, skipping `own_names`, which the item reads
+/// itself. Returns the variable found and the candidate it resembles.
+fn similar_var(candidates: &[String], own_names: &[String], read_env: &impl ReadEnv) -> Option<(String, String)> {
+    let mut names = read_env.var_names();
+    names.sort();
+    let mut best: Option<(usize, String, String)> = None;
+    for candidate in candidates {
+        let expected = candidate.to_ascii_uppercase();
+        for name in &names {
+            let found = name.to_ascii_uppercase();
+            if own_names.iter().any(|own| own.eq_ignore_ascii_case(&found)) {
+                continue;
+            }
+            let distance = args::edit_distance(&found, &expected);
+            // Longer names allow more edits, but never enough to match a sibling such as
+            // `APP_DATABASE_USER` for `APP_DATABASE_URL`.
+            let close = distance <= std::cmp::max(1, expected.len() / 4).min(2);
+            if close && best.as_ref().is_none_or(|(best_distance, _, _)| distance < *best_distance) {
+                best = Some((distance, name.clone(), candidate.clone()));
+            }
+        }
+    }
+    best.map(|(_, found, expected)| (found, expected))
+}
+
+/// Converts a variable's value into the replacement expression for `expr` and checks it
+/// against the item's validations.
+fn value_expr(args: &ItemArgs, var_name: &str, value: &str, declared: Option<&DeclaredType>, expr: &Expr, span: proc_macro2::Span, read_env: &impl ReadEnv) -> Result<Expr, FromEnvError> {
+    if let Some((expected, _)) = &args.sha256 {
+        check_digest(expected, value.as_bytes(), &format!("environment variable `{}`", var_name), span)?;
+    }
+    if args.include.is_some() {
+        return include_macro(var_name, value, expr, span, read_env).map_err(FromEnvError::from);
+    }
+    if args.include_expr.is_some() {
+        let new_expr = file_expr(var_name, value, span, read_env)?;
+        validate::validate(args, var_name, &new_expr, span)?;
+        return Ok(new_expr);
+    }
+    let checked = |mut new_expr: Expr| -> Result<Expr, FromEnvError> {
+        float::check(args, var_name, value, &new_expr, declared, expr, span)?;
+        list::sort_and_dedup(args, var_name, &mut new_expr, span)?;
+        validate::validate(args, var_name, &new_expr, span)?;
+        Ok(new_expr)
+    };
+    match (args.path_list, args.path_sep) {
+        (Some(_), Some((separator, _))) => checked(list::path_list_value(args, separator, value, expr)),
+        (Some(_), None) => path_list_for_target(args, value, expr, checked),
+        (None, _) => checked(convert_value(args, var_name, value, declared, expr, span)?),
+    }
+}
+
+/// The value of `split = "path_list"` without `path_sep`, split on the separator of the
+/// platform the crate is compiled for, `;` for Windows and `:` otherwise. The macro runs
+/// on the host, so the choice is left to `cfg!` when the two splits differ.
+fn path_list_for_target(args: &ItemArgs, value: &str, expr: &Expr, checked: impl Fn(Expr) -> Result<Expr, FromEnvError>) -> Result<Expr, FromEnvError> {
+    let windows_expr = checked(list::path_list_value(args, ';', value, expr))?;
+    let other_expr = checked(list::path_list_value(args, ':', value, expr))?;
+    if value::same_value(&windows_expr, &other_expr) {
+        return Ok(other_expr);
+    }
+    Ok(syn::parse_quote!(if ::core::cfg!(windows) { #windows_expr } else { #other_expr }))
+}
+
+fn convert_value(args: &ItemArgs, var_name: &str, value: &str, declared: Option<&DeclaredType>, expr: &Expr, span: proc_macro2::Span) -> Result<Expr, FromEnvError> {
+    let field = match (args.field, args.split_fields) {
+        (Some((index, _)), Some((count, strict))) => {
+            let separator = args.field_sep.map_or(',', |(separator, _)| separator);
+            let fields = list::record_fields(separator, value);
+            if strict && fields.len() > count {
+                return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                    "Environment variable `{}` has {} fields separated by `{}`, more than the {} of `split_into`\n  value: {}",
+                    var_name, fields.len(), separator, count, display_value(args, value))).into());
+            }
+            match fields.into_iter().nth(index) {
+                Some(field) => Some(field),
+                None => return Ok(expr.clone()),
+            }
+        }
+        (Some((index, _)), None) => {
+            let separator = args.field_sep.map_or(',', |(separator, _)| separator);
+            Some(list::record_field(args, index, separator, var_name, value, span)?)
+        }
+        (None, _) => None,
+    };
+    let value = field.as_deref().unwrap_or(value);
+    let captured = match &args.capture {
+        Some((regex, _)) => Some(regex::captured_value(args, regex, args.capture_group.as_ref().map(|(group, _)| group), var_name, value, expr)?),
+        None => None,
+    };
+    let value = captured.as_deref().unwrap_or(value);
+    let value = match args.decimal_comma {
+        Some(_) => decimal_comma(args, var_name, value, span)?,
+        None => Cow::Borrowed(value),
+    };
+    let value = &*value;
+    if args.ranges.is_some() {
+        return ranges::ranges_value(args, var_name, value, declared, expr, span);
+    }
+    if let Some((flags, flags_span)) = &args.flags {
+        return flags::flags_value(args, flags, var_name, value, declared, expr, *flags_span);
+    }
+    if let Some((options, _)) = &args.options {
+        return flags::option_value(args, options, var_name, value, span);
+    }
+    if let Some((scale, _)) = args.fixed_point {
+        return fixed_point::fixed_point_value(args, scale, var_name, value, expr);
+    }
+    if let Some((pad, _)) = args.pad {
+        return pad::padded_value(args, pad, var_name, value, expr, span);
+    }
+    if let Some((endian, _)) = args.endian {
+        return endian::endian_value(args, endian, var_name, value, declared, expr, span);
+    }
+    if args.exists.is_some() {
+        let lit = syn::LitBool::new(args.invert.is_none(), expr.span());
+        return Ok(syn::parse_quote!(#lit));
+    }
+    if args.invert.is_some() {
+        return match literal_value(args, declared, var_name, value, expr) {
+            Ok(Expr::Lit(ExprLit { attrs, lit: Lit::Bool(lit_bool) })) => Ok(ExprLit {
+                attrs,
+                lit: Lit::Bool(syn::LitBool::new(!lit_bool.value, lit_bool.span))
+            }.into()),
+            _ => Err(explain::error(explain::PARSE_FAILURE, span, format!("Expected environment variable `{}` to be `true` or `false`, found {}", var_name, display_value(args, value))).into())
+        };
+    }
+    if args.expr.is_some() {
+        return syn::parse_str::<Expr>(value).map_err(|err| explain::error(explain::PARSE_FAILURE, span, format!(
+            "Failed to parse environment variable `{}` as an expression: {}\n  value: {}", var_name, err, display_value(args, value))).into());
+    }
+    if let Some((pointer, _)) = &args.json_pointer {
+        let mut new_expr = json::pointer_value(args, pointer, var_name, value, declared, expr)?;
+        if let Some((case, _)) = args.case {
+            apply_case(&mut new_expr, case);
+        }
+        return Ok(new_expr);
+    }
+    if let Some(constructor) = duration::constructor(expr) {
+        return duration::duration_value(args, &constructor, var_name, value);
+    }
+    if let (Expr::Struct(_), None) = (expr, args.placeholder) {
+        return json::struct_value(args, var_name, value, expr);
+    }
+    let mut new_expr = if args.placeholder.is_some() {
+        fill_placeholder(value, expr)?
+    } else {
+        literal_value(args, declared, var_name, value, expr)?
+    };
+    if let Some((case, _)) = args.case {
+        apply_case(&mut new_expr, case);
+    }
+    Ok(new_expr)
+}
+
+/// Replaces a single decimal comma with a point, as in `3,14`. A value with several
+/// commas, or with both a comma and a point, is ambiguous.
+fn decimal_comma<'a>(args: &ItemArgs, var_name: &str, value: &'a str, span: proc_macro2::Span) -> syn::Result<Cow<'a, str>> {
+    match value.matches(',').count() {
+        0 => Ok(Cow::Borrowed(value)),
+        1 if !value.contains('.') => Ok(Cow::Owned(value.replace(',', "."))),
+        _ => Err(explain::error(explain::PARSE_FAILURE, span, format!(
+            "Environment variable `{}` is ambiguous with `decimal_comma`; use a single comma or a single point as the decimal separator\n  value: {}",
+            var_name, display_value(args, value)))),
+    }
+}
+
+/// Fails if the SHA-256 digest of `bytes` differs from `expected`. The value itself is
+/// never included in the error.
+fn check_digest(expected: &str, bytes: &[u8], source: &str, span: proc_macro2::Span) -> syn::Result<()> {
+    let actual = sha256::hex_digest(bytes);
+    if actual == expected {
+        return Ok(());
+    }
+    Err(explain::error(explain::DIGEST_MISMATCH, span, format!(
+        "The value of {} does not match the expected SHA-256 digest\n  expected: {}\n  actual:   {}", source, expected, actual)))
+}
+
+/// Compares the value of an item with the one recorded in the lockfile, or records it
+/// when `CONST_ENV_LOCK_WRITE=1`.
+fn check_lockfile(args: &ItemArgs, ident: &syn::Ident, path: &std::path::Path, value: &str, read_env: &impl ReadEnv) -> syn::Result<()> {
+    let crate_name = read_env.read_env_utf8("CARGO_CRATE_NAME")
+        .map_err(|err| env_error(err, ident.span()))?
+        .unwrap_or_else(|| "crate".to_string());
+    let item = syn::ext::IdentExt::unraw(ident).to_string();
+    let value = lockfile::recorded_value(args.sensitive, value);
+    if is_enabled(lockfile::LOCK_WRITE_VAR, ident.span(), read_env)? {
+        return lockfile::record(path, &crate_name, &item, &value).map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message));
+    }
+    let message = match lockfile::check(path, &crate_name, &item, &value).map_err(|message| explain::error(explain::FILE_ERROR, ident.span(), message))? {
+        Ok(()) => return Ok(()),
+        Err(lockfile::Mismatch::Missing) => format!("`{}` of crate `{}` is missing from the lockfile `{}`", item, crate_name, path.display()),
+        Err(lockfile::Mismatch::Changed { expected }) => format!(
+            "The value of `{}` differs from the one recorded in the lockfile `{}`\n  expected: {}\n  actual:   {}",
+            item, path.display(), expected, value),
+    };
+    Err(explain::error(explain::LOCK_MISMATCH, ident.span(), format!(
+        "{}\nRun the build with `{}=1` to record the current values", message, lockfile::LOCK_WRITE_VAR)))
+}
+
+/// Reports another item of the crate reading the same variable, as a warning unless
+/// `CONST_ENV_DUPLICATES` asks for an error or for no check at all.
+fn check_duplicate(var_name: &str, ident: &syn::Ident, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<()> {
+    let mode = read_env.read_env_utf8(DUPLICATES_VAR).map_err(|err| env_error(err, span))?;
+    let is_error = match mode.as_deref() {
+        Some("off") => return Ok(()),
+        Some("error") => true,
+        Some("warn") | None => false,
+        Some(other) => return Err(explain::error(explain::INVALID_SETTING, span, format!(
+            "Invalid value {:?} for `{}`, expected `warn`, `error` or `off`", other, DUPLICATES_VAR))),
+    };
+    let (file, line) = span_location(ident.span());
+    let previous = match claims::claim(var_name, &ident.to_string(), &file, line, read_env) {
+        Ok(previous) => previous,
+        Err(message) if is_error => return Err(explain::error(explain::FILE_ERROR, span, message)),
+        // Failing to keep track of claims should not break the build.
+        Err(_) => None,
+    };
+    if let Some(previous) = previous {
+        let message = format!("Environment variable `{}` read by `{}` is also read by {}", var_name, ident, previous);
+        if is_error {
+            return Err(explain::error(explain::DUPLICATE_VARIABLE, span, message));
+        }
+        diagnostics::emit_warning(span, &message);
+    }
+    Ok(())
+}
+
+/// Formats a value for diagnostics, hiding it if the item is `sensitive`.
+pub(crate) fn display_value(args: &ItemArgs, value: &str) -> String {
+    if args.sensitive {
+        format!("<redacted> ({} bytes)", value.len())
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Builds an `include_bytes!` or `include_str!` invocation for the file named by
+/// `path`, so that rustc reads the file and tracks it for rebuilds.
+fn include_macro(var_name: &str, path: &str, expr: &Expr, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<Expr> {
+    let resolved = file::resolve_path(path, read_env).map_err(|message| explain::error(explain::FILE_ERROR, span, message))?;
+    if !resolved.is_file() {
+        return Err(explain::error(explain::FILE_ERROR, span, format!(
+            "File `{}` does not exist (path taken from environment variable `{}`)", resolved.display(), var_name)));
+    }
+    let path_expr = file::include_path(path, &resolved, read_env).map_err(|message| explain::error(explain::FILE_ERROR, span, message))?;
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::ByteStr(_), .. }) => Ok(syn::parse_quote!(::core::include_bytes!(#path_expr))),
+        _ => Ok(syn::parse_quote!(::core::include_str!(#path_expr)))
+    }
+}
+
+/// Parses the file named by `path` as the expression replacing the whole initializer,
+/// and has rustc track the file, which it would not know about otherwise. Parse errors
+/// point at the line and column in the file.
+fn file_expr(var_name: &str, path: &str, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<Expr> {
+    let resolved = file::resolve_path(path, read_env).map_err(|message| explain::error(explain::FILE_ERROR, span, message))?;
+    let contents = std::fs::read_to_string(&resolved).map_err(|err| explain::error(explain::FILE_ERROR, span, format!(
+        "Failed to read file `{}` (path taken from environment variable `{}`): {}", resolved.display(), var_name, err)))?;
+    file::track_file(file::include_path(path, &resolved, read_env).map_err(|message| explain::error(explain::FILE_ERROR, span, message))?);
+    syn::parse_str::<Expr>(&contents).map_err(|err| {
+        let location = match locate::expr_error_location(&contents) {
+            Some((line, column)) => format!("{}:{}:{}", resolved.display(), line, column),
+            None => resolved.display().to_string(),
+        };
+        explain::error(explain::PARSE_FAILURE, span, format!(
+            "Failed to parse file `{}` (path taken from environment variable `{}`) as an expression: {}\n  --> {}",
+            resolved.display(), var_name, err, location))
+    })
+}
+
+/// Setting this variable to `1` makes every lookup case-insensitive, as if each item
+/// had the `ignore_case` flag.
+const IGNORE_CASE_VAR: &str = "CONST_ENV_IGNORE_CASE";
+
+/// Setting this variable to `1` enables the `<NAME>_FILE` fallback for every item, as
+/// if each item had the `file_fallback` flag.
+const FILE_FALLBACK_VAR: &str = "CONST_ENV_FILE_FALLBACK";
+
+/// Setting this variable to `1` prints a line to stderr for every item describing how
+/// it was substituted.
+const DEBUG_VAR: &str = "CONST_ENV_DEBUG";
+
+/// Setting this variable to `1` records every item, whether or not it was overridden,
+/// on stderr or in the file named by `CONST_ENV_LIST_FILE`.
+const LIST_VAR: &str = "CONST_ENV_LIST";
+
+/// File that `CONST_ENV_LIST` lines are appended to instead of stderr.
+const LIST_FILE_VAR: &str = "CONST_ENV_LIST_FILE";
+
+/// Setting this variable to `1` makes every item `required` unless it is marked
+/// `optional`.
+const STRICT_VAR: &str = "CONST_ENV_STRICT";
+
+/// How to report several items reading the same variable: `warn` (the default),
+/// `error` or `off`.
+const DUPLICATES_VAR: &str = "CONST_ENV_DUPLICATES";
+
+/// Setting this variable to `1` enables `warn_redundant` for every item.
+const WARN_REDUNDANT_VAR: &str = "CONST_ENV_WARN_REDUNDANT";
+
+/// Setting this variable to `0` disables the `fast-parse` feature's handling of items of
+/// the common shape, so that every item is parsed by `syn`.
+#[cfg(feature = "fast-parse")]
+const FAST_PARSE_VAR: &str = "CONST_ENV_FAST_PARSE";
+
+/// Setting this variable to `1` enables `cargo_metadata` for every item.
+const CARGO_METADATA_VAR: &str = "CONST_ENV_CARGO_METADATA";
+
+/// Setting this variable to `1` makes every item record its value for `fingerprint!()`.
+const FINGERPRINT_VAR: &str = "CONST_ENV_FINGERPRINT";
+
+/// Layers the `.env` file named by `CONST_ENV_DOTENV`, if any, below the environment.
+fn with_dotenv<R: ReadEnv>(read_env: R) -> syn::Result<ChainEnv<R, DotenvEnv>> {
+    let dotenv = dotenv::load(&read_env)
+        .map_err(|message| explain::error(explain::DOTENV_ERROR, proc_macro2::Span::call_site(), message))?;
+    Ok(read_env.chain(dotenv))
+}
+
+/// Runs `expand` with the `.env` file layered below `read_env`, following its output with
+/// the items that track the file for rebuilds.
+fn expand_with_dotenv<R: ReadEnv, E: From<syn::Error>>(read_env: R, expand: impl FnOnce(ChainEnv<R, DotenvEnv>) -> Result<TokenStream, E>) -> Result<TokenStream, E> {
+    let (output, tracked_files) = file::collect_tracked_files(|| with_dotenv(read_env).map_err(E::from).and_then(expand));
+    let mut output = output?;
+    output.extend(tracked_files);
+    Ok(output)
+}
+
+/// Whether a crate-wide setting such as `CONST_ENV_DEBUG` is set to `1`.
+fn is_enabled(var_name: &str, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<bool> {
+    let value = read_env.read_env_utf8(var_name).map_err(|err| env_error(err, span))?;
+    Ok(value.as_deref() == Some("1"))
+}
+
+/// Reports an error from the environment source against `span`.
+pub(crate) fn env_error(err: EnvError, span: proc_macro2::Span) -> syn::Error {
+    let code = match err {
+        EnvError::NotUnicode { .. } => explain::INVALID_UTF8,
+        EnvError::Lookup { .. } => explain::LOOKUP_FAILURE,
+    };
+    explain::error(code, span, err)
+}
+
+/// The file and line at which `span` starts. The compiler gives them to a macro; outside
+/// of one, as in tests, proc-macro2 only tracks them with the `span-locations` feature,
+/// and they are `<unknown>` and 0 without it.
+pub(crate) fn span_location(span: proc_macro2::Span) -> (String, usize) {
+    if proc_macro::is_available() {
+        let span = span.unwrap();
+        return (span.file(), span.line());
+    }
+    fallback_span_location(span)
+}
+
+#[cfg(feature = "span-locations")]
+fn fallback_span_location(span: proc_macro2::Span) -> (String, usize) {
+    (span.file(), span.start().line)
+}
+
+#[cfg(not(feature = "span-locations"))]
+fn fallback_span_location(_span: proc_macro2::Span) -> (String, usize) {
+    ("<unknown>".to_string(), 0)
+}
+
+/// Lists every variable an item may read, for `CONST_ENV_LIST`.
+pub(crate) fn variable_names(args: &ItemArgs, ident: &syn::Ident, read_env: &impl ReadEnv) -> syn::Result<Vec<String>> {
+    if let Some(format) = &args.format {
+        return Ok(template::parse_template(format)
+            .map(|segments| segments.into_iter().filter_map(|segment| match segment {
+                Segment::Var(name) => Some(name),
+                Segment::Literal(_) => None,
+            }).collect())
+            .unwrap_or_default());
+    }
+    if let Some(out_file) = &args.out_file {
+        return Ok(vec![format!("OUT_DIR/{}", out_file.value())]);
+    }
+    if let Some((prefix, _)) = &args.prefix_map {
+        return Ok(prefix_map::matching_names(prefix, read_env));
+    }
+    if let Some((presence, _)) = &args.presence {
+        return Ok(presence.names());
+    }
+    let var_name = match &args.var_name {
+        Some(var_name) => var_name.value(),
+        None => args.rename_all.apply(ident)
+    };
+    let mut names: Vec<String> = args.debug_var.iter().chain(&args.release_var).map(syn::LitStr::value).collect();
+    names.extend(candidate_names(args, &var_name, ident.span(), read_env)?);
+    Ok(names)
+}
+
+/// Lists the variable names to try for an item, in order of precedence.
+fn candidate_names(args: &ItemArgs, var_name: &str, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<Vec<String>> {
+    let mut candidates = Vec::new();
+    if args.per_target.is_some() {
+        if let Some(target) = read_env.read_env_utf8("TARGET").map_err(|err| env_error(err, span))? {
+            candidates.push(format!("{}_{}", var_name, target.replace(['-', '.'], "_")));
+        }
+    }
+    candidates.push(var_name.to_string());
+    Ok(candidates)
+}
+
+/// Reads a variable, expanding references inside its value if requested. If
+/// `byte_string` is set, bytes of a value that is not valid UTF-8 are escaped instead of
+/// causing an error.
+fn read_var(args: &ItemArgs, var_name: &str, byte_string: bool, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<Option<String>> {
+    let file_fallback = args.file_fallback || is_enabled(FILE_FALLBACK_VAR, span, read_env)?;
+    let (source_name, value, from_file) = match read_raw(args, var_name, byte_string, span, read_env)? {
+        Some(value) => (var_name.to_string(), value, args.file),
+        None if file_fallback => {
+            let file_var_name = format!("{}_FILE", var_name);
+            match read_raw(args, &file_var_name, false, span, read_env)? {
+                Some(value) => (file_var_name, value, true),
+                None => return Ok(None)
+            }
+        }
+        None => return Ok(None)
+    };
+    let value = match args.expand {
+        Some(missing) => expand::expand_value(&source_name, &value, missing, read_env)
+            .map_err(|message| explain::error(explain::EXPANSION_FAILURE, span, format!("Failed to expand environment variable `{}`: {}", source_name, message)))?,
+        None => value
+    };
+    if from_file {
+        return file::read_value_file(&value, args.trim_newline, read_env)
+            .map(Some)
+            .map_err(|message| explain::error(explain::FILE_ERROR, span, format!("{} (path taken from environment variable `{}`)", message, source_name)));
+    }
+    Ok(Some(value))
+}
+
+/// Reads a variable by its exact name, falling back to a case-insensitive search if
+/// requested.
+fn read_raw(args: &ItemArgs, var_name: &str, byte_string: bool, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<Option<String>> {
+    if let Some(value) = read_utf8(var_name, byte_string, span, read_env)? {
+        return Ok(Some(value));
+    }
+    if !args.ignore_case && !is_enabled(IGNORE_CASE_VAR, span, read_env)? {
+        return Ok(None);
+    }
+    let mut matches: Vec<String> = read_env.var_names()
+        .into_iter()
+        .filter(|name| name.eq_ignore_ascii_case(var_name))
+        .collect();
+    matches.sort();
+    match matches.len() {
+        0 => Ok(None),
+        1 => read_utf8(&matches[0], byte_string, span, read_env),
+        _ => {
+            let names: Vec<String> = matches.iter().map(|name| format!("`{}`", name)).collect();
+            Err(explain::error(explain::AMBIGUOUS_VARIABLE, span, format!(
+                "Environment variable `{}` matches several variables when ignoring case: {}", var_name, names.join(", "))))
+        }
+    }
+}
+
+/// Reads a variable, failing if it is set but not valid UTF-8 unless `byte_string` is
+/// set, in which case every non-ASCII byte is written as a `\xNN` escape.
+fn read_utf8(var_name: &str, byte_string: bool, span: proc_macro2::Span, read_env: &impl ReadEnv) -> syn::Result<Option<String>> {
+    match read_env.read_env_utf8(var_name) {
+        Ok(value) => Ok(value),
+        Err(EnvError::NotUnicode { value, .. }) if byte_string => Ok(Some(os_str_bytes(&value).iter().map(|&byte| {
+            if byte.is_ascii() {
+                char::from(byte).to_string()
+            } else {
+                format!("\\x{:02x}", byte)
+            }
+        }).collect())),
+        Err(err) => Err(env_error(err, span))
+    }
+}
+
+/// Assembles the value of a `format` template, or returns `None` if a referenced
+/// variable is unset and the item is not `required`.
+fn format_value(format: &syn::LitStr, args: &ItemArgs, read_env: &impl ReadEnv) -> syn::Result<Option<String>> {
+    let mut value = String::new();
+    let mut missing = Vec::new();
+    for segment in template::parse_template(format)? {
+        match segment {
+            Segment::Literal(text) => value.push_str(&text),
+            Segment::Var(name) => match read_var(args, &name, false, format.span(), read_env)? {
+                Some(var_value) => value.push_str(&var_value),
+                None => missing.push(format!("`{}`", name))
+            }
+        }
+    }
+    if missing.is_empty() {
+        Ok(Some(value))
+    } else if args.required {
+        Err(explain::error(explain::MISSING_REQUIRED, format.span(), format!("Environment variables referenced by `format` are required but not set: {}", missing.join(", "))))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Checks that the default is a string literal containing exactly one `{}`.
+fn check_placeholder(placeholder_span: proc_macro2::Span, expr: &Expr) -> syn::Result<()> {
+    let default = match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit_str), .. }) => lit_str,
+        _ => return Err(explain::error(explain::UNSUPPORTED_DEFAULT, placeholder_span, "`placeholder` can only be used on items with a string default"))
+    };
+    match default.value().matches("{}").count() {
+        1 => Ok(()),
+        0 => Err(explain::error(explain::UNSUPPORTED_DEFAULT, default.span(), "Expected the default string to contain a `{}` placeholder")),
+        _ => Err(explain::error(explain::UNSUPPORTED_DEFAULT, default.span(), "Expected the default string to contain exactly one `{}` placeholder"))
+    }
+}
+
+/// Replaces the `{}` placeholder in the default string with `value`.
+fn fill_placeholder(value: &str, original_expr: &Expr) -> syn::Result<Expr> {
+    match original_expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(original), .. }) => {
+            string_to_literal(&original.value().replacen("{}", value, 1), original_expr)
+        },
+        _ => Err(explain::error(explain::UNSUPPORTED_DEFAULT, original_expr.span(), "Expected the default to be a string literal"))
+    }
+}
+
+/// Builds a string or byte string literal, matching the kind of the original
+/// expression, whose contents are exactly `value` with no escape processing.
+fn string_to_literal(value: &str, original_expr: &Expr) -> syn::Result<Expr> {
+    match original_expr {
+        Expr::Lit(ExprLit { attrs, lit: Lit::ByteStr(original) }) => Ok(ExprLit {
+            attrs: attrs.clone(),
+            lit: Lit::ByteStr(syn::LitByteStr::new(value.as_bytes(), original.span()))
+        }.into()),
+        Expr::Lit(ExprLit { attrs, lit }) => Ok(ExprLit {
+            attrs: attrs.clone(),
+            lit: Lit::Str(syn::LitStr::new(value, lit.span()))
+        }.into()),
+        _ => Err(explain::error(explain::UNSUPPORTED_DEFAULT, original_expr.span(), "Expected the default to be a string literal"))
+    }
+}
+
+fn is_string_like(expr: &Expr) -> bool {
+    matches!(expr, Expr::Lit(ExprLit { lit: Lit::Str(_) | Lit::ByteStr(_), .. }))
+}
+
+/// Applies a case transformation to the parsed contents of a string literal, so that
+/// escape sequences in the raw value are unaffected.
+fn apply_case(expr: &mut Expr, case: Case) {
+    if let Expr::Lit(ExprLit { lit, .. }) = expr {
+        match lit {
+            Lit::Str(lit_str) => {
+                *lit_str = syn::LitStr::new(&case.apply(&lit_str.value()), lit_str.span());
+            }
+            Lit::ByteStr(lit_byte_str) => {
+                *lit_byte_str = syn::LitByteStr::new(&case.apply_bytes(&lit_byte_str.value()), lit_byte_str.span());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Describes the kind of value expected for an item whose default is `expr`.
+fn expected_kind(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Array(_) | Expr::Reference(_) => "an array",
+        Expr::Tuple(_) => "a tuple",
+        Expr::Unary(_) => "a number",
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(_) => "a string",
+            Lit::ByteStr(_) => "a byte string",
+            Lit::Byte(_) => "a byte",
+            Lit::Char(_) => "a character",
+            Lit::Bool(_) => "a boolean",
+            Lit::Int(_) => "an integer",
+            Lit::Float(_) => "a float",
+            Lit::Verbatim(_) => "a literal",
+        },
+        _ => "a literal",
+    }
+}
+
+/// Names the kind of an expression for `CONST_ENV_DEBUG` output.
+fn kind_name(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Lit(_) | Expr::Unary(_) | Expr::Array(_) | Expr::Tuple(_) | Expr::Reference(_) => {
+            let kind = expected_kind(expr);
+            kind.split_once(' ').map_or(kind, |(_, kind)| kind)
+        }
+        _ => "expression",
+    }
+}
+
+/// Whether `new` is a literal of the same kind as the numeric or boolean `original`,
+/// allowing a leading `-` on numbers.
+fn same_literal_kind(new: &Expr, original: &Lit) -> bool {
+    let new_lit = match new {
+        Expr::Lit(ExprLit { lit, .. }) => lit,
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) if !matches!(original, Lit::Bool(_)) => match &**expr {
+            Expr::Lit(ExprLit { lit, .. }) => lit,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    match (new_lit, original) {
+        (Lit::Int(_), Lit::Int(_)) | (Lit::Float(_), Lit::Float(_)) | (Lit::Bool(_), Lit::Bool(_)) => true,
+        // `1f32` is lexed as an integer with a float suffix.
+        (Lit::Int(new), Lit::Float(_)) => new.suffix().starts_with('f'),
+        _ => false,
+    }
+}
+
+/// Parses a variable's value as a literal of the item's declared type if it is known, and
+/// of the same kind as the default otherwise.
+fn literal_value(args: &ItemArgs, declared: Option<&DeclaredType>, var_name: &str, value: &str, expr: &Expr) -> Result<Expr, FromEnvError> {
+    match declared {
+        Some(declared) => declared::typed_value(args, declared, var_name, value, expr),
+        None => value_to_literal(args, var_name, value, expr),
+    }
+}
+
+/// Parses a variable's value as a literal of the same kind as the original expression.
+/// Errors name the variable and its value and point at the original expression.
+pub(crate) fn value_to_literal(args: &ItemArgs, var_name: &str, value: &str, original_expr: &Expr) -> Result<Expr, FromEnvError> {
+    let parse_error = |err: Option<syn::Error>| FromEnvError::ParseFailure {
+        var: var_name.to_string(),
+        value: display_value(args, value),
+        kind: expected_kind(original_expr),
+        reason: err.map(|err| err.to_string()),
+        span: original_expr.span(),
+    };
+    match original_expr {
+        Expr::Array(_) => {
+            syn::parse_str::<syn::ExprArray>(value)
+                .map(syn::Expr::Array)
+                .map_err(|err| parse_error(Some(err)))
+        },
+        Expr::Tuple(_) => {
+            syn::parse_str::<syn::ExprTuple>(value)
+                .map(syn::Expr::Tuple)
+                .map_err(|err| parse_error(Some(err)))
+        },
+        // A reference to an array is a slice default, written in the variable without
+        // the leading `&`.
+        Expr::Reference(reference) if matches!(*reference.expr, Expr::Array(_)) => {
+            let array = syn::parse_str::<syn::ExprArray>(value).map_err(|err| parse_error(Some(err)))?;
+            Ok(syn::parse_quote!(&#array))
+        },
+        Expr::Unary(unary) => {
+            // A unary sign indicates this is a numeric literal which doesn't need any
+            // escaping, so we can parse it directly.
+            let new: Expr = syn::parse_str(value).map_err(|err| parse_error(Some(err)))?;
+            let numeric = match &new {
+                Expr::Lit(ExprLit { lit: Lit::Int(_) | Lit::Float(_), .. }) => true,
+                Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => matches!(&**expr, Expr::Lit(ExprLit { lit: Lit::Int(_) | Lit::Float(_), .. })),
+                _ => false,
+            };
+            // The value must be the same kind of number as the negated default, so that
+            // `-1` does not accept `2.5`.
+            let same_kind = match &*unary.expr {
+                Expr::Lit(ExprLit { lit, .. }) => same_literal_kind(&new, lit),
+                _ => true,
+            };
+            match numeric && same_kind {
+                true => Ok(new),
+                false => Err(parse_error(None)),
+            }
+        },
+        Expr::Lit(literal) => {
+            let new_lit = match &literal.lit {
+                Lit::Str(original) => {
+                    let mut new: syn::LitStr = syn::parse_str(&format!("\"{}\"", value))
+                        .map_err(|err| parse_error(Some(err)))?;
+                    new.set_span(original.span());
+                    Lit::Str(new)
+                },
+                Lit::ByteStr(original) => {
+                    let mut new: syn::LitByteStr = syn::parse_str(&format!("b\"{}\"", value))
+                        .map_err(|err| parse_error(Some(err)))?;
+                    new.set_span(original.span());
+                    Lit::ByteStr(new)
+                },
+                Lit::Byte(original) => {
+                    let mut new: syn::LitByte = syn::parse_str(&format!("b'{}'", value))
+                        .map_err(|err| parse_error(Some(err)))?;
+                    new.set_span(original.span());
+                    Lit::Byte(new)
+                },
+                Lit::Char(original) => {
+                    let mut new: syn::LitChar = syn::parse_str(&format!("'{}'", value))
+                        .map_err(|err| parse_error(Some(err)))?;
+                    new.set_span(original.span());
+                    Lit::Char(new)
+                },
+                // These variants do not need any escaping and can be parsed as an expression
+                // directly.
+                Lit::Bool(_) | Lit::Int(_) | Lit::Float(_) => {
+                    let new: Expr = syn::parse_str(value).map_err(|err| parse_error(Some(err)))?;
+                    if !same_literal_kind(&new, &literal.lit) {
+                        return Err(parse_error(None));
+                    }
+                    return Ok(new);
+                }
+                Lit::Verbatim(_) => {
+                    return syn::parse_str(value).map_err(|err| parse_error(Some(err)));
+                }
+            };
+            Ok(ExprLit {
+                attrs: literal.attrs.clone(),
+                lit: new_lit
+            }.into())
+        },
+        _ => Err(explain::error(explain::UNSUPPORTED_DEFAULT, original_expr.span(), "Expected the default to be a literal, an array, a tuple, or a negative number; use the `expr` flag for other expressions").into())
+    }
+}
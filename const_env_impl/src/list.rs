@@ -0,0 +1,128 @@
+use std::cmp::Ordering;
+
+use syn::{Expr, ExprArray};
+
+use crate::args::ItemArgs;
+use crate::value;
+use crate::{explain, FromEnvError};
+
+/// Returns the elements of an array or a reference to one.
+pub(crate) fn list_elements(expr: &Expr) -> Option<Vec<&Expr>> {
+    match expr {
+        Expr::Array(array) => Some(array.elems.iter().collect()),
+        Expr::Reference(reference) => list_elements(&reference.expr),
+        Expr::Paren(paren) => list_elements(&paren.expr),
+        Expr::Group(group) => list_elements(&group.expr),
+        _ => None,
+    }
+}
+
+/// Whether `expr` is a slice of string literals, the default `split = "path_list"` needs.
+pub(crate) fn is_str_slice(expr: &Expr) -> bool {
+    let elements = match expr {
+        Expr::Reference(reference) => list_elements(&reference.expr),
+        _ => None,
+    };
+    elements.is_some_and(|elements| elements.iter().all(|element| matches!(element, Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(_), .. }))))
+}
+
+/// Splits a path list such as `PLUGIN_PATH` on `separator` into a slice of string
+/// literals, dropping empty segments unless `keep_empty` is given.
+pub(crate) fn path_list_value(args: &ItemArgs, separator: char, value: &str, expr: &Expr) -> Expr {
+    let span = syn::spanned::Spanned::span(expr);
+    let elements = value.split(separator)
+        .filter(|segment| args.keep_empty || !segment.is_empty())
+        .map(|segment| match args.case {
+            Some((case, _)) => syn::LitStr::new(&case.apply(segment), span),
+            None => syn::LitStr::new(segment, span),
+        });
+    syn::parse_quote!(&[#(#elements),*])
+}
+
+/// The zero-based field `index` of a record such as `1.4.2|2024-06-01|release`, split
+/// on `separator` and trimmed.
+pub(crate) fn record_field(args: &ItemArgs, index: usize, separator: char, var_name: &str, value: &str, span: proc_macro2::Span) -> Result<String, FromEnvError> {
+    let fields = record_fields(separator, value);
+    let count = fields.len();
+    match fields.into_iter().nth(index) {
+        Some(field) => Ok(field),
+        None => Err(FromEnvError::ParseFailure {
+            var: var_name.to_string(),
+            value: crate::display_value(args, value),
+            kind: "a record",
+            reason: Some(format!("expected at least {} fields separated by `{}` to take field {}, found {}", index + 1, separator, index, count)),
+            span,
+        }),
+    }
+}
+
+/// The trimmed fields of a record split on `separator`. A backslash escapes the
+/// separator and itself, so that `a\|b` is the single field `a|b`; other backslashes
+/// are kept for the literal parser.
+pub(crate) fn record_fields(separator: char, value: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == separator || next == '\\' => fields.last_mut().unwrap().push(next),
+                Some(next) => fields.last_mut().unwrap().extend(['\\', next]),
+                None => fields.last_mut().unwrap().push('\\'),
+            },
+            c if c == separator => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+/// Returns the array of a list and whether it is behind a reference, i.e. whether the
+/// item is a slice whose length may change.
+fn list_array_mut(expr: &mut Expr) -> Option<(&mut ExprArray, bool)> {
+    match expr {
+        Expr::Array(array) => Some((array, false)),
+        Expr::Reference(reference) => list_array_mut(&mut reference.expr).map(|(array, _)| (array, true)),
+        Expr::Paren(paren) => list_array_mut(&mut paren.expr),
+        Expr::Group(group) => list_array_mut(&mut group.expr),
+        _ => None,
+    }
+}
+
+/// Sorts and deduplicates the elements of a list value as requested by the `sort` and
+/// `dedup` flags. Without `sort`, `dedup` keeps the first occurrence of each element.
+pub(crate) fn sort_and_dedup(args: &ItemArgs, var_name: &str, new_expr: &mut Expr, span: proc_macro2::Span) -> syn::Result<()> {
+    if args.sort.is_none() && args.dedup.is_none() {
+        return Ok(());
+    }
+    let (array, is_slice) = match list_array_mut(new_expr) {
+        Some(list) => list,
+        None => return Ok(()),
+    };
+    let mut elements: Vec<Expr> = array.elems.iter().cloned().collect();
+    if args.sort.is_some() {
+        for pair in elements.windows(2) {
+            if value::order(&pair[0], &pair[1]).is_none() {
+                return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                    "Cannot sort environment variable `{}`: elements must all be numbers or all be strings", var_name)));
+            }
+        }
+        elements.sort_by(|a, b| value::order(a, b).unwrap_or(Ordering::Equal));
+    }
+    if args.dedup.is_some() {
+        let count = elements.len();
+        let mut unique: Vec<Expr> = Vec::with_capacity(count);
+        for element in elements {
+            if !unique.iter().any(|kept| value::same_value(kept, &element)) {
+                unique.push(element);
+            }
+        }
+        if !is_slice && unique.len() != count {
+            return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Environment variable `{}` has {} duplicate elements, which would change the length of the array; use a slice to allow this",
+                var_name, count - unique.len())));
+        }
+        elements = unique;
+    }
+    array.elems = elements.into_iter().collect();
+    Ok(())
+}
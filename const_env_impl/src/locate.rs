@@ -0,0 +1,246 @@
+//! Locates where text fails to parse as an expression. Inside a macro, the compiler gives
+//! every token of parsed text the span of the invocation, so the error's span cannot tell
+//! where it is. Instead, prefixes of the text, cut between the tokens found by a small lexer
+//! of its own, are parsed until one fails the way the whole text does.
+
+/// A token of the text, by the byte offsets at which it starts and ends.
+struct Token {
+    start: usize,
+    end: usize,
+    kind: Kind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// An opening delimiter, with the character that closes it.
+    Open(char),
+    Close,
+    Other,
+}
+
+/// The line and column, counted from 1, at which `contents` fails to parse as an
+/// expression, or `None` if it parses or the error has no token to point at, as at the
+/// end of the text.
+pub(crate) fn expr_error_location(contents: &str) -> Option<(usize, usize)> {
+    let message = syn::parse_str::<syn::Expr>(contents).err()?.to_string();
+    let tokens = match lex(contents) {
+        Ok(tokens) => tokens,
+        Err(offset) => return Some(line_column(contents, offset)),
+    };
+    // Each candidate is a place to cut the text, with the token the error is at if the
+    // text cut there fails alike. An error at the end of a group, such as a missing value
+    // before `}`, is at its closing delimiter; any other error is at the last token kept.
+    let candidates: Vec<(usize, usize)> = match message.starts_with("unexpected end of input") {
+        true => tokens.iter()
+            .filter(|token| token.kind == Kind::Close)
+            .map(|token| (token.end, token.start))
+            .collect(),
+        false => tokens.iter().enumerate()
+            .map(|(index, token)| (tokens.get(index + 1).map_or(contents.len(), |next| next.start), token.start))
+            .collect(),
+    };
+    let fails_alike = |cut: usize| match syn::parse_str::<syn::Expr>(&prefix(contents, &tokens, cut)) {
+        Err(err) => err.to_string() == message,
+        Ok(_) => false,
+    };
+    // Cutting before the error parses, or fails at the end of the text, and cutting after
+    // it fails alike, so the first candidate that does is found by bisection.
+    let (mut low, mut high) = (0, candidates.len());
+    while low < high {
+        let middle = (low + high) / 2;
+        match fails_alike(candidates[middle].0) {
+            true => high = middle,
+            false => low = middle + 1,
+        }
+    }
+    candidates.get(low).map(|&(_, at)| line_column(contents, at))
+}
+
+/// The text before `cut`, with the groups still open there closed.
+fn prefix(contents: &str, tokens: &[Token], cut: usize) -> String {
+    let mut open = Vec::new();
+    for token in tokens.iter().take_while(|token| token.start < cut) {
+        match token.kind {
+            Kind::Open(close) => open.push(close),
+            Kind::Close => {
+                open.pop();
+            }
+            Kind::Other => {}
+        }
+    }
+    let mut text = contents[..cut].to_string();
+    text.extend(open.iter().rev());
+    text
+}
+
+/// The line and column, counted from 1, of the byte `offset` of `contents`.
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// Splits `text` into tokens, as far as finding the places to cut it needs, nesting the
+/// delimiters. Fails with the offset of an unterminated literal or comment, or of an
+/// unbalanced delimiter.
+fn lex(text: &str) -> Result<Vec<Token>, usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let at = |index: usize| chars.get(index).map(|&(_, c)| c);
+    let is_ident = |c: Option<char>| c.is_some_and(|c| c == '_' || c.is_alphanumeric());
+    let mut tokens = Vec::new();
+    let mut open: Vec<(char, usize)> = Vec::new();
+    let mut index = 0;
+    while let Some(&(start, c)) = chars.get(index) {
+        let mut kind = Kind::Other;
+        let end = match c {
+            c if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '/' if at(index + 1) == Some('/') => {
+                while at(index).is_some_and(|c| c != '\n') {
+                    index += 1;
+                }
+                continue;
+            }
+            '/' if at(index + 1) == Some('*') => {
+                let mut depth = 0;
+                loop {
+                    match (at(index), at(index + 1)) {
+                        (Some('/'), Some('*')) => {
+                            depth += 1;
+                            index += 2;
+                        }
+                        (Some('*'), Some('/')) => {
+                            depth -= 1;
+                            index += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        (Some(_), _) => index += 1,
+                        (None, _) => return Err(start),
+                    }
+                }
+                continue;
+            }
+            '(' | '[' | '{' => {
+                let close = match c {
+                    '(' => ')',
+                    '[' => ']',
+                    _ => '}',
+                };
+                open.push((close, start));
+                kind = Kind::Open(close);
+                index + 1
+            }
+            ')' | ']' | '}' => match open.pop() {
+                Some((close, _)) if close == c => {
+                    kind = Kind::Close;
+                    index + 1
+                }
+                _ => return Err(start),
+            },
+            '"' => quoted(&chars, index + 1, '"').ok_or(start)?,
+            '\'' => match (at(index + 1), at(index + 2)) {
+                (Some('\\'), _) => quoted(&chars, index + 2, '\'').ok_or(start)?,
+                (Some(_), Some('\'')) => index + 3,
+                // A lifetime or label.
+                _ => {
+                    let mut end = index + 1;
+                    while is_ident(at(end)) {
+                        end += 1;
+                    }
+                    end
+                }
+            },
+            c if c.is_ascii_digit() => {
+                let hex = c == '0' && matches!(at(index + 1), Some('x' | 'X'));
+                let mut end = index + 1;
+                loop {
+                    match at(end) {
+                        c if is_ident(c) => end += 1,
+                        Some('.') if at(end + 1).is_some_and(|c| c.is_ascii_digit()) => end += 1,
+                        Some('+' | '-') if !hex && matches!(at(end - 1), Some('e' | 'E')) => end += 1,
+                        _ => break,
+                    }
+                }
+                end
+            }
+            c if is_ident(Some(c)) => {
+                let mut end = index + 1;
+                while is_ident(at(end)) {
+                    end += 1;
+                }
+                let word: String = chars[index..end].iter().map(|&(_, c)| c).collect();
+                match (word.as_str(), at(end)) {
+                    ("b" | "c", Some('"')) => quoted(&chars, end + 1, '"').ok_or(start)?,
+                    ("r" | "br" | "cr", Some('"' | '#')) => match raw(&chars, end) {
+                        Some(end) => end,
+                        // A raw identifier such as `r#type`.
+                        None if word == "r" && is_ident(at(end + 1)) => {
+                            let mut end = end + 1;
+                            while is_ident(at(end)) {
+                                end += 1;
+                            }
+                            end
+                        }
+                        None => return Err(start),
+                    },
+                    ("b", Some('\'')) => match at(end + 1) {
+                        Some('\\') => quoted(&chars, end + 2, '\'').ok_or(start)?,
+                        _ => end + 3,
+                    },
+                    _ => end,
+                }
+            }
+            _ => index + 1,
+        };
+        // A literal's suffix, such as `u8` or `f32`, is part of it.
+        let mut end = end;
+        if kind == Kind::Other && end > index + 1 && !is_ident(Some(c)) {
+            while is_ident(at(end)) {
+                end += 1;
+            }
+        }
+        tokens.push(Token { start, end: chars.get(end).map_or(text.len(), |&(offset, _)| offset), kind });
+        index = end;
+    }
+    match open.pop() {
+        Some((_, start)) => Err(start),
+        None => Ok(tokens),
+    }
+}
+
+/// The index after the `quote` that ends a literal whose contents start at `index`,
+/// skipping escapes.
+fn quoted(chars: &[(usize, char)], mut index: usize, quote: char) -> Option<usize> {
+    loop {
+        match chars.get(index)?.1 {
+            '\\' => index += 2,
+            c if c == quote => return Some(index + 1),
+            _ => index += 1,
+        }
+    }
+}
+
+/// The index after a raw string whose `#`s or opening `"` start at `index`, or `None` if
+/// no raw string starts there.
+fn raw(chars: &[(usize, char)], mut index: usize) -> Option<usize> {
+    let mut hashes = 0;
+    while chars.get(index)?.1 == '#' {
+        hashes += 1;
+        index += 1;
+    }
+    if chars.get(index)?.1 != '"' {
+        return None;
+    }
+    index += 1;
+    loop {
+        if chars.get(index)?.1 == '"' && (1..=hashes).all(|offset| chars.get(index + offset).is_some_and(|&(_, c)| c == '#')) {
+            return Some(index + 1 + hashes);
+        }
+        index += 1;
+    }
+}
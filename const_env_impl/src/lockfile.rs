@@ -0,0 +1,121 @@
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::toml::{self, TomlValue};
+use crate::ReadEnv;
+
+/// Variable naming the checked-in file that records the value of every item.
+pub(crate) const LOCK_VAR: &str = "CONST_ENV_LOCK";
+
+/// Setting this variable to `1` records the values in the lockfile instead of checking
+/// them.
+pub(crate) const LOCK_WRITE_VAR: &str = "CONST_ENV_LOCK_WRITE";
+
+/// The file named by `CONST_ENV_LOCK`, resolved against the crate directory, if set.
+pub(crate) fn path(read_env: &impl ReadEnv) -> Result<Option<PathBuf>, String> {
+    match read_env.read_env(LOCK_VAR).map_err(|err| err.to_string())? {
+        Some(path) => crate::file::resolve_path(&*path, read_env).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// How an item's value is recorded: as written for most items, and as the digest of
+/// the value for `sensitive` ones, so that the lockfile can be checked in.
+pub(crate) fn recorded_value(sensitive: bool, value: &str) -> String {
+    match sensitive {
+        true => format!("sha256:{}", crate::sha256::hex_digest(value.as_bytes())),
+        false => value.to_string(),
+    }
+}
+
+/// A failed check of an item against the lockfile.
+pub(crate) enum Mismatch {
+    /// The lockfile has no entry for the item.
+    Missing,
+    /// The lockfile records another value.
+    Changed { expected: String },
+}
+
+/// Checks the recorded value of `item` of `crate_name` against `value`, which was
+/// produced by `recorded_value`.
+pub(crate) fn check(path: &Path, crate_name: &str, item: &str, value: &str) -> Result<Result<(), Mismatch>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read `{}` named by `{}`: {}", path.display(), LOCK_VAR, err))?;
+    let entries = parse(path, &contents)?;
+    match entries.iter().find(|(entry_crate, entry_item, _)| entry_crate == crate_name && entry_item == item) {
+        Some((_, _, expected)) if expected == value => Ok(Ok(())),
+        Some((_, _, expected)) => Ok(Err(Mismatch::Changed { expected: expected.clone() })),
+        None => Ok(Err(Mismatch::Missing)),
+    }
+}
+
+/// Records `value` for `item` of `crate_name` in the lockfile at `path`, keeping the
+/// entries of other items.
+///
+/// Crates and codegen units are expanded in parallel, so the file is locked while it
+/// is rewritten, and entries are sorted so that the file does not depend on the order
+/// in which items were expanded.
+pub(crate) fn record(path: &Path, crate_name: &str, item: &str, value: &str) -> Result<(), String> {
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+        .map_err(|err| format!("Failed to open `{}` named by `{}`: {}", path.display(), LOCK_VAR, err))?;
+    file.lock().map_err(|err| format!("Failed to lock `{}`: {}", path.display(), err))?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|err| format!("Failed to read `{}`: {}", path.display(), err))?;
+    let mut entries = parse(path, &contents)?;
+    entries.retain(|(entry_crate, entry_item, _)| !(entry_crate == crate_name && entry_item == item));
+    entries.push((crate_name.to_string(), item.to_string(), value.to_string()));
+    entries.sort();
+
+    let mut contents = format!("# Values built by const_env, checked when `{}` names this file.\n# Regenerate with `{}=1`.\n", LOCK_VAR, LOCK_WRITE_VAR);
+    let mut table = None;
+    for (entry_crate, entry_item, value) in &entries {
+        if table != Some(entry_crate) {
+            contents.push_str(&format!("\n[{}]\n", toml_key(entry_crate)));
+            table = Some(entry_crate);
+        }
+        contents.push_str(&format!("{} = {}\n", toml_key(entry_item), toml_string(value)));
+    }
+    file.set_len(0)
+        .and_then(|_| file.rewind())
+        .and_then(|_| file.write_all(contents.as_bytes()))
+        .map_err(|err| format!("Failed to write `{}`: {}", path.display(), err))
+}
+
+/// The crate, item and recorded value of every entry of a lockfile.
+fn parse(path: &Path, contents: &str) -> Result<Vec<(String, String, String)>, String> {
+    let invalid = |message: String| format!("Invalid lockfile `{}` named by `{}`: {}", path.display(), LOCK_VAR, message);
+    toml::parse_document(contents).map_err(|err| invalid(err.to_string()))?
+        .into_iter()
+        .map(|entry| match (&entry.path[..], entry.value) {
+            ([crate_name, item], TomlValue::String(value)) => Ok((crate_name.clone(), item.clone(), value)),
+            _ => Err(invalid(format!("line {}: expected a string under a `[crate]` table", entry.line))),
+        })
+        .collect()
+}
+
+fn toml_key(key: &str) -> String {
+    match !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        true => key.to_string(),
+        false => toml_string(key),
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    let mut toml = String::with_capacity(value.len() + 2);
+    toml.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => toml.push_str("\\\""),
+            '\\' => toml.push_str("\\\\"),
+            '\n' => toml.push_str("\\n"),
+            '\r' => toml.push_str("\\r"),
+            '\t' => toml.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => toml.push_str(&format!("\\u{:04x}", c as u32)),
+            c => toml.push(c),
+        }
+    }
+    toml.push('"');
+    toml
+}
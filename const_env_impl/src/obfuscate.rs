@@ -0,0 +1,94 @@
+use std::hash::{BuildHasher, Hasher};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote_spanned;
+use syn::{Expr, ExprLit, Lit};
+
+use crate::explain;
+
+/// Replaces the string or byte string value of `item` with its bytes XOR'd with a key
+/// drawn for this expansion, and returns the key and the helper that restores the value
+/// at run time, to be emitted after the item.
+pub(crate) fn obfuscate(item: &mut syn::Item, flag_span: Span) -> syn::Result<TokenStream> {
+    let (vis, keyword, ident, ty, expr) = match item {
+        syn::Item::Const(item) => (&item.vis, quote_spanned!(item.const_token.span => const), &item.ident, &mut item.ty, &mut item.expr),
+        syn::Item::Static(item) => (&item.vis, quote_spanned!(item.static_token.span => static), &item.ident, &mut item.ty, &mut item.expr),
+        _ => unreachable!("only `const` and `static` items are expanded"),
+    };
+    let unsupported = || explain::error(explain::UNSUPPORTED_DEFAULT, flag_span,
+        "`obfuscate` can only be used on `&str` and `&[u8]` items with a string or byte string value");
+    let (bytes, string) = match (&**expr, str_or_bytes(ty)) {
+        (Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }), Some(true)) => (lit.value().into_bytes(), true),
+        (Expr::Lit(ExprLit { lit: Lit::ByteStr(lit), .. }), Some(false)) => (lit.value(), false),
+        _ => return Err(unsupported()),
+    };
+    let key = random_key(bytes.len());
+    let obfuscated: Vec<u8> = bytes.iter().zip(&key).map(|(byte, key)| byte ^ key).collect();
+
+    let span = ident.span();
+    let len = bytes.len();
+    **ty = syn::parse_quote_spanned!(span => [u8; #len]);
+    **expr = syn::parse_quote_spanned!(span => [#(#obfuscated),*]);
+    let unraw = syn::ext::IdentExt::unraw(ident).to_string();
+    let key_ident = syn::Ident::new(&format!("{}_KEY", unraw), span);
+    let helper = syn::Ident::new(&format!("{}_deobfuscated", unraw.to_lowercase()), span);
+    // The bytes pass through `black_box`, so that the optimizer cannot fold the XOR into
+    // a copy of the plaintext in the binary.
+    let (output, result) = match string {
+        true => (quote_spanned!(span => String), quote_spanned!(span =>
+            String::from_utf8(bytes.to_vec()).expect("the value was valid UTF-8 before it was obfuscated"))),
+        false => (quote_spanned!(span => [u8; #len]), quote_spanned!(span => bytes)),
+    };
+    Ok(quote_spanned! {span =>
+        #vis #keyword #key_ident: [u8; #len] = [#(#key),*];
+        #vis fn #helper() -> #output {
+            let mut bytes = ::std::hint::black_box(#ident);
+            let key = ::std::hint::black_box(#key_ident);
+            let mut index = 0;
+            while index < #len {
+                bytes[index] ^= key[index];
+                index += 1;
+            }
+            #result
+        }
+    })
+}
+
+/// Whether `ty` is `&str`, as `Some(true)`, or `&[u8]`, as `Some(false)`, with any
+/// lifetime.
+fn str_or_bytes(ty: &syn::Type) -> Option<bool> {
+    match ty {
+        syn::Type::Reference(syn::TypeReference { mutability: None, elem, .. }) => match &**elem {
+            syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("str") => Some(true),
+            syn::Type::Slice(slice) => match &*slice.elem {
+                syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("u8") => Some(false),
+                _ => None,
+            },
+            _ => None,
+        },
+        syn::Type::Paren(paren) => str_or_bytes(&paren.elem),
+        syn::Type::Group(group) => str_or_bytes(&group.elem),
+        _ => None,
+    }
+}
+
+/// A key of `len` non-zero bytes, so that no byte of the value is stored as it is. The
+/// generator is seeded from `RandomState`, which the standard library seeds from the
+/// operating system, since the key only has to differ between builds, not resist
+/// analysis.
+fn random_key(len: usize) -> Vec<u8> {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_usize(len);
+    let mut state = hasher.finish() | 1;
+    let mut key = Vec::with_capacity(len);
+    while key.len() < len {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let byte = (state >> 56) as u8;
+        if byte != 0 {
+            key.push(byte);
+        }
+    }
+    key
+}
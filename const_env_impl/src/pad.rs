@@ -0,0 +1,55 @@
+use syn::spanned::Spanned;
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::{explain, FromEnvError};
+
+/// The length of a fixed-size byte array default: `*b"..."`, an array of elements or a
+/// repeat expression such as `[0; 16]`.
+pub(crate) fn array_len(expr: &Expr) -> Option<usize> {
+    match expr {
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Deref(_), expr, .. }) => match &**expr {
+            Expr::Lit(ExprLit { lit: Lit::ByteStr(lit), .. }) => Some(lit.value().len()),
+            _ => None,
+        },
+        Expr::Array(array) => Some(array.elems.len()),
+        Expr::Repeat(repeat) => match &*repeat.len {
+            Expr::Lit(ExprLit { lit: Lit::Int(len), .. }) => len.base10_parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Copies the bytes of `value` into a default of the same form and length, right-padded
+/// with `pad`. A value longer than the default is an error.
+pub(crate) fn padded_value(args: &ItemArgs, pad: u8, var_name: &str, value: &str, expr: &Expr, span: proc_macro2::Span) -> Result<Expr, FromEnvError> {
+    let len = array_len(expr).ok_or_else(|| explain::error(explain::UNSUPPORTED_DEFAULT, span,
+        "`pad` can only be used on items with a `*b\"...\"` or fixed-size byte array default"))?;
+    let mut bytes = match args.case {
+        Some((case, _)) => case.apply_bytes(value.as_bytes()),
+        None => value.as_bytes().to_vec(),
+    };
+    if bytes.len() > len {
+        return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+            "Environment variable `{}` is {} bytes long, longer than the {} bytes of the default\n  value: {}",
+            var_name, bytes.len(), len, crate::display_value(args, value))).into());
+    }
+    bytes.resize(len, pad);
+    match expr {
+        Expr::Unary(_) => {
+            let lit = syn::LitByteStr::new(&bytes, expr.span());
+            Ok(syn::parse_quote!(*#lit))
+        }
+        _ => {
+            // `LitByte::new` renders an integer in syn 1, so the literal is parsed instead.
+            let elements = bytes.iter().map(|byte| {
+                let mut lit: syn::LitByte = syn::parse_str(&format!("b'{}'", std::ascii::escape_default(*byte)))
+                    .expect("escaped bytes are valid byte literals");
+                lit.set_span(expr.span());
+                lit
+            });
+            Ok(syn::parse_quote!([#(#elements),*]))
+        }
+    }
+}
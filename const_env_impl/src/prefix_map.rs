@@ -0,0 +1,155 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, ExprLit, Lit, Token};
+
+use crate::args::ItemArgs;
+use crate::declared::DeclaredType;
+use crate::{explain, FromEnvError, ReadEnv};
+
+/// Whether `expr` is a `&[("key", "value"), ...]` slice of string pairs, possibly empty.
+fn is_pair_slice(expr: &Expr) -> bool {
+    let array = match expr {
+        Expr::Reference(reference) => match &*reference.expr {
+            Expr::Array(array) => array,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    array.elems.iter().all(|element| match element {
+        Expr::Tuple(tuple) => tuple.elems.len() == 2 && tuple.elems.iter().all(|elem| matches!(elem, Expr::Lit(ExprLit { lit: Lit::Str(_), .. }))),
+        _ => false,
+    })
+}
+
+/// The variables whose names start with `prefix` and are longer than it, sorted so that
+/// the expansion does not depend on the order of the environment.
+pub(crate) fn matching_names(prefix: &str, read_env: &impl ReadEnv) -> Vec<String> {
+    let mut names: Vec<String> = read_env.var_names().into_iter()
+        .filter(|name| name.len() > prefix.len() && name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Collects the variables starting with `prefix` into a slice of `(suffix, value)`
+/// pairs, sorted by suffix and checked against `min_items` and `max_items`.
+pub(crate) fn prefix_map_value(args: &ItemArgs, prefix: &str, declared: Option<&DeclaredType>, expr: &Expr, span: Span, read_env: &impl ReadEnv) -> Result<Expr, FromEnvError> {
+    let pairs = DeclaredType::Slice(Box::new(DeclaredType::Tuple(vec![DeclaredType::Str, DeclaredType::Str])), None);
+    if declared.is_some_and(|declared| *declared != pairs) || !is_pair_slice(expr) {
+        return Err(explain::error(explain::UNSUPPORTED_DEFAULT, span, "`prefix_map` can only be used on items with a `&[(&str, &str)]` default").into());
+    }
+    let mut pairs = Vec::new();
+    for name in matching_names(prefix, read_env) {
+        let value = match read_env.read_env_utf8(&name).map_err(|err| crate::env_error(err, span))? {
+            Some(value) => value,
+            // Sources may list names that they no longer hold.
+            None => continue,
+        };
+        let key = syn::LitStr::new(&name[prefix.len()..], span);
+        let value = syn::LitStr::new(&value, span);
+        pairs.push(quote::quote_spanned!(span => (#key, #value)));
+    }
+    if let Some((min_items, _)) = args.min_items {
+        if pairs.len() < min_items {
+            return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Found {} environment variables starting with `{}`, fewer than the minimum of {}", pairs.len(), prefix, min_items)).into());
+        }
+    }
+    if let Some((max_items, _)) = args.max_items {
+        if pairs.len() > max_items {
+            return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Found {} environment variables starting with `{}`, more than the maximum of {}", pairs.len(), prefix, max_items)).into());
+        }
+    }
+    Ok(syn::parse_quote_spanned!(span => &[#(#pairs),*]))
+}
+
+/// The arguments of `consts_from_prefix!`, such as
+/// `prefix = "LIMIT_", ty = u32, vis = pub, required`.
+pub(crate) struct PrefixConsts {
+    prefix: syn::LitStr,
+    ty: syn::Type,
+    vis: syn::Visibility,
+    required: bool,
+}
+
+impl Parse for PrefixConsts {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mut prefix, mut ty, mut vis, mut required) = (None, None, None, false);
+        let mut seen: Vec<String> = Vec::new();
+        while !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            let name = key.to_string();
+            if seen.contains(&name) {
+                return Err(syn::Error::new(key.span(), format!("Duplicate argument `{}`", name)));
+            }
+            seen.push(name.clone());
+            match name.as_str() {
+                "required" => required = true,
+                "prefix" | "ty" | "vis" => {
+                    input.parse::<Token![=]>()?;
+                    match name.as_str() {
+                        "prefix" => {
+                            let lit: syn::LitStr = input.parse()?;
+                            if lit.value().is_empty() {
+                                return Err(syn::Error::new(lit.span(), "`prefix` cannot be empty"));
+                            }
+                            prefix = Some(lit);
+                        }
+                        "ty" => ty = Some(input.parse()?),
+                        _ => vis = Some(input.parse()?),
+                    }
+                }
+                _ => return Err(syn::Error::new(key.span(), format!("Unknown argument `{}`; expected `prefix = \"...\"`, `ty = TYPE`, `vis = VISIBILITY` or `required`", name))),
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        let prefix = prefix.ok_or_else(|| input.error("Missing `prefix = \"...\"`"))?;
+        let ty = ty.ok_or_else(|| input.error("Missing `ty = TYPE`"))?;
+        Ok(PrefixConsts { prefix, ty, vis: vis.unwrap_or(syn::Visibility::Inherited), required })
+    }
+}
+
+/// Generates a `const` named after the suffix of each variable starting with the prefix,
+/// in the order of their names, each expanded as `env_item` would with the variable's
+/// name. Errors follow the constants that expanded, as in `from_env!`.
+pub(crate) fn prefix_consts(input: PrefixConsts, read_env: &impl ReadEnv) -> Result<TokenStream, FromEnvError> {
+    let PrefixConsts { prefix, ty, vis, required } = input;
+    let span = prefix.span();
+    let template = match crate::declared::declared_type(&ItemArgs::default(), &ty) {
+        Some(declared) => declared.template(span),
+        None => return Err(explain::error(explain::UNSUPPORTED_DEFAULT, syn::spanned::Spanned::span(&ty), format!(
+            "`consts_from_prefix!` cannot parse values as `{}`; use a type that `env_item` recognizes, such as an integer, `bool`, `char` or `&str`",
+            crate::pretty::to_pretty_string(quote::ToTokens::to_token_stream(&ty)))).into()),
+    };
+    let names = matching_names(&prefix.value(), read_env);
+    if required && names.is_empty() {
+        return Err(explain::error(explain::MISSING_REQUIRED, span, format!("No environment variables start with `{}`, but at least one is required", prefix.value())).into());
+    }
+    let mut output = TokenStream::new();
+    let mut errors = TokenStream::new();
+    for name in names {
+        let suffix = &name[prefix.value().len()..];
+        let ident = match syn::parse_str::<syn::Ident>(suffix) {
+            Ok(_) => syn::Ident::new(suffix, span),
+            Err(_) => {
+                errors.extend(explain::error(explain::INVALID_ARGUMENT, span, format!(
+                    "Environment variable `{}` would name a constant `{}`, which is not a valid identifier", name, suffix)).to_compile_error());
+                continue;
+            }
+        };
+        let var_name = syn::LitStr::new(&name, span);
+        let item = quote!(#vis const #ident: #ty = #template;);
+        match crate::try_from_env(quote!(#var_name), item, crate::EnvRef(read_env)) {
+            Ok(tokens) => output.extend(tokens),
+            Err(err) => errors.extend(err.to_compile_error()),
+        }
+    }
+    output.extend(errors);
+    Ok(output)
+}
@@ -0,0 +1,142 @@
+use proc_macro2::{Span, TokenStream};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{Expr, LitStr, Token};
+
+use crate::args::ItemArgs;
+use crate::{FromEnvError, ReadEnv};
+
+/// A predicate over which variables are set, from `any_set(...)`, `all_set(...)` and
+/// `not(...)`, which nest.
+pub(crate) enum Presence {
+    /// A variable name, which holds when the variable is set, even to an empty value.
+    Set(LitStr),
+    Any(Vec<Presence>),
+    All(Vec<Presence>),
+    Not(Box<Presence>),
+}
+
+impl Presence {
+    /// Parses the arguments of the combinator `keyword`, i.e. the tokens inside its
+    /// parentheses.
+    pub(crate) fn parse_list(keyword: &syn::Ident, tokens: TokenStream) -> syn::Result<Presence> {
+        let operands: Vec<Presence> = Punctuated::<Presence, Token![,]>::parse_terminated.parse2(tokens)?.into_iter().collect();
+        match keyword.to_string().as_str() {
+            "not" => {
+                let mut operands = operands.into_iter();
+                match (operands.next(), operands.next()) {
+                    (Some(operand), None) => Ok(Presence::Not(Box::new(operand))),
+                    _ => Err(syn::Error::new(keyword.span(), "`not` takes exactly one variable name or combinator")),
+                }
+            }
+            _ if operands.is_empty() => Err(syn::Error::new(keyword.span(), format!("`{}` needs at least one variable name", keyword))),
+            "any_set" => Ok(Presence::Any(operands)),
+            "all_set" => Ok(Presence::All(operands)),
+            _ => unreachable!("only the presence combinators are parsed"),
+        }
+    }
+
+    /// The combinator written at the top of the predicate.
+    pub(crate) fn keyword(&self) -> &'static str {
+        match self {
+            Presence::Any(_) => "any_set",
+            Presence::All(_) => "all_set",
+            Presence::Not(_) => "not",
+            Presence::Set(_) => unreachable!("a variable name is only an operand of a combinator"),
+        }
+    }
+
+    /// The variables named in the predicate, in the order written, without repeats.
+    pub(crate) fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names(&self, names: &mut Vec<String>) {
+        match self {
+            Presence::Set(name) => {
+                if !names.contains(&name.value()) {
+                    names.push(name.value());
+                }
+            }
+            Presence::Any(operands) | Presence::All(operands) => operands.iter().for_each(|operand| operand.collect_names(names)),
+            Presence::Not(operand) => operand.collect_names(names),
+        }
+    }
+
+    /// Whether the predicate holds, with the variables that decided it, such as
+    /// `["GITHUB_ACTIONS:set"]`. `any_set` stops at the first operand that holds and
+    /// `all_set` at the first that does not, so only the variables read up to there
+    /// are listed.
+    pub(crate) fn evaluate(&self, span: Span, read_env: &impl ReadEnv) -> Result<(bool, Vec<String>), FromEnvError> {
+        match self {
+            Presence::Set(name) => {
+                let set = read_env.read_env(&name.value()).map_err(|err| crate::env_error(err, span))?.is_some();
+                Ok((set, vec![format!("{}:{}", name.value(), if set { "set" } else { "unset" })]))
+            }
+            Presence::Any(operands) => Self::evaluate_all(operands, true, span, read_env),
+            Presence::All(operands) => Self::evaluate_all(operands, false, span, read_env),
+            Presence::Not(operand) => operand.evaluate(span, read_env).map(|(holds, decided_by)| (!holds, decided_by)),
+        }
+    }
+
+    /// Evaluates `operands` until one of them is `stop_at`, which is then the result.
+    fn evaluate_all(operands: &[Presence], stop_at: bool, span: Span, read_env: &impl ReadEnv) -> Result<(bool, Vec<String>), FromEnvError> {
+        let mut decided_by = Vec::new();
+        for operand in operands {
+            let (holds, operand_decided_by) = operand.evaluate(span, read_env)?;
+            if holds == stop_at {
+                return Ok((stop_at, operand_decided_by));
+            }
+            decided_by.extend(operand_decided_by);
+        }
+        Ok((!stop_at, decided_by))
+    }
+}
+
+impl Parse for Presence {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Presence::Set(input.parse()?));
+        }
+        let keyword: syn::Ident = input.parse()
+            .map_err(|err| syn::Error::new(err.span(), "Expected a variable name or `any_set(...)`, `all_set(...)` or `not(...)`"))?;
+        if !matches!(keyword.to_string().as_str(), "any_set" | "all_set" | "not") || !input.peek(syn::token::Paren) {
+            return Err(syn::Error::new(keyword.span(), "Expected a variable name or `any_set(...)`, `all_set(...)` or `not(...)`"));
+        }
+        let content;
+        syn::parenthesized!(content in input);
+        Presence::parse_list(&keyword, content.parse()?)
+    }
+}
+
+impl std::fmt::Display for Presence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (keyword, operands) = match self {
+            Presence::Set(name) => return write!(f, "{:?}", name.value()),
+            Presence::Any(operands) => ("any_set", &operands[..]),
+            Presence::All(operands) => ("all_set", &operands[..]),
+            Presence::Not(operand) => return write!(f, "not({})", operand),
+        };
+        write!(f, "{}(", keyword)?;
+        for (index, operand) in operands.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", operand)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// The value of a boolean item decided by `presence`: `true`, or `false` with `invert`,
+/// when the predicate holds, and otherwise the default.
+pub(crate) fn presence_value(args: &ItemArgs, presence: &Presence, expr: &Expr, span: Span, read_env: &impl ReadEnv) -> Result<Option<Expr>, FromEnvError> {
+    let (holds, _) = presence.evaluate(span, read_env)?;
+    if !holds {
+        return Ok(None);
+    }
+    let lit = syn::LitBool::new(args.invert.is_none(), syn::spanned::Spanned::span(expr));
+    Ok(Some(syn::parse_quote!(#lit)))
+}
@@ -0,0 +1,104 @@
+//! Prints token streams the way rustfmt would lay out a single line, for readable
+//! snapshot assertions. Only the spacing rules needed for items are handled: the tokens
+//! themselves are never changed.
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// A token, with groups flattened into their delimiters.
+#[derive(Clone, PartialEq)]
+enum Token {
+    Open(Delimiter),
+    Close(Delimiter),
+    Punct(char, Spacing),
+    Word(String),
+}
+
+/// Formats `tokens` on one line, such as `const PORT: u16 = 8080;`.
+pub(crate) fn to_pretty_string(tokens: TokenStream) -> String {
+    let mut flat = Vec::new();
+    flatten(tokens, &mut flat);
+    let mut output = String::new();
+    let mut generics = 0usize;
+    for (index, token) in flat.iter().enumerate() {
+        let prev = index.checked_sub(1).map(|i| &flat[i]);
+        let before_prev = index.checked_sub(2).map(|i| &flat[i]);
+        if let Some(prev) = prev {
+            if space_between(before_prev, prev, token, generics) {
+                output.push(' ');
+            }
+        }
+        match token {
+            Token::Open(delimiter) => output.push_str(match delimiter {
+                Delimiter::Parenthesis => "(",
+                Delimiter::Bracket => "[",
+                Delimiter::Brace => "{",
+                Delimiter::None => "",
+            }),
+            Token::Close(delimiter) => output.push_str(match delimiter {
+                Delimiter::Parenthesis => ")",
+                Delimiter::Bracket => "]",
+                Delimiter::Brace => "}",
+                Delimiter::None => "",
+            }),
+            Token::Punct(c, _) => {
+                if *c == '<' && matches!(prev, Some(Token::Word(_))) {
+                    generics += 1;
+                } else if *c == '>' && generics > 0 {
+                    generics -= 1;
+                }
+                output.push(*c);
+            }
+            Token::Word(word) => output.push_str(word),
+        }
+    }
+    output
+}
+
+fn flatten(tokens: TokenStream, flat: &mut Vec<Token>) {
+    for tree in tokens {
+        match tree {
+            TokenTree::Group(group) => {
+                flat.push(Token::Open(group.delimiter()));
+                flatten(group.stream(), flat);
+                flat.push(Token::Close(group.delimiter()));
+            }
+            TokenTree::Ident(ident) => flat.push(Token::Word(ident.to_string())),
+            TokenTree::Literal(literal) => flat.push(Token::Word(literal.to_string())),
+            TokenTree::Punct(punct) => flat.push(Token::Punct(punct.as_char(), punct.spacing())),
+        }
+    }
+}
+
+/// Keywords after which a `::` starts a new path rather than continuing one.
+const KEYWORDS: &[&str] = &["as", "else", "if", "in", "match", "mut", "return"];
+
+/// Whether a space separates `prev` from `next`, given the token before `prev` and the
+/// number of open generic argument lists.
+fn space_between(before_prev: Option<&Token>, prev: &Token, next: &Token, generics: usize) -> bool {
+    // A sign or reference operator is a prefix when it does not follow an operand.
+    let prefix = matches!(before_prev, None | Some(Token::Open(_)) | Some(Token::Punct(..)));
+    match (prev, next) {
+        (Token::Open(Delimiter::Brace), Token::Close(Delimiter::Brace)) => false,
+        (Token::Open(Delimiter::Brace), _) | (_, Token::Close(Delimiter::Brace)) => true,
+        (Token::Open(_), _) | (_, Token::Close(_)) => false,
+        (_, Token::Punct(',' | ';', _)) => false,
+        // The colons of a `::` are joined to each other and to the path segment after
+        // them, and a single `:` to the name before it.
+        (Token::Punct(':', Spacing::Joint), Token::Punct(':', _)) => false,
+        (Token::Punct(':', Spacing::Alone), _) if before_prev == Some(&Token::Punct(':', Spacing::Joint)) => false,
+        (Token::Word(word), Token::Punct(':', Spacing::Joint)) => KEYWORDS.contains(&word.as_str()),
+        (Token::Word(_), Token::Punct(':', _)) => false,
+        (Token::Punct(_, Spacing::Joint), Token::Punct(..)) => false,
+        (Token::Punct('#' | '\'', _), _) => false,
+        (Token::Punct('!', _), Token::Open(Delimiter::Brace)) => true,
+        (Token::Word(_), Token::Punct('!', _)) => false,
+        (Token::Punct('!', _), _) if matches!(before_prev, Some(Token::Word(_))) => false,
+        (Token::Punct('.', _), _) | (_, Token::Punct('.', _)) => false,
+        (Token::Punct('-' | '&' | '*' | '!', _), _) if prefix => false,
+        (Token::Word(_), Token::Punct('<', _)) => false,
+        (Token::Punct('<', _), _) if generics > 0 => false,
+        (_, Token::Punct('>', _)) if generics > 0 => false,
+        (Token::Word(_), Token::Open(Delimiter::Parenthesis | Delimiter::Bracket)) => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,177 @@
+use syn::spanned::Spanned;
+use syn::{Expr, ExprLit, Lit};
+
+use crate::args::ItemArgs;
+use crate::declared::{self, DeclaredType};
+use crate::list::list_elements;
+use crate::{explain, FromEnvError};
+
+/// The most elements a value may expand to, so that a range such as `0-4294967295`
+/// fails the build instead of exhausting the compiler's memory.
+const MAX_ELEMENTS: u128 = 1 << 16;
+
+/// The integer list that an item with `ranges` holds.
+pub(crate) struct Target {
+    /// The integer type of the elements, if the declared type or a suffix names it.
+    ty: Option<String>,
+    /// The suffix written on the elements of the default, which the expansion keeps.
+    suffix: String,
+    /// Whether the item is a slice, whose length follows the value.
+    is_slice: bool,
+    /// The length of a fixed-size array, if it is a literal.
+    len: Option<usize>,
+}
+
+/// An integer literal, possibly negated, that is not a float such as `1f32`.
+fn int_literal(expr: &Expr) -> Option<&syn::LitInt> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) if !matches!(int.suffix(), "f32" | "f64") => Some(int),
+        Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => int_literal(expr),
+        _ => None,
+    }
+}
+
+/// The integer list of an item with declared type `declared` and default `expr`: an
+/// array or slice of a declared integer type, or else a default array or slice of
+/// integer literals. `None` for any other item.
+pub(crate) fn target(declared: Option<&DeclaredType>, expr: &Expr) -> Option<Target> {
+    let elements = list_elements(expr);
+    let suffix = elements.iter().flatten()
+        .filter_map(|element| int_literal(element))
+        .map(|int| int.suffix())
+        .find(|suffix| !suffix.is_empty())
+        .unwrap_or("")
+        .to_string();
+    let (ty, is_slice, len) = match declared {
+        Some(DeclaredType::Array(elem, len)) | Some(DeclaredType::Slice(elem, len)) => match &**elem {
+            DeclaredType::Int(name) => (Some(name.clone()), matches!(declared, Some(DeclaredType::Slice(..))), *len),
+            _ => return None,
+        },
+        Some(_) => return None,
+        None => {
+            let elements = elements?;
+            if !elements.iter().all(|element| int_literal(element).is_some()) {
+                return None;
+            }
+            let is_slice = matches!(expr, Expr::Reference(_));
+            let len = if is_slice { None } else { Some(elements.len()) };
+            (declared::int_range(&suffix).map(|_| suffix.clone()), is_slice, len)
+        }
+    };
+    Some(Target { ty, suffix, is_slice, len })
+}
+
+/// Parses a bound of a segment: a decimal integer with an optional `-` sign.
+fn bound(text: &str) -> Option<i128> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Parses a segment such as `8` or `10-11` into its first and last value.
+fn segment(text: &str) -> Option<(i128, i128)> {
+    // The separator is the first `-` after the first character, which may be a sign, so
+    // that `-3--1` is the range from -3 to -1.
+    match text.char_indices().skip(1).find(|(_, c)| *c == '-') {
+        Some((index, _)) => Some((bound(text[..index].trim())?, bound(text[index + 1..].trim())?)),
+        None => bound(text).map(|value| (value, value)),
+    }
+}
+
+/// Whether `value` is in the range of the integer type `ty`.
+fn fits(value: i128, ty: &str) -> bool {
+    let (min, max) = declared::int_range(ty).expect("integer types have a range");
+    match value < 0 {
+        true => value.unsigned_abs() <= min,
+        false => value as u128 <= max,
+    }
+}
+
+/// Expands `value`, a comma-separated list of integers and inclusive ranges such as
+/// `0-3,8,10-11`, into an array or slice of integer literals. Every segment that is
+/// malformed or does not fit the element type is reported, and so are reversed ranges
+/// and segments that overlap an earlier one, unless `dedup` is given to allow them. The
+/// expansion of a fixed-size array must have its length.
+pub(crate) fn ranges_value(args: &ItemArgs, var_name: &str, value: &str, declared: Option<&DeclaredType>, expr: &Expr, span: proc_macro2::Span) -> Result<Expr, FromEnvError> {
+    let target = target(declared, expr).expect("`ranges` is checked against the item before the value is read");
+    let subject = |text: &str| match args.sensitive {
+        true => format!("A segment of environment variable `{}`", var_name),
+        false => format!("Segment `{}` of environment variable `{}`", text, var_name),
+    };
+    let segments: Vec<&str> = match value.trim() {
+        "" => Vec::new(),
+        value => value.split(',').map(str::trim).collect(),
+    };
+    let mut errors = Vec::new();
+    let mut ranges: Vec<(&str, i128, i128)> = Vec::new();
+    for text in segments {
+        let (start, end) = match segment(text) {
+            Some(range) => range,
+            None => {
+                errors.push(explain::error(explain::PARSE_FAILURE, span, format!(
+                    "{} is not an integer or an inclusive range such as `0-3`", subject(text))));
+                continue;
+            }
+        };
+        if let Some(ty) = &target.ty {
+            if let Some(outside) = [start, end].into_iter().find(|bound| !fits(*bound, ty)) {
+                errors.push(explain::error(explain::PARSE_FAILURE, span, match args.sensitive {
+                    true => format!("{} does not fit in `{}`", subject(text), ty),
+                    false => format!("{} includes `{}`, which does not fit in `{}`", subject(text), outside, ty),
+                }));
+                continue;
+            }
+        }
+        if args.dedup.is_none() {
+            if start > end {
+                errors.push(explain::error(explain::VALIDATION_FAILURE, span, match args.sensitive {
+                    true => format!("{} is a reversed range; add `dedup` to allow it", subject(text)),
+                    false => format!("{} is a reversed range; write it as `{}-{}`, or add `dedup` to allow it", subject(text), end, start),
+                }));
+                continue;
+            }
+            if let Some((earlier, _, _)) = ranges.iter().find(|(_, earlier_start, earlier_end)| start <= *earlier_end && *earlier_start <= end) {
+                errors.push(explain::error(explain::VALIDATION_FAILURE, span, match args.sensitive {
+                    true => format!("{} overlaps an earlier segment; add `dedup` to allow this", subject(text)),
+                    false => format!("{} overlaps `{}`; add `dedup` to allow this", subject(text), earlier),
+                }));
+                continue;
+            }
+        }
+        ranges.push((text, start, end));
+    }
+    if let Some(err) = explain::combine(errors) {
+        return Err(err.into());
+    }
+    let count = ranges.iter()
+        .map(|(_, start, end)| start.abs_diff(*end).saturating_add(1))
+        .fold(0, u128::saturating_add);
+    if count > MAX_ELEMENTS {
+        return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+            "Environment variable `{}` expands to {} elements, more than the limit of {}", var_name, count, MAX_ELEMENTS)).into());
+    }
+    if let (false, Some(len)) = (target.is_slice, target.len) {
+        if count != len as u128 {
+            return Err(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Environment variable `{}` expands to {} elements, but the array has {}; use a slice to allow any number\n  value: {}",
+                var_name, count, len, crate::display_value(args, value))).into());
+        }
+    }
+    let elements = ranges.iter().flat_map(|&(_, start, end)| (0..=start.abs_diff(end)).map(move |offset| match start <= end {
+        true => start + offset as i128,
+        false => start - offset as i128,
+    }));
+    let elements = elements.map(|element| -> Expr {
+        let lit = syn::LitInt::new(&format!("{}{}", element.unsigned_abs(), target.suffix), expr.span());
+        match element < 0 {
+            true => syn::parse_quote!(-#lit),
+            false => syn::parse_quote!(#lit),
+        }
+    });
+    match target.is_slice {
+        true => Ok(syn::parse_quote!(&[#(#elements),*])),
+        false => Ok(syn::parse_quote!([#(#elements),*])),
+    }
+}
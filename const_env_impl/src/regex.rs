@@ -0,0 +1,597 @@
+//! A small regular expression engine for `capture`, compiled to a program for a
+//! backtracking matcher that remembers the states it tried, so that matching takes time
+//! linear in the size of the program times the length of the value.
+//!
+//! The syntax is the common subset of the `regex` crate: literals, `.`, classes such as
+//! `[a-z]` and `[^,]`, `\d`, `\w` and `\s` and their negations, `^`, `$`, `\A`, `\z`,
+//! `\b` and `\B`, groups `(...)`, `(?:...)`, `(?P<name>...)` and `(?<name>...)`,
+//! alternation and the greedy and lazy repetitions `*`, `+`, `?` and `{n,m}`. `\d` and
+//! `\w` are ASCII. Flags and Unicode classes are not supported.
+//!
+//! Matches are leftmost-first as in the `regex` crate, except that a repeated group that
+//! can match the empty string may stop repeating at another point.
+
+use syn::spanned::Spanned;
+
+use crate::args::ItemArgs;
+use crate::{explain, FromEnvError};
+
+/// Programs larger than this are rejected, such as those of nested counted repetitions.
+const MAX_PROGRAM: usize = 10_000;
+
+/// The largest count of a counted repetition.
+const MAX_REPEAT: u32 = 1_000;
+
+/// The group of a `capture` pattern whose text is taken, from `capture_group`.
+pub(crate) enum CaptureGroup {
+    Index(usize),
+    Name(String),
+}
+
+/// A compiled pattern.
+pub(crate) struct Regex {
+    pattern: String,
+    program: Vec<Inst>,
+    /// The number of groups, including the whole match as group 0.
+    groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+/// Takes the text of `group` in the first match of `regex` in `value`, or of group 1,
+/// or of the whole match if the pattern has no groups.
+pub(crate) fn captured_value(args: &ItemArgs, regex: &Regex, group: Option<&CaptureGroup>, var_name: &str, value: &str, expr: &syn::Expr) -> Result<String, FromEnvError> {
+    let index = match group {
+        Some(group) => regex.group_index(group).expect("`capture_group` is checked against the pattern"),
+        None => usize::from(regex.groups > 1),
+    };
+    let captures = regex.captures(value).ok_or_else(|| explain::error(explain::PARSE_FAILURE, expr.span(), format!(
+        "Environment variable `{}` does not match the `capture` pattern `{}`\n  value: {}",
+        var_name, regex.pattern, crate::display_value(args, value))))?;
+    match captures[index] {
+        Some((start, end)) => Ok(value[start..end].to_string()),
+        None => Err(explain::error(explain::PARSE_FAILURE, expr.span(), format!(
+            "Environment variable `{}` matches the `capture` pattern `{}`, but group {} is not part of the match\n  value: {}",
+            var_name, regex.pattern, index, crate::display_value(args, value))).into()),
+    }
+}
+
+impl Regex {
+    pub(crate) fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser { chars: pattern.char_indices().collect(), pos: 0, groups: 1, names: Vec::new() };
+        let node = parser.parse_alternation()?;
+        if let Some(&(offset, c)) = parser.chars.get(parser.pos) {
+            // Only an unmatched `)` stops the top-level alternation early.
+            debug_assert_eq!(c, ')');
+            return Err(format!("unmatched `)` at offset {}", offset));
+        }
+        let mut program = vec![Inst::Save(0)];
+        compile(&node, &mut program)?;
+        program.push(Inst::Save(1));
+        program.push(Inst::Match);
+        Ok(Regex { pattern: pattern.to_string(), program, groups: parser.groups, names: parser.names })
+    }
+
+    /// The index of `group`, if the pattern has it.
+    pub(crate) fn group_index(&self, group: &CaptureGroup) -> Option<usize> {
+        match group {
+            CaptureGroup::Index(index) => (*index < self.groups).then_some(*index),
+            CaptureGroup::Name(name) => self.names.iter().find(|(group, _)| group == name).map(|(_, index)| *index),
+        }
+    }
+
+    /// The number of groups of the pattern, not counting the whole match.
+    pub(crate) fn group_count(&self) -> usize {
+        self.groups - 1
+    }
+
+    /// The byte ranges of the groups of the leftmost match, preferring earlier
+    /// alternatives and, for greedy repetitions, more repetitions.
+    fn captures(&self, text: &str) -> Option<Vec<Option<(usize, usize)>>> {
+        let width = text.len() + 1;
+        let mut visited = vec![0u64; (self.program.len() * width).div_ceil(64)];
+        let mut slots = vec![None; self.groups * 2];
+        let starts = text.char_indices().map(|(pos, _)| pos).chain(std::iter::once(text.len()));
+        for start in starts {
+            let mut stack = vec![Job::Explore(0, start)];
+            while let Some(job) = stack.pop() {
+                let (mut pc, mut pos) = match job {
+                    Job::Explore(pc, pos) => (pc, pos),
+                    Job::Restore(slot, value) => {
+                        slots[slot] = value;
+                        continue;
+                    }
+                };
+                loop {
+                    // A state that was tried before failed then, and fails again.
+                    let state = pc * width + pos;
+                    if visited[state / 64] & (1 << (state % 64)) != 0 {
+                        break;
+                    }
+                    visited[state / 64] |= 1 << (state % 64);
+                    let next = text[pos..].chars().next();
+                    match &self.program[pc] {
+                        Inst::Char(expected) => match next {
+                            Some(c) if c == *expected => (pc, pos) = (pc + 1, pos + c.len_utf8()),
+                            _ => break,
+                        },
+                        Inst::Any => match next {
+                            Some(c) if c != '\n' => (pc, pos) = (pc + 1, pos + c.len_utf8()),
+                            _ => break,
+                        },
+                        Inst::Class(class) => match next {
+                            Some(c) if class.matches(c) => (pc, pos) = (pc + 1, pos + c.len_utf8()),
+                            _ => break,
+                        },
+                        Inst::Assert(assertion) => match assertion.holds(text, pos) {
+                            true => pc += 1,
+                            false => break,
+                        },
+                        Inst::Split(first, second) => {
+                            stack.push(Job::Explore(*second, pos));
+                            pc = *first;
+                        }
+                        Inst::Jump(target) => pc = *target,
+                        Inst::Save(slot) => {
+                            stack.push(Job::Restore(*slot, slots[*slot]));
+                            slots[*slot] = Some(pos);
+                            pc += 1;
+                        }
+                        Inst::Match => {
+                            let groups = (0..self.groups)
+                                .map(|group| slots[group * 2].zip(slots[group * 2 + 1]))
+                                .collect();
+                            return Some(groups);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+enum Job {
+    Explore(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    /// Tries the first target, then the second.
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Match,
+}
+
+#[derive(Clone, Copy)]
+enum Assertion {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+impl Assertion {
+    fn holds(self, text: &str, pos: usize) -> bool {
+        let before = text[..pos].chars().next_back().is_some_and(is_word);
+        let after = text[pos..].chars().next().is_some_and(is_word);
+        match self {
+            Assertion::Start => pos == 0,
+            Assertion::End => pos == text.len(),
+            Assertion::WordBoundary => before != after,
+            Assertion::NotWordBoundary => before == after,
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[derive(Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Clone)]
+enum ClassItem {
+    Range(char, char),
+    Perl(Perl, bool),
+}
+
+#[derive(Clone, Copy)]
+enum Perl {
+    Digit,
+    Word,
+    Space,
+}
+
+impl Perl {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Perl::Digit => c.is_ascii_digit(),
+            Perl::Word => is_word(c),
+            Perl::Space => c.is_whitespace(),
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match item {
+            ClassItem::Range(low, high) => (*low..=*high).contains(&c),
+            ClassItem::Perl(perl, negated) => perl.matches(c) != *negated,
+        });
+        found != self.negated
+    }
+}
+
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32>, greedy: bool },
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) -> Result<(), String> {
+    if program.len() > MAX_PROGRAM {
+        return Err("the pattern is too large".to_string());
+    }
+    match node {
+        Node::Empty => {}
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Assert(assertion) => program.push(Inst::Assert(*assertion)),
+        Node::Group(node, index) => match index {
+            Some(index) => {
+                program.push(Inst::Save(index * 2));
+                compile(node, program)?;
+                program.push(Inst::Save(index * 2 + 1));
+            }
+            None => compile(node, program)?,
+        },
+        Node::Concat(nodes) => {
+            for node in nodes {
+                compile(node, program)?;
+            }
+        }
+        Node::Alternation(alternatives) => {
+            let mut jumps = Vec::new();
+            for (index, alternative) in alternatives.iter().enumerate() {
+                if index + 1 == alternatives.len() {
+                    compile(alternative, program)?;
+                    break;
+                }
+                let split = program.len();
+                program.push(Inst::Split(split + 1, 0));
+                compile(alternative, program)?;
+                jumps.push(program.len());
+                program.push(Inst::Jump(0));
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max, greedy } => {
+            match max {
+                // `x{n,}` as `n - 1` copies of `x` and `x+`, which loops back to its own
+                // copy.
+                None if *min > 0 => {
+                    for _ in 1..*min {
+                        compile(node, program)?;
+                    }
+                    let body = program.len();
+                    compile(node, program)?;
+                    let end = program.len() + 1;
+                    program.push(split_inst(*greedy, body, end));
+                }
+                // `x*` as `(x+)?`, so that an `x` that matches the empty string still
+                // matches once before the loop back to a tried state is cut off.
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(0, 0));
+                    compile(node, program)?;
+                    let end = program.len() + 1;
+                    program.push(split_inst(*greedy, split + 1, end));
+                    program[split] = split_inst(*greedy, split + 1, end);
+                }
+                // `x?` nested `max - min` times, so that later copies are only tried after
+                // earlier ones matched.
+                Some(max) => {
+                    for _ in 0..*min {
+                        compile(node, program)?;
+                    }
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(node, program)?;
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = split_inst(*greedy, split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn split_inst(greedy: bool, body: usize, skip: usize) -> Inst {
+    match greedy {
+        true => Inst::Split(body, skip),
+        false => Inst::Split(skip, body),
+    }
+}
+
+struct Parser {
+    chars: Vec<(usize, char)>,
+    pos: usize,
+    /// The number of groups opened so far, including the whole match.
+    groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|&(_, c)| c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars.get(self.pos).map_or_else(|| self.chars.last().map_or(0, |&(offset, c)| offset + c.len_utf8()), |&(offset, _)| offset)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        match self.peek() == Some(expected) {
+            true => {
+                self.pos += 1;
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at offset {}", message, self.offset())
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![self.parse_concat()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_concat()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Node::Alternation(alternatives),
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repetition(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_repetition(&mut self, mut atom: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => {
+                    self.pos += 1;
+                    let min = self.parse_count()?;
+                    let max = match self.eat(',') {
+                        true if self.peek() == Some('}') => None,
+                        true => Some(self.parse_count()?),
+                        false => Some(min),
+                    };
+                    if self.peek() != Some('}') {
+                        return Err(self.error("expected `}` to close the repetition"));
+                    }
+                    if max.is_some_and(|max| max < min) {
+                        return Err(self.error("the repetition's minimum is greater than its maximum"));
+                    }
+                    (min, max)
+                }
+                _ => return Ok(atom),
+            };
+            self.pos += 1;
+            if matches!(atom, Node::Empty | Node::Assert(_)) {
+                return Err(self.error("nothing to repeat"));
+            }
+            let greedy = !self.eat('?');
+            atom = Node::Repeat { node: Box::new(atom), min, max, greedy };
+        }
+    }
+
+    fn parse_count(&mut self) -> Result<u32, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+        match digits.parse::<u32>() {
+            Ok(count) if count <= MAX_REPEAT => Ok(count),
+            Ok(_) => Err(self.error(&format!("repetition counts are at most {}", MAX_REPEAT))),
+            Err(_) => Err(self.error("expected a repetition count")),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Assert(Assertion::Start)),
+            Some('$') => Ok(Node::Assert(Assertion::End)),
+            Some('[') => self.parse_class().map(Node::Class),
+            Some('(') => self.parse_group(),
+            Some('\\') => self.parse_escape(),
+            Some(c @ ('*' | '+' | '?' | '{')) => {
+                self.pos -= 1;
+                Err(self.error(&format!("nothing to repeat with `{}`", c)))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Ok(Node::Empty),
+        }
+    }
+
+    fn parse_group(&mut self) -> Result<Node, String> {
+        let index = match self.eat('?') {
+            true if self.eat(':') => None,
+            true => {
+                let named = (self.eat('P') && self.eat('<')) || self.eat('<');
+                if !named {
+                    return Err(self.error("flags and groups other than `(?:...)`, `(?P<name>...)` and `(?<name>...)` are not supported"));
+                }
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+                if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || !self.eat('>') {
+                    return Err(self.error("expected a group name of letters, digits and `_` followed by `>`"));
+                }
+                if self.names.iter().any(|(existing, _)| *existing == name) {
+                    return Err(self.error(&format!("duplicate group name `{}`", name)));
+                }
+                self.names.push((name, self.groups));
+                self.groups += 1;
+                Some(self.groups - 1)
+            }
+            false => {
+                self.groups += 1;
+                Some(self.groups - 1)
+            }
+        };
+        let node = self.parse_alternation()?;
+        if !self.eat(')') {
+            return Err(self.error("unclosed group"));
+        }
+        Ok(Node::Group(Box::new(node), index))
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        let perl = |perl, negated| Node::Class(Class { negated: false, items: vec![ClassItem::Perl(perl, negated)] });
+        match self.peek() {
+            Some('d') => Ok(self.skip(perl(Perl::Digit, false))),
+            Some('D') => Ok(self.skip(perl(Perl::Digit, true))),
+            Some('w') => Ok(self.skip(perl(Perl::Word, false))),
+            Some('W') => Ok(self.skip(perl(Perl::Word, true))),
+            Some('s') => Ok(self.skip(perl(Perl::Space, false))),
+            Some('S') => Ok(self.skip(perl(Perl::Space, true))),
+            Some('b') => Ok(self.skip(Node::Assert(Assertion::WordBoundary))),
+            Some('B') => Ok(self.skip(Node::Assert(Assertion::NotWordBoundary))),
+            Some('A') => Ok(self.skip(Node::Assert(Assertion::Start))),
+            Some('z') => Ok(self.skip(Node::Assert(Assertion::End))),
+            _ => self.parse_char_escape().map(Node::Char),
+        }
+    }
+
+    fn skip(&mut self, node: Node) -> Node {
+        self.pos += 1;
+        node
+    }
+
+    /// The character of an escape after the backslash that stands for one character.
+    fn parse_char_escape(&mut self) -> Result<char, String> {
+        match self.next() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('x') => {
+                let braced = self.eat('{');
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) && (braced || self.pos < start + 2) {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[start..self.pos].iter().map(|&(_, c)| c).collect();
+                if (braced && !self.eat('}')) || (!braced && digits.len() != 2) {
+                    return Err(self.error("expected `\\xHH` or `\\x{H...}`"));
+                }
+                u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid code point in `\\x` escape"))
+            }
+            Some(c) if !c.is_alphanumeric() => Ok(c),
+            Some(c) => {
+                self.pos -= 1;
+                Err(self.error(&format!("unsupported escape `\\{}`", c)))
+            }
+            None => Err(self.error("incomplete escape")),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        // A `]` first in the class is a literal.
+        if self.eat(']') {
+            items.push(ClassItem::Range(']', ']'));
+        }
+        loop {
+            let low = match self.next() {
+                None => return Err(self.error("unclosed character class")),
+                Some(']') => return Ok(Class { negated, items }),
+                Some('[') if self.peek() == Some(':') => return Err(self.error("POSIX classes such as `[:alpha:]` are not supported")),
+                Some('\\') => match self.peek() {
+                    Some(c @ ('d' | 'D' | 'w' | 'W' | 's' | 'S')) => {
+                        self.pos += 1;
+                        let perl = match c.to_ascii_lowercase() {
+                            'd' => Perl::Digit,
+                            'w' => Perl::Word,
+                            _ => Perl::Space,
+                        };
+                        items.push(ClassItem::Perl(perl, c.is_ascii_uppercase()));
+                        continue;
+                    }
+                    _ => self.parse_char_escape()?,
+                },
+                Some(c) => c,
+            };
+            // A `-` last in the class, or first, is a literal.
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&(_, c)| c != ']') {
+                self.pos += 1;
+                let high = match self.next() {
+                    Some('\\') => self.parse_char_escape()?,
+                    Some(c) => c,
+                    None => return Err(self.error("unclosed character class")),
+                };
+                if high < low {
+                    return Err(self.error(&format!("invalid range `{}-{}`", low, high)));
+                }
+                items.push(ClassItem::Range(low, high));
+            } else {
+                items.push(ClassItem::Range(low, low));
+            }
+        }
+    }
+}
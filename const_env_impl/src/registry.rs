@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::{EnvError, ReadEnv};
+
+/// An item registered with the `register` flag, as shown by `registry!()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub const_name: String,
+    pub env_var: String,
+    pub declared_type: String,
+    pub default_str: String,
+    pub value_str: String,
+    pub overridden: bool,
+}
+
+/// The entries registered for a crate, by item and variable.
+type Registered = BTreeMap<(String, String), Entry>;
+
+/// The entries registered by this compiler process, per crate.
+///
+/// `registry!()` only lists the items of the current build, so they are kept in the
+/// macro's memory, which every invocation of a compiler process shares. A file would be
+/// shared with the other targets of the package, such as its tests, which cargo compiles
+/// at the same time in other processes. A long-lived process such as rust-analyzer
+/// expands several crates, hence the key.
+static REGISTERED: Mutex<BTreeMap<String, Registered>> = Mutex::new(BTreeMap::new());
+
+/// Adds `entry` to the registry of the crate being compiled, replacing an entry for the
+/// same item and variable.
+pub(crate) fn register(entry: Entry, read_env: &impl ReadEnv) -> Result<(), EnvError> {
+    let mut registered = REGISTERED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    registered.entry(crate_key(read_env)?).or_default()
+        .insert((entry.const_name.clone(), entry.env_var.clone()), entry);
+    Ok(())
+}
+
+/// The entries registered so far by the current build of the crate, sorted by name.
+pub(crate) fn entries(read_env: &impl ReadEnv) -> Result<Vec<Entry>, EnvError> {
+    let registered = REGISTERED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(registered.get(&crate_key(read_env)?)
+        .map(|entries| entries.values().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Tells the crates expanded by one process apart, by the package's directory and the
+/// crate's name where cargo sets them.
+pub(crate) fn crate_key(read_env: &impl ReadEnv) -> Result<String, EnvError> {
+    let manifest_dir = read_env.read_env_utf8("CARGO_MANIFEST_DIR")?;
+    let crate_name = read_env.read_env_utf8("CARGO_CRATE_NAME")?;
+    Ok(format!("{}\t{}", manifest_dir.as_deref().unwrap_or(""), crate_name.as_deref().unwrap_or("")))
+}
+
+/// Escapes the characters that separate fields and lines.
+pub(crate) fn escape(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+pub(crate) fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
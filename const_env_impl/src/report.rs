@@ -0,0 +1,65 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::ReadEnv;
+
+/// Variable naming the file to which every item appends a JSON line describing the value
+/// it was given.
+pub(crate) const REPORT_VAR: &str = "CONST_ENV_REPORT";
+
+/// A line of the report.
+pub(crate) struct Record<'a> {
+    pub crate_name: &'a str,
+    pub item: &'a str,
+    pub vars: &'a [String],
+    pub overridden: bool,
+    pub value: &'a str,
+    pub file: &'a str,
+    pub line: usize,
+}
+
+impl Record<'_> {
+    fn to_json(&self) -> String {
+        let vars: Vec<String> = self.vars.iter().map(|var| json_string(var)).collect();
+        format!(
+            "{{\"crate\":{},\"item\":{},\"vars\":[{}],\"overridden\":{},\"value\":{},\"file\":{},\"line\":{}}}",
+            json_string(self.crate_name), json_string(self.item), vars.join(","), self.overridden,
+            json_string(self.value), json_string(self.file), self.line)
+    }
+}
+
+/// The file named by `CONST_ENV_REPORT`, resolved against the crate directory, if set.
+pub(crate) fn path(read_env: &impl ReadEnv) -> Result<Option<PathBuf>, String> {
+    match read_env.read_env(REPORT_VAR).map_err(|err| err.to_string())? {
+        Some(path) => crate::file::resolve_path(&*path, read_env).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Appends `record` to the report at `path`. Codegen units and crates are compiled in
+/// parallel, so the file is locked while the line is written.
+pub(crate) fn append(path: &Path, record: &Record) -> Result<(), String> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|err| format!("Failed to open `{}` named by `{}`: {}", path.display(), REPORT_VAR, err))?;
+    file.lock().map_err(|err| format!("Failed to lock `{}`: {}", path.display(), err))?;
+    file.write_all(format!("{}\n", record.to_json()).as_bytes())
+        .map_err(|err| format!("Failed to write to `{}` named by `{}`: {}", path.display(), REPORT_VAR, err))
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
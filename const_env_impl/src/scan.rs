@@ -0,0 +1,393 @@
+//! Lists the configurable items of a source tree without building it, for the
+//! `const-env-scan` tool. Files are parsed with `syn` and the variables of each item are
+//! named as the macros name them, so that prefixes and `rename_all` are applied.
+
+use std::path::{Path, PathBuf};
+
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+use crate::args::{self, ItemArgs};
+use crate::report::json_string;
+use crate::{derive, pretty, split, TestEnv};
+
+/// The target triple in the names of `per_target` variables, which depend on the build.
+const TARGET_PLACEHOLDER: &str = "<target>";
+
+/// An item or field configured by the environment.
+pub struct ScannedItem {
+    pub file: PathBuf,
+    pub line: usize,
+    /// The item's name, or `Struct::field` for a field of a derive.
+    pub item: String,
+    pub ty: String,
+    /// The variables read, in order of precedence.
+    pub vars: Vec<String>,
+    /// The default as written, or `<redacted>` for `sensitive` items.
+    pub default: String,
+}
+
+/// An attribute whose variables cannot be named without building the crate, or a file
+/// that cannot be parsed.
+pub struct Unresolved {
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+/// The items found by a scan, in the order of their files and lines.
+#[derive(Default)]
+pub struct Scan {
+    pub items: Vec<ScannedItem>,
+    pub unresolved: Vec<Unresolved>,
+}
+
+impl Scan {
+    /// The items as a table with a column for the file and line, the item, its type, its
+    /// variables and its default.
+    pub fn to_table(&self) -> String {
+        let rows: Vec<[String; 5]> = self.items.iter().map(|item| [
+            format!("{}:{}", item.file.display(), item.line),
+            item.item.clone(),
+            item.ty.clone(),
+            item.vars.join(", "),
+            item.default.clone(),
+        ]).collect();
+        let header = ["FILE", "ITEM", "TYPE", "VARIABLE", "DEFAULT"].map(String::from);
+        let mut widths = [0; 5];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = std::cmp::max(*width, cell.chars().count());
+            }
+        }
+        let mut table = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let cells: Vec<String> = row.iter().zip(widths).map(|(cell, width)| format!("{:width$}", cell, width = width)).collect();
+            table.push_str(cells.join("  ").trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// The items and unresolved attributes as a JSON object.
+    pub fn to_json(&self) -> String {
+        let items: Vec<String> = self.items.iter().map(|item| {
+            let vars: Vec<String> = item.vars.iter().map(|var| json_string(var)).collect();
+            format!("{{\"file\":{},\"line\":{},\"item\":{},\"type\":{},\"vars\":[{}],\"default\":{}}}",
+                json_string(&item.file.display().to_string()), item.line, json_string(&item.item),
+                json_string(&item.ty), vars.join(","), json_string(&item.default))
+        }).collect();
+        let unresolved: Vec<String> = self.unresolved.iter().map(|unresolved| {
+            format!("{{\"file\":{},\"line\":{},\"message\":{}}}",
+                json_string(&unresolved.file.display().to_string()), unresolved.line, json_string(&unresolved.message))
+        }).collect();
+        format!("{{\"items\":[{}],\"unresolved\":[{}]}}", items.join(","), unresolved.join(","))
+    }
+}
+
+/// Scans every `.rs` file under `root`, skipping `target` and hidden directories. Paths
+/// are reported relative to `root`.
+pub fn scan_dir(root: &Path) -> std::io::Result<Scan> {
+    let mut files = Vec::new();
+    find_sources(root, &mut files)?;
+    files.sort();
+    let mut scan = Scan::default();
+    for file in files {
+        let source = std::fs::read_to_string(&file)?;
+        let relative = file.strip_prefix(root).unwrap_or(&file);
+        let found = scan_source(relative, &source);
+        scan.items.extend(found.items);
+        scan.unresolved.extend(found.unresolved);
+    }
+    Ok(scan)
+}
+
+fn find_sources(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if entry.file_type()?.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                find_sources(&path, files)?;
+            }
+        } else if name.ends_with(".rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Scans the source of one file, reported as `file`.
+pub fn scan_source(file: &Path, source: &str) -> Scan {
+    let mut scanner = Scanner { file, scan: Scan::default() };
+    match syn::parse_file(source) {
+        Ok(parsed) => scanner.items(&parsed.items),
+        Err(err) => scanner.unresolved(err.span(), format!("Failed to parse the file: {}", err)),
+    }
+    scanner.scan
+}
+
+struct Scanner<'a> {
+    file: &'a Path,
+    scan: Scan,
+}
+
+impl Scanner<'_> {
+    fn items(&mut self, items: &[syn::Item]) {
+        for item in items {
+            self.item(item);
+        }
+    }
+
+    fn item(&mut self, item: &syn::Item) {
+        match item {
+            syn::Item::Const(item) => self.attributed(&item.attrs, &item.ident, item.ident.to_string(), &item.ty, &item.expr),
+            syn::Item::Static(item) => self.attributed(&item.attrs, &item.ident, item.ident.to_string(), &item.ty, &item.expr),
+            syn::Item::Mod(item) => {
+                self.module(item);
+                if let Some((_, items)) = &item.content {
+                    self.items(items);
+                }
+            }
+            syn::Item::Fn(item) => {
+                self.misplaced(&item.attrs);
+                self.block(&item.block);
+            }
+            syn::Item::Impl(item) => {
+                self.misplaced(&item.attrs);
+                let self_ty = pretty::to_pretty_string(item.self_ty.to_token_stream());
+                for impl_item in &item.items {
+                    match impl_item {
+                        syn::ImplItem::Const(konst) => {
+                            self.attributed(&konst.attrs, &konst.ident, format!("{}::{}", self_ty, konst.ident), &konst.ty, &konst.expr);
+                        }
+                        syn::ImplItem::Method(method) => self.block(&method.block),
+                        _ => {}
+                    }
+                }
+            }
+            syn::Item::Struct(item) => self.derive(item),
+            syn::Item::Macro(item) if is_path(&item.mac.path, "from_env") => self.block_items(&item.mac.tokens),
+            syn::Item::Enum(item) => self.misplaced(&item.attrs),
+            syn::Item::Trait(item) => self.misplaced(&item.attrs),
+            syn::Item::Type(item) => self.misplaced(&item.attrs),
+            syn::Item::Use(item) => self.misplaced(&item.attrs),
+            _ => {}
+        }
+    }
+
+    fn block(&mut self, block: &syn::Block) {
+        for stmt in &block.stmts {
+            match stmt {
+                syn::Stmt::Item(item) => self.item(item),
+                syn::Stmt::Semi(syn::Expr::Macro(expr), _) | syn::Stmt::Expr(syn::Expr::Macro(expr)) if is_path(&expr.mac.path, "from_env") => {
+                    self.block_items(&expr.mac.tokens);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The items of a `from_env!` block, which are configured with or without an
+    /// attribute of their own.
+    fn block_items(&mut self, tokens: &proc_macro2::TokenStream) {
+        let file: syn::File = match syn::parse2(tokens.clone()) {
+            Ok(file) => file,
+            Err(err) => return self.unresolved(err.span(), format!("Failed to parse the items of `from_env!`: {}", err)),
+        };
+        for item in &file.items {
+            let (attrs, ident, ty, expr) = match item {
+                syn::Item::Const(item) => (&item.attrs, &item.ident, &item.ty, &item.expr),
+                syn::Item::Static(item) => (&item.attrs, &item.ident, &item.ty, &item.expr),
+                other => {
+                    self.unresolved(other.span(), "Items of `from_env!` must be `const` or `static` items".to_string());
+                    continue;
+                }
+            };
+            let attr = attrs.iter().find(|attr| is_env_attr(attr));
+            self.resolve(attr.map_or_else(Default::default, |attr| attr.tokens.clone()), ident, ident.to_string(), ty, expr);
+        }
+    }
+
+    /// An item configured by its `env_item` attribute, if it has one.
+    fn attributed(&mut self, attrs: &[syn::Attribute], ident: &syn::Ident, name: String, ty: &syn::Type, expr: &syn::Expr) {
+        self.conditional(attrs);
+        if let Some(attr) = attrs.iter().find(|attr| is_env_attr(attr)) {
+            self.resolve(attr.tokens.clone(), ident, name, ty, expr);
+        }
+    }
+
+    fn resolve(&mut self, attr: proc_macro2::TokenStream, ident: &syn::Ident, name: String, ty: &syn::Type, expr: &syn::Expr) {
+        match args::parse_item_args(attr) {
+            Ok(args) => self.push(&args, ident, name, ty, expr),
+            Err(err) => self.unresolved(err.span(), format!("Invalid arguments of `{}`: {}", name, err)),
+        }
+    }
+
+    /// The fields of a struct deriving `ConstEnv`, which are all configured, or
+    /// `FromEnvDefaults`, whose fields with a `from_env` attribute are.
+    fn derive(&mut self, item: &syn::ItemStruct) {
+        let all_fields = match derived(&item.attrs) {
+            Some(all_fields) => all_fields,
+            None => return,
+        };
+        let input = syn::DeriveInput::from(item.clone());
+        let prefix = match derive::struct_prefix(&input) {
+            Ok(prefix) => prefix,
+            Err(err) => return self.unresolved(err.span(), format!("Invalid `from_env` attribute of `{}`: {}", item.ident, err)),
+        };
+        for field in &item.fields {
+            let ident = match &field.ident {
+                Some(ident) => ident,
+                None => return self.unresolved(item.ident.span(), format!("`{}` has no named fields", item.ident)),
+            };
+            let name = format!("{}::{}", item.ident, ident);
+            self.conditional(&field.attrs);
+            let attr = match field.attrs.iter().find(|attr| attr.path.is_ident("from_env")) {
+                Some(attr) => attr,
+                None if all_fields => {
+                    self.unresolved(ident.span(), format!("`{}` has no `from_env` attribute", name));
+                    continue;
+                }
+                None => continue,
+            };
+            let mut args = match args::parse_item_args(attr.tokens.clone()) {
+                Ok(args) => args,
+                Err(err) => {
+                    self.unresolved(err.span(), format!("Invalid arguments of `{}`: {}", name, err));
+                    continue;
+                }
+            };
+            let default = match args.default.take() {
+                Some((default, _)) => default,
+                None => {
+                    self.unresolved(attr.span(), format!("`{}` has no `default = ...`", name));
+                    continue;
+                }
+            };
+            if args.var_name.is_none() {
+                args.var_name = Some(syn::LitStr::new(&derive::field_var_name(prefix.as_deref(), ident), ident.span()));
+            }
+            self.push(&args, ident, name, &field.ty, &default);
+        }
+    }
+
+    /// The items of a module that `split_into` configures, each taking a field of one
+    /// variable. Other attributes on modules are misplaced.
+    fn module(&mut self, item: &syn::ItemMod) {
+        self.conditional(&item.attrs);
+        let attr = match item.attrs.iter().find(|attr| is_env_attr(attr)) {
+            Some(attr) => attr,
+            None => return,
+        };
+        let members = match args::parse_item_args(attr.tokens.clone()) {
+            Ok(args) => match args.split_into {
+                Some((members, _)) => members,
+                None => return self.unresolved(attr.span(), "Only `const` and `static` items can be configured".to_string()),
+            },
+            Err(err) => return self.unresolved(err.span(), format!("Invalid arguments of `{}`: {}", item.ident, err)),
+        };
+        let items = match &item.content {
+            Some((_, items)) => items,
+            None => return self.unresolved(attr.span(), format!("`{}` is split into items but has no body", item.ident)),
+        };
+        for (name, index) in members {
+            let member = items.iter().find_map(|member| match member {
+                syn::Item::Const(member) if member.ident == name => Some((&member.ident, &member.ty, &member.expr)),
+                syn::Item::Static(member) if member.ident == name => Some((&member.ident, &member.ty, &member.expr)),
+                _ => None,
+            });
+            let (ident, ty, expr) = match member {
+                Some(member) => member,
+                None => {
+                    self.unresolved(name.span(), format!("`{}` names `{}`, which is not a `const` or `static` item of the module", item.ident, name));
+                    continue;
+                }
+            };
+            // The arguments parsed above, so they parse again.
+            let args = args::parse_item_args(attr.tokens.clone()).expect("the arguments parsed before");
+            let args = split::member_args(args, &item.ident, index);
+            self.push(&args, ident, format!("{}::{}", item.ident, ident), ty, expr);
+        }
+    }
+
+    fn push(&mut self, args: &ItemArgs, ident: &syn::Ident, name: String, ty: &syn::Type, expr: &syn::Expr) {
+        let env = TestEnv::builder().set("TARGET", TARGET_PLACEHOLDER).build();
+        let vars = match (&args.prefix_map, crate::variable_names(args, ident, &env)) {
+            // The variables collected by `prefix_map` are only known when building.
+            (Some((prefix, _)), _) => vec![format!("{}*", prefix)],
+            (None, Ok(vars)) => crate::dedup_names(vars),
+            (None, Err(err)) => return self.unresolved(err.span(), format!("Cannot name the variables of `{}`: {}", name, err)),
+        };
+        if vars.is_empty() {
+            return self.unresolved(ident.span(), format!("Cannot name the variables of `{}`: it reads none", name));
+        }
+        let default = match args.sensitive {
+            true => "<redacted>".to_string(),
+            false => pretty::to_pretty_string(expr.to_token_stream()),
+        };
+        self.scan.items.push(ScannedItem {
+            file: self.file.to_path_buf(),
+            line: ident.span().start().line,
+            item: name,
+            ty: pretty::to_pretty_string(ty.to_token_stream()),
+            vars,
+            default,
+        });
+    }
+
+    /// Reports a `env_item` attribute on an item that it cannot configure.
+    fn misplaced(&mut self, attrs: &[syn::Attribute]) {
+        self.conditional(attrs);
+        if let Some(attr) = attrs.iter().find(|attr| is_env_attr(attr)) {
+            self.unresolved(attr.span(), "Only `const` and `static` items can be configured".to_string());
+        }
+    }
+
+    /// Reports attributes applied under `cfg_attr`, which depend on the build.
+    fn conditional(&mut self, attrs: &[syn::Attribute]) {
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("cfg_attr")) {
+            let mentions = attr.tokens.clone().into_iter().any(|token| matches!(token,
+                proc_macro2::TokenTree::Group(group) if group.stream().into_iter().any(|token| matches!(token,
+                    proc_macro2::TokenTree::Ident(ident) if ident == "env_item" || ident == "from_env"))));
+            if mentions {
+                self.unresolved(attr.span(), "`env_item` under `cfg_attr` depends on the build configuration".to_string());
+            }
+        }
+    }
+
+    fn unresolved(&mut self, span: proc_macro2::Span, message: String) {
+        self.scan.unresolved.push(Unresolved { file: self.file.to_path_buf(), line: span.start().line, message });
+    }
+}
+
+/// Whether `attr` is `#[env_item]`, possibly by its path or under the name `from_env`.
+fn is_env_attr(attr: &syn::Attribute) -> bool {
+    is_path(&attr.path, "env_item") || is_path(&attr.path, "from_env")
+}
+
+fn is_path(path: &syn::Path, name: &str) -> bool {
+    path.segments.last().is_some_and(|segment| segment.ident == name)
+}
+
+/// Whether the struct derives `ConstEnv`, which configures every field, or
+/// `FromEnvDefaults`, which configures fields with a `from_env` attribute.
+fn derived(attrs: &[syn::Attribute]) -> Option<bool> {
+    let mut derived = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("derive")) {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            for nested in &list.nested {
+                if let syn::NestedMeta::Meta(syn::Meta::Path(path)) = nested {
+                    if is_path(path, "ConstEnv") {
+                        derived = Some(true);
+                    } else if is_path(path, "FromEnvDefaults") && derived.is_none() {
+                        derived = Some(false);
+                    }
+                }
+            }
+        }
+    }
+    derived
+}
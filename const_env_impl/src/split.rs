@@ -0,0 +1,59 @@
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
+
+use crate::args::{ItemArgs, RenameRule};
+use crate::{explain, FromEnvError, ReadEnv, Substitution};
+
+/// The name of a `const` or `static` item.
+fn item_ident(item: &syn::Item) -> Option<&syn::Ident> {
+    match item {
+        syn::Item::Const(item) => Some(&item.ident),
+        syn::Item::Static(item) => Some(&item.ident),
+        _ => None,
+    }
+}
+
+/// The arguments of the item of a `split_into` module that takes field `index` of the
+/// variable, which is named after the module in SCREAMING_SNAKE_CASE unless the
+/// attribute names it.
+pub(crate) fn member_args(mut args: ItemArgs, module: &syn::Ident, index: usize) -> ItemArgs {
+    let (members, span) = args.split_into.take().expect("only the items of a `split_into` module are split");
+    let count = members.iter().map(|(_, index)| index + 1).max().unwrap_or(0);
+    args.field = Some((index, span));
+    args.field_sep = args.split_sep.take();
+    args.split_fields = Some((count, args.strict_fields.take().is_some()));
+    if args.var_name.is_none() {
+        args.var_name = Some(syn::LitStr::new(&RenameRule::ScreamingSnakeCase.apply(module), module.span()));
+    }
+    args
+}
+
+/// Expands each item of `module` named by `split_into`, as `env_item` would with the
+/// module's arguments and `field` set to its index. Items that fail are left unchanged
+/// and their errors follow the module, as in `from_env!`.
+pub(crate) fn expand_module(attr: TokenStream, members: &[(syn::Ident, usize)], span: Span, mut module: syn::ItemMod, read_env: &impl ReadEnv, mut substitutions: Option<&mut Vec<Substitution>>) -> Result<TokenStream, FromEnvError> {
+    let module_ident = module.ident.clone();
+    let items = match &mut module.content {
+        Some((_, items)) => items,
+        None => return Err(explain::error(explain::UNSUPPORTED_ITEM, span, "`split_into` can only be used on a module with a body").into()),
+    };
+    for (name, _) in members {
+        if !items.iter().any(|item| item_ident(item) == Some(name)) {
+            return Err(explain::error(explain::INVALID_ARGUMENT, name.span(), format!(
+                "`split_into` names `{}`, which is not a `const` or `static` item of the module", name)).into());
+        }
+    }
+    let mut errors = TokenStream::new();
+    for item in items.iter_mut() {
+        let index = match item_ident(item).and_then(|ident| members.iter().find(|(name, _)| name == ident)) {
+            Some((_, index)) => *index,
+            None => continue,
+        };
+        let args = member_args(crate::item_args(attr.clone(), read_env)?, &module_ident, index);
+        match crate::expand_item(&args, item.to_token_stream(), item.clone(), read_env, substitutions.as_deref_mut()) {
+            Ok(tokens) => *item = syn::Item::Verbatim(tokens),
+            Err(err) => errors.extend(err.to_compile_error()),
+        }
+    }
+    Ok(quote::quote!(#module #errors))
+}
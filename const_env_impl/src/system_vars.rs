@@ -0,0 +1,16 @@
+/// Variables set by the operating system, shells or cargo, which are unlikely to hold a
+/// value meant for an item of the same name.
+const SYSTEM_VARS: &[&str] = &[
+    "PATH", "HOME", "PWD", "OLDPWD", "USER", "LOGNAME", "SHELL", "TERM", "LANG", "HOSTNAME",
+    "TMPDIR", "TMP", "TEMP", "LD_LIBRARY_PATH", "RUSTFLAGS", "RUSTDOCFLAGS", "RUSTC",
+    "RUSTC_WRAPPER", "RUSTDOC", "TARGET", "HOST", "OUT_DIR", "PROFILE", "OPT_LEVEL", "DEBUG",
+    "NUM_JOBS",
+];
+
+/// Prefixes of variable families set by cargo and the dynamic linker.
+const SYSTEM_PREFIXES: &[&str] = &["CARGO_", "DYLD_", "LC_"];
+
+/// Whether `name` is a well-known system or cargo variable.
+pub(crate) fn is_system_var(name: &str) -> bool {
+    SYSTEM_VARS.contains(&name) || SYSTEM_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) || name == "CARGO"
+}
@@ -0,0 +1,69 @@
+use syn::LitStr;
+
+use crate::explain;
+
+/// A piece of a `format = "..."` template.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// Text copied verbatim into the value, with `{{` and `}}` already unescaped.
+    Literal(String),
+    /// A `{NAME}` placeholder naming an environment variable.
+    Var(String),
+}
+
+/// Splits a template into literal text and `{NAME}` placeholders. Placeholder names
+/// must be valid environment variable identifiers; anything else is reported against
+/// the template literal.
+pub(crate) fn parse_template(template: &LitStr) -> syn::Result<Vec<Segment>> {
+    let source = template.value();
+    let error = |message: String| explain::error(explain::INVALID_TEMPLATE, template.span(), message);
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(error(format!("Unclosed placeholder `{{{}` in format template", name))),
+                    }
+                }
+                if !is_valid_var_name(&name) {
+                    return Err(error(format!(
+                        "Invalid placeholder `{{{}}}` in format template: expected `{{VAR_NAME}}`, use `{{{{` and `}}}}` for literal braces",
+                        name)));
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Var(name));
+            }
+            '}' => return Err(error("Unmatched `}` in format template: use `}}` for a literal brace".to_string())),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+fn is_valid_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,417 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::{EnvError, ReadEnv};
+
+/// Variables read from a TOML document.
+///
+/// Keys of nested tables are joined with their table names, so that `port` in the
+/// `[server]` table becomes `SERVER_PORT`. Names are upper-cased unless the builder is
+/// asked to preserve case, and the separator defaults to `_`. Strings are used as they
+/// are, other scalars as written, and arrays as Rust array literals with quoted strings,
+/// such as `[1, 2]` or `["a", "b"]`, matching how array items read variables. Inline
+/// tables and arrays of tables are not supported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TomlEnv {
+    vars: HashMap<String, String>
+}
+
+/// Options for reading a `TomlEnv`.
+#[derive(Clone, Debug)]
+pub struct TomlEnvBuilder {
+    separator: String,
+    preserve_case: bool
+}
+
+/// An error loading a TOML document.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TomlError {
+    /// The file could not be read.
+    Io { path: PathBuf, error: std::io::Error },
+    /// The line with the given number, counting from 1, is malformed. If the line
+    /// defines a key, its dotted name is given.
+    Parse { path: Option<PathBuf>, line: usize, key: Option<String>, message: String },
+}
+
+impl std::fmt::Display for TomlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TomlError::Io { path, error } => write!(f, "Failed to read `{}`: {}", path.display(), error),
+            TomlError::Parse { path, line, key, message } => {
+                if let Some(path) = path {
+                    write!(f, "Failed to parse `{}`: ", path.display())?;
+                }
+                write!(f, "{}", ParseError { line: *line, key: key.clone(), message: message.clone() })
+            }
+        }
+    }
+}
+
+impl std::error::Error for TomlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TomlError::Io { error, .. } => Some(error),
+            TomlError::Parse { .. } => None,
+        }
+    }
+}
+
+impl TomlEnv {
+    pub fn builder() -> TomlEnvBuilder {
+        TomlEnvBuilder {
+            separator: "_".to_string(),
+            preserve_case: false
+        }
+    }
+
+    /// Reads and parses the file at `path` with the default options.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, TomlError> {
+        TomlEnv::builder().load(path)
+    }
+}
+
+impl std::str::FromStr for TomlEnv {
+    type Err = TomlError;
+
+    fn from_str(source: &str) -> Result<Self, TomlError> {
+        TomlEnv::builder().parse(source)
+    }
+}
+
+impl TomlEnvBuilder {
+    /// Sets the text placed between table names and keys.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Keeps names as written instead of upper-casing them.
+    pub fn preserve_case(mut self) -> Self {
+        self.preserve_case = true;
+        self
+    }
+
+    /// Reads and parses the file at `path`.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<TomlEnv, TomlError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| TomlError::Io { path: path.to_path_buf(), error })?;
+        self.parse(&source).map_err(|err| match err {
+            TomlError::Parse { line, key, message, .. } => TomlError::Parse { path: Some(path.to_path_buf()), line, key, message },
+            err => err,
+        })
+    }
+
+    pub fn parse(&self, source: &str) -> Result<TomlEnv, TomlError> {
+        let parse_error = |err: ParseError| TomlError::Parse { path: None, line: err.line, key: err.key, message: err.message };
+        let mut vars = HashMap::new();
+        let mut keys: HashMap<String, String> = HashMap::new();
+        for entry in parse_document(source).map_err(parse_error)? {
+            let mut name = entry.path.join(&self.separator);
+            if !self.preserve_case {
+                name = name.to_uppercase();
+            }
+            let key = entry.path.join(".");
+            if let Some(other) = keys.insert(name.clone(), key.clone()) {
+                return Err(parse_error(ParseError {
+                    line: entry.line,
+                    key: Some(key),
+                    message: format!("becomes `{}`, which is also the name of key `{}`", name, other),
+                }));
+            }
+            vars.insert(name, entry.value.to_env_string());
+        }
+        Ok(TomlEnv { vars })
+    }
+}
+
+impl ReadEnv for TomlEnv {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        Ok(self.vars.get(var_name).map(|value| Cow::Borrowed(OsStr::new(value))))
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.vars.keys().cloned().collect()
+    }
+}
+
+/// A malformed line of a TOML document.
+pub(crate) struct ParseError {
+    pub(crate) line: usize,
+    /// The key being defined, if the line defines one.
+    pub(crate) key: Option<String>,
+    pub(crate) message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.key {
+            Some(key) => write!(f, "line {}, key `{}`: {}", self.line, key, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+/// A value in a TOML document.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TomlValue {
+    String(String),
+    /// The integer as written, without a leading `+`.
+    Integer(String),
+    /// The float as written, without a leading `+`.
+    Float(String),
+    Bool(bool),
+    Array(Vec<TomlValue>),
+}
+
+impl TomlValue {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            TomlValue::String(_) => "a string",
+            TomlValue::Integer(_) => "an integer",
+            TomlValue::Float(_) => "a float",
+            TomlValue::Bool(_) => "a boolean",
+            TomlValue::Array(_) => "an array",
+        }
+    }
+
+    /// Renders the value the way it would be written in an environment variable.
+    /// Strings are written as they are, and arrays as Rust array literals whose strings
+    /// are quoted, such as `[1, "two"]`.
+    pub(crate) fn to_env_string(&self) -> String {
+        match self {
+            TomlValue::String(value) => value.clone(),
+            _ => self.to_element_string(),
+        }
+    }
+
+    fn to_element_string(&self) -> String {
+        match self {
+            TomlValue::String(value) => format!("{:?}", value),
+            TomlValue::Integer(value) | TomlValue::Float(value) => value.clone(),
+            TomlValue::Bool(value) => value.to_string(),
+            TomlValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(TomlValue::to_element_string).collect();
+                format!("[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+/// A key of a TOML document with its value.
+pub(crate) struct Entry {
+    /// The table names and the key, outermost first.
+    pub(crate) path: Vec<String>,
+    pub(crate) value: TomlValue,
+    /// The line the key is defined on, counting from 1.
+    pub(crate) line: usize,
+}
+
+/// Parses a TOML document consisting of `[table]` headers and `key = value` pairs
+/// whose values are strings, integers, floats, booleans or arrays of those written on
+/// a single line. Keys may be dotted, such as `server.port = 80`.
+pub(crate) fn parse_document(source: &str) -> Result<Vec<Entry>, ParseError> {
+    parse_lines(source, None)
+}
+
+/// Parses the keys of the table named by `section` and of its subtables, with paths
+/// relative to it. The rest of the document is skipped without being checked, so that
+/// it may use TOML features not supported here, as manifests often do.
+pub(crate) fn parse_section(source: &str, section: &[&str]) -> Result<Vec<Entry>, ParseError> {
+    parse_lines(source, Some(section))
+}
+
+fn parse_lines(source: &str, section: Option<&[&str]>) -> Result<Vec<Entry>, ParseError> {
+    let mut entries = Vec::new();
+    let mut defined = HashSet::new();
+    let mut table = Vec::new();
+    // Whether keys at this point are parsed, always so without a section.
+    let mut inside = section.is_none_or(<[&str]>::is_empty);
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |key: Option<&str>, message: String| ParseError { line: line_number, key: key.map(str::to_string), message };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with("[[") {
+            if section.is_none() {
+                return Err(error(None, "arrays of tables are not supported".to_string()));
+            }
+            inside = false;
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = parse_path(header)
+                .and_then(|(path, rest)| {
+                    let rest = rest.strip_prefix(']').ok_or_else(|| "expected `]` after table name".to_string())?;
+                    check_trailing(rest).map(|_| path)
+                });
+            match (header, section) {
+                (Ok(path), None) => table = path,
+                (Ok(path), Some(section)) => {
+                    inside = path.len() >= section.len() && path.iter().zip(section).all(|(part, name)| part == name);
+                    table = if inside { path[section.len()..].to_vec() } else { Vec::new() };
+                }
+                (Err(message), _) if inside => return Err(error(None, message)),
+                // Outside the section, this may be a line of a multi-line array.
+                (Err(_), _) => {}
+            }
+            continue;
+        }
+        if !inside {
+            continue;
+        }
+        let (key, rest) = parse_path(line).map_err(|message| error(None, message))?;
+        let name = key.join(".");
+        let rest = rest.strip_prefix('=')
+            .ok_or_else(|| error(None, format!("expected `=` after key `{}`", name)))?;
+        let value = parse_value(rest.trim())
+            .and_then(|(value, rest)| check_trailing(rest).map(|_| value))
+            .map_err(|message| error(Some(&name), message))?;
+        let mut path = table.clone();
+        path.extend(key);
+        if !defined.insert(path.clone()) {
+            return Err(error(None, format!("key `{}` is defined more than once", path.join("."))));
+        }
+        entries.push(Entry { path, value, line: line_number });
+    }
+    Ok(entries)
+}
+
+/// Parses a possibly dotted key, returning its parts along with the rest of the line
+/// with leading whitespace removed.
+fn parse_path(text: &str) -> Result<(Vec<String>, &str), String> {
+    let mut parts = Vec::new();
+    let mut rest = text.trim_start();
+    loop {
+        let (part, after) = parse_key(rest)?;
+        parts.push(part);
+        rest = after.trim_start();
+        match rest.strip_prefix('.') {
+            Some(after) => rest = after.trim_start(),
+            None => return Ok((parts, rest)),
+        }
+    }
+}
+
+/// Parses a bare or quoted key, returning it along with the rest of the text.
+fn parse_key(text: &str) -> Result<(String, &str), String> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        return parse_string(text);
+    }
+    let end = text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')).unwrap_or(text.len());
+    if end == 0 {
+        return Err("expected a key".to_string());
+    }
+    Ok((text[..end].to_string(), &text[end..]))
+}
+
+/// Parses a value, returning it along with the rest of the text.
+fn parse_value(text: &str) -> Result<(TomlValue, &str), String> {
+    if text.starts_with("\"\"\"") || text.starts_with("'''") {
+        return Err("multi-line strings are not supported".to_string());
+    }
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (value, rest) = parse_string(text)?;
+        return Ok((TomlValue::String(value), rest));
+    }
+    if text.starts_with('{') {
+        return Err("inline tables are not supported".to_string());
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((TomlValue::Array(values), after));
+            }
+            if rest.is_empty() || rest.starts_with('#') {
+                return Err("unterminated array, arrays must be written on a single line".to_string());
+            }
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected `,` or `]` in array".to_string());
+            }
+        }
+    }
+    let end = text.find(|c: char| c == ',' || c == ']' || c == '#' || c.is_whitespace()).unwrap_or(text.len());
+    let (token, rest) = text.split_at(end);
+    Ok((parse_scalar(token)?, rest))
+}
+
+fn parse_scalar(text: &str) -> Result<TomlValue, String> {
+    match text {
+        "true" => return Ok(TomlValue::Bool(true)),
+        "false" => return Ok(TomlValue::Bool(false)),
+        "" => return Err("expected a value".to_string()),
+        _ => {}
+    }
+    let unsigned = text.strip_prefix('+').unwrap_or(text);
+    let digits = unsigned.strip_prefix('-').unwrap_or(unsigned);
+    if ["0x", "0o", "0b"].iter().any(|prefix| digits.starts_with(prefix)) {
+        if digits.len() > 2 && digits[2..].chars().all(|c| c.is_ascii_hexdigit() || c == '_') {
+            return Ok(TomlValue::Integer(unsigned.to_string()));
+        }
+    } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit() || c == '_') {
+        return Ok(TomlValue::Integer(unsigned.to_string()));
+    } else if digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.replace('_', "").parse::<f64>().is_ok_and(f64::is_finite)
+    {
+        return Ok(TomlValue::Float(unsigned.to_string()));
+    }
+    Err(format!("unsupported value `{}`", text))
+}
+
+/// Parses a basic (`"..."`) or literal (`'...'`) string, returning its contents along
+/// with the rest of the text.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let quote = text.chars().next().unwrap();
+    let mut value = String::new();
+    let mut chars = text[1..].char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((value, &text[1 + index + 1..])),
+            '\\' if quote == '"' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some(kind @ ('u' | 'U')) => {
+                        let len = if kind == 'u' { 4 } else { 8 };
+                        let hex: String = chars.by_ref().take(len).map(|(_, c)| c).collect();
+                        u32::from_str_radix(&hex, 16).ok()
+                            .filter(|_| hex.len() == len)
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid unicode escape `\\{}{}`", kind, hex))?
+                    }
+                    Some(other) => return Err(format!("invalid escape `\\{}`", other)),
+                    None => break,
+                };
+                value.push(escaped);
+            }
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+fn check_trailing(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected `{}` after value", rest))
+    }
+}
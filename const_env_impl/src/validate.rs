@@ -0,0 +1,84 @@
+use std::cmp::Ordering;
+
+use quote::ToTokens;
+use syn::Expr;
+
+use crate::args::ItemArgs;
+use crate::explain;
+use crate::list::list_elements;
+use crate::value;
+
+/// Checks the value produced for `var_name` against the item's `min`, `max` and `one_of`
+/// arguments. Lists, including references to arrays, are checked element by element,
+/// and their length is checked against `min_items` and `max_items`. Every failing
+/// element is reported.
+pub(crate) fn validate(args: &ItemArgs, var_name: &str, new_expr: &Expr, span: proc_macro2::Span) -> syn::Result<()> {
+    match explain::combine(failures(args, var_name, new_expr, &[], span)) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// The failures of `validate`, skipping the elements at `skip`, which are already
+/// reported as not fitting the declared type.
+pub(crate) fn failures(args: &ItemArgs, var_name: &str, new_expr: &Expr, skip: &[usize], span: proc_macro2::Span) -> Vec<syn::Error> {
+    let elements = match list_elements(new_expr) {
+        Some(elements) => elements,
+        None if args.min.is_none() && args.max.is_none() && args.one_of.is_none() => return Vec::new(),
+        None => return check(args, new_expr).err().map(|problem| explain::error(explain::VALIDATION_FAILURE, span, format!(
+            "Environment variable `{}` is {}, {}", var_name, display(args, new_expr), problem))).into_iter().collect(),
+    };
+    let mut failures = Vec::new();
+    if let Some((min_items, _)) = args.min_items {
+        if elements.len() < min_items {
+            failures.push(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Environment variable `{}` has {} items, fewer than the minimum of {}", var_name, elements.len(), min_items)));
+        }
+    }
+    if let Some((max_items, _)) = args.max_items {
+        if elements.len() > max_items {
+            failures.push(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Environment variable `{}` has {} items, more than the maximum of {}", var_name, elements.len(), max_items)));
+        }
+    }
+    for (index, element) in elements.iter().enumerate().filter(|(index, _)| !skip.contains(index)) {
+        if let Err(problem) = check(args, element) {
+            failures.push(explain::error(explain::VALIDATION_FAILURE, span, format!(
+                "Element {} of environment variable `{}` is {}, {}", index, var_name, display(args, element), problem)));
+        }
+    }
+    failures
+}
+
+/// Checks a single value, returning a description of the first violated bound.
+fn check(args: &ItemArgs, value: &Expr) -> Result<(), String> {
+    for (bound, name, rejected, relation) in [
+        (&args.min, "minimum", Ordering::Less, "below"),
+        (&args.max, "maximum", Ordering::Greater, "above"),
+    ] {
+        if let Some(bound) = bound {
+            match value::compare(value, bound) {
+                Some(ordering) if ordering == rejected => {
+                    return Err(format!("which is {} the {} of {}", relation, name, bound.to_token_stream()));
+                }
+                Some(_) => {}
+                None => return Err(format!("which is not a number and cannot be checked against the {}", name)),
+            }
+        }
+    }
+    if let Some((allowed, _)) = &args.one_of {
+        if !allowed.iter().any(|allowed| value::same_value(value, allowed)) {
+            let allowed: Vec<String> = allowed.iter().map(|allowed| format!("`{}`", allowed.to_token_stream())).collect();
+            return Err(format!("which is not one of {}", allowed.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+fn display(args: &ItemArgs, value: &Expr) -> String {
+    if args.sensitive {
+        "<redacted>".to_string()
+    } else {
+        format!("`{}`", value.to_token_stream())
+    }
+}
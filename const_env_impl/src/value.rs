@@ -0,0 +1,91 @@
+use std::cmp::Ordering;
+
+use syn::{Expr, ExprLit, ExprUnary, Lit, UnOp};
+
+/// The value denoted by a literal expression, used to compare literals written in
+/// different ways such as `0x10` and `16`.
+#[derive(Debug, PartialEq)]
+enum LitValue {
+    Str(String),
+    Bytes(Vec<u8>),
+    Char(char),
+    Bool(bool),
+    Int(bool, u128),
+    Float(f64),
+    Array(Vec<LitValue>),
+    Tuple(Vec<LitValue>),
+}
+
+fn lit_value(expr: &Expr) -> Option<LitValue> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(lit) => Some(LitValue::Str(lit.value())),
+            Lit::ByteStr(lit) => Some(LitValue::Bytes(lit.value())),
+            Lit::Byte(lit) => Some(LitValue::Int(false, lit.value() as u128)),
+            Lit::Char(lit) => Some(LitValue::Char(lit.value())),
+            Lit::Bool(lit) => Some(LitValue::Bool(lit.value)),
+            Lit::Int(lit) => lit.base10_parse::<u128>().ok().map(|value| LitValue::Int(false, value)),
+            Lit::Float(lit) => lit.base10_parse::<f64>().ok().map(LitValue::Float),
+            Lit::Verbatim(_) => None,
+        },
+        Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => match lit_value(expr)? {
+            LitValue::Int(negative, value) => Some(LitValue::Int(!negative && value != 0, value)),
+            LitValue::Float(value) => Some(LitValue::Float(-value)),
+            _ => None,
+        },
+        Expr::Paren(paren) => lit_value(&paren.expr),
+        Expr::Group(group) => lit_value(&group.expr),
+        Expr::Array(array) => array.elems.iter().map(lit_value).collect::<Option<_>>().map(LitValue::Array),
+        Expr::Tuple(tuple) => tuple.elems.iter().map(lit_value).collect::<Option<_>>().map(LitValue::Tuple),
+        Expr::Reference(reference) => lit_value(&reference.expr),
+        _ => None,
+    }
+}
+
+/// Whether two expressions denote the same value. Literals are compared by value and
+/// anything else by its tokens.
+pub(crate) fn same_value(a: &Expr, b: &Expr) -> bool {
+    match (lit_value(a), lit_value(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => quote::quote!(#a).to_string() == quote::quote!(#b).to_string(),
+    }
+}
+
+/// Whether an expression is an integer or float literal, optionally negated.
+pub(crate) fn is_number(expr: &Expr) -> bool {
+    matches!(lit_value(expr), Some(LitValue::Int(..) | LitValue::Float(_)))
+}
+
+/// Compares two numeric literals by value, or returns `None` if either is not a number.
+pub(crate) fn compare(a: &Expr, b: &Expr) -> Option<Ordering> {
+    match (lit_value(a)?, lit_value(b)?) {
+        (LitValue::Int(a_negative, a), LitValue::Int(b_negative, b)) => Some(match (a_negative, b_negative) {
+            (false, false) => a.cmp(&b),
+            (true, true) => b.cmp(&a),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }),
+        (a, b) => as_float(&a)?.partial_cmp(&as_float(&b)?),
+    }
+}
+
+fn as_float(value: &LitValue) -> Option<f64> {
+    match *value {
+        LitValue::Int(negative, value) if negative => Some(-(value as f64)),
+        LitValue::Int(_, value) => Some(value as f64),
+        LitValue::Float(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Orders two literals of the same kind: numbers by value, and strings, byte strings,
+/// characters and booleans lexicographically. Returns `None` for anything else.
+pub(crate) fn order(a: &Expr, b: &Expr) -> Option<Ordering> {
+    match (lit_value(a)?, lit_value(b)?) {
+        (LitValue::Str(a), LitValue::Str(b)) => Some(a.cmp(&b)),
+        (LitValue::Bytes(a), LitValue::Bytes(b)) => Some(a.cmp(&b)),
+        (LitValue::Char(a), LitValue::Char(b)) => Some(a.cmp(&b)),
+        (LitValue::Bool(a), LitValue::Bool(b)) => Some(a.cmp(&b)),
+        _ => compare(a, b),
+    }
+}
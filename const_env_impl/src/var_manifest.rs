@@ -0,0 +1,106 @@
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::registry::{escape, unescape};
+use crate::ReadEnv;
+
+/// Variable naming the file that lists every variable read by the crate's items.
+pub(crate) const MANIFEST_VAR: &str = "CONST_ENV_MANIFEST";
+
+/// The file named by `CONST_ENV_MANIFEST`, resolved against the crate directory, if set.
+pub(crate) fn path(read_env: &impl ReadEnv) -> Result<Option<PathBuf>, String> {
+    match read_env.read_env(MANIFEST_VAR).map_err(|err| err.to_string())? {
+        Some(path) => crate::file::resolve_path(&*path, read_env).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Adds `names`, read by an item of the source file `source`, to the manifest at `path`.
+///
+/// The lib, bin and test targets of a package share its `OUT_DIR` and so the manifest,
+/// which lists the names read by any of them. As with claims, each name is recorded with
+/// the source file it is read in and the file's modification time, and is dropped once
+/// the file changes, so that the names of removed items go away when the items left
+/// are expanded again. The file is locked while it is rewritten, so that concurrent
+/// invocations do not lose each other's names.
+pub(crate) fn record(path: &Path, names: &[String], source: &str) -> Result<(), String> {
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+        .map_err(|err| format!("Failed to open `{}` named by `{}`: {}", path.display(), MANIFEST_VAR, err))?;
+    file.lock().map_err(|err| format!("Failed to lock `{}`: {}", path.display(), err))?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|err| format!("Failed to read `{}`: {}", path.display(), err))?;
+    let mut current: Vec<(String, String)> = vec![(source.to_string(), crate::file::modified_time(source))];
+    let mut recorded: Vec<(String, String, String)> = Vec::new();
+    for (name, line_source, modified) in contents.lines().filter_map(parse_line) {
+        let current_modified = match current.iter().find(|(file, _)| *file == line_source) {
+            Some((_, modified)) => modified.clone(),
+            None => {
+                let modified = crate::file::modified_time(&line_source);
+                current.push((line_source.clone(), modified.clone()));
+                modified
+            }
+        };
+        if modified == current_modified {
+            recorded.push((name, line_source, modified));
+        }
+    }
+    for name in names {
+        if !recorded.iter().any(|(recorded_name, recorded_source, _)| recorded_name == name && recorded_source == source) {
+            recorded.push((name.clone(), source.to_string(), current[0].1.clone()));
+        }
+    }
+
+    let contents: String = recorded.iter()
+        .map(|(name, source, modified)| format!("{}\t{}\t{}\n", escape(name), escape(source), modified))
+        .collect();
+    file.set_len(0)
+        .and_then(|_| file.rewind())
+        .and_then(|_| file.write_all(contents.as_bytes()))
+        .map_err(|err| format!("Failed to write `{}`: {}", path.display(), err))
+}
+
+/// Parses a line of the manifest into the name, its source file and the file's
+/// modification time.
+fn parse_line(line: &str) -> Option<(String, String, String)> {
+    match line.split('\t').collect::<Vec<&str>>().as_slice() {
+        [name, source, modified] => Some((unescape(name), unescape(source), modified.to_string())),
+        _ => None,
+    }
+}
+
+/// Reads the variable names listed in a file written through `CONST_ENV_MANIFEST`,
+/// sorted and without duplicates. A missing file lists no names.
+pub fn consumed_vars(path: impl AsRef<Path>) -> std::io::Result<Vec<String>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut names: Vec<String> = contents.lines()
+        .filter_map(parse_line)
+        .map(|(name, _, _)| name)
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// For build scripts: prints `cargo:rerun-if-env-changed` for every variable the crate's
+/// items read during the previous build, as recorded at `path`, and sets
+/// `CONST_ENV_MANIFEST` to `path` so that this build records them again.
+///
+/// ```no_run
+/// // build.rs
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// const_env_impl::emit_rerun_directives(format!("{}/const_env_vars.txt", out_dir)).unwrap();
+/// ```
+pub fn emit_rerun_directives(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    for name in consumed_vars(path)? {
+        println!("cargo:rerun-if-env-changed={}", name);
+    }
+    println!("cargo:rustc-env={}={}", MANIFEST_VAR, path.display());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use const_env_impl::{from_env, TestEnv};
+
+use quote::quote;
+
+/// A deterministic xorshift generator, so that failures can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.next() as usize % n
+    }
+
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.below(choices.len())]
+    }
+}
+
+/// A random pattern of the syntax `capture` supports.
+fn random_pattern(rng: &mut Rng, depth: usize) -> String {
+    let mut pattern = String::new();
+    for _ in 0..1 + rng.below(3) {
+        let atom = match rng.below(if depth == 0 { 8 } else { 11 }) {
+            0..=3 => rng.pick(&["a", "b", "1", " ", "\\.", ".", "[ab]", "[^a]", "[a-b1]", "\\d", "\\w", "\\s", "\\W"]).to_string(),
+            4 => rng.pick(&["^", "$", "\\b", "\\B"]).to_string(),
+            5..=7 => rng.pick(&["a", "b", "1"]).to_string(),
+            8 => format!("({})", random_pattern(rng, depth - 1)),
+            9 => format!("(?:{})", random_pattern(rng, depth - 1)),
+            _ => format!("({}|{})", random_pattern(rng, depth - 1), random_pattern(rng, depth - 1)),
+        };
+        let group = atom.starts_with('(');
+        let repeatable = !matches!(&*atom, "^" | "$" | "\\b" | "\\B");
+        pattern.push_str(&atom);
+        // Repeated groups that can match the empty string are left out, since engines
+        // differ in how they stop such repetitions.
+        if repeatable && rng.below(3) == 0 {
+            match group {
+                true => pattern.push_str(rng.pick(&["?", "??"])),
+                false => pattern.push_str(rng.pick(&["*", "+", "?", "{2}", "{1,2}", "{0,}", "*?", "+?", "??", "{1,3}?"])),
+            }
+        }
+    }
+    pattern
+}
+
+#[test]
+fn test_capture_matches_regex_crate() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..3000 {
+        let pattern = random_pattern(&mut rng, 3);
+        let value: String = (0..rng.below(8)).map(|_| rng.pick(&["a", "b", "1", " ", "."])).collect();
+        let regex = regex::Regex::new(&pattern).unwrap();
+        let group = usize::from(regex.captures_len() > 1);
+        let env = TestEnv::builder().set("VALUE", &value).build();
+        let result = from_env(quote!((capture = #pattern, no_doc)), quote!(const VALUE: &str = "";), env).to_string();
+        let context = format!("pattern: {:?}, value: {:?}", pattern, value);
+        match regex.captures(&value) {
+            None => assert!(result.contains("does not match the `capture` pattern"), "{}\n{}", context, result),
+            Some(captures) => match captures.get(group) {
+                None => assert!(result.contains("is not part of the match"), "{}\n{}", context, result),
+                Some(found) => {
+                    let text = found.as_str();
+                    assert_eq!(quote!(const VALUE: &str = #text;).to_string(), result, "{}", context);
+                }
+            },
+        }
+    }
+}
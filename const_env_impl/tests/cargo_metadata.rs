@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use const_env_impl::{from_env, CargoMetadataEnv, ReadEnv, TestEnv, TomlError};
+
+use quote::quote;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("const_env_impl_cargo_metadata_tests_{}", std::process::id()))
+        .join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn value(env: &CargoMetadataEnv, name: &str) -> Option<String> {
+    env.read_env_utf8(name).unwrap()
+}
+
+const MANIFEST: &str = "\
+[package]
+name = \"app\"
+version = \"0.1.0\"
+authors = [
+    \"Someone <someone@example.com>\",
+]
+metadata.docs = { all-features = true }
+
+[package.metadata.const_env]
+PORT = 8080
+HOST = \"localhost\"
+FEATURES = [\"a\", \"b\"]
+
+[package.metadata.const_env.log]
+level = \"info\"
+
+[dependencies]
+serde = { version = \"1\", features = [\"derive\"] }
+
+[[bin]]
+name = \"app\"
+";
+
+#[test]
+fn test_from_manifest() {
+    let dir = temp_dir("package");
+    let path = dir.join("Cargo.toml");
+    std::fs::write(&path, MANIFEST).unwrap();
+    let env = CargoMetadataEnv::from_manifest(&path).unwrap();
+    assert_eq!(value(&env, "PORT").as_deref(), Some("8080"));
+    assert_eq!(value(&env, "HOST").as_deref(), Some("localhost"));
+    assert_eq!(value(&env, "FEATURES").as_deref(), Some("[\"a\", \"b\"]"));
+    assert_eq!(value(&env, "log_level").as_deref(), Some("info"));
+    assert_eq!(value(&env, "name"), None);
+    assert_eq!(env.var_names(), vec!["FEATURES", "HOST", "PORT", "log_level"]);
+}
+
+#[test]
+fn test_workspace_metadata_is_a_fallback() {
+    let root = temp_dir("workspace");
+    std::fs::write(root.join("Cargo.toml"), "\
+[workspace]
+members = [\"member\"]
+
+[workspace.metadata.const_env]
+PORT = 80
+REGION = \"eu\"
+").unwrap();
+    let member = root.join("member");
+    std::fs::create_dir_all(&member).unwrap();
+    std::fs::write(member.join("Cargo.toml"), MANIFEST).unwrap();
+    let env = CargoMetadataEnv::from_manifest_dir(&member).unwrap();
+    assert_eq!(value(&env, "PORT").as_deref(), Some("8080"));
+    assert_eq!(value(&env, "REGION").as_deref(), Some("eu"));
+
+    let env = CargoMetadataEnv::from_manifest(member.join("Cargo.toml")).unwrap();
+    assert_eq!(value(&env, "REGION"), None);
+}
+
+#[test]
+fn test_errors() {
+    let dir = temp_dir("invalid");
+    let path = dir.join("Cargo.toml");
+    std::fs::write(&path, "[package.metadata.const_env]\nPORT = { value = 1 }\n").unwrap();
+    let err = CargoMetadataEnv::from_manifest(&path).unwrap_err();
+    assert!(matches!(err, TomlError::Parse { line: 2, .. }), "{:?}", err);
+
+    let err = CargoMetadataEnv::from_manifest_dir(temp_dir("missing")).unwrap_err();
+    assert!(matches!(err, TomlError::Io { .. }), "{:?}", err);
+}
+
+#[test]
+fn test_macro_reads_metadata_when_enabled() {
+    let dir = temp_dir("macro");
+    std::fs::write(dir.join("Cargo.toml"), MANIFEST).unwrap();
+    let env = |enabled: bool| {
+        let builder = TestEnv::builder().set("CARGO_MANIFEST_DIR", dir.to_str().unwrap());
+        match enabled {
+            true => builder.set("CONST_ENV_CARGO_METADATA", "1").build(),
+            false => builder.build(),
+        }
+    };
+
+    let result = from_env(quote!((cargo_metadata, no_doc)), quote!(const PORT: u16 = 80;), env(false));
+    assert_eq!(quote!(const PORT: u16 = 8080;).to_string(), result.to_string());
+
+    let result = from_env(quote!((no_doc)), quote!(const PORT: u16 = 80;), env(true));
+    assert_eq!(quote!(const PORT: u16 = 8080;).to_string(), result.to_string());
+
+    let result = from_env(quote!((no_doc)), quote!(const PORT: u16 = 80;), env(false));
+    assert_eq!(quote!(const PORT: u16 = 80;).to_string(), result.to_string());
+
+    let env = TestEnv::builder().set("PORT", "9090").set("CARGO_MANIFEST_DIR", dir.to_str().unwrap()).build();
+    let result = from_env(quote!((cargo_metadata, no_doc)), quote!(const PORT: u16 = 80;), env);
+    assert_eq!(quote!(const PORT: u16 = 9090;).to_string(), result.to_string());
+
+    let missing = temp_dir("macro_missing");
+    let env = TestEnv::builder().set("CARGO_MANIFEST_DIR", missing.to_str().unwrap()).build();
+    let result = from_env(quote!((cargo_metadata, no_doc)), quote!(const PORT: u16 = 80;), env);
+    assert!(result.to_string().contains("[CE0018]"), "{}", result);
+}
@@ -0,0 +1,84 @@
+use const_env_impl::{DotenvEnv, DotenvError, ReadEnv, TestEnv};
+
+fn parse(source: &str) -> DotenvEnv {
+    source.parse().unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn value(env: &DotenvEnv, name: &str) -> Option<String> {
+    env.read_env_utf8(name).unwrap()
+}
+
+#[test]
+fn test_parse() {
+    let env = parse("\
+# a comment
+
+PLAIN=value
+SPACED = hello world  
+export EXPORTED=1
+COMMENTED=value # trailing comment
+URL=http://example.com/#fragment
+DOUBLE=\"line\\none \\\"quoted\\\" # not a comment\"
+SINGLE='raw \\n $HOME'
+EMPTY=
+REPEATED=first
+REPEATED=second
+");
+    assert_eq!(value(&env, "PLAIN").as_deref(), Some("value"));
+    assert_eq!(value(&env, "SPACED").as_deref(), Some("hello world"));
+    assert_eq!(value(&env, "EXPORTED").as_deref(), Some("1"));
+    assert_eq!(value(&env, "COMMENTED").as_deref(), Some("value"));
+    assert_eq!(value(&env, "URL").as_deref(), Some("http://example.com/#fragment"));
+    assert_eq!(value(&env, "DOUBLE").as_deref(), Some("line\none \"quoted\" # not a comment"));
+    assert_eq!(value(&env, "SINGLE").as_deref(), Some("raw \\n $HOME"));
+    assert_eq!(value(&env, "EMPTY").as_deref(), Some(""));
+    assert_eq!(value(&env, "REPEATED").as_deref(), Some("second"));
+    assert_eq!(value(&env, "UNSET"), None);
+    assert_eq!(env.var_names().len(), 9);
+}
+
+#[test]
+fn test_parse_errors_have_line_numbers() {
+    for (source, line, message) in [
+        ("A=1\nnot a pair\n", 2, "expected `KEY=value`"),
+        ("\n\n1A=1", 3, "invalid key `1A`"),
+        ("A=\"unterminated", 1, "unterminated quoted value"),
+        ("A='x' y", 1, "unexpected `y` after quoted value"),
+        ("A=\"\\q\"", 1, "invalid escape `\\q`"),
+    ] {
+        match source.parse::<DotenvEnv>() {
+            Err(err @ DotenvError::Parse { .. }) => {
+                assert_eq!(err.to_string(), format!("line {}: {}", line, message), "{:?}", source);
+            }
+            other => panic!("{:?}: {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn test_from_path() {
+    let dir = std::env::temp_dir().join(format!("const_env_impl_dotenv_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("valid.env");
+    std::fs::write(&path, "PORT=8080\n").unwrap();
+    let env = DotenvEnv::from_path(&path).unwrap();
+    assert_eq!(value(&env, "PORT").as_deref(), Some("8080"));
+
+    let path = dir.join("invalid.env");
+    std::fs::write(&path, "PORT=8080\nHOST\n").unwrap();
+    let err = DotenvEnv::from_path(&path).unwrap_err();
+    assert_eq!(err.to_string(), format!("Failed to parse `{}`: line 2: expected `KEY=value`", path.display()));
+
+    let err = DotenvEnv::from_path(dir.join("missing.env")).unwrap_err();
+    assert!(matches!(err, DotenvError::Io { .. }), "{:?}", err);
+}
+
+#[test]
+fn test_environment_takes_precedence() {
+    let env = TestEnv::builder()
+        .set("PORT", "9090")
+        .build()
+        .chain(parse("PORT=8080\nHOST=localhost\n"));
+    assert_eq!(env.read_env_utf8("PORT").unwrap().as_deref(), Some("9090"));
+    assert_eq!(env.read_env_utf8("HOST").unwrap().as_deref(), Some("localhost"));
+}
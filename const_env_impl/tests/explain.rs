@@ -0,0 +1,109 @@
+use const_env_impl::{env_lit, env_or, explain, fingerprint, from_env, TestEnv};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Extracts the codes of every `compile_error!` message in `output`.
+fn error_codes(output: &TokenStream) -> Vec<String> {
+    let output = output.to_string();
+    output.match_indices("compile_error ! { \"").map(|(index, prefix)| {
+        let message = &output[index + prefix.len()..];
+        assert!(message.starts_with("[CE"), "error without a code: {}", output);
+        message[1..7].to_string()
+    }).collect()
+}
+
+fn assert_code(output: TokenStream, expected: &str) {
+    let codes = error_codes(&output);
+    assert!(!codes.is_empty(), "expected an error: {}", output);
+    for code in &codes {
+        assert!(explain(code).is_some(), "`{}` is not registered: {}", code, output);
+    }
+    assert_eq!(codes[0], expected, "{}", output);
+}
+
+#[test]
+fn test_explain() {
+    assert!(explain("CE0001").unwrap().contains("required"));
+    assert!(explain("CE0003").unwrap().contains("`const`"));
+    assert_eq!(explain("CE9999"), None);
+    assert_eq!(explain("ce0001"), None);
+}
+
+#[test]
+fn test_every_error_has_a_registered_code() {
+    let out_dir = std::env::temp_dir().join(format!("const_env_impl_explain_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let config = out_dir.join("defaults.toml");
+    std::fs::write(&config, "PORT = \"eighty\"\n").unwrap();
+    let config = config.to_str().unwrap();
+    let lockfile = out_dir.join("const_env.lock");
+    std::fs::write(&lockfile, "[crate]\nPORT = \"8080\"\n").unwrap();
+    let lockfile = lockfile.to_str().unwrap();
+    let cases: Vec<(&str, TokenStream, TokenStream, TestEnv)> = vec![
+        ("CE0001", quote!((required)), quote!(const PORT: u16 = 80;), TestEnv::builder().build()),
+        ("CE0001", quote!((format = "{HOST}", required)), quote!(const URL: &str = "";), TestEnv::builder().build()),
+        ("CE0002", quote!(), quote!(const PORT: u16 = 80;), TestEnv::builder().set("PORT", "eighty").build()),
+        ("CE0002", quote!((expr)), quote!(const PORT: u16 = 80;), TestEnv::builder().set("PORT", "1 +").build()),
+        ("CE0003", quote!(), quote!(fn port() -> u16 { 80 }), TestEnv::builder().build()),
+        ("CE0004", quote!((requried)), quote!(const PORT: u16 = 80;), TestEnv::builder().build()),
+        ("CE0004", quote!((required, required)), quote!(const PORT: u16 = 80;), TestEnv::builder().build()),
+        ("CE0004", quote!((42)), quote!(const PORT: u16 = 80;), TestEnv::builder().build()),
+        ("CE0005", quote!((case = "lower")), quote!(const PORT: u16 = 80;), TestEnv::builder().build()),
+        ("CE0005", quote!(), quote!(const PORT: Port = 40 + 40;), TestEnv::builder().set("PORT", "80").build()),
+        ("CE0006", quote!((max = 100)), quote!(const PORT: u16 = 80;), TestEnv::builder().set("PORT", "8080").build()),
+        ("CE0006", quote!((dedup)), quote!(const PORTS: [u16; 2] = [80, 443];), TestEnv::builder().set("PORTS", "[80, 80]").build()),
+        ("CE0007", quote!((sha256 = "0000000000000000000000000000000000000000000000000000000000000000")),
+            quote!(const KEY: &str = "";), TestEnv::builder().set("KEY", "secret").build()),
+        ("CE0008", quote!((file)), quote!(const KEY: &str = "";), TestEnv::builder().set("KEY", "/nonexistent/const_env/key").build()),
+        ("CE0009", quote!((expand)), quote!(const URL: &str = "";), TestEnv::builder().set("URL", "http://${HOST}").build()),
+        ("CE0010", quote!(), quote!(const NAME: &str = "";), TestEnv::builder().set_bytes("NAME", vec![0xff]).build()),
+        ("CE0011", quote!((ignore_case)), quote!(const PORT: u16 = 80;), TestEnv::builder().set("Port", "1").set("port", "2").build()),
+        ("CE0012", quote!(), quote!(const PORT: u16 = 80;), TestEnv::builder().set("CONST_ENV_CONFIG", config).build()),
+        ("CE0014", quote!(), quote!(const PORT: u16 = 80;), TestEnv::builder().set("CONST_ENV_DUPLICATES", "always").build()),
+        ("CE0015", quote!((format = "{host")), quote!(const URL: &str = "";), TestEnv::builder().build()),
+        ("CE0019", quote!(), quote!(const PORT: u16 = 80;), TestEnv::builder().set("CONST_ENV_LOCK", lockfile).build()),
+        ("CE0020", quote!(), quote!(const RATIO: u32 = 0.5;), TestEnv::builder().build()),
+    ];
+    for (expected, attr, item, env) in cases {
+        assert_code(from_env(attr, item, env), expected);
+    }
+}
+
+#[test]
+fn test_fingerprint_order_code() {
+    let env = || TestEnv::builder()
+        .set("CONST_ENV_FINGERPRINT", "1")
+        .set("CARGO_CRATE_NAME", "explain_fingerprint")
+        .build();
+    fingerprint(quote!(), env());
+    assert_code(from_env(quote!(), quote!(const PORT: u16 = 80;), env()), "CE0021");
+}
+
+#[test]
+fn test_duplicate_variable_code() {
+    let out_dir = std::env::temp_dir().join(format!("const_env_impl_explain_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let env = || TestEnv::builder()
+        .set("OUT_DIR", out_dir.to_str().unwrap())
+        .set("CARGO_CRATE_NAME", "explain_duplicates")
+        .set("CONST_ENV_DUPLICATES", "error")
+        .build();
+    let result = from_env(quote!(("RETRIES")), quote!(const RETRIES: u32 = 3;), env());
+    assert!(error_codes(&result).is_empty(), "{}", result);
+    assert_code(from_env(quote!(("RETRIES")), quote!(const MAX_RETRIES: u32 = 5;), env()), "CE0013");
+}
+
+#[test]
+fn test_env_lit_errors_have_codes() {
+    assert_code(env_lit(quote!("PORT"), TestEnv::builder().build()), "CE0004");
+    assert_code(env_lit(quote!(PORT, 80), TestEnv::builder().build()), "CE0004");
+    assert_code(env_lit(quote!("NAME", 'a'), TestEnv::builder().set("NAME", "ab").build()), "CE0002");
+}
+
+#[test]
+fn test_env_or_errors_have_codes() {
+    assert_code(env_or(quote!("PORT"), TestEnv::builder().build()), "CE0004");
+    assert_code(env_or(quote!("PORT", 80u16), TestEnv::builder().set("PORT", "eighty").build()), "CE0002");
+    assert_code(env_or(quote!("PORT", 80u16), TestEnv::builder().set_bytes("PORT", vec![0xff]).build()), "CE0010");
+}
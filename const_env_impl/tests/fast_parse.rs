@@ -0,0 +1,98 @@
+#![cfg(feature = "fast-parse")]
+
+use const_env_impl::{from_env, try_from_env_with_substitutions, TestEnv, TestEnvBuilder};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// A deterministic xorshift generator, so that failures can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.next() as usize % choices.len()]
+    }
+}
+
+/// Expands `item` with and without the fast path, checking that both produce the same
+/// tokens and substitutions.
+fn assert_same(attr: TokenStream, item: TokenStream, env: impl Fn() -> TestEnvBuilder) {
+    let fast = from_env(attr.clone(), item.clone(), env().build());
+    let full = from_env(attr.clone(), item.clone(), env().set("CONST_ENV_FAST_PARSE", "0").build());
+    assert_eq!(full.to_string(), fast.to_string(), "attr: {}\nitem: {}", attr, item);
+    let fast = try_from_env_with_substitutions(attr.clone(), item.clone(), env().build()).map(|(_, substitutions)| substitutions);
+    let full = try_from_env_with_substitutions(attr.clone(), item.clone(), env().set("CONST_ENV_FAST_PARSE", "0").build()).map(|(_, substitutions)| substitutions);
+    assert_eq!(format!("{:?}", full), format!("{:?}", fast), "attr: {}\nitem: {}", attr, item);
+}
+
+#[test]
+fn test_generated_items_expand_identically() {
+    let mut rng = Rng(0x5eed_c0de_f00d_1234);
+    let shapes: &[(&str, &[&str], &[&str])] = &[
+        ("u16", &["80", "80u16", "0x50", "8_080"], &["8080", "0x1f90", "eighty", "-1", "70000"]),
+        ("i32", &["-5", "0", "- 12i32"], &["-42", "7", "1.5"]),
+        ("f64", &["0.5", "-1.25", "1e3"], &["2.5", "-0.125", "1e-3", "nan", "x"]),
+        ("bool", &["true", "false"], &["true", "false", "yes"]),
+        ("char", &["'a'", "'\\n'"], &["b", "bc", ""]),
+        ("&str", &["\"app\"", "r\"raw\"", "\"\""], &["hello", "with \"quotes\"", "é"]),
+        ("&'static str", &["\"app\""], &["hello"]),
+        ("&[u8]", &["b\"bytes\""], &["other"]),
+        ("Option<u8>", &["5"], &["6"]),
+        ("[u8; 2]", &["[1, 2]"], &["[3, 4]", "[5]"]),
+        ("Vec<Vec<u8>>", &["42"], &["1"]),
+    ];
+    let attrs = ["", "#[doc = \" The port.\"]", "/// The port.", "#[allow(dead_code)]", "#[cfg(all())] #[doc(hidden)]", "#[rustfmt::skip]"];
+    let visibilities = ["", "pub", "pub(crate)", "pub(super)", "pub(in crate)"];
+    let keywords = ["const", "static", "static mut"];
+    let args = ["", "no_doc", "emit_len", "emit_default, no_doc", "sensitive", "\"OTHER\"", "one_of(1, 2), no_doc", "required", "emit_var_name"];
+    for _ in 0..2000 {
+        let (ty, defaults, values) = shapes[rng.next() as usize % shapes.len()];
+        let item = format!("{} {} {} PORT: {} = {};", rng.pick(&attrs), rng.pick(&visibilities), rng.pick(&keywords), ty, rng.pick(defaults));
+        let item: TokenStream = item.parse().unwrap();
+        let attr: TokenStream = format!("({})", rng.pick(&args)).parse().unwrap();
+        let value = match rng.next() % 3 {
+            0 => None,
+            _ => Some(rng.pick(values)),
+        };
+        assert_same(attr, item, || {
+            let builder = TestEnv::builder();
+            match value {
+                Some(value) => builder.set("PORT", value).set("OTHER", value),
+                None => builder,
+            }
+        });
+    }
+}
+
+#[test]
+fn test_other_shapes_fall_back() {
+    let env = || TestEnv::builder().set("PORT", "8080");
+    let items = [
+        quote!(const PORT: u16 = 40 + 40;),
+        quote!(const PORT: u16 = -(80);),
+        quote!(const PORT: i16 = - -80;),
+        quote!(const PORT: bool = !false;),
+        quote!(const PORT: u16 = 80; const OTHER: u16 = 1;),
+        quote!(const PORT: u16 = 80),
+        quote!(const PORT = 80;),
+        quote!(const PORT: = 80;),
+        quote!(const fn port() -> u16 { 80 }),
+        quote!(static PORT: u16 = 80u17;),
+        quote!(#![allow(dead_code)] const PORT: u16 = 80;),
+        quote!(crate const PORT: u16 = 80;),
+        quote!(const _: u16 = 80;),
+        quote!(const r#PORT: u16 = 80;),
+        quote!(fn port() -> u16 { 80 }),
+    ];
+    for item in items {
+        assert_same(quote!(), item.clone(), env);
+        assert_same(quote!(("PORT", runtime_fallback)), item, env);
+    }
+}
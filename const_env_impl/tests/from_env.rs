@@ -1,15 +1,26 @@
-use const_env_impl::{from_env, TestEnv};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+
+use const_env_impl::{const_env_derive, consts_from_prefix, consumed_vars, env_lit, env_or, fingerprint, from_env, from_env_defaults, from_env_items, registry, EnvError, PrefixedEnv, ReadEnv, RecordingEnv, TestEnv, TestEnvBuilder};
 
 use proc_macro2::TokenStream;
 use quote::quote;
 
+/// Expands `from_env` with `attr` on `item` while `var_name` is set to `value`, as text
+/// to compare with `quote!` output.
+fn expand_var(var_name: &str, value: &str, attr: TokenStream, item: TokenStream) -> String {
+    let env = TestEnv::builder().set(var_name, value).build();
+    format!("{}", from_env(attr, item, env))
+}
+
 #[test]
 fn test_str() {
     let env = TestEnv::builder()
         .set("MYVAR", "world")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: &'static str = "Hello";
@@ -27,7 +38,7 @@ fn test_str_with_escapes() {
         .set("MYVAR", "world\\tfoo")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: &'static str = "Hello";
@@ -45,7 +56,7 @@ fn test_byte_str() {
         .set("MYVAR", "world")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: &'static [u8] = b"Hello";
@@ -63,7 +74,7 @@ fn test_u32() {
         .set("MYVAR", "1")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: u32 = 0;
@@ -81,7 +92,7 @@ fn test_u32_with_suffix() {
         .set("MYVAR", "1u32")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: u32 = 0;
@@ -99,7 +110,7 @@ fn test_i64() {
         .set("MYVAR", "1")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: i64 = 0;
@@ -117,7 +128,7 @@ fn test_i64_with_suffix() {
         .set("MYVAR", "1i64")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: i64 = 0;
@@ -135,7 +146,7 @@ fn test_i64_with_negative() {
         .set("MYVAR", "-1")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: i64 = -0;
@@ -153,7 +164,7 @@ fn test_char() {
         .set("MYVAR", "b")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: char = 'a';
@@ -171,7 +182,7 @@ fn test_byte() {
         .set("MYVAR", "b")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: u8 = b'a';
@@ -189,7 +200,7 @@ fn test_byte_with_escape() {
         .set("MYVAR", "\\n")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: u8 = b'\t';
@@ -207,7 +218,7 @@ fn test_f32() {
         .set("MYVAR", "1.0")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: f32 = 0.0;
@@ -225,7 +236,7 @@ fn test_f32_with_suffix() {
         .set("MYVAR", "1f32")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: f32 = 0.0;
@@ -243,7 +254,7 @@ fn test_f32_with_negative() {
         .set("MYVAR", "-1.0")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: f32 = -0.0;
@@ -261,7 +272,7 @@ fn test_bool() {
         .set("MYVAR", "true")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         const MYVAR: bool = false;
@@ -279,7 +290,7 @@ fn test_str_static() {
         .set("MYVAR", "world")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         static MYVAR: &'static str = "Hello";
@@ -297,7 +308,7 @@ fn test_i16_negative() {
         .set("MYVAR", "-123")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         static MYVAR: i16 = 0;
@@ -315,7 +326,7 @@ fn test_f32_negative() {
         .set("MYVAR", "-123.0")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         static MYVAR: f32 = 0.0;
@@ -332,7 +343,7 @@ fn test_default_name() {
     let env = TestEnv::builder()
         .set("MYVAR", "world")
         .build();
-    let attr: TokenStream = TokenStream::new();
+    let attr: TokenStream = quote!((no_doc));
     let item: TokenStream = quote! {
         static MYVAR: &'static str = "Hello";
     };
@@ -349,7 +360,7 @@ fn test_i32_negative_whitespace() {
         .set("MYVAR", " - 123 ")
         .build();
     let attr: TokenStream = quote! {
-        ("MYVAR")
+        ("MYVAR", no_doc)
     };
     let item: TokenStream = quote! {
         static MYVAR: i32 = 0;
@@ -359,4 +370,3768 @@ fn test_i32_negative_whitespace() {
     };
     let result = from_env(attr, item, env);
     assert_eq!(format!("{}", expected), format!("{}", result));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_str_case_lower() {
+    let env = TestEnv::builder()
+        .set("MYVAR", "Content-Type")
+        .build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", case = "lower", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: &'static str = "accept";
+    };
+    let expected: TokenStream = quote! {
+        const MYVAR: &'static str = "content-type";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_byte_str_case_upper() {
+    let env = TestEnv::builder()
+        .set("MYVAR", "get\\n")
+        .build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", case = "upper", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: &'static [u8] = b"POST";
+    };
+    let expected: TokenStream = quote! {
+        const MYVAR: &'static [u8] = b"GET\n";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_case_unset_keeps_default() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", case = "upper", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: &'static str = "Hello";
+    };
+    let expected: TokenStream = quote! {
+        const MYVAR: &'static str = "Hello";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_case_on_non_string_is_error() {
+    let env = TestEnv::builder()
+        .set("MYVAR", "1")
+        .build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", case = "lower")
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: u32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("`case` can only be used"), "{}", result);
+}
+
+#[test]
+fn test_case_unknown_value_is_error() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", case = "title")
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: &'static str = "Hello";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+}
+
+#[test]
+fn test_format() {
+    let env = TestEnv::builder()
+        .set("PGUSER", "admin")
+        .set("PGHOST", "db.internal")
+        .set("PGPORT", "5432")
+        .build();
+    let attr: TokenStream = quote! {
+        (format = "postgres://{PGUSER}@{PGHOST}:{PGPORT}/app", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const DSN: &'static str = "postgres://localhost/app";
+    };
+    let expected: TokenStream = quote! {
+        const DSN: &'static str = "postgres://admin@db.internal:5432/app";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_format_escapes_values_and_braces() {
+    let env = TestEnv::builder()
+        .set("NAME", "say \"hi\"\\n")
+        .build();
+    let attr: TokenStream = quote! {
+        (format = "{{{NAME}}}", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: &'static str = "";
+    };
+    let expected: TokenStream = quote! {
+        const MYVAR: &'static str = "{say \"hi\"\\n}";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_format_missing_var_keeps_default() {
+    let env = TestEnv::builder()
+        .set("PGHOST", "db.internal")
+        .build();
+    let attr: TokenStream = quote! {
+        (format = "{PGHOST}:{PGPORT}", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const ADDR: &'static str = "localhost:5432";
+    };
+    let expected: TokenStream = quote! {
+        const ADDR: &'static str = "localhost:5432";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_format_missing_var_required_is_error() {
+    let env = TestEnv::builder()
+        .set("PGHOST", "db.internal")
+        .build();
+    let attr: TokenStream = quote! {
+        (format = "{PGHOST}:{PGPORT}", required)
+    };
+    let item: TokenStream = quote! {
+        const ADDR: &'static str = "localhost:5432";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("`PGPORT`"), "{}", result);
+}
+
+#[test]
+fn test_format_invalid_placeholder_is_error() {
+    for template in ["{}", "{0}", "{HOST", "HOST}", "{HOST:x}"] {
+        let attr: TokenStream = quote! {
+            (format = #template)
+        };
+        let item: TokenStream = quote! {
+            const ADDR: &'static str = "localhost";
+        };
+        let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+        assert!(result.contains("compile_error"), "{}: {}", template, result);
+    }
+}
+
+#[test]
+fn test_required_unset_is_error() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", required)
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: u32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("`MYVAR` is required"), "{}", result);
+}
+
+#[test]
+fn test_placeholder() {
+    let env = TestEnv::builder()
+        .set("USER_NAME", "Ferris")
+        .build();
+    let attr: TokenStream = quote! {
+        ("USER_NAME", placeholder, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const GREETING: &'static str = "Hello, {}!";
+    };
+    let expected: TokenStream = quote! {
+        const GREETING: &'static str = "Hello, Ferris!";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_placeholder_unset_keeps_default() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("USER_NAME", placeholder, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const GREETING: &'static str = "Hello, {}!";
+    };
+    let expected: TokenStream = quote! {
+        const GREETING: &'static str = "Hello, {}!";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_placeholder_count_is_checked() {
+    for default in ["Hello!", "{} and {}"] {
+        let env = TestEnv::builder()
+            .set("USER_NAME", "Ferris")
+            .build();
+        let attr: TokenStream = quote! {
+            ("USER_NAME", placeholder)
+        };
+        let item: TokenStream = quote! {
+            const GREETING: &'static str = #default;
+        };
+        let result = format!("{}", from_env(attr, item, env));
+        assert!(result.contains("compile_error"), "{}: {}", default, result);
+    }
+}
+
+#[test]
+fn test_expand() {
+    let env = TestEnv::builder()
+        .set("API_URL", "https://${API_HOST}:$API_PORT/v1")
+        .set("API_HOST", "${API_DOMAIN}")
+        .set("API_DOMAIN", "example.com")
+        .set("API_PORT", "8443")
+        .build();
+    let attr: TokenStream = quote! {
+        ("API_URL", expand, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const API_URL: &'static str = "http://localhost/v1";
+    };
+    let expected: TokenStream = quote! {
+        const API_URL: &'static str = "https://example.com:8443/v1";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_expand_dollar_escape() {
+    let env = TestEnv::builder()
+        .set("PRICE", "$$5")
+        .build();
+    let attr: TokenStream = quote! {
+        ("PRICE", expand, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const PRICE: &'static str = "";
+    };
+    let expected: TokenStream = quote! {
+        const PRICE: &'static str = "$5";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_expand_missing_reference() {
+    let attr: TokenStream = quote! {
+        ("API_URL", expand, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const API_URL: &'static str = "";
+    };
+    let env = TestEnv::builder()
+        .set("API_URL", "https://${API_HOST}/v1")
+        .build();
+    let result = format!("{}", from_env(attr, item.clone(), env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("`API_HOST` is not set"), "{}", result);
+
+    let attr: TokenStream = quote! {
+        ("API_URL", expand, expand_missing = "empty", no_doc)
+    };
+    let expected: TokenStream = quote! {
+        const API_URL: &'static str = "https:///v1";
+    };
+    let env = TestEnv::builder()
+        .set("API_URL", "https://${API_HOST}/v1")
+        .build();
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_expand_cycle_is_error() {
+    let env = TestEnv::builder()
+        .set("A", "${B}")
+        .set("B", "x${A}")
+        .build();
+    let attr: TokenStream = quote! {
+        ("A", expand)
+    };
+    let item: TokenStream = quote! {
+        const A: &'static str = "";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("A -> B -> A"), "{}", result);
+}
+
+#[test]
+fn test_per_target() {
+    let env = TestEnv::builder()
+        .set("TARGET", "thumbv7em-none-eabihf")
+        .set("STACK_SIZE_thumbv7em_none_eabihf", "4096")
+        .set("STACK_SIZE", "65536")
+        .build();
+    let attr: TokenStream = quote! {
+        ("STACK_SIZE", per_target, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const STACK_SIZE: usize = 1024;
+    };
+    let expected: TokenStream = quote! {
+        const STACK_SIZE: usize = 4096;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_per_target_falls_back_to_plain_name() {
+    let env = TestEnv::builder()
+        .set("TARGET", "x86_64-unknown-linux-gnu")
+        .set("STACK_SIZE", "65536")
+        .build();
+    let attr: TokenStream = quote! {
+        ("STACK_SIZE", per_target, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const STACK_SIZE: usize = 1024;
+    };
+    let expected: TokenStream = quote! {
+        const STACK_SIZE: usize = 65536;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_per_target_required_names_both_variables() {
+    let env = TestEnv::builder()
+        .set("TARGET", "wasm32-wasi")
+        .build();
+    let attr: TokenStream = quote! {
+        ("STACK_SIZE", per_target, required)
+    };
+    let item: TokenStream = quote! {
+        const STACK_SIZE: usize = 1024;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`STACK_SIZE_wasm32_wasi` or `STACK_SIZE`"), "{}", result);
+}
+
+#[test]
+fn test_debug_and_release_vars() {
+    let env = TestEnv::builder()
+        .set("LOG_BUF_DEBUG", "4096")
+        .set("LOG_BUF_RELEASE", "256")
+        .build();
+    let attr: TokenStream = quote! {
+        ("LOG_BUF", debug_var = "LOG_BUF_DEBUG", release_var = "LOG_BUF_RELEASE", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const LOG_BUF: usize = 1024;
+    };
+    let expected: TokenStream = quote! {
+        const LOG_BUF: usize = if ::core::cfg!(debug_assertions) { 4096 } else { 256 };
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_debug_var_falls_back_to_generic_then_default() {
+    let env = TestEnv::builder()
+        .set("LOG_BUF_DEBUG", "4096")
+        .build();
+    let attr: TokenStream = quote! {
+        ("LOG_BUF", debug_var = "LOG_BUF_DEBUG", release_var = "LOG_BUF_RELEASE", allow_warnings, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const LOG_BUF: usize = 1024;
+    };
+    let expected: TokenStream = quote! {
+        const LOG_BUF: usize = if ::core::cfg!(debug_assertions) { 4096 } else { 1024 };
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("LOG_BUF_DEBUG", "4096")
+        .set("LOG_BUF", "512")
+        .build();
+    let expected: TokenStream = quote! {
+        const LOG_BUF: usize = if ::core::cfg!(debug_assertions) { 4096 } else { 512 };
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder().build();
+    let result = from_env(attr, item.clone(), env);
+    assert_eq!(format!("{}", item), format!("{}", result));
+}
+
+#[test]
+fn test_expr() {
+    let env = TestEnv::builder()
+        .set("RETRY_POLICY", "Policy::Exponential { base_ms: 50, max: 10 }")
+        .build();
+    let attr: TokenStream = quote! {
+        ("RETRY_POLICY", expr, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const RETRY_POLICY: Policy = Policy::Never;
+    };
+    let expected: TokenStream = quote! {
+        const RETRY_POLICY: Policy = Policy::Exponential { base_ms: 50, max: 10 };
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_expr_parse_failure_quotes_value() {
+    let env = TestEnv::builder()
+        .set("RETRY_POLICY", "Policy::{")
+        .build();
+    let attr: TokenStream = quote! {
+        ("RETRY_POLICY", expr)
+    };
+    let item: TokenStream = quote! {
+        const RETRY_POLICY: Policy = Policy::Never;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("`RETRY_POLICY`"), "{}", result);
+    assert!(result.contains("Policy::{"), "{}", result);
+}
+
+#[test]
+fn test_ignore_case() {
+    let env = TestEnv::builder()
+        .set("app_port", "9090")
+        .build();
+    let attr: TokenStream = quote! {
+        ("APP_PORT", ignore_case, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const APP_PORT: u16 = 8080;
+    };
+    let expected: TokenStream = quote! {
+        const APP_PORT: u16 = 9090;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_ignore_case_prefers_exact_match() {
+    let env = TestEnv::builder()
+        .set("app_port", "9090")
+        .set("APP_PORT", "7070")
+        .build();
+    let attr: TokenStream = quote! {
+        ("APP_PORT", ignore_case, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const APP_PORT: u16 = 8080;
+    };
+    let expected: TokenStream = quote! {
+        const APP_PORT: u16 = 7070;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_ignore_case_ambiguous_is_error() {
+    let env = TestEnv::builder()
+        .set("app_port", "9090")
+        .set("App_Port", "7070")
+        .build();
+    let attr: TokenStream = quote! {
+        ("APP_PORT", ignore_case)
+    };
+    let item: TokenStream = quote! {
+        const APP_PORT: u16 = 8080;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+}
+
+#[test]
+fn test_ignore_case_crate_wide_toggle() {
+    let item: TokenStream = quote! {
+        const APP_PORT: u16 = 8080;
+    };
+    let env = TestEnv::builder()
+        .set("app_port", "9090")
+        .build();
+    let result = from_env(quote!((no_doc)), item.clone(), env);
+    assert_eq!(format!("{}", item), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("app_port", "9090")
+        .set("CONST_ENV_IGNORE_CASE", "1")
+        .build();
+    let expected: TokenStream = quote! {
+        const APP_PORT: u16 = 9090;
+    };
+    let result = from_env(quote!((no_doc)), item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_exists() {
+    let attr: TokenStream = quote! {
+        ("ENABLE_TELEMETRY", exists, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const TELEMETRY: bool = false;
+    };
+    let expected: TokenStream = quote! {
+        const TELEMETRY: bool = true;
+    };
+    let env = TestEnv::builder()
+        .set("ENABLE_TELEMETRY", "")
+        .build();
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder().build();
+    let result = from_env(attr, item.clone(), env);
+    assert_eq!(format!("{}", item), format!("{}", result));
+}
+
+#[test]
+fn test_exists_inverted() {
+    let env = TestEnv::builder()
+        .set("NO_TELEMETRY", "not a bool")
+        .build();
+    let attr: TokenStream = quote! {
+        ("NO_TELEMETRY", exists, invert, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const TELEMETRY: bool = true;
+    };
+    let expected: TokenStream = quote! {
+        const TELEMETRY: bool = false;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_invert() {
+    let env = TestEnv::builder()
+        .set("NO_COLOR", "true")
+        .build();
+    let attr: TokenStream = quote! {
+        ("NO_COLOR", invert, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const COLOR: bool = true;
+    };
+    let expected: TokenStream = quote! {
+        const COLOR: bool = false;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_exists_on_non_bool_is_error() {
+    let env = TestEnv::builder()
+        .set("ENABLE_TELEMETRY", "1")
+        .build();
+    let attr: TokenStream = quote! {
+        ("ENABLE_TELEMETRY", exists)
+    };
+    let item: TokenStream = quote! {
+        const TELEMETRY: u32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("`exists` can only be used"), "{}", result);
+}
+
+fn write_temp_file(name: &str, contents: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("const_env_impl_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_file() {
+    let path = write_temp_file("token.txt", "s3cr3t\n");
+    let env = TestEnv::builder()
+        .set("TOKEN_PATH", path.to_str().unwrap())
+        .build();
+    let attr: TokenStream = quote! {
+        ("TOKEN_PATH", file, trim_newline, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const TOKEN: &'static str = "";
+    };
+    // The file is included next to the item, so that rustc rebuilds when it changes.
+    let absolute = path.to_str().unwrap();
+    let expected: TokenStream = quote! {
+        const TOKEN: &'static str = "s3cr3t";
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_file_relative_to_manifest_dir() {
+    let path = write_temp_file("workers.txt", "16");
+    let env = TestEnv::builder()
+        .set("CARGO_MANIFEST_DIR", path.parent().unwrap().to_str().unwrap())
+        .set("WORKERS_PATH", "workers.txt")
+        .build();
+    let attr: TokenStream = quote! {
+        ("WORKERS_PATH", file, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const WORKERS: u32 = 4;
+    };
+    let expected: TokenStream = quote! {
+        const WORKERS: u32 = 16;
+        const _: &[u8] = ::core::include_bytes!(::core::concat!(::core::env!("CARGO_MANIFEST_DIR"), "/", "workers.txt"));
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_file_missing_reports_resolved_path() {
+    let env = TestEnv::builder()
+        .set("CARGO_MANIFEST_DIR", "/nonexistent/crate")
+        .set("TOKEN_PATH", "secrets/token.txt")
+        .build();
+    let attr: TokenStream = quote! {
+        ("TOKEN_PATH", file)
+    };
+    let item: TokenStream = quote! {
+        const TOKEN: &'static str = "";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("/nonexistent/crate/secrets/token.txt"), "{}", result);
+}
+
+#[test]
+fn test_file_fallback() {
+    let path = write_temp_file("db_password.txt", "hunter2\n");
+    let env = TestEnv::builder()
+        .set("DB_PASSWORD_FILE", path.to_str().unwrap())
+        .build();
+    let attr: TokenStream = quote! {
+        ("DB_PASSWORD", file_fallback, trim_newline, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const DB_PASSWORD: &'static str = "";
+    };
+    let absolute = path.to_str().unwrap();
+    let expected: TokenStream = quote! {
+        const DB_PASSWORD: &'static str = "hunter2";
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_file_fallback_prefers_direct_variable() {
+    let path = write_temp_file("db_user.txt", "from_file");
+    let env = TestEnv::builder()
+        .set("DB_USER", "direct")
+        .set("DB_USER_FILE", path.to_str().unwrap())
+        .build();
+    let attr: TokenStream = quote! {
+        ("DB_USER", file_fallback, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const DB_USER: &'static str = "";
+    };
+    let expected: TokenStream = quote! {
+        const DB_USER: &'static str = "direct";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_file_fallback_error_names_source() {
+    let env = TestEnv::builder()
+        .set("CONST_ENV_FILE_FALLBACK", "1")
+        .set("DB_HOST_FILE", "/nonexistent/db_host.txt")
+        .build();
+    let item: TokenStream = quote! {
+        const DB_HOST: &'static str = "";
+    };
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("/nonexistent/db_host.txt"), "{}", result);
+    assert!(result.contains("`DB_HOST_FILE`"), "{}", result);
+}
+
+#[test]
+fn test_include_bytes() {
+    let path = write_temp_file("schema.bin", "schema");
+    let dir = path.parent().unwrap().to_str().unwrap();
+    let env = TestEnv::builder()
+        .set("CARGO_MANIFEST_DIR", dir)
+        .set("SCHEMA_PATH", "schema.bin")
+        .build();
+    let attr: TokenStream = quote! {
+        ("SCHEMA_PATH", include, no_doc)
+    };
+    let item: TokenStream = quote! {
+        static SCHEMA: &'static [u8] = b"";
+    };
+    let expected: TokenStream = quote! {
+        static SCHEMA: &'static [u8] = ::core::include_bytes!(::core::concat!(::core::env!("CARGO_MANIFEST_DIR"), "/", "schema.bin"));
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_include_str_absolute() {
+    let path = write_temp_file("schema.sql", "CREATE TABLE t ();");
+    let path = path.to_str().unwrap();
+    let env = TestEnv::builder()
+        .set("SCHEMA_PATH", path)
+        .build();
+    let attr: TokenStream = quote! {
+        ("SCHEMA_PATH", include, no_doc)
+    };
+    let item: TokenStream = quote! {
+        static SCHEMA: &'static str = "";
+    };
+    let expected: TokenStream = quote! {
+        static SCHEMA: &'static str = ::core::include_str!(#path);
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_include_missing_file_is_error() {
+    let env = TestEnv::builder()
+        .set("CARGO_MANIFEST_DIR", "/nonexistent/crate")
+        .set("SCHEMA_PATH", "schema.sql")
+        .build();
+    let attr: TokenStream = quote! {
+        ("SCHEMA_PATH", include)
+    };
+    let item: TokenStream = quote! {
+        static SCHEMA: &'static str = "";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("/nonexistent/crate/schema.sql"), "{}", result);
+}
+
+#[test]
+fn test_include_expr() {
+    let path = write_temp_file("include_expr_table.rs", "[\n    1, 2,\n    3, 4,\n]\n");
+    let dir = path.parent().unwrap().to_str().unwrap();
+    let env = |file: &str| TestEnv::builder()
+        .set("CARGO_MANIFEST_DIR", dir)
+        .set("TABLE_FILE", file)
+        .build();
+    let attr: TokenStream = quote! {
+        ("TABLE_FILE", include_expr, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const TABLE: [u8; 4] = [0; 4];
+    };
+    // The file is included next to the item, so that rustc rebuilds when it changes.
+    // Relative paths are resolved against the crate's directory.
+    let expected: TokenStream = quote! {
+        const TABLE: [u8; 4] = [1, 2, 3, 4,];
+        const _: &[u8] = ::core::include_bytes!(::core::concat!(::core::env!("CARGO_MANIFEST_DIR"), "/", "include_expr_table.rs"));
+    };
+    assert_eq!(format!("{}", expected), format!("{}", from_env(attr.clone(), item.clone(), env("include_expr_table.rs"))));
+    let absolute = path.to_str().unwrap();
+    let expected: TokenStream = quote! {
+        const TABLE: [u8; 4] = [1, 2, 3, 4,];
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    assert_eq!(format!("{}", expected), format!("{}", from_env(attr.clone(), item.clone(), env(absolute))));
+
+    // The written default is kept while the variable is unset.
+    let result = from_env(attr.clone(), item.clone(), TestEnv::builder().build());
+    assert_eq!(format!("{}", item), format!("{}", result));
+
+    let result = format!("{}", from_env(attr.clone(), item.clone(), env("include_expr_missing.rs")));
+    assert!(result.contains("Failed to read file"), "{}", result);
+    assert!(result.contains("include_expr_missing.rs"), "{}", result);
+
+    let result = format!("{}", from_env(quote!((include_expr, expr)), quote!(const TABLE: u8 = 0;), env("include_expr_table.rs")));
+    assert!(result.contains("`include_expr` cannot be combined with `expr`"), "{}", result);
+}
+
+#[test]
+fn test_include_expr_parse_error_location() {
+    let path = write_temp_file("include_expr_invalid.rs", "Config {\n    retries: 3,\n    backoff: = 10,\n}\n");
+    let env = TestEnv::builder()
+        .set("CONFIG_FILE", path.to_str().unwrap())
+        .build();
+    let result = format!("{}", from_env(quote!(("CONFIG_FILE", include_expr)), quote!(const CONFIG: Config = Config::DEFAULT;), env));
+    assert!(result.contains("[CE0002] Failed to parse file"), "{}", result);
+    assert!(result.contains("as an expression: expected expression"), "{}", result);
+    assert!(result.contains(&format!("--> {}:3:14", path.display())), "{}", result);
+
+    // Errors are located within the file's own text, at the end of a group, and at an
+    // unterminated literal or unbalanced delimiter.
+    let location = |name: &str, contents: &str| {
+        let path = write_temp_file(name, contents);
+        let env = TestEnv::builder()
+            .set("CONFIG_FILE", path.to_str().unwrap())
+            .build();
+        let result = format!("{}", from_env(quote!(("CONFIG_FILE", include_expr)), quote!(const CONFIG: Config = Config::DEFAULT;), env));
+        result.split(&format!("--> {}", path.display())).nth(1).unwrap_or_else(|| panic!("{}", result)).split('"').next().unwrap().to_string()
+    };
+    assert_eq!(":2:13", location("include_expr_nested.rs", "[\n    (1, \"é\" 2),\n]"));
+    assert_eq!(":3:1", location("include_expr_group_end.rs", "Config {\n    retries: /* unset */\n}"));
+    assert_eq!(":1:12", location("include_expr_trailing.rs", "r#\"(\"# + 1 2"));
+    assert_eq!(":2:5", location("include_expr_unterminated.rs", "[\n    \"abc,\n]"));
+    assert_eq!(":1:7", location("include_expr_unbalanced.rs", "[1, (2]"));
+
+    // Without a token to point at, the error names the file alone.
+    let path = write_temp_file("include_expr_empty.rs", "");
+    let env = TestEnv::builder()
+        .set("CONFIG_FILE", path.to_str().unwrap())
+        .build();
+    let result = format!("{}", from_env(quote!(("CONFIG_FILE", include_expr)), quote!(const CONFIG: Config = Config::DEFAULT;), env));
+    assert!(result.contains(&format!("--> {}", path.display())), "{}", result);
+}
+
+#[test]
+fn test_when() {
+    let env = TestEnv::builder()
+        .set("TICK_HZ", "1000")
+        .build();
+    let attr: TokenStream = quote! {
+        ("TICK_HZ", when(all(target_os = "none", not(feature = "std"))), no_doc)
+    };
+    let item: TokenStream = quote! {
+        const TICK_HZ: u32 = 100;
+    };
+    let expected: TokenStream = quote! {
+        const TICK_HZ: u32 = if ::core::cfg!(all(target_os = "none", not(feature = "std"))) { 1000 } else { 100 };
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_when_unset_keeps_item() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("TICK_HZ", when(unix), no_doc)
+    };
+    let item: TokenStream = quote! {
+        const TICK_HZ: u32 = 100;
+    };
+    let result = from_env(attr, item.clone(), env);
+    assert_eq!(format!("{}", item), format!("{}", result));
+}
+
+#[test]
+fn test_sensitive_value_is_redacted_from_errors() {
+    let attr: TokenStream = quote! {
+        ("API_KEY", expr)
+    };
+    let item: TokenStream = quote! {
+        const API_KEY: Key = Key::None;
+    };
+    let env = TestEnv::builder()
+        .set("API_KEY", "Key::{hunter2")
+        .build();
+    let result = format!("{}", from_env(attr, item.clone(), env));
+    assert!(result.contains("hunter2"), "{}", result);
+
+    let attr: TokenStream = quote! {
+        ("API_KEY", expr, sensitive)
+    };
+    let env = TestEnv::builder()
+        .set("API_KEY", "Key::{hunter2")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(!result.contains("hunter2"), "{}", result);
+    assert!(result.contains("<redacted> (13 bytes)"), "{}", result);
+}
+
+#[test]
+fn test_sensitive_value_is_still_embedded() {
+    let env = TestEnv::builder()
+        .set("API_KEY", "hunter2")
+        .build();
+    let attr: TokenStream = quote! {
+        ("API_KEY", sensitive, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const API_KEY: &'static str = "";
+    };
+    let expected: TokenStream = quote! {
+        const API_KEY: &'static str = "hunter2";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_warn_redundant() {
+    for (value, default) in [("16", quote!(0x10)), ("-0", quote!(0)), ("\"a\"", quote!("a")), ("[1, 2]", quote!([1, 2]))] {
+        let env = TestEnv::builder()
+            .set("MYVAR", value)
+            .build();
+        let attr: TokenStream = quote! {
+            ("MYVAR", expr, warn_redundant, no_doc)
+        };
+        let item: TokenStream = quote! {
+            const MYVAR: T = #default;
+        };
+        let new_expr: TokenStream = value.parse().unwrap();
+        let expected: TokenStream = quote! {
+            const MYVAR: T = #new_expr;
+            const _: () = {
+                #[deprecated(note = "`MYVAR` is set to the same value as the default of `MYVAR`")]
+                #[allow(non_upper_case_globals)]
+                const warning: () = ();
+                let _ = warning;
+            };
+        };
+        let result = from_env(attr, item, env);
+        assert_eq!(format!("{}", expected), format!("{}", result));
+    }
+}
+
+#[test]
+fn test_rename_all_screaming_snake_case() {
+    let cases = [
+        (quote!(HTTPServerPort), "HTTP_SERVER_PORT"),
+        (quote!(maxConnections), "MAX_CONNECTIONS"),
+        (quote!(APP_PORT), "APP_PORT"),
+        (quote!(app_port), "APP_PORT"),
+        (quote!(Ipv6Enabled), "IPV6_ENABLED"),
+        (quote!(TLS13Only), "TLS13_ONLY"),
+        (quote!(r#type), "TYPE"),
+    ];
+    for (ident, var_name) in cases {
+        let env = TestEnv::builder()
+            .set(var_name, "1")
+            .build();
+        let attr: TokenStream = quote! {
+            (rename_all = "SCREAMING_SNAKE_CASE", no_doc)
+        };
+        let item: TokenStream = quote! {
+            static #ident: u16 = 0;
+        };
+        let expected: TokenStream = quote! {
+            static #ident: u16 = 1;
+        };
+        let result = from_env(attr, item, env);
+        assert_eq!(format!("{}", expected), format!("{}", result), "{}", var_name);
+    }
+}
+
+#[test]
+fn test_raw_ident_default_name() {
+    let env = TestEnv::builder()
+        .set("type", "json")
+        .build();
+    let item: TokenStream = quote! {
+        static r#type: &'static str = "text";
+    };
+    let expected: TokenStream = quote! {
+        static r#type: &'static str = "json";
+    };
+    let result = from_env(quote!((no_doc)), item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_unknown_argument_suggests_known_one() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", requird)
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: u32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("did you mean `required`?"), "{}", result);
+}
+
+#[test]
+fn test_unknown_argument_lists_expected() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", colour = "red")
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: u32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("Unknown attribute argument `colour`, expected one of `case`"), "{}", result);
+}
+
+#[test]
+fn test_argument_in_wrong_form() {
+    let cases = [
+        (quote!(("MYVAR", required = true)), "`required` is a flag"),
+        (quote!(("MYVAR", case)), "`case` expects a value"),
+        (quote!(("MYVAR", when = "unix")), "`when` expects arguments"),
+    ];
+    for (attr, message) in cases {
+        let item: TokenStream = quote! {
+            const MYVAR: &'static str = "";
+        };
+        let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+        assert!(result.contains(message), "{}", result);
+    }
+}
+
+#[test]
+fn test_duplicate_argument_mentions_both() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        ("MYVAR", case = "lower", required, case = "upper")
+    };
+    let item: TokenStream = quote! {
+        const MYVAR: &'static str = "";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("Duplicate attribute argument `case`"), "{}", result);
+    assert!(result.contains("`case` is first specified here"), "{}", result);
+}
+
+#[test]
+fn test_out_file() {
+    let path = write_temp_file("probe_result.txt", "  42\n");
+    let env = TestEnv::builder()
+        .set("OUT_DIR", path.parent().unwrap().to_str().unwrap())
+        .build();
+    let attr: TokenStream = quote! {
+        (out_file = "probe_result.txt")
+    };
+    let item: TokenStream = quote! {
+        const PROBED: u32 = 0;
+    };
+    let expected: TokenStream = quote! {
+        const PROBED: u32 = 42;
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_out_file_missing() {
+    let path = write_temp_file("probe_other.txt", "");
+    let out_dir = path.parent().unwrap().to_str().unwrap();
+    let item: TokenStream = quote! {
+        const PROBED: u32 = 0;
+    };
+    let attr: TokenStream = quote! {
+        (out_file = "probe_missing.txt")
+    };
+    let env = TestEnv::builder()
+        .set("OUT_DIR", out_dir)
+        .build();
+    let result = from_env(attr, item.clone(), env);
+    assert_eq!(format!("{}", item), format!("{}", result));
+
+    let attr: TokenStream = quote! {
+        (out_file = "probe_missing.txt", required)
+    };
+    let env = TestEnv::builder()
+        .set("OUT_DIR", out_dir)
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("compile_error"), "{}", result);
+}
+
+#[test]
+fn test_out_file_without_out_dir_is_error() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        (out_file = "probe_result.txt")
+    };
+    let item: TokenStream = quote! {
+        const PROBED: u32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`OUT_DIR` is not set"), "{}", result);
+}
+
+#[cfg(not(feature = "command-source"))]
+#[test]
+fn test_command_requires_feature() {
+    let env = TestEnv::builder().build();
+    let attr: TokenStream = quote! {
+        (command = "git rev-parse --short HEAD")
+    };
+    let item: TokenStream = quote! {
+        const REVISION: &str = "unknown";
+    };
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`command-source` feature"), "{}", result);
+}
+
+#[cfg(feature = "command-source")]
+#[test]
+fn test_command_used_when_unset() {
+    let attr: TokenStream = quote! {
+        (command = "echo  hello   world")
+    };
+    let item: TokenStream = quote! {
+        const GREETING: &str = "unknown";
+    };
+    let expected: TokenStream = quote! {
+        const GREETING: &str = "hello world";
+    };
+    let result = from_env(attr.clone(), item.clone(), TestEnv::builder().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("GREETING", "from env")
+        .build();
+    let expected: TokenStream = quote! {
+        const GREETING: &str = "from env";
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[cfg(feature = "command-source")]
+#[test]
+fn test_command_failure_keeps_default() {
+    let item: TokenStream = quote! {
+        const REVISION: &str = "unknown";
+    };
+    for command in &["false", "const-env-no-such-binary --version"] {
+        let attr: TokenStream = quote! {
+            (command = #command)
+        };
+        let result = from_env(attr, item.clone(), TestEnv::builder().build());
+        assert_eq!(format!("{}", item), format!("{}", result));
+
+        let attr: TokenStream = quote! {
+            (command = #command, required)
+        };
+        let result = format!("{}", from_env(attr, item.clone(), TestEnv::builder().build()));
+        assert!(result.contains("compile_error"), "{}", result);
+    }
+}
+
+#[test]
+fn test_config_defaults() {
+    let path = write_temp_file("defaults.toml", concat!(
+        "# Defaults for local builds\n",
+        "BUF_SIZE = 4_096\n",
+        "RATIO = 0.5 # inline comment\n",
+        "SCALE = 2\n",
+        "VERBOSE = true\n",
+        "GREETING = \"hello\"\n",
+        "'PATH_SEP' = '/'\n",
+    ));
+    let config = || TestEnv::builder().set("CONST_ENV_CONFIG", path.to_str().unwrap());
+    // The file is included next to each item that consulted it, so that rustc rebuilds
+    // when it changes.
+    let absolute = path.to_str().unwrap();
+
+    let item: TokenStream = quote! {
+        const BUF_SIZE: usize = 512;
+    };
+    let expected: TokenStream = quote! {
+        const BUF_SIZE: usize = 4_096;
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item.clone(), config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let expected: TokenStream = quote! {
+        const BUF_SIZE: usize = 1024;
+    };
+    let result = from_env(quote!((no_doc)), item, config().set("BUF_SIZE", "1024").build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        const RATIO: f64 = 1.0;
+    };
+    let expected: TokenStream = quote! {
+        const RATIO: f64 = 0.5;
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item, config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        const SCALE: f32 = 1.5;
+    };
+    let expected: TokenStream = quote! {
+        const SCALE: f32 = 2.0;
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item, config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        static VERBOSE: bool = false;
+    };
+    let expected: TokenStream = quote! {
+        static VERBOSE: bool = true;
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item, config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        const GREETING: &str = "hi";
+    };
+    let expected: TokenStream = quote! {
+        const GREETING: &str = "hello";
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item, config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        const PATH_SEP: char = '\\';
+    };
+    let expected: TokenStream = quote! {
+        const PATH_SEP: char = '/';
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item, config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        const ABSENT: u32 = 7;
+    };
+    let expected: TokenStream = quote! {
+        #item
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item.clone(), config().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // The variable naming the file is tracked too, whether it is set or not.
+    let expected: TokenStream = quote! {
+        #item
+        const _: ::core::option::Option<&str> = ::core::option_env!("CONST_ENV_CONFIG");
+        const _: ::core::option::Option<&str> = ::core::option_env!("ABSENT");
+        const _: ::core::option::Option<&str> = ::core::option_env!("CONST_ENV_DOTENV");
+    };
+    let result = from_env(quote!((no_doc)), item, ProcessEnv(TestEnv::builder().build()));
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_config_type_mismatch_is_error() {
+    let path = write_temp_file("mismatch.toml", "BUF_SIZE = \"large\"\n");
+    let env = TestEnv::builder()
+        .set("CONST_ENV_CONFIG", path.to_str().unwrap())
+        .build();
+    let item: TokenStream = quote! {
+        const BUF_SIZE: usize = 512;
+    };
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("Key `BUF_SIZE`"), "{}", result);
+    assert!(result.contains("is a string"), "{}", result);
+}
+
+#[test]
+fn test_config_parse_error_names_path_and_key() {
+    let path = write_temp_file("invalid.toml", "OK = 1\nBROKEN = \"unterminated\n");
+    let env = TestEnv::builder()
+        .set("CONST_ENV_CONFIG", path.to_str().unwrap())
+        .build();
+    let item: TokenStream = quote! {
+        const OK: u32 = 0;
+    };
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("invalid.toml"), "{}", result);
+    assert!(result.contains("line 2, key `BROKEN`"), "{}", result);
+}
+
+#[test]
+fn test_min_max_scalar() {
+    let attr: TokenStream = quote! {
+        (min = 1, max = 100, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const PERCENT: u8 = 50;
+    };
+    let env = TestEnv::builder()
+        .set("PERCENT", "100")
+        .build();
+    let expected: TokenStream = quote! {
+        const PERCENT: u8 = 100;
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("PERCENT", "101")
+        .build();
+    let result = format!("{}", from_env(attr.clone(), item.clone(), env));
+    assert!(result.contains("`PERCENT` is `101`, which is above the maximum of 100"), "{}", result);
+
+    let env = TestEnv::builder()
+        .set("PERCENT", "0")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("which is below the minimum of 1"), "{}", result);
+}
+
+#[test]
+fn test_min_max_element_wise() {
+    let attr: TokenStream = quote! {
+        ("WEIGHTS", min = 0, max = 100, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const WEIGHTS: [u8; 4] = [25, 25, 25, 25];
+    };
+    let env = TestEnv::builder()
+        .set("WEIGHTS", "[10, 20, 30, 40]")
+        .build();
+    let expected: TokenStream = quote! {
+        const WEIGHTS: [u8; 4] = [10, 20, 30, 40];
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("WEIGHTS", "[10, 20, 130, 40]")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("Element 2 of environment variable `WEIGHTS` is `130`, which is above the maximum of 100"), "{}", result);
+}
+
+#[test]
+fn test_one_of_slice_elements() {
+    let attr: TokenStream = quote! {
+        (one_of("debug", "info", "warn"), no_doc)
+    };
+    let item: TokenStream = quote! {
+        const LEVELS: &[&str] = &["info"];
+    };
+    let env = TestEnv::builder()
+        .set("LEVELS", r#"["warn", "debug"]"#)
+        .build();
+    let expected: TokenStream = quote! {
+        const LEVELS: &[&str] = &["warn", "debug"];
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("LEVELS", r#"["warn", "trace"]"#)
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("Element 1 of environment variable `LEVELS` is `\\\"trace\\\"`"), "{}", result);
+}
+
+#[test]
+fn test_validation_redacts_sensitive_values() {
+    let attr: TokenStream = quote! {
+        (max = 10, sensitive)
+    };
+    let item: TokenStream = quote! {
+        const PIN: u32 = 0;
+    };
+    let env = TestEnv::builder()
+        .set("PIN", "1234")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`PIN` is <redacted>, which is above the maximum of 10"), "{}", result);
+    assert!(!result.contains("1234"), "{}", result);
+}
+
+#[test]
+fn test_min_greater_than_max_is_error() {
+    let attr: TokenStream = quote! {
+        (min = 10, max = -1)
+    };
+    let item: TokenStream = quote! {
+        const LIMIT: i32 = 0;
+    };
+    let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+    assert!(result.contains("`min` cannot be greater than `max`"), "{}", result);
+}
+
+#[test]
+fn test_item_count_bounds() {
+    let attr: TokenStream = quote! {
+        (min_items = 1, max_items = 3, no_doc)
+    };
+    let item: TokenStream = quote! {
+        static HOSTS: &[&str] = &["localhost"];
+    };
+    let env = TestEnv::builder()
+        .set("HOSTS", r#"["a", "b", "c"]"#)
+        .build();
+    let expected: TokenStream = quote! {
+        static HOSTS: &[&str] = &["a", "b", "c"];
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("HOSTS", "[]")
+        .build();
+    let result = format!("{}", from_env(attr.clone(), item.clone(), env));
+    assert!(result.contains("`HOSTS` has 0 items, fewer than the minimum of 1"), "{}", result);
+
+    let env = TestEnv::builder()
+        .set("HOSTS", r#"["a", "b", "c", "d"]"#)
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`HOSTS` has 4 items, more than the maximum of 3"), "{}", result);
+}
+
+#[test]
+fn test_empty_list_allowed_without_min_items() {
+    let attr: TokenStream = quote! {
+        (max_items = 16, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const PORTS: &[u16] = &[80];
+    };
+    let env = TestEnv::builder()
+        .set("PORTS", "[]")
+        .build();
+    let expected: TokenStream = quote! {
+        const PORTS: &[u16] = &[];
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_item_count_requires_list_default() {
+    let attr: TokenStream = quote! {
+        (min_items = 1)
+    };
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+    assert!(result.contains("`min_items` can only be used on items with an array or slice default"), "{}", result);
+}
+
+#[test]
+fn test_sort_and_dedup_slice() {
+    let attr: TokenStream = quote! {
+        (sort, dedup, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const FEATURES: &[&str] = &[];
+    };
+    let env = TestEnv::builder()
+        .set("FEATURES", r#"["simd", "alloc", "simd", "std"]"#)
+        .build();
+    let expected: TokenStream = quote! {
+        const FEATURES: &[&str] = &["alloc", "simd", "std"];
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_sort_numbers_by_value() {
+    let attr: TokenStream = quote! {
+        (sort, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const OFFSETS: [i32; 4] = [0, 0, 0, 0];
+    };
+    let env = TestEnv::builder()
+        .set("OFFSETS", "[10, -2, 0x3, 9]")
+        .build();
+    let expected: TokenStream = quote! {
+        const OFFSETS: [i32; 4] = [-2, 0x3, 9, 10];
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_dedup_keeps_first_occurrence() {
+    let attr: TokenStream = quote! {
+        (dedup, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const PORTS: &[u16] = &[];
+    };
+    let env = TestEnv::builder()
+        .set("PORTS", "[443, 80, 443, 8080, 80]")
+        .build();
+    let expected: TokenStream = quote! {
+        const PORTS: &[u16] = &[443, 80, 8080];
+    };
+    let result = from_env(attr, item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_dedup_changing_array_length_is_error() {
+    let attr: TokenStream = quote! {
+        (dedup)
+    };
+    let item: TokenStream = quote! {
+        const PORTS: [u16; 3] = [1, 2, 3];
+    };
+    let env = TestEnv::builder()
+        .set("PORTS", "[443, 80, 443]")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`PORTS` has 1 duplicate elements, which would change the length of the array"), "{}", result);
+}
+
+#[test]
+fn test_sort_mixed_elements_is_error() {
+    let attr: TokenStream = quote! {
+        (sort)
+    };
+    let item: TokenStream = quote! {
+        const MIXED: &[&str] = &[];
+    };
+    let env = TestEnv::builder()
+        .set("MIXED", r#"["a", 1]"#)
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("expected a string, found an integer at index [1]"), "{}", result);
+}
+
+#[test]
+fn test_sha256_matching_value() {
+    let attr: TokenStream = quote! {
+        ("ROOT_KEY", sha256 = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const ROOT_KEY: &str = "default";
+    };
+    let env = TestEnv::builder()
+        .set("ROOT_KEY", "abc")
+        .build();
+    let expected: TokenStream = quote! {
+        const ROOT_KEY: &str = "abc";
+    };
+    let result = from_env(attr.clone(), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // The default is exempt unless `verify_default` is given.
+    let result = from_env(attr, item, TestEnv::builder().build());
+    assert!(!format!("{}", result).contains("compile_error"), "{}", result);
+}
+
+#[test]
+fn test_sha256_mismatch_hides_value() {
+    let attr: TokenStream = quote! {
+        (sha256 = "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+    };
+    let item: TokenStream = quote! {
+        const BOOTSTRAP_URL: &str = "https://example.com";
+    };
+    let env = TestEnv::builder()
+        .set("BOOTSTRAP_URL", "https://attacker.example")
+        .build();
+    let result = format!("{}", from_env(attr.clone(), item.clone(), env));
+    assert!(result.contains("does not match the expected SHA-256 digest"), "{}", result);
+    assert!(result.contains("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"), "{}", result);
+    assert!(!result.contains("attacker"), "{}", result);
+
+    let env = TestEnv::builder()
+        .set("BOOTSTRAP_URL", "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(!result.contains("compile_error"), "{}", result);
+}
+
+#[test]
+fn test_sha256_verify_default() {
+    let attr: TokenStream = quote! {
+        (sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", verify_default, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const SALT: &[u8] = b"";
+    };
+    let result = from_env(attr.clone(), item.clone(), TestEnv::builder().build());
+    assert_eq!(format!("{}", item), format!("{}", result));
+
+    let item: TokenStream = quote! {
+        const SALT: &[u8] = b"changed";
+    };
+    let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+    assert!(result.contains("The value of the default does not match"), "{}", result);
+}
+
+#[test]
+fn test_sha256_invalid_digest_is_error() {
+    let attr: TokenStream = quote! {
+        (sha256 = "ab12")
+    };
+    let item: TokenStream = quote! {
+        const ROOT_KEY: &str = "";
+    };
+    let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+    assert!(result.contains("64 hexadecimal digits"), "{}", result);
+}
+
+#[test]
+fn test_unsupported_item_is_error() {
+    let item: TokenStream = quote! {
+        fn not_a_const() {}
+    };
+    let result = format!("{}", from_env(TokenStream::new(), item, TestEnv::builder().build()));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("can only be applied to `const` and `static` items"), "{}", result);
+}
+
+#[test]
+fn test_invalid_value_is_error() {
+    let item: TokenStream = quote! {
+        const WEIGHTS: [u8; 2] = [1, 2];
+    };
+    let env = TestEnv::builder()
+        .set("WEIGHTS", "[1, 2")
+        .build();
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("Failed to parse environment variable `WEIGHTS` as an array"), "{}", result);
+
+    let item: TokenStream = quote! {
+        const NAME: &str = "name";
+    };
+    let env = TestEnv::builder()
+        .set("NAME", "unterminated \\")
+        .build();
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("Failed to parse environment variable `NAME` as a string"), "{}", result);
+}
+
+#[test]
+fn test_unsupported_default_is_error() {
+    let item: TokenStream = quote! {
+        const ORIGIN: Point = Point::new(0, 0);
+    };
+    let env = TestEnv::builder()
+        .set("ORIGIN", "Point::new(1, 1)")
+        .build();
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("use the `expr` flag"), "{}", result);
+}
+
+#[test]
+fn test_malformed_attribute_arguments() {
+    let cases = [
+        (quote! { (123) }, "The variable name must be a string literal"),
+        (quote! { ("A" "B") }, "Expected `,` between attribute arguments"),
+        (quote! { (= "X") }, "Expected an attribute argument"),
+        (quote! { ("A", , required) }, "Expected an attribute argument"),
+        (quote! { ("A", case =) }, "Expected a value after `case =`"),
+    ];
+    for (attr, message) in cases {
+        let item: TokenStream = quote! {
+            const FOO: u32 = 0;
+        };
+        let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+        assert!(result.contains(message), "{}", result);
+        assert!(result.contains("#[env_item(\\\"VAR_NAME\\\")]") || message.starts_with("Expected a value"), "{}", result);
+    }
+}
+
+#[test]
+fn test_parse_failure_names_variable_and_value() {
+    let cases = [
+        (quote! { const PORT: u16 = 80; }, "eighty", "`PORT` as an integer"),
+        (quote! { const RATIO: f64 = 0.5; }, "half", "`RATIO` as a float"),
+        (quote! { const VERBOSE: bool = false; }, "yes", "`VERBOSE` as a boolean"),
+        (quote! { const OFFSET: i32 = -1; }, "minus one", "`OFFSET` as a number"),
+        (quote! { const SEP: char = ','; }, "ab", "`SEP` as a character"),
+        (quote! { const TAG: u8 = b'x'; }, "xy", "`TAG` as a byte"),
+        (quote! { const MAGIC: &[u8] = b"x"; }, "\\q", "`MAGIC` as a byte string"),
+        (quote! { const MAGIC: &[u8] = &[0x7f, 0x45]; }, "[0x7f, 0x4g]", "`MAGIC` as a list of `u8`"),
+    ];
+    for (item, value, message) in cases {
+        let name = match syn::parse2::<syn::ItemConst>(item.clone()) {
+            Ok(item) => item.ident.to_string(),
+            Err(err) => panic!("{}", err),
+        };
+        let env = TestEnv::builder()
+            .set(name, value)
+            .build();
+        let result = format!("{}", from_env(TokenStream::new(), item, env));
+        assert!(result.contains(message), "{}", result);
+        assert!(result.contains("value:"), "{}", result);
+    }
+}
+
+#[test]
+fn test_negative_default_keeps_number_kind() {
+    let expand = |item, value| expand_var("OFFSET", value, quote!((no_doc)), item);
+    for (item, value) in [(quote! { const OFFSET: Offset = -1; }, "2.5"), (quote! { const OFFSET: Offset = -1.5; }, "-2")] {
+        let out = expand(item, value);
+        assert!(out.contains("Failed to parse environment variable `OFFSET` as a number"), "{}", out);
+    }
+    assert_eq!(expand(quote! { const OFFSET: Offset = -1; }, "-7"), format!("{}", quote! { const OFFSET: Offset = -7; }));
+    assert_eq!(expand(quote! { const OFFSET: Offset = -1.5; }, "2.25"), format!("{}", quote! { const OFFSET: Offset = 2.25; }));
+}
+
+#[test]
+fn test_parse_failure_redacts_sensitive_value() {
+    let attr: TokenStream = quote! {
+        (sensitive)
+    };
+    let item: TokenStream = quote! {
+        const PIN: u32 = 0;
+    };
+    let env = TestEnv::builder()
+        .set("PIN", "hunter2")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("`PIN` as an integer"), "{}", result);
+    assert!(result.contains("<redacted> (7 bytes)"), "{}", result);
+    assert!(!result.contains("hunter2"), "{}", result);
+}
+
+#[test]
+fn test_non_utf8_value_is_error() {
+    let item: TokenStream = quote! {
+        const NAME: &str = "default";
+    };
+    let env = TestEnv::builder()
+        .set_bytes("NAME", b"ab\xffcd".to_vec())
+        .build();
+    let result = format!("{}", from_env(TokenStream::new(), item, env));
+    assert!(result.contains("Environment variable `NAME` is not valid UTF-8 (invalid byte at offset 2)"), "{}", result);
+}
+
+#[test]
+fn test_non_utf8_value_accepted_by_byte_string() {
+    let item: TokenStream = quote! {
+        const MAGIC: &[u8] = b"default";
+    };
+    let env = TestEnv::builder()
+        .set_bytes("MAGIC", b"ab\xff\x80".to_vec())
+        .build();
+    let expected: TokenStream = quote! {
+        const MAGIC: &[u8] = b"ab\xff\x80";
+    };
+    let result = from_env(quote!((no_doc)), item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_set_bytes_with_valid_utf8() {
+    let item: TokenStream = quote! {
+        const NAME: &str = "default";
+    };
+    let env = TestEnv::builder()
+        .set_bytes("NAME", "valid".as_bytes())
+        .build();
+    let expected: TokenStream = quote! {
+        const NAME: &str = "valid";
+    };
+    let result = from_env(quote!((no_doc)), item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_read_env_utf8() {
+    let env = TestEnv::builder()
+        .set("NAME", "valid")
+        .set_bytes("BYTES", b"ab\xff".to_vec())
+        .build();
+    assert_eq!(env.read_env_utf8("NAME"), Ok(Some("valid".to_string())));
+    assert_eq!(env.read_env_utf8("UNSET"), Ok(None));
+    let err = env.read_env_utf8("BYTES").unwrap_err();
+    assert!(matches!(err, EnvError::NotUnicode { ref name, .. } if name == "BYTES"), "{:?}", err);
+    assert_eq!(err.to_string(), "Environment variable `BYTES` is not valid UTF-8 (invalid byte at offset 2)");
+}
+
+#[test]
+fn test_closure_env() {
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+    };
+    let result = from_env(quote!((no_doc)), item, |name: &str| (name == "PORT").then(|| "8080".to_string()));
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_map_envs() {
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+    };
+    let hash_map: HashMap<String, String> = [("PORT".to_string(), "8080".to_string())].into_iter().collect();
+    let result = from_env(quote!((no_doc)), item.clone(), hash_map);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let btree_map: BTreeMap<String, String> = [("PORT".to_string(), "8080".to_string())].into_iter().collect();
+    let result = from_env(quote!((no_doc)), item.clone(), btree_map);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let pairs: &[(&str, &str)] = &[("PORT", "8080"), ("PORT", "9090")];
+    let result = from_env(quote!((no_doc)), item, pairs);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+    assert_eq!(pairs.var_names(), vec!["PORT".to_string()]);
+}
+
+#[test]
+fn test_test_env_extend_and_remove() {
+    let env = TestEnv::builder()
+        .extend([("HOST", "localhost"), ("PORT", "80"), ("USER", "admin")])
+        .extend(vec![("PORT".to_string(), "8080".to_string())])
+        .remove("USER")
+        .remove("UNSET")
+        .build();
+    assert_eq!(env.len(), 2);
+    assert!(env.contains("HOST"));
+    assert!(!env.contains("USER"));
+    assert_eq!(env.read_env_utf8("PORT"), Ok(Some("8080".to_string())));
+
+    let env: TestEnv = vec![("PORT".to_string(), "8080".to_string())].into_iter().collect();
+    assert_eq!(env.len(), 1);
+    assert!(TestEnv::builder().build().is_empty());
+}
+
+#[test]
+fn test_recording_env() {
+    let env = RecordingEnv::new(TestEnv::builder()
+        .set("TARGET", "x86_64-unknown-linux-gnu")
+        .set("STACK_SIZE", "4096")
+        .set("CONST_ENV_DUPLICATES", "off")
+        .build());
+    let attr: TokenStream = quote! {
+        (per_target, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const STACK_SIZE: usize = 1024;
+    };
+    from_env(attr, item, &env);
+    let lookups: Vec<(String, bool)> = env.queries()
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("CONST_ENV_"))
+        .collect();
+    assert_eq!(lookups, vec![
+        ("TARGET".to_string(), true),
+        ("STACK_SIZE_x86_64_unknown_linux_gnu".to_string(), false),
+        ("STACK_SIZE".to_string(), true),
+    ]);
+
+    let env = RecordingEnv::new(TestEnv::builder().build());
+    env_lit(quote!("PORT", 80), &env);
+    env.assert_read(&["CONST_ENV_DOTENV", "PORT"]);
+}
+
+#[test]
+fn test_snapshot() {
+    let path = std::env::var_os("PATH");
+    let env = TestEnv::snapshot();
+    assert_eq!(env.read_env("PATH").unwrap().map(|value| value.into_owned()), path);
+
+    let env = TestEnvBuilder::from_snapshot()
+        .set("CONST_ENV_SNAPSHOT_TEST", "1")
+        .remove("PATH")
+        .build();
+    assert!(env.contains("CONST_ENV_SNAPSHOT_TEST"));
+    assert!(!env.contains("PATH"));
+}
+
+#[test]
+fn test_dotenv_file() {
+    let path = write_temp_file("local.env", "PORT=8080\nexport HOST=\"example.com\"\n");
+    let env = || TestEnv::builder().set("CONST_ENV_DOTENV", path.to_str().unwrap());
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    // The file is included after the items, so that rustc rebuilds when it changes.
+    let absolute = path.to_str().unwrap();
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item.clone(), env().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // The environment takes precedence over the file.
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 9090;
+        const _: &[u8] = ::core::include_bytes!(#absolute);
+    };
+    let result = from_env(quote!((no_doc)), item.clone(), env().set("PORT", "9090").build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = format!("{}", env_lit(quote!("HOST", "localhost"), env().build()));
+    assert_eq!(result, "\"example.com\"");
+
+    let invalid = write_temp_file("invalid.env", "PORT=8080\nHOST\n");
+    let env = TestEnv::builder().set("CONST_ENV_DOTENV", invalid.to_str().unwrap()).build();
+    let result = format!("{}", from_env(quote!((no_doc)), item, env));
+    assert!(result.contains("[CE0017]"), "{}", result);
+    assert!(result.contains("line 2: expected `KEY=value` (named by `CONST_ENV_DOTENV`)"), "{}", result);
+}
+
+#[test]
+fn test_chain_env_precedence() {
+    let env = TestEnv::builder()
+        .set("HOST", "override")
+        .build()
+        .chain(TestEnv::builder()
+            .set("HOST", "base")
+            .set("PORT", "8080")
+            .build());
+    assert_eq!(env.read_env_utf8("HOST"), Ok(Some("override".to_string())));
+    assert_eq!(env.read_env_utf8("PORT"), Ok(Some("8080".to_string())));
+    assert_eq!(env.read_env_utf8("USER"), Ok(None));
+    assert_eq!(env.var_names(), vec!["HOST".to_string(), "PORT".to_string()]);
+
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+    };
+    let result = from_env(quote!((no_doc)), item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+}
+
+#[test]
+fn test_chain_env_does_not_hide_errors() {
+    let env = FailingEnv.chain(TestEnv::builder()
+        .set("PORT", "8080")
+        .set("HOST", "base")
+        .build());
+    assert!(matches!(env.read_env("PORT"), Err(EnvError::Lookup { .. })));
+    assert_eq!(env.read_env_utf8("HOST"), Ok(Some("base".to_string())));
+}
+
+#[test]
+fn test_prefixed_env() {
+    let inner = || TestEnv::builder()
+        .set("MYAPP_PORT", "8080")
+        .set("HOST", "localhost")
+        .set("MYAPP_", "empty name")
+        .build();
+    let env = PrefixedEnv::new("MYAPP_", inner());
+    assert_eq!(env.read_env_utf8("PORT"), Ok(Some("8080".to_string())));
+    assert_eq!(env.read_env_utf8("HOST"), Ok(None));
+    assert_eq!(env.var_names(), vec!["PORT".to_string()]);
+
+    let env = PrefixedEnv::new("MYAPP_", inner()).fallthrough();
+    assert_eq!(env.read_env_utf8("HOST"), Ok(Some("localhost".to_string())));
+    assert_eq!(env.var_names(), vec!["HOST".to_string(), "MYAPP_".to_string(), "MYAPP_PORT".to_string(), "PORT".to_string()]);
+
+    let env = RecordingEnv::new(PrefixedEnv::new("MYAPP_", inner()).chain(TestEnv::builder().set("PORT", "1").build()));
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+    };
+    let result = from_env(quote!((no_doc)), item, &env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+    assert!(env.queries().contains(&("PORT".to_string(), true)));
+}
+
+/// A source standing in for the compiler's environment, which requests rebuild tracking.
+struct ProcessEnv(TestEnv);
+
+impl ReadEnv for ProcessEnv {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        self.0.read_env(var_name)
+    }
+
+    fn needs_rebuild_tracking(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_rebuild_tracking() {
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+        const _: ::core::option::Option<&str> = ::core::option_env!("PORT");
+        const _: ::core::option::Option<&str> = ::core::option_env!("CONST_ENV_DOTENV");
+    };
+    let env = ProcessEnv(TestEnv::builder().set("PORT", "8080").build());
+    let result = from_env(quote!((no_doc)), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // Unset variables are tracked too, so that setting one later triggers a rebuild, as
+    // is `CONST_ENV_CONFIG`, which is consulted when none is set.
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 80;
+        const _: ::core::option::Option<&str> = ::core::option_env!("CONST_ENV_CONFIG");
+        const _: ::core::option::Option<&str> = ::core::option_env!("PORT");
+        const _: ::core::option::Option<&str> = ::core::option_env!("PORT_wasm32");
+        const _: ::core::option::Option<&str> = ::core::option_env!("CONST_ENV_DOTENV");
+    };
+    let env = ProcessEnv(TestEnv::builder().set("TARGET", "wasm32").build());
+    let result = from_env(quote!((per_target, no_doc)), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // The item's variables are left out, but not the crate's `.env` file.
+    let expected: TokenStream = quote! {
+        const PORT: u16 = 8080;
+        const _: ::core::option::Option<&str> = ::core::option_env!("CONST_ENV_DOTENV");
+    };
+    let env = ProcessEnv(TestEnv::builder().set("PORT", "8080").build());
+    let result = from_env(quote!((untracked, no_doc)), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // Test sources do not request tracking.
+    let result = from_env(quote!((no_doc)), item, TestEnv::builder().build());
+    assert_eq!(format!("{}", quote!(const PORT: u16 = 80;)), format!("{}", result));
+}
+
+#[test]
+fn test_var_manifest() {
+    // A name read in a source file that has changed since is dropped, and names recorded
+    // by the other targets of the package are kept.
+    let path = write_temp_file("const_env_vars.txt", "STALE\tgone.rs\t1\nOTHER_TARGET\t<unknown>\t-\n");
+    let env = || TestEnv::builder()
+        .set("CONST_ENV_MANIFEST", path.to_str().unwrap())
+        .set("TARGET", "wasm32")
+        .set("PORT", "8080")
+        .build();
+    from_env(quote!((per_target)), quote!(const PORT: u16 = 80;), env());
+    from_env(quote!(("PORT")), quote!(const MAX_PORT: u16 = 80;), env());
+    from_env(quote!((format = "{HOST}:{PORT}")), quote!(const ADDR: &str = "";), env());
+    assert_eq!(consumed_vars(&path).unwrap(), vec!["HOST", "OTHER_TARGET", "PORT", "PORT_wasm32"]);
+
+    let handles: Vec<_> = (0..8).map(|i| {
+        let path = path.clone();
+        std::thread::spawn(move || {
+            let env = TestEnv::builder().set("CONST_ENV_MANIFEST", path.to_str().unwrap()).build();
+            from_env(quote!(), format!("const VAR_{}: u8 = 0;", i).parse().unwrap(), env);
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let names = consumed_vars(&path).unwrap();
+    assert_eq!(names.len(), 12, "{:?}", names);
+    assert!(names.contains(&"VAR_7".to_string()));
+
+    assert_eq!(consumed_vars(path.with_file_name("missing.txt")).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn test_emit_var_name() {
+    let env = || TestEnv::builder().set("APP_PORT", "8080").build();
+    let item: TokenStream = quote! {
+        pub(crate) static PORT: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        pub(crate) static PORT: u16 = 8080;
+        pub(crate) const PORT_ENV_VAR: &str = "APP_PORT";
+    };
+    let result = from_env(quote!(("APP_PORT", emit_var_name, no_doc)), item, env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // The constant is generated whether or not the variable is set.
+    let item: TokenStream = quote! {
+        const HTTPServerPort: u16 = 80;
+    };
+    let expected: TokenStream = quote! {
+        const HTTPServerPort: u16 = 80;
+        const HTTPServerPort_ENV_VAR: &str = "HTTP_SERVER_PORT";
+    };
+    let result = from_env(quote!((rename_all = "SCREAMING_SNAKE_CASE", emit_var_name, no_doc)), item, env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = format!("{}", from_env(quote!((format = "{HOST}", emit_var_name)), quote!(const ADDR: &str = "";), env()));
+    assert!(result.contains("`emit_var_name` cannot be combined with `format`"), "{}", result);
+}
+
+#[test]
+fn test_emit_default() {
+    let item: TokenStream = quote! {
+        pub static NAME: &'static str = "app";
+    };
+    let expected: TokenStream = quote! {
+        pub static NAME: &'static str = "server";
+        pub const NAME_DEFAULT: &'static str = "app";
+    };
+    let set = || TestEnv::builder().set("NAME", "server").build();
+    let result = from_env(quote!((emit_default, no_doc)), item.clone(), set());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+    let result = from_env(quote!((emit_default, only_when_overridden, no_doc)), item.clone(), set());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let expected: TokenStream = quote! {
+        pub static NAME: &'static str = "app";
+        pub const NAME_DEFAULT: &'static str = "app";
+    };
+    let result = from_env(quote!((emit_default, no_doc)), item.clone(), TestEnv::builder().build());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+    let result = from_env(quote!((emit_default, only_when_overridden, no_doc)), item.clone(), TestEnv::builder().build());
+    assert_eq!(format!("{}", item), format!("{}", result));
+
+    let result = format!("{}", from_env(quote!((only_when_overridden)), item, set()));
+    assert!(result.contains("`only_when_overridden` requires the `emit_default` flag"), "{}", result);
+}
+
+#[test]
+fn test_registry() {
+    let env = || TestEnv::builder()
+        .set("CARGO_CRATE_NAME", "registry_test")
+        .set("APP_PORT", "8080")
+        .set("TOKEN", "secret")
+        .build();
+    from_env(quote!(("APP_PORT", register)), quote!(pub const PORT: u16 = 80;), env());
+    from_env(quote!((register, sensitive)), quote!(static TOKEN: &str = "dev";), env());
+    from_env(quote!((register)), quote!(const HOST: &str = "localhost";), env());
+    from_env(quote!(), quote!(const TIMEOUT: u64 = 30;), env());
+    // Expanding an item again replaces its entry.
+    from_env(quote!(("APP_PORT", register)), quote!(pub const PORT: u16 = 80;), env());
+
+    let expected: TokenStream = quote! {
+        {
+            #[allow(dead_code)]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            struct EnvConstInfo {
+                pub const_name: &'static str,
+                pub env_var: &'static str,
+                pub declared_type: &'static str,
+                pub default_str: &'static str,
+                pub value_str: &'static str,
+                pub overridden: bool,
+            }
+            const REGISTRY: &'static [EnvConstInfo] = &[
+                EnvConstInfo {
+                    const_name: "HOST",
+                    env_var: "HOST",
+                    declared_type: "&str",
+                    default_str: "\"localhost\"",
+                    value_str: "\"localhost\"",
+                    overridden: false,
+                },
+                EnvConstInfo {
+                    const_name: "PORT",
+                    env_var: "APP_PORT",
+                    declared_type: "u16",
+                    default_str: "80",
+                    value_str: "8080",
+                    overridden: true,
+                },
+                EnvConstInfo {
+                    const_name: "TOKEN",
+                    env_var: "TOKEN",
+                    declared_type: "&str",
+                    default_str: "<redacted>",
+                    value_str: "<redacted>",
+                    overridden: true,
+                }
+            ];
+            REGISTRY
+        }
+    };
+    assert_eq!(format!("{}", expected), format!("{}", registry(quote!(), env())));
+
+    // The entries of another crate compiled by the same process are kept apart.
+    let other = TestEnv::builder().set("CARGO_CRATE_NAME", "registry_other").build();
+    let result = format!("{}", registry(quote!(), other));
+    assert!(result.contains("REGISTRY : & 'static [EnvConstInfo] = & [] ;"), "{}", result);
+
+    let result = format!("{}", registry(quote!(crate), env()));
+    assert!(result.contains("`registry!` takes no arguments"), "{}", result);
+}
+
+#[test]
+fn test_runtime_fallback() {
+    // The expectations are printed by `syn`, which spaces empty closure parameters.
+    let env = || TestEnv::builder().set("APP_PORT", "8080").build();
+    // Integers are parsed as at compile time, with underscores and radix prefixes.
+    let expected: syn::Item = syn::parse_quote! {
+        pub static PORT: ::std::sync::LazyLock<u16> = ::std::sync::LazyLock::new(|| {
+            let parse = |value: ::std::string::String| -> Option<u16> {
+                let value = value.trim().replace('_', "");
+                let (sign, digits) = match value.strip_prefix('-') {
+                    Some(digits) => ("-", digits),
+                    None => ("", &value[..]),
+                };
+                let (radix, digits) = match digits.get(..2) {
+                    Some("0x") => (16, &digits[2..]),
+                    Some("0o") => (8, &digits[2..]),
+                    Some("0b") => (2, &digits[2..]),
+                    _ => (10, digits),
+                };
+                let value = <u16>::from_str_radix(&format!("{}{}", sign, digits), radix).ok()?;
+                if value < 1024 { return None; }
+                Some(value)
+            };
+            ::std::env::var("APP_PORT").ok().and_then(parse).unwrap_or(8080)
+        });
+    };
+    let result = from_env(quote!(("APP_PORT", runtime_fallback, min = 1024, no_doc)), quote!(pub static PORT: u16 = 80;), env());
+    assert_eq!(format!("{}", quote!(#expected)), format!("{}", result));
+
+    // The default is kept as the fallback when the variable is unset at compile time.
+    let expected: syn::Item = syn::parse_quote! {
+        static VERBOSE: ::std::sync::LazyLock<bool> = ::std::sync::LazyLock::new(|| {
+            let parse = |value: ::std::string::String| -> Option<bool> {
+                let value = value.trim().parse::<bool>().ok()?;
+                Some(value)
+            };
+            ::std::env::var("VERBOSE").ok().and_then(parse).unwrap_or(false)
+        });
+    };
+    let result = from_env(quote!((runtime_fallback, no_doc)), quote!(static VERBOSE: bool = false;), env());
+    assert_eq!(format!("{}", quote!(#expected)), format!("{}", result));
+
+    let result = format!("{}", from_env(quote!((runtime_fallback)), quote!(const PORT: u16 = 80;), env()));
+    assert!(result.contains("[CE0005] `runtime_fallback` can only be used on `static` items"), "{}", result);
+    let result = format!("{}", from_env(quote!((runtime_fallback)), quote!(static NAME: &str = "app";), env()));
+    assert!(result.contains("implementing `FromStr`, not `&str`"), "{}", result);
+    let result = format!("{}", from_env(quote!((runtime_fallback, format = "{HOST}")), quote!(static ADDR: u16 = 0;), env()));
+    assert!(result.contains("`runtime_fallback` cannot be combined with `format`"), "{}", result);
+    let result = format!("{}", from_env(quote!((runtime_fallback, per_target)), quote!(static PORT: u16 = 0;), env()));
+    assert!(result.contains("`runtime_fallback` cannot be combined with `per_target`"), "{}", result);
+    let result = format!("{}", from_env(quote!((runtime_fallback, debug_var = "PORT_DEBUG")), quote!(static PORT: u16 = 0;), env()));
+    assert!(result.contains("`runtime_fallback` cannot be combined with `debug_var`"), "{}", result);
+}
+
+#[test]
+fn test_assert() {
+    let env = || TestEnv::builder().set("BUF", "4096").build();
+    let expected: TokenStream = quote! {
+        const BUF_SIZE: usize = 4096;
+        const _: () = assert!(BUF_SIZE.is_power_of_two(), "`BUF_SIZE.is_power_of_two()` does not hold for `BUF_SIZE`; set `BUF` to a value for which it does");
+        const _: () = assert!(BUF_SIZE >= 512, "`BUF_SIZE >= 512` does not hold for `BUF_SIZE`; set `BUF` to a value for which it does");
+    };
+    let attr = quote!(("BUF", assert = "BUF_SIZE.is_power_of_two()", assert = "BUF_SIZE >= 512", no_doc));
+    let result = from_env(attr, quote!(const BUF_SIZE: usize = 1024;), env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // Braces in the predicate are escaped in the message.
+    let expected: TokenStream = quote! {
+        const LEN: usize = 8;
+        const _: () = assert!({ LEN > 0 }, "`{{ LEN > 0 }}` does not hold for `LEN`; set `LEN` to a value for which it does");
+    };
+    let result = from_env(quote!((assert = "{ LEN > 0 }", no_doc)), quote!(const LEN: usize = 8;), env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = format!("{}", from_env(quote!((assert = "BUF >")), quote!(const BUF: usize = 8;), env()));
+    assert!(result.contains("Invalid `assert` predicate"), "{}", result);
+    let result = format!("{}", from_env(quote!((assert = 1)), quote!(const BUF: usize = 8;), env()));
+    assert!(result.contains("Expected a string literal"), "{}", result);
+}
+
+#[test]
+fn test_env_or() {
+    let env = || TestEnv::builder()
+        .set("RETRIES", "5")
+        .set("OFFSET", "-2")
+        .set("RATIO", "1.25")
+        .set("GREETING", "hello world")
+        .set("PORTS", "[8080, 8443]")
+        .build();
+    let cases = [
+        (quote!("RETRIES", 3u32), quote!(5u32)),
+        (quote!("OFFSET", 0i8), quote!(-2i8)),
+        (quote!("RATIO", 0.5f32), quote!(1.25f32)),
+        (quote!("GREETING", "hi"), quote!("hello world")),
+        (quote!("PORTS", [80, 443]), quote!([8080, 8443])),
+        (quote!("TIMEOUT", 30), quote!(30)),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(format!("{}", expected), format!("{}", env_or(input, env())));
+    }
+
+    let result = format!("{}", env_or(quote!("GREETING", 3u32), env()));
+    assert!(result.contains("[CE0002] Failed to parse environment variable `GREETING` as an integer"), "{}", result);
+}
+
+#[test]
+fn test_from_env_items() {
+    let env = || TestEnv::builder()
+        .set("A", "2")
+        .set("B", "y")
+        .set("CUSTOM", "true")
+        .build();
+    let expected: TokenStream = quote! {
+        #[doc = " *Configurable at build time via the `A` environment variable.*"]
+        pub const A: u32 = 2;
+        #[doc = "B."]
+        static B: &str = "y";
+        const C: bool = true;
+        #[doc = " *Configurable at build time via the `D` environment variable.*"]
+        const D: u8 = 4;
+    };
+    let result = from_env_items(quote! {
+        pub const A: u32 = 1;
+        #[doc = "B."]
+        #[from_env(no_doc)]
+        static B: &str = "x";
+        #[env_item("CUSTOM", no_doc)]
+        const C: bool = false;
+        #[from_env(allow_warnings)]
+        const D: u8 = 4;
+    }, env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    // Failing items are kept, and every error is reported after the items.
+    let result = from_env_items(quote! {
+        #[from_env(no_doc)]
+        const A: u32 = 1;
+        #[from_env(no_doc)]
+        const B: u32 = 1;
+        #[from_env("CUSTOM", no_doc)]
+        const C: u8 = 0;
+        fn d() {}
+        #[from_env(no_doc)]
+        #[from_env(no_doc)]
+        const E: u8 = 0;
+    }, env());
+    let result = format!("{}", result);
+    assert!(result.starts_with(&format!("{}", quote! {
+        const A: u32 = 2;
+        const B: u32 = 1;
+        const C: u8 = 0;
+        fn d() {}
+        const E: u8 = 0;
+    })), "{}", result);
+    assert!(result.contains("Failed to parse environment variable `B` as an integer"), "{}", result);
+    assert!(result.contains("Failed to parse environment variable `CUSTOM` as an integer"), "{}", result);
+    assert!(result.contains("can only be applied to `const` and `static` items"), "{}", result);
+    assert!(result.contains("takes at most one `from_env` attribute"), "{}", result);
+}
+
+#[test]
+fn test_derive_warnings() {
+    let env = || TestEnv::builder().set("PORT", "80").build();
+    let input = |attr: TokenStream| quote! {
+        struct Config {
+            #[from_env(#attr)]
+            port: u16,
+        }
+    };
+    let result = format!("{}", from_env_defaults(input(quote!(default = 80, warn_redundant)), env()));
+    let shim = quote! {
+        const _: () = {
+            #[deprecated(note = "`PORT` is set to the same value as the default of `port`")]
+            #[allow(non_upper_case_globals)]
+            const warning: () = ();
+            let _ = warning;
+        };
+    };
+    assert!(result.ends_with(&format!("}} {}", shim)), "{}", result);
+
+    let result = format!("{}", from_env_defaults(input(quote!(default = 80, warn_redundant, allow_warnings)), env()));
+    assert!(!result.contains("deprecated"), "{}", result);
+}
+
+#[test]
+fn test_from_env_defaults() {
+    let env = || TestEnv::builder()
+        .set("APP_PORT", "8080")
+        .set("LOG_LEVEL", "debug")
+        .set("RATIO", "0.25")
+        .build();
+    let expected: TokenStream = quote! {
+        impl<T: Default> ::std::default::Default for Config<T> {
+            fn default() -> Self {
+                Config {
+                    port: 8080,
+                    log_level: ::std::string::String::from("debug"),
+                    ratio: 0.25,
+                    extra: ::std::default::Default::default(),
+                }
+            }
+        }
+    };
+    let result = from_env_defaults(quote! {
+        struct Config<T: Default> {
+            #[from_env("APP_PORT", default = 80)]
+            port: u16,
+            #[from_env(default = "info")]
+            log_level: String,
+            #[from_env(default = 0.5, untracked)]
+            ratio: f64,
+            extra: T,
+        }
+    }, env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = format!("{}", from_env_defaults(quote! {
+        struct Config {
+            #[from_env]
+            port: u16,
+            #[from_env(default = 0, register)]
+            retries: u8,
+            #[from_env(default = ::std::time::Duration::from_secs(1))]
+            timeout: ::std::time::Duration,
+            #[from_env(default = 80)]
+            #[from_env(default = 81)]
+            backup: u16,
+            #[from_env(default = 1)]
+            ratio: f64,
+        }
+    }, env()));
+    assert!(result.contains("The `from_env` attribute of `port` needs a `default = ...` literal"), "{}", result);
+    assert!(result.contains("`register` cannot be used on fields of `#[derive(FromEnvDefaults)]`"), "{}", result);
+    assert!(result.contains("cannot configure a field of type `::std::time::Duration`"), "{}", result);
+    assert!(result.contains("A field takes at most one `from_env` attribute"), "{}", result);
+    assert!(result.contains("The default of `ratio` does not match its declared type `f64`"), "{}", result);
+
+    let result = format!("{}", from_env_defaults(quote!(struct Config(u16);), env()));
+    assert!(result.contains("can only be derived for structs with named fields"), "{}", result);
+}
+
+#[test]
+fn test_const_env_derive() {
+    let env = || TestEnv::builder()
+        .set("LIMITS_MAX_CONN", "16")
+        .set("LABEL", "edge")
+        .build();
+    let expected: TokenStream = quote! {
+        impl Limits {
+            pub const MAX_CONN: u32 = 16;
+            pub const NAME: &'static str = "edge";
+            pub const WEIGHTS: [u8; 2] = [1, 2];
+        }
+    };
+    let result = const_env_derive(quote! {
+        #[from_env(prefix = "LIMITS_")]
+        struct Limits {
+            #[from_env(default = 4)]
+            max_conn: u32,
+            #[from_env("LABEL", default = "limits")]
+            name: &'static str,
+            #[from_env(default = [1, 2])]
+            weights: [u8; 2],
+        }
+    }, env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = format!("{}", const_env_derive(quote! {
+        struct Limits {
+            #[from_env(default = 1)]
+            max_conn: u32,
+            #[from_env(default = 2)]
+            maxConn: u32,
+            #[from_env(default = "x")]
+            label: String,
+            other: u8,
+        }
+    }, env()));
+    assert!(result.contains("`max_conn` and `maxConn` would both generate the constant `MAX_CONN`"), "{}", result);
+    assert!(result.contains("`ConstEnv` cannot configure a field of type `String`"), "{}", result);
+    assert!(result.contains("`other` has none"), "{}", result);
+
+    let result = format!("{}", const_env_derive(quote! {
+        #[from_env(rename = "X")]
+        struct Limits {}
+    }, env()));
+    assert!(result.contains("only accepts `prefix = \\\"...\\\"`"), "{}", result);
+}
+
+#[test]
+fn test_flags() {
+    let expand = |value, item| expand_var("FEATURES", value, quote!(("FEATURES", flags(metrics = 0x1, tls = 0x4, ipv6 = 0x10), no_doc)), item);
+    let item = quote!(const FEATURES: u32 = 0b0000_0101;);
+    assert_eq!(expand("metrics,tls,ipv6", item.clone()), format!("{}", quote!(const FEATURES: u32 = 0x15;)));
+    assert_eq!(expand(" TLS , Metrics ", item.clone()), format!("{}", quote!(const FEATURES: u32 = 0x5;)));
+    assert_eq!(expand("", item.clone()), format!("{}", quote!(const FEATURES: u32 = 0x0;)));
+    assert_eq!(expand("+ipv6", item.clone()), format!("{}", quote!(const FEATURES: u32 = 0x15;)));
+    assert_eq!(expand("metrics", quote!(const FEATURES: u8 = 0u8;)), format!("{}", quote!(const FEATURES: u8 = 0x1u8;)));
+
+    let result = expand("metrics,quic", item.clone());
+    assert!(result.contains("[CE0002] Unknown flag `quic` in environment variable `FEATURES`; expected a comma-separated list of `metrics`, `tls`, `ipv6`"), "{}", result);
+    let result = expand("tls", quote!(const FEATURES: &str = "";));
+    assert!(result.contains("[CE0005] `flags` can only be used on items with an integer default"), "{}", result);
+    let result = format!("{}", from_env(quote!((flags(low = 0x80, high = 0x100))), quote!(const FEATURES: u8 = 0;), TestEnv::builder().set("FEATURES", "low,high").build()));
+    assert!(result.contains("[CE0002] Environment variable `FEATURES` sets the bits 0x180, which do not fit in `u8`"), "{}", result);
+    let result = format!("{}", from_env(quote!((ty = "u8", flags(low = 0x80, high = 0x100))), quote!(const FEATURES: Features = 0u8;), TestEnv::builder().set("FEATURES", "high").build()));
+    assert!(result.contains("sets the bits 0x100, which do not fit in `u8`"), "{}", result);
+    let env = TestEnv::builder().build();
+    let result = format!("{}", from_env(quote!((flags(tls = 1, TLS = 2))), item, env));
+    assert!(result.contains("Duplicate flag `TLS`"), "{}", result);
+}
+
+#[test]
+fn test_report() {
+    let path = write_temp_file("const_env_report.jsonl", "");
+    let env = || TestEnv::builder()
+        .set("CONST_ENV_REPORT", path.to_str().unwrap())
+        .set("CARGO_CRATE_NAME", "app")
+        .set("APP_PORT", "8080")
+        .set("TOKEN", "secret")
+        .build();
+    from_env(quote!(("APP_PORT")), quote!(const PORT: u16 = 80;), env());
+    from_env(quote!((sensitive)), quote!(static TOKEN: &str = "";), env());
+    let handles: Vec<_> = (0..8).map(|i| {
+        let env = env();
+        std::thread::spawn(move || {
+            from_env(quote!(), format!("const VAR_{}: u8 = 0;", i).parse().unwrap(), env);
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let records: Vec<serde_json::Value> = contents.lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|err| panic!("{}: {}", err, line)))
+        .collect();
+    assert_eq!(records.len(), 10);
+    for record in &records {
+        let record = record.as_object().unwrap();
+        let mut keys: Vec<&str> = record.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["crate", "file", "item", "line", "overridden", "value", "vars"]);
+        assert_eq!(record["crate"], "app");
+        assert!(record["item"].is_string() && record["value"].is_string() && record["file"].is_string());
+        assert!(record["overridden"].is_boolean() && record["line"].is_u64());
+        assert!(record["vars"].as_array().unwrap().iter().all(serde_json::Value::is_string));
+    }
+    assert_eq!(records[0]["item"], "PORT");
+    assert_eq!(records[0]["vars"], serde_json::json!(["APP_PORT"]));
+    assert_eq!(records[0]["overridden"], true);
+    assert_eq!(records[0]["value"], "8080");
+    assert_eq!(records[1]["value"], "<redacted>");
+    assert!(records[2..].iter().all(|record| record["overridden"] == false && record["value"] == "0"));
+}
+
+#[test]
+fn test_lockfile() {
+    let path = write_temp_file("const_env.lock", "");
+    std::fs::remove_file(&path).unwrap();
+    let env = |write: bool, port: &str| {
+        let mut builder = TestEnv::builder()
+            .set("CONST_ENV_LOCK", path.to_str().unwrap())
+            .set("CARGO_CRATE_NAME", "app")
+            .set("PORT", port)
+            .set("TOKEN", "secret");
+        if write {
+            builder = builder.set("CONST_ENV_LOCK_WRITE", "1");
+        }
+        builder.build()
+    };
+    let port = || quote!(const PORT: u16 = 80;);
+    let result = from_env(quote!((no_doc)), port(), env(false, "8080")).to_string();
+    assert!(result.contains("[CE0008] Failed to read"), "{}", result);
+
+    from_env(quote!((no_doc)), port(), env(true, "8080"));
+    let result = from_env(quote!((sensitive, no_doc)), quote!(static TOKEN: &str = "";), env(true, "8080")).to_string();
+    assert!(!result.contains("compile_error"), "{}", result);
+    let handles: Vec<_> = (0..8).map(|i| {
+        let env = env(true, "8080");
+        std::thread::spawn(move || {
+            from_env(quote!(), format!("const VAR_{}: &str = \"a\\\"b\";", i).parse().unwrap(), env);
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("\n[app]\nPORT = \"8080\"\nTOKEN = \"sha256:"), "{}", contents);
+    assert!(!contents.contains("secret"), "{}", contents);
+    assert_eq!(contents.matches("VAR_").count(), 8, "{}", contents);
+
+    assert_eq!(format!("{}", quote!(const PORT: u16 = 8080;)), format!("{}", from_env(quote!((no_doc)), port(), env(false, "8080"))));
+    let result = from_env(quote!((sensitive, no_doc)), quote!(static TOKEN: &str = "";), env(false, "8080")).to_string();
+    assert!(!result.contains("compile_error"), "{}", result);
+    let result = from_env(quote!((no_doc)), quote!(const VAR_3: &str = "a\"b";), env(false, "8080")).to_string();
+    assert!(!result.contains("compile_error"), "{}", result);
+
+    let result = from_env(quote!((no_doc)), port(), env(false, "9000")).to_string();
+    assert!(result.contains("[CE0019] The value of `PORT` differs from the one recorded in the lockfile"), "{}", result);
+    assert!(result.contains("expected: 8080\\n  actual:   9000"), "{}", result);
+    let result = from_env(quote!((no_doc)), quote!(const RETRIES: u8 = 3;), env(false, "9000")).to_string();
+    assert!(result.contains("[CE0019] `RETRIES` of crate `app` is missing from the lockfile"), "{}", result);
+
+    from_env(quote!((no_doc)), port(), env(true, "9000"));
+    assert_eq!(format!("{}", quote!(const PORT: u16 = 9000;)), format!("{}", from_env(quote!((no_doc)), port(), env(false, "9000"))));
+}
+
+#[test]
+fn test_emit_len() {
+    let env = || TestEnv::builder()
+        .set("NAME", "héllo")
+        .set("PORTS", "[80, 443, 8080]")
+        .build();
+    let cases = [
+        (quote!(pub const NAME: &str = "app";), quote!(pub const NAME: &str = "héllo"; pub const NAME_LEN: usize = 6;)),
+        (quote!(static MAGIC: &[u8] = b"CE";), quote!(static MAGIC: &[u8] = b"CE"; const MAGIC_LEN: usize = 2;)),
+        (quote!(pub(crate) static PORTS: &[u16] = &[];), quote!(pub(crate) static PORTS: &[u16] = &[80, 443, 8080]; pub(crate) const PORTS_LEN: usize = 3;)),
+        (quote!(const IDS: [u8; 2] = [1, 2];), quote!(const IDS: [u8; 2] = [1, 2]; const IDS_LEN: usize = 2;)),
+    ];
+    for (item, expected) in cases {
+        assert_eq!(format!("{}", expected), format!("{}", from_env(quote!((emit_len, no_doc)), item, env())));
+    }
+
+    // Values chosen by `when` are measured by const evaluation.
+    let result = format!("{}", from_env(quote!((emit_len, when(unix), no_doc)), quote!(const NAME: &str = "app";), env()));
+    assert!(result.ends_with(&format!("{}", quote!(const NAME_LEN: usize = NAME.len();))), "{}", result);
+
+    for item in [quote!(const PORT: u16 = 80;), quote!(const OFFSET: i8 = -1;), quote!(const ON: bool = true;), quote!(const SEP: char = ',';)] {
+        let result = format!("{}", from_env(quote!((emit_len)), item, env()));
+        assert!(result.contains("[CE0005] `emit_len` can only be used on items with a string, byte string, array or slice default"), "{}", result);
+    }
+}
+
+#[test]
+fn test_duration() {
+    let expand = |value, item| expand_var("POLL", value, quote!((no_doc)), item);
+    let millis = quote!(const POLL: Duration = Duration::from_millis(500););
+    let cases = [
+        ("2s", quote!(const POLL: Duration = Duration::from_millis(2000);)),
+        ("750ms", quote!(const POLL: Duration = Duration::from_millis(750);)),
+        ("1m30s", quote!(const POLL: Duration = Duration::from_millis(90000);)),
+        ("1_500", quote!(const POLL: Duration = Duration::from_millis(1500);)),
+        ("1.5us", quote!(const POLL: Duration = Duration::new(0, 1500);)),
+        (" 1h 2m ", quote!(const POLL: Duration = Duration::from_millis(3720000);)),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(format!("{}", expected), expand(value, millis.clone()), "{}", value);
+    }
+
+    let secs = quote!(static POLL: std::time::Duration = std::time::Duration::from_secs(1););
+    assert_eq!(format!("{}", quote!(static POLL: std::time::Duration = std::time::Duration::new(1, 500000000);)), expand("1.5s", secs.clone()));
+    assert_eq!(format!("{}", quote!(static POLL: std::time::Duration = std::time::Duration::from_secs(5);)), expand("5", secs));
+    let new = quote!(const POLL: Duration = Duration::new(0, 0););
+    assert_eq!(format!("{}", quote!(const POLL: Duration = Duration::new(2, 0);)), expand("2", new.clone()));
+    assert_eq!(format!("{}", quote!(const POLL: Duration = Duration::new(0, 250);)), expand("250ns", new));
+
+    let errors = [
+        ("2 weeks", "unknown unit `weeks`"),
+        ("1.5ns", "`1.5ns` is finer than a nanosecond"),
+        ("1m30", "`30` needs a unit"),
+        ("", "expected a number followed by a unit"),
+        ("1..5s", "`1..5` is not a number"),
+        ("99999999999999999999999d", "the duration is too long to be represented"),
+    ];
+    for (value, message) in errors {
+        let result = expand(value, millis.clone());
+        assert!(result.contains("[CE0002] Failed to parse environment variable `POLL` as a duration"), "{}", result);
+        assert!(result.contains(message), "{}: {}", value, result);
+    }
+}
+
+#[test]
+fn test_fixed_point() {
+    let expand = |attr, value, item| expand_var("PRICE", value, attr, item);
+    let item = quote!(const PRICE: i64 = 0;);
+    let cases = [
+        ("19.99", quote!(const PRICE: i64 = 1999;)),
+        ("19.9", quote!(const PRICE: i64 = 1990;)),
+        ("19", quote!(const PRICE: i64 = 1900;)),
+        ("-0.05", quote!(const PRICE: i64 = -5;)),
+        ("+.5", quote!(const PRICE: i64 = 50;)),
+        ("5.", quote!(const PRICE: i64 = 500;)),
+        ("-0.00", quote!(const PRICE: i64 = 0;)),
+        ("19.990", quote!(const PRICE: i64 = 1999;)),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(format!("{}", expected), expand(quote!((fixed_point = 2, no_doc)), value, item.clone()), "{}", value);
+    }
+    assert_eq!(format!("{}", quote!(const PRICE: u32 = 1999u32;)), expand(quote!((fixed_point = 2, no_doc)), "19.99", quote!(const PRICE: u32 = 0u32;)));
+
+    let rounding = [
+        ("down", "-1.239", quote!(const PRICE: i64 = -123;)),
+        ("half_up", "1.235", quote!(const PRICE: i64 = 124;)),
+        ("half_up", "-1.234", quote!(const PRICE: i64 = -123;)),
+        ("half_even", "1.225", quote!(const PRICE: i64 = 122;)),
+        ("half_even", "1.235", quote!(const PRICE: i64 = 124;)),
+        ("half_even", "1.2251", quote!(const PRICE: i64 = 123;)),
+    ];
+    for (policy, value, expected) in rounding {
+        let attr = quote!((fixed_point = 2, round = #policy, no_doc));
+        assert_eq!(format!("{}", expected), expand(attr, value, item.clone()), "{} {}", policy, value);
+    }
+
+    let result = expand(quote!((fixed_point = 2)), "19.999", item.clone());
+    assert!(result.contains("[CE0002] Failed to parse environment variable `PRICE` as a decimal number: more than 2 decimal places"), "{}", result);
+    for value in [".", "-.", "1e3", "", "1.2.3", "--1"] {
+        let result = expand(quote!((fixed_point = 2)), value, item.clone());
+        assert!(result.contains("expected digits with an optional sign and decimal point, such as `-19.99`, `.5` or `5.`"), "{}: {}", value, result);
+    }
+    let result = expand(quote!((fixed_point = 2)), "1.28", quote!(const PRICE: i8 = 0;));
+    assert!(result.contains("[CE0002] The `fixed_point` value 128 of `PRICE` does not fit in `i8`"), "{}", result);
+    assert_eq!(format!("{}", quote!(const PRICE: i8 = -128;)), expand(quote!((fixed_point = 2, no_doc)), "-1.28", quote!(const PRICE: i8 = 0;)));
+    let result = expand(quote!((fixed_point = 2)), "-1", quote!(const PRICE: u64 = 0;));
+    assert!(result.contains("does not fit in `u64`"), "{}", result);
+
+    let result = expand(quote!((fixed_point = 2)), "1", quote!(const PRICE: f64 = 0.0;));
+    assert!(result.contains("[CE0005] `fixed_point` can only be used on items with an integer default"), "{}", result);
+    let result = expand(quote!((round = "down")), "1", item.clone());
+    assert!(result.contains("`round` requires `fixed_point`"), "{}", result);
+    let result = expand(quote!((fixed_point = 2, round = "up")), "1", item);
+    assert!(result.contains("Expected `round` to be"), "{}", result);
+}
+
+#[test]
+fn test_pad() {
+    let expand = |attr, value, item| expand_var("DEVICE_NAME", value, attr, item);
+    let item = quote!(const DEVICE_NAME: [u8; 8] = *b"unnamed\0";);
+    assert_eq!(format!("{}", quote!(const DEVICE_NAME: [u8; 8] = *b"sensor\0\0";)), expand(quote!((pad = 0, no_doc)), "sensor", item.clone()));
+    assert_eq!(format!("{}", quote!(const DEVICE_NAME: [u8; 8] = *b"SENSOR  ";)), expand(quote!((pad = b' ', case = "upper", no_doc)), "sensor", item.clone()));
+    assert_eq!(format!("{}", quote!(const DEVICE_NAME: [u8; 8] = *b"12345678";)), expand(quote!((pad = 0, no_doc)), "12345678", item.clone()));
+    assert_eq!(format!("{}", quote!(const DEVICE_NAME: [u8; 4] = [b'a', b'b', b'\x00', b'\x00'];)),
+        expand(quote!((pad = 0, no_doc)), "ab", quote!(const DEVICE_NAME: [u8; 4] = [0; 4];)));
+    assert_eq!(format!("{}", quote!(static DEVICE_NAME: [u8; 3] = [b'x', b'-', b'-'];)),
+        expand(quote!((pad = b'-', no_doc)), "x", quote!(static DEVICE_NAME: [u8; 3] = [1, 2, 3];)));
+
+    let result = expand(quote!((pad = 0)), "sensor-01", item.clone());
+    assert!(result.contains("[CE0006] Environment variable `DEVICE_NAME` is 9 bytes long, longer than the 8 bytes of the default"), "{}", result);
+    let result = expand(quote!((pad = 0)), "x", quote!(const DEVICE_NAME: &[u8] = b"unnamed";));
+    assert!(result.contains("[CE0005] `pad` can only be used on items with a `*b\\\"...\\\"` or fixed-size byte array default"), "{}", result);
+    let result = expand(quote!((pad = 256)), "x", item.clone());
+    assert!(result.contains("number too large to fit in target type"), "{}", result);
+    let result = expand(quote!((pad = 0, flags(a = 1))), "x", item);
+    assert!(result.contains("`pad` cannot be combined with `flags`"), "{}", result);
+}
+
+#[test]
+fn test_options() {
+    let expand = |value, item| expand_var("HASH_IMPL", value, quote!(("HASH_IMPL", options(fast = crate::hash::fast_hash, secure = crate::hash::siphash), no_doc)), item);
+    let item = quote!(static HASHER: fn(&[u8]) -> u64 = crate::hash::fast_hash;);
+    assert_eq!(format!("{}", quote!(static HASHER: fn(&[u8]) -> u64 = crate::hash::siphash;)), expand("secure", item.clone()));
+    assert_eq!(format!("{}", quote!(static HASHER: fn(&[u8]) -> u64 = crate::hash::fast_hash;)), expand(" Fast ", item.clone()));
+    assert_eq!(format!("{}", item), format!("{}", from_env(
+        quote!((options(fast = fast_hash), no_doc)), item.clone(), TestEnv::builder().build())));
+
+    let result = expand("md5", item);
+    assert!(result.contains("[CE0002] Unknown option `md5` in environment variable `HASH_IMPL`; expected one of `fast`, `secure`"), "{}", result);
+
+    let env = TestEnv::builder().set("LEVEL", "high").build();
+    let result = from_env(quote!((options(low = Level::LOW, high = Level::HIGH), no_doc)), quote!(const LEVEL: u8 = Level::LOW;), env);
+    assert_eq!(format!("{}", quote!(const LEVEL: u8 = Level::HIGH;)), format!("{}", result));
+
+    let result = format!("{}", from_env(quote!((options(a = x, A = y))), quote!(const LEVEL: u8 = x;), TestEnv::builder().build()));
+    assert!(result.contains("Duplicate option `A`; names are matched case-insensitively"), "{}", result);
+    let result = format!("{}", from_env(quote!((options(a = x), expr)), quote!(const LEVEL: u8 = x;), TestEnv::builder().build()));
+    assert!(result.contains("`options` cannot be combined with `expr`"), "{}", result);
+}
+
+#[test]
+fn test_decimal_comma() {
+    let expand = |attr, value, item| expand_var("THRESHOLD", value, attr, item);
+    let item = quote!(const THRESHOLD: f64 = 1.0;);
+    assert_eq!(format!("{}", quote!(const THRESHOLD: f64 = 3.14;)), expand(quote!((decimal_comma, no_doc)), "3,14", item.clone()));
+    assert_eq!(format!("{}", quote!(const THRESHOLD: f64 = -0.5;)), expand(quote!((decimal_comma, no_doc)), "-0,5", quote!(const THRESHOLD: f64 = -1.0;)));
+    assert_eq!(format!("{}", quote!(const THRESHOLD: f64 = 2.5;)), expand(quote!((decimal_comma, no_doc)), "2.5", item.clone()));
+    assert_eq!(format!("{}", quote!(const THRESHOLD: i64 = 1999;)), expand(quote!((decimal_comma, fixed_point = 2, no_doc)), "19,99", quote!(const THRESHOLD: i64 = 0;)));
+
+    for value in ["1,000.5", "1,000,000"] {
+        let result = expand(quote!((decimal_comma)), value, item.clone());
+        assert!(result.contains("[CE0002] Environment variable `THRESHOLD` is ambiguous with `decimal_comma`"), "{}", result);
+    }
+    // Without the flag, the comma is not accepted.
+    let result = expand(quote!(), "3,14", item);
+    assert!(result.contains("Failed to parse environment variable `THRESHOLD` as a float"), "{}", result);
+    let result = expand(quote!((decimal_comma)), "3,14", quote!(static LIMITS: &[f64] = &[1.0];));
+    assert!(result.contains("[CE0005] `decimal_comma` can only be used on items with a float default or with `fixed_point`"), "{}", result);
+}
+
+#[test]
+fn test_path_list() {
+    let expand = |attr, value| expand_var("PLUGIN_PATH", value, attr, quote!(static PLUGIN_PATH: &[&str] = &["/usr/lib/plugins"];));
+    let list = |elements: &[&str]| format!("{}", quote!(static PLUGIN_PATH: &[&str] = &[#(#elements),*];));
+    // The macro runs on the host, so a value is split on both separators, and `cfg!`
+    // chooses the target's split.
+    let per_target = |windows: &[&str], other: &[&str]| format!("{}", quote! {
+        static PLUGIN_PATH: &[&str] = if ::core::cfg!(windows) { &[#(#windows),*] } else { &[#(#other),*] };
+    });
+    let attr = quote!((split = "path_list", no_doc));
+    assert_eq!(per_target(&["/opt/a::/opt/b:"], &["/opt/a", "/opt/b"]), expand(attr.clone(), "/opt/a::/opt/b:"));
+    assert_eq!(per_target(&["C:\\a", "D:\\b"], &["C", "\\a;D", "\\b"]), expand(attr.clone(), "C:\\a;D:\\b"));
+    assert_eq!(per_target(&["/opt/a::/opt/b"], &["/opt/a", "", "/opt/b"]), expand(quote!((split = "path_list", keep_empty, no_doc)), "/opt/a::/opt/b"));
+    assert_eq!(list(&["a:b", "c"]), expand(quote!((split = "path_list", path_sep = ",", no_doc)), "a:b,c"));
+    // Values that both separators split alike need no choice.
+    assert_eq!(list(&[]), expand(attr.clone(), ""));
+    assert_eq!(list(&["/OPT/A"]), expand(quote!((split = "path_list", case = "upper", no_doc)), "/opt/a"));
+    let result = expand(quote!((split = "path_list", max_items = 1)), "/a:/b");
+    assert!(result.contains("has 2 items, more than the maximum of 1"), "{}", result);
+
+    let env = || TestEnv::builder().set("PLUGIN_PATH", "/a").build();
+    let result = format!("{}", from_env(attr, quote!(const PLUGIN_PATH: [&str; 1] = ["/a"];), env()));
+    assert!(result.contains("[CE0005] `split = \\\"path_list\\\"` can only be used on items with a `&[&str]` default"), "{}", result);
+    let result = format!("{}", from_env(quote!((split = "lines")), quote!(const PLUGIN_PATH: &[&str] = &[];), env()));
+    assert!(result.contains("Expected `split` to be \\\"path_list\\\""), "{}", result);
+    let result = format!("{}", from_env(quote!((path_sep = ";")), quote!(const PLUGIN_PATH: &[&str] = &[];), env()));
+    assert!(result.contains("`path_sep` requires `split = \\\"path_list\\\"`"), "{}", result);
+    let result = format!("{}", from_env(quote!((split = "path_list", path_sep = "::")), quote!(const PLUGIN_PATH: &[&str] = &[];), env()));
+    assert!(result.contains("Expected `path_sep` to be a single character"), "{}", result);
+}
+
+#[test]
+fn test_json_struct() {
+    let expand = |attr, value, item| expand_var("NET", value, attr, item);
+    let item = quote!(const NET: NetCfg = NetCfg { port: 8080, backlog: 64, host: "localhost", ratio: 0.5f32, tls: Tls { enabled: false } };);
+    assert_eq!(format!("{}", quote!(const NET: NetCfg = NetCfg { port: 9000, backlog: 128, host: "example.com", ratio: -2.0f32, tls: Tls { enabled: true } };)),
+        expand(quote!((no_doc)), r#"{"port": 9000, "backlog": 128, "host": "example.com", "ratio": -2, "tls": {"enabled": true}}"#, item.clone()));
+    assert_eq!(format!("{}", quote!(const NET: NetCfg = NetCfg { port: 8080, backlog: 64, host: "a\"b", ratio: 0.5f32, tls: Tls { enabled: false } };)),
+        expand(quote!((partial, no_doc)), r#" {"host": "a\"b"} "#, item.clone()));
+    assert_eq!(format!("{}", quote!(const NET: Cfg = Cfg { ports: [1u16, 2u16, 3u16], name: 'x' };)),
+        expand(quote!((no_doc)), r#"{"ports": [1, 2, 3], "name": "x"}"#, quote!(const NET: Cfg = Cfg { ports: [80u16], name: 'n' };)));
+
+    let result = expand(quote!(()), r#"{"port": 9000}"#, item.clone());
+    assert!(result.contains("missing `backlog`, `host`, `ratio`, `tls`; add `partial` to keep the defaults of missing fields"), "{}", result);
+    let result = expand(quote!((partial)), r#"{"tls": {"enabled": true, "cert": "a.pem"}}"#, item.clone());
+    assert!(result.contains("unknown key `cert` at `tls`"), "{}", result);
+    let result = expand(quote!((partial)), r#"{"port": 90.5}"#, item.clone());
+    assert!(result.contains("expected an integer at `port`, found a number"), "{}", result);
+    let result = expand(quote!(()), r#"{"port": 70000, "ratio": 1e39, "offset": -129}"#, quote!(const NET: Cfg = Cfg { port: 8080u16, ratio: 0.5f32, offset: 0i8 };));
+    assert!(result.contains("expected an integer that fits in `u16`, found `70000` at `port`"), "{}", result);
+    assert!(result.contains("found `1e39` at `ratio`") && result.contains("found `-129` at `offset`"), "{}", result);
+    let result = expand(quote!((partial)), r#"{"port": 1, "port": 2}"#, item.clone());
+    assert!(result.contains("duplicate key `port`"), "{}", result);
+    let result = expand(quote!((partial)), r#"{"port": 1,}"#, item.clone());
+    assert!(result.contains("[CE0002]") && result.contains("expected a string key at offset 11"), "{}", result);
+    let result = expand(quote!((partial)), "9000", quote!(const NET: u16 = 8080;));
+    assert!(result.contains("[CE0005] `partial` can only be used on items with a struct literal default"), "{}", result);
+}
+
+#[test]
+fn test_json_pointer() {
+    let config = r#"{"server": {"port": 9000, "host": "example.com", "tls": true, "ratio": -0.5}, "hosts": ["a", "b"], "a/b": {"~x": 7}}"#;
+    let expand = |pointer: &str, item| expand_var("APP_CONFIG", config, quote!(("APP_CONFIG", json_pointer = #pointer, no_doc)), item);
+    let cases = [
+        ("/server/port", quote!(const PORT: u16 = 8080;), quote!(const PORT: u16 = 9000;)),
+        ("/server/host", quote!(const HOST: &str = "localhost";), quote!(const HOST: &str = "example.com";)),
+        ("/server/tls", quote!(const TLS: bool = false;), quote!(const TLS: bool = true;)),
+        ("/server/ratio", quote!(const RATIO: f32 = 1.0f32;), quote!(const RATIO: f32 = -0.5f32;)),
+        ("/server/port", quote!(const PORT: u16 = DEFAULT_PORT;), quote!(const PORT: u16 = 9000;)),
+        ("/hosts/1", quote!(const HOST: &str = "";), quote!(const HOST: &str = "b";)),
+        ("/hosts/0", quote!(const HOST: char = ' ';), quote!(const HOST: char = 'a';)),
+        ("/a~1b/~0x", quote!(const X: i64 = -1;), quote!(const X: i64 = 7;)),
+    ];
+    for (pointer, item, expected) in cases {
+        assert_eq!(format!("{}", expected), expand(pointer, item));
+    }
+
+    let errors = [
+        ("/server/port", quote!(const PORT: &str = "";), "[CE0002] Failed to parse environment variable `APP_CONFIG` as JSON: expected a string at `/server/port`, found a number"),
+        ("/server/host", quote!(const HOST: u16 = 0;), "expected an integer at `/server/host`, found a string"),
+        ("/server/ratio", quote!(const RATIO: u8 = 0;), "expected an integer at `/server/ratio`, found a number"),
+        ("/server/port", quote!(const PORT: u8 = 0;), "expected an integer that fits in `u8`, found `9000` at `/server/port`"),
+        ("/server/missing", quote!(const PORT: u16 = 0;), "no key `missing` in the object at `/server`"),
+        ("/missing", quote!(const PORT: u16 = 0;), "no key `missing` in the object at the root"),
+        ("/hosts/2", quote!(const HOST: &str = "";), "index 2 is out of bounds of the array of 2 elements at `/hosts`"),
+        ("/hosts/01", quote!(const HOST: &str = "";), "`01` is not an index of the array at `/hosts`"),
+        ("/hosts/-", quote!(const HOST: &str = "";), "`-` is not an index of the array at `/hosts`"),
+        ("/server/port/x", quote!(const PORT: u16 = 0;), "expected an object or array at `/server/port`, found a number"),
+        ("/server", quote!(const PORT: u16 = 0;), "expected an integer at `/server`, found an object"),
+        ("server", quote!(const PORT: u16 = 0;), "[CE0004] Invalid `json_pointer`: a JSON pointer must be empty or start with `/`"),
+        ("/a~2", quote!(const PORT: u16 = 0;), "Invalid `json_pointer`: `~` in `a~2` must be followed by `0` or `1`"),
+    ];
+    for (pointer, item, expected) in errors {
+        let result = expand(pointer, item);
+        assert!(result.contains(expected), "{}", result);
+    }
+    let env = TestEnv::builder().set("APP_CONFIG", "{").build();
+    let result = format!("{}", from_env(quote!(("APP_CONFIG", json_pointer = "/port")), quote!(const PORT: u16 = 0;), env));
+    assert!(result.contains("Failed to parse environment variable `APP_CONFIG` as JSON: expected a string key at offset 1"), "{}", result);
+    let result = format!("{}", from_env(quote!((json_pointer = "/port", expr)), quote!(const PORT: u16 = 0;), TestEnv::builder().build()));
+    assert!(result.contains("`json_pointer` cannot be combined with `expr`"), "{}", result);
+}
+
+#[test]
+fn test_record_field() {
+    let expand = |attr, value, item| expand_var("BUILD_INFO", value, attr, item);
+    let cases = [
+        (quote!((field = 0, field_sep = "|", no_doc)), "1.4.2|2024-06-01|release", quote!(const BUILD_INFO: &str = "";), quote!(const BUILD_INFO: &str = "1.4.2";)),
+        (quote!((field = 2, field_sep = "|", no_doc)), "1.4.2 | 2024-06-01 | release ", quote!(const BUILD_INFO: &str = "";), quote!(const BUILD_INFO: &str = "release";)),
+        (quote!((field = 1, no_doc)), "a, 42, c", quote!(const BUILD_INFO: u16 = 0;), quote!(const BUILD_INFO: u16 = 42;)),
+        (quote!((field = 1, field_sep = ";", no_doc)), "x;true", quote!(const BUILD_INFO: bool = false;), quote!(const BUILD_INFO: bool = true;)),
+        (quote!((field = 1, field_sep = "|", no_doc)), r"a\|b|c\|d|e", quote!(const BUILD_INFO: &str = "";), quote!(const BUILD_INFO: &str = "c|d";)),
+        (quote!((field = 2, field_sep = "|", no_doc)), r"a|b|\n", quote!(const BUILD_INFO: &str = "";), quote!(const BUILD_INFO: &str = "\n";)),
+        (quote!((field = 1, field_sep = ":", no_doc)), "x:", quote!(const BUILD_INFO: &str = "default";), quote!(const BUILD_INFO: &str = "";)),
+    ];
+    for (attr, value, item, expected) in cases {
+        assert_eq!(format!("{}", expected), expand(attr, value, item));
+    }
+
+    let result = expand(quote!((field = 3, field_sep = "|")), "1.4.2|2024-06-01|release", quote!(const BUILD_INFO: &str = "";));
+    assert!(result.contains("[CE0002] Failed to parse environment variable `BUILD_INFO` as a record: expected at least 4 fields separated by `|` to take field 3, found 3"), "{}", result);
+    let result = expand(quote!((field = 1)), "a,x", quote!(const BUILD_INFO: u16 = 0;));
+    assert!(result.contains("as an integer"), "{}", result);
+    let result = expand(quote!((field_sep = "|")), "a", quote!(const BUILD_INFO: &str = "";));
+    assert!(result.contains("`field_sep` requires `field`"), "{}", result);
+    let result = expand(quote!((field = 0, field_sep = "||")), "a", quote!(const BUILD_INFO: &str = "";));
+    assert!(result.contains("Expected `field_sep` to be a single character"), "{}", result);
+    let result = expand(quote!((field = 0, field_sep = "\\")), "a", quote!(const BUILD_INFO: &str = "";));
+    assert!(result.contains("`field_sep` cannot be a backslash"), "{}", result);
+    let result = expand(quote!((field = 0, json_pointer = "/a")), "a", quote!(const BUILD_INFO: &str = "";));
+    assert!(result.contains("`field` cannot be combined with `json_pointer`"), "{}", result);
+}
+
+#[test]
+fn test_capture() {
+    let expand = |attr, value, item| expand_var("RUSTC_VERSION", value, attr, item);
+    let version = "rustc 1.79.0 (129f3b996 2024-06-10)";
+    let cases = [
+        (quote!((capture = r"^rustc (\d+\.\d+)", no_doc)), version, quote!(const RUSTC_VERSION: &str = "";), quote!(const RUSTC_VERSION: &str = "1.79";)),
+        (quote!((capture = r"(?P<minor>\d+)\.\d+ \(", capture_group = "minor", no_doc)), version, quote!(const RUSTC_VERSION: u32 = 0;), quote!(const RUSTC_VERSION: u32 = 79;)),
+        (quote!((capture = r"(\w+) (\d+)", capture_group = 2, no_doc)), version, quote!(const RUSTC_VERSION: u8 = 0;), quote!(const RUSTC_VERSION: u8 = 1;)),
+        (quote!((capture = r"\d{4}-\d\d-\d\d", no_doc)), version, quote!(const RUSTC_VERSION: &str = "";), quote!(const RUSTC_VERSION: &str = "2024-06-10";)),
+        (quote!((capture = r"^rustc (\d+)\.(\d+)", fixed_point = 2, no_doc)), version, quote!(const RUSTC_VERSION: u32 = 0;), quote!(const RUSTC_VERSION: u32 = 100;)),
+        (quote!((capture = r"-(\w+)$", one_of("beta", "nightly"), no_doc)), "rustc 1.80.0-nightly", quote!(const RUSTC_VERSION: &str = "";), quote!(const RUSTC_VERSION: &str = "nightly";)),
+    ];
+    for (attr, value, item, expected) in cases {
+        assert_eq!(format!("{}", expected), expand(attr, value, item));
+    }
+
+    let result = expand(quote!((capture = r"^rustc (\d+\.\d+)")), "cargo 1.79.0", quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains(r"[CE0002] Environment variable `RUSTC_VERSION` does not match the `capture` pattern `^rustc (\\d+\\.\\d+)`"), "{}", result);
+    assert!(result.contains("value: \\\"cargo 1.79.0\\\""), "{}", result);
+    let result = expand(quote!((capture = r"(-\w+)?$")), "rustc 1.79.0", quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains("matches the `capture` pattern `(-\\\\w+)?$`, but group 1 is not part of the match"), "{}", result);
+    let result = expand(quote!((capture = r"-(\w+)$", one_of("beta", "nightly"))), "rustc 1.80.0-dev", quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains("is `\\\"dev\\\"`, which is not one of"), "{}", result);
+    let result = expand(quote!((capture = r"(\d+")), version, quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains("Invalid `capture` pattern"), "{}", result);
+    let result = expand(quote!((capture_group = 1)), version, quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains("`capture_group` requires `capture`"), "{}", result);
+    let result = expand(quote!((capture = r"(\d+)", capture_group = "ver")), version, quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains("The `capture` pattern has no group named `ver`"), "{}", result);
+    let result = expand(quote!((capture = r"(\d+)", capture_group = 2)), version, quote!(const RUSTC_VERSION: &str = "";));
+    assert!(result.contains("The `capture` pattern has no group 2; it has 1 groups"), "{}", result);
+    let result = expand(quote!((capture = r"(\d+)", split = "path_list")), version, quote!(const RUSTC_VERSION: &[&str] = &[];));
+    assert!(result.contains("`capture` cannot be combined with `split`"), "{}", result);
+}
+
+#[test]
+fn test_prefix_map() {
+    let env = || TestEnv::builder()
+        .set("FEATURE_LOGGING", "on")
+        .set("FEATURE_BETA", "say \"hi\"\n")
+        .set("FEATURE_", "empty suffix")
+        .set("FEATURES", "not under the prefix")
+        .set("OTHER_FEATURE_X", "1")
+        .build();
+    let item = quote!(static FEATURES: &[(&str, &str)] = &[];);
+    let result = from_env(quote!((prefix_map = "FEATURE_", no_doc)), item.clone(), env());
+    assert_eq!(format!("{}", quote!(static FEATURES: &[(&str, &str)] = &[("BETA", "say \"hi\"\n"), ("LOGGING", "on")];)), format!("{}", result));
+    let result = from_env(quote!((prefix_map = "UNSET_", no_doc)), quote!(static FEATURES: &[(&str, &str)] = &[("a", "b")];), env());
+    assert_eq!(format!("{}", quote!(static FEATURES: &[(&str, &str)] = &[];)), format!("{}", result));
+
+    let result = from_env(quote!((prefix_map = "FEATURE_")), item.clone(), env()).to_string();
+    assert!(result.contains("Collected at build time from the environment variables starting with `FEATURE_`."), "{}", result);
+    let result = from_env(quote!((prefix_map = "FEATURE_", max_items = 1)), item.clone(), env()).to_string();
+    assert!(result.contains("[CE0006] Found 2 environment variables starting with `FEATURE_`, more than the maximum of 1"), "{}", result);
+    let result = from_env(quote!((prefix_map = "UNSET_", min_items = 1)), item.clone(), env()).to_string();
+    assert!(result.contains("Found 0 environment variables starting with `UNSET_`, fewer than the minimum of 1"), "{}", result);
+    let result = from_env(quote!((prefix_map = "FEATURE_")), quote!(static FEATURES: &[&str] = &[];), env()).to_string();
+    assert!(result.contains("`prefix_map` can only be used on items with a `&[(&str, &str)]` default"), "{}", result);
+    let result = from_env(quote!(("FEATURES", prefix_map = "FEATURE_")), item.clone(), env()).to_string();
+    assert!(result.contains("`prefix_map` cannot be combined with a variable name"), "{}", result);
+    let result = from_env(quote!((prefix_map = "")), item, env()).to_string();
+    assert!(result.contains("`prefix_map` cannot be empty"), "{}", result);
+}
+
+/// The bytes of the `[u8; N]` array initializing the item named `name` of `file`.
+fn array_bytes(file: &syn::File, name: &str) -> Vec<u8> {
+    let expr = file.items.iter().find_map(|item| match item {
+        syn::Item::Const(item) if item.ident == name => Some(&*item.expr),
+        syn::Item::Static(item) if item.ident == name => Some(&*item.expr),
+        _ => None,
+    }).unwrap_or_else(|| panic!("no item `{}`", name));
+    match expr {
+        syn::Expr::Array(array) => array.elems.iter().map(|elem| match elem {
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. }) => lit.base10_parse().unwrap(),
+            other => panic!("not a byte: {}", quote!(#other)),
+        }).collect(),
+        other => panic!("not an array: {}", quote!(#other)),
+    }
+}
+
+#[test]
+fn test_obfuscate() {
+    // Byte string values are written with escapes, as in a literal.
+    let bytes: Vec<u8> = (0..=255).collect();
+    let escaped: String = bytes.iter().map(|byte| format!("\\x{:02x}", byte)).collect();
+    let cases: [(&str, TokenStream, &str, &[u8]); 4] = [
+        ("s3cr3t-t0ken", quote!(const API_TOKEN: &str = "";), "fn api_token_deobfuscated () -> String", b"s3cr3t-t0ken"),
+        ("", quote!(static API_TOKEN: &'static str = "unused";), "fn api_token_deobfuscated () -> String", b""),
+        (&escaped, quote!(const API_TOKEN: &[u8] = b"";), "fn api_token_deobfuscated () -> [u8 ; 256usize]", &bytes),
+        (r#"quote \" and \\ backslash"#, quote!(pub static API_TOKEN: &'static [u8] = b"";), "pub fn api_token_deobfuscated () -> [u8 ; 23usize]", br#"quote " and \ backslash"#),
+    ];
+    for (value, item, helper, expected) in cases {
+        let env = TestEnv::builder().set("API_TOKEN", value).build();
+        let result = from_env(quote!((obfuscate, no_doc)), item, env);
+        let output = result.to_string();
+        assert!(output.contains(helper), "{}", output);
+        if value.len() >= 4 {
+            assert!(!output.contains(&value[..4]), "{}", output);
+        }
+        let file: syn::File = syn::parse2(result).unwrap();
+        let obfuscated = array_bytes(&file, "API_TOKEN");
+        let key = array_bytes(&file, "API_TOKEN_KEY");
+        assert!(key.iter().all(|byte| *byte != 0), "{:?}", key);
+        let restored: Vec<u8> = obfuscated.iter().zip(&key).map(|(byte, key)| byte ^ key).collect();
+        assert_eq!(expected, &restored[..]);
+    }
+
+    let env = || TestEnv::builder().set("API_TOKEN", "s3cr3t").build();
+    let result = from_env(quote!((obfuscate, emit_default)), quote!(const API_TOKEN: &str = "";), env()).to_string();
+    assert!(result.contains("`obfuscate` cannot be combined with `emit_default`"), "{}", result);
+    let result = from_env(quote!((obfuscate, register)), quote!(const API_TOKEN: &str = "";), env()).to_string();
+    assert!(result.contains("`obfuscate` cannot be combined with `register`"), "{}", result);
+    let result = from_env(quote!((obfuscate)), quote!(const API_TOKEN: u32 = 0;), TestEnv::builder().set("API_TOKEN", "7").build()).to_string();
+    assert!(result.contains("`obfuscate` can only be used on `&str` and `&[u8]` items"), "{}", result);
+    let result = from_env(quote!((obfuscate, one_of("x"))), quote!(const API_TOKEN: &str = "";), TestEnv::builder().set("API_TOKEN", "abc").build()).to_string();
+    assert!(result.contains("is <redacted>, which is not one of") && !result.contains("abc"), "{}", result);
+
+    let report = std::env::temp_dir().join(format!("const_env_obfuscate_report_{}.jsonl", std::process::id()));
+    let env = TestEnv::builder().set("API_TOKEN", "s3cr3t").set("CONST_ENV_REPORT", report.to_str().unwrap()).build();
+    let result = from_env(quote!((obfuscate)), quote!(const API_TOKEN: &str = "";), env).to_string();
+    assert!(result.contains("`obfuscate` cannot be combined with `CONST_ENV_REPORT`, which records the value of every item"), "{}", result);
+    assert!(!report.exists());
+}
+
+#[test]
+fn test_presence() {
+    let env = || TestEnv::builder()
+        .set("GITHUB_ACTIONS", "")
+        .set("GITLAB_CI", "not a bool")
+        .set("CONST_ENV_DEBUG", "1")
+        .build();
+    let item = quote!(const IS_CI: bool = false;);
+    let set = quote!(const IS_CI: bool = true;);
+    let cases = [
+        (quote!((any_set("CI", "GITHUB_ACTIONS", "BUILDKITE"), no_doc)), &set),
+        (quote!((any_set("CI", "BUILDKITE"), no_doc)), &item),
+        (quote!((all_set("GITHUB_ACTIONS", "GITLAB_CI"), no_doc)), &set),
+        (quote!((all_set("GITHUB_ACTIONS", "CI"), no_doc)), &item),
+        (quote!((not("CI"), no_doc)), &set),
+        (quote!((not(any_set("CI", "GITLAB_CI")), no_doc)), &item),
+        (quote!((any_set("CI", all_set("GITHUB_ACTIONS", not("BUILDKITE"))), no_doc)), &set),
+    ];
+    for (attr, expected) in cases {
+        let result = from_env(attr.clone(), item.clone(), env());
+        assert_eq!(format!("{}", expected), format!("{}", result), "{}", attr);
+    }
+    let result = from_env(quote!((any_set("GITHUB_ACTIONS"), invert, no_doc)), quote!(const COLOR: bool = true;), env());
+    assert_eq!(format!("{}", quote!(const COLOR: bool = false;)), format!("{}", result));
+    let result = from_env(quote!((any_set("CI"), invert, no_doc)), quote!(const COLOR: bool = true;), env());
+    assert_eq!(format!("{}", quote!(const COLOR: bool = true;)), format!("{}", result));
+
+    let result = from_env(quote!((any_set("CI", "GITHUB_ACTIONS"))), item.clone(), env()).to_string();
+    assert!(result.contains("Decided at build time by which of the `CI`, `GITHUB_ACTIONS` environment variables are set."), "{}", result);
+    let result = from_env(quote!((any_set("CI"))), quote!(const RETRIES: u32 = 0;), env()).to_string();
+    assert!(result.contains("`any_set` can only be used on items with a boolean default"), "{}", result);
+    let result = from_env(quote!((any_set())), item.clone(), env()).to_string();
+    assert!(result.contains("`any_set` needs at least one variable name"), "{}", result);
+    let result = from_env(quote!((not("CI", "BUILDKITE"))), item.clone(), env()).to_string();
+    assert!(result.contains("`not` takes exactly one variable name or combinator"), "{}", result);
+    let result = from_env(quote!((any_set("CI", exists("BUILDKITE")))), item.clone(), env()).to_string();
+    assert!(result.contains("Expected a variable name or `any_set(...)`, `all_set(...)` or `not(...)`"), "{}", result);
+    let result = from_env(quote!((any_set("CI"), all_set("BUILDKITE"))), item.clone(), env()).to_string();
+    assert!(result.contains("`all_set` cannot be combined with `any_set`; nest one inside the other instead"), "{}", result);
+    let result = from_env(quote!(("IS_CI", any_set("CI"))), item.clone(), env()).to_string();
+    assert!(result.contains("`any_set` cannot be combined with a variable name"), "{}", result);
+    let result = from_env(quote!((all_set("CI"), exists)), item, env()).to_string();
+    assert!(result.contains("`all_set` cannot be combined with `exists`"), "{}", result);
+}
+
+#[test]
+fn test_split_into() {
+    let env = |value: &str| TestEnv::builder().set("ENDPOINT", value).build();
+    let module = quote! {
+        mod endpoint {
+            pub const HOST: &str = "localhost";
+            pub const PORT: u16 = 5432;
+            pub const SCHEME: &str = "tcp";
+        }
+    };
+    let attr = quote!((split_into(HOST = 0, PORT = 1), split_sep = ":", no_doc));
+    let result = from_env(attr.clone(), module.clone(), env("db.internal:6432"));
+    let expected = quote! {
+        mod endpoint {
+            pub const HOST: &str = "db.internal";
+            pub const PORT: u16 = 6432;
+            pub const SCHEME: &str = "tcp";
+        }
+    };
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = from_env(attr.clone(), module.clone(), env("db.internal"));
+    let expected = quote! {
+        mod endpoint {
+            pub const HOST: &str = "db.internal";
+            pub const PORT: u16 = 5432;
+            pub const SCHEME: &str = "tcp";
+        }
+    };
+    assert_eq!(format!("{}", expected), format!("{}", result));
+    let result = from_env(attr.clone(), module.clone(), env("db.internal:6432:extra"));
+    assert!(result.to_string().contains("\"db.internal\""), "{}", result);
+    let result = from_env(attr.clone(), module.clone(), TestEnv::builder().build());
+    assert_eq!(format!("{}", module), format!("{}", result));
+    let result = from_env(quote!(("DB_ENDPOINT", split_into(PORT = 1), no_doc)), module.clone(), TestEnv::builder().set("DB_ENDPOINT", "db, 7000").build());
+    assert!(result.to_string().contains("pub const PORT : u16 = 7000"), "{}", result);
+    let result = from_env(quote!((split_into(HOST = 0, PORT = 1), split_sep = ":")), module.clone(), env("db:1")).to_string();
+    assert!(result.contains("Configurable at build time via the `ENDPOINT` environment variable."), "{}", result);
+
+    let result = from_env(quote!((split_into(HOST = 0, PORT = 1), split_sep = ":", strict_fields)), module.clone(), env("db:6432:extra")).to_string();
+    assert!(result.contains("[CE0006] Environment variable `ENDPOINT` has 3 fields separated by `:`, more than the 2 of `split_into`"), "{}", result);
+    assert!(result.contains("mod endpoint"), "{}", result);
+    let result = from_env(attr.clone(), module.clone(), env("db:not a port")).to_string();
+    assert!(result.contains("`ENDPOINT`"), "{}", result);
+    assert!(result.contains("pub const HOST : & str = \"db\""), "{}", result);
+    let result = from_env(quote!((split_into(HOST = 0, USER = 2))), module.clone(), env("db")).to_string();
+    assert!(result.contains("`split_into` names `USER`, which is not a `const` or `static` item of the module"), "{}", result);
+    let result = from_env(quote!((split_into(HOST = 0))), quote!(const HOST: &str = "";), env("db")).to_string();
+    assert!(result.contains("`split_into` can only be used on a module, whose `const` and `static` items it names"), "{}", result);
+    let result = from_env(quote!((split_into(HOST = 0, HOST = 1))), module.clone(), env("db")).to_string();
+    assert!(result.contains("`HOST` is named more than once in `split_into`"), "{}", result);
+    let result = from_env(quote!((split_sep = ":")), quote!(const HOST: &str = "";), env("db")).to_string();
+    assert!(result.contains("`split_sep` requires `split_into`"), "{}", result);
+    let result = from_env(quote!((split_into(HOST = 0), field = 1)), module, env("db")).to_string();
+    assert!(result.contains("`split_into` cannot be combined with `field`"), "{}", result);
+}
+
+#[test]
+fn test_endian() {
+    let env = |value: &str| TestEnv::builder().set("MAGIC", value).build();
+    let item = quote!(const MAGIC: [u8; 4] = [0; 4];);
+    let cases = [
+        (quote!((endian = "big", no_doc)), "0xDEADBEEF", quote!(const MAGIC: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];)),
+        (quote!((endian = "little", no_doc)), "0xDEAD_BEEF", quote!(const MAGIC: [u8; 4] = [0xef, 0xbe, 0xad, 0xde];)),
+        (quote!((endian = "big", no_doc)), "258", quote!(const MAGIC: [u8; 4] = [0x00, 0x00, 0x01, 0x02];)),
+        (quote!((endian = "little", no_doc)), "0b1", quote!(const MAGIC: [u8; 4] = [0x01, 0x00, 0x00, 0x00];)),
+        (quote!((endian = "big", no_doc)), "0", quote!(const MAGIC: [u8; 4] = [0x00, 0x00, 0x00, 0x00];)),
+    ];
+    for (attr, value, expected) in cases {
+        let result = from_env(attr, item.clone(), env(value));
+        assert_eq!(format!("{}", expected), format!("{}", result), "{}", value);
+    }
+    let result = from_env(quote!((endian = "big", no_doc)), quote!(const MAGIC: [u8; 2] = *b"\0\0";), env("0x7f45"));
+    assert_eq!(format!("{}", quote!(const MAGIC: [u8; 2] = *b"\x7FE";)), format!("{}", result));
+    let result = from_env(quote!((endian = "big", no_doc)), quote!(const WIDE: [u8; 20] = [0; 20];), TestEnv::builder().set("WIDE", "1").build()).to_string();
+    assert!(result.ends_with("0x00 , 0x01] ;"), "{}", result);
+
+    let result = from_env(quote!((endian = "big")), item.clone(), env("0x1_0000_0000")).to_string();
+    assert!(result.contains("[CE0006] Environment variable `MAGIC` needs 5 bytes, more than the 4 bytes of the default"), "{}", result);
+    for value in ["-1", "+1", "0xG", ""] {
+        let result = from_env(quote!((endian = "big")), item.clone(), env(value)).to_string();
+        assert!(result.contains("as a non-negative integer"), "{}: {}", value, result);
+    }
+    let result = from_env(quote!((endian = "big")), quote!(const MAGIC: [u16; 4] = [0; 4];), env("1")).to_string();
+    assert!(result.contains("or fixed-size byte array default"), "{}", result);
+    let result = from_env(quote!((endian = "big")), quote!(const MAGIC: u32 = 0;), env("1")).to_string();
+    assert!(result.contains("`endian` can only be used on items with"), "{}", result);
+    let result = from_env(quote!((endian = "middle")), item.clone(), env("1")).to_string();
+    assert!(result.contains("Expected `endian` to be \\\"big\\\" or \\\"little\\\""), "{}", result);
+    let result = from_env(quote!((endian = "big", pad = 0)), item, env("1")).to_string();
+    assert!(result.contains("`endian` cannot be combined with `pad`"), "{}", result);
+}
+
+#[test]
+fn test_consts_from_prefix() {
+    let env = || TestEnv::builder()
+        .set("LIMIT_USERS", "500")
+        .set("LIMIT_UPLOADS", "0x10")
+        .set("LIMIT_", "no suffix")
+        .set("LIMITS", "not under the prefix")
+        .build();
+    let result = consts_from_prefix(quote!(prefix = "LIMIT_", ty = u32, vis = pub), env());
+    let expected = quote! {
+        #[doc = " *Configurable at build time via the `LIMIT_UPLOADS` environment variable.*"]
+        pub const UPLOADS: u32 = 0x10;
+        #[doc = " *Configurable at build time via the `LIMIT_USERS` environment variable.*"]
+        pub const USERS: u32 = 500;
+    };
+    assert_eq!(format!("{}", expected), format!("{}", result));
+    let result = consts_from_prefix(quote!(ty = &str, prefix = "LIMIT_U"), env());
+    assert!(result.to_string().contains("const SERS : & str = \"500\""), "{}", result);
+    let result = consts_from_prefix(quote!(prefix = "UNSET_", ty = bool), env());
+    assert!(result.is_empty(), "{}", result);
+
+    let result = consts_from_prefix(quote!(prefix = "UNSET_", ty = bool, required), env()).to_string();
+    assert!(result.contains("[CE0001] No environment variables start with `UNSET_`, but at least one is required"), "{}", result);
+    let env = || TestEnv::builder().set("LIMIT_USERS", "many").set("LIMIT_1X", "1").set("LIMIT_type", "1").set("LIMIT_RATE", "2").build();
+    let result = consts_from_prefix(quote!(prefix = "LIMIT_", ty = u32), env()).to_string();
+    assert!(result.contains("const RATE : u32 = 2"), "{}", result);
+    assert!(result.contains("`LIMIT_1X` would name a constant `1X`, which is not a valid identifier"), "{}", result);
+    assert!(result.contains("`LIMIT_type` would name a constant `type`, which is not a valid identifier"), "{}", result);
+    assert!(result.contains("`LIMIT_USERS`"), "{}", result);
+    let result = consts_from_prefix(quote!(prefix = "LIMIT_", ty = Vec<u32>), env()).to_string();
+    assert!(result.contains("`consts_from_prefix!` cannot parse values as `Vec<u32>`"), "{}", result);
+    let result = consts_from_prefix(quote!(ty = u32), env()).to_string();
+    assert!(result.contains("Missing `prefix = "), "{}", result);
+    let result = consts_from_prefix(quote!(prefix = "LIMIT_", ty = u32, prefix = "X_"), env()).to_string();
+    assert!(result.contains("Duplicate argument `prefix`"), "{}", result);
+    let result = consts_from_prefix(quote!(prefix = "LIMIT_", ty = u32, name = "X"), env()).to_string();
+    assert!(result.contains("Unknown argument `name`"), "{}", result);
+}
+
+#[test]
+fn test_fingerprint() {
+    let env = |crate_name: &str, port: &str| TestEnv::builder()
+        .set("CONST_ENV_FINGERPRINT", "1")
+        .set("CONST_ENV_DUPLICATES", "off")
+        .set("CARGO_CRATE_NAME", crate_name)
+        .set("PORT", port)
+        .set("TOKEN", "hunter2")
+        .build();
+
+    // The fingerprint does not depend on the order in which items are expanded.
+    from_env(quote!(), quote!(const PORT: u16 = 80;), env("fingerprint_a", "8080"));
+    from_env(quote!((sensitive)), quote!(const TOKEN: &str = "";), env("fingerprint_a", "8080"));
+    from_env(quote!((sensitive)), quote!(const TOKEN: &str = "";), env("fingerprint_b", "8080"));
+    from_env(quote!(), quote!(const PORT: u16 = 80;), env("fingerprint_b", "8080"));
+    let fingerprint_a = format!("{}", fingerprint(quote!(), env("fingerprint_a", "8080")));
+    assert!(fingerprint_a.starts_with("0x") && fingerprint_a.ends_with("u64"), "{}", fingerprint_a);
+    assert_eq!(fingerprint_a, format!("{}", fingerprint(quote!(), env("fingerprint_b", "8080"))));
+
+    // It changes with a value.
+    from_env(quote!(), quote!(const PORT: u16 = 80;), env("fingerprint_c", "9090"));
+    from_env(quote!((sensitive)), quote!(const TOKEN: &str = "";), env("fingerprint_c", "9090"));
+    assert_ne!(fingerprint_a, format!("{}", fingerprint(quote!(), env("fingerprint_c", "9090"))));
+
+    // An item first expanded after the fingerprint is an error. An item expanded again,
+    // as rust-analyzer does as it is edited, was included.
+    let result = from_env(quote!((no_doc)), quote!(const PORT: u16 = 80;), env("fingerprint_a", "9090"));
+    assert_eq!(format!("{}", result), format!("{}", quote!(const PORT: u16 = 9090;)));
+    let result = format!("{}", from_env(quote!(), quote!(const HOST: &str = "localhost";), env("fingerprint_a", "8080")));
+    assert!(result.contains("[CE0021] `HOST` is expanded after `fingerprint!()`"), "{}", result);
+
+    let result = format!("{}", fingerprint(quote!(PORT), env("fingerprint_a", "8080")));
+    assert!(result.contains("`fingerprint!` takes no arguments"), "{}", result);
+
+    // Items only record their values when the crate opts in.
+    let env = || TestEnv::builder().set("CONST_ENV_DUPLICATES", "off").set("CARGO_CRATE_NAME", "fingerprint_d").build();
+    let result = format!("{}", from_env(quote!((no_doc)), quote!(const PORT: u16 = 80;), env()));
+    assert_eq!(result, format!("{}", quote!(const PORT: u16 = 80;)));
+    let result = format!("{}", fingerprint(quote!(), env()));
+    assert!(result.contains("[CE0014] `fingerprint!()` needs `CONST_ENV_FINGERPRINT=1`"), "{}", result);
+}
+
+#[test]
+fn test_errors_are_aggregated() {
+    let errors = |attr: TokenStream, item: TokenStream, value: &str| -> Vec<String> {
+        let env = TestEnv::builder().set("WEIGHTS", value).build();
+        let result = from_env(attr, item, env).to_string();
+        result.match_indices("compile_error").map(|(index, _)| result[index..].split(" }").next().unwrap().to_string()).collect()
+    };
+    let item = quote!(const WEIGHTS: [u8; 4] = [1, 2, 3, 4];);
+
+    let found = errors(quote!(), item.clone(), "[10, abc, 300, -1]");
+    assert_eq!(3, found.len(), "{:?}", found);
+    assert!(found[0].contains("found `abc` at index [1]"), "{:?}", found);
+    assert!(found[1].contains("found `300` at index [2]"), "{:?}", found);
+    assert!(found[2].contains("found `-1` at index [3]"), "{:?}", found);
+
+    // Elements that fit the type are still validated.
+    let found = errors(quote!((max = 100)), item.clone(), "[10, 200, 300, 1]");
+    assert_eq!(2, found.len(), "{:?}", found);
+    assert!(found[0].contains("[CE0002]") && found[0].contains("found `300` at index [2]"), "{:?}", found);
+    assert!(found[1].contains("[CE0006] Element 1 of environment variable `WEIGHTS` is `200`, which is above the maximum of 100"), "{:?}", found);
+
+    let found = errors(quote!((min = 2)), item.clone(), "[1, 2, 3, 0]");
+    assert_eq!(2, found.len(), "{:?}", found);
+    assert!(found[0].contains("Element 0 ") && found[1].contains("Element 3 "), "{:?}", found);
+
+    let found = errors(quote!(), quote!(const WEIGHTS: (u8, &str, bool) = (1, "a", true);), "(300, \"b\", 7)");
+    assert_eq!(2, found.len(), "{:?}", found);
+    assert!(found[0].contains("as a tuple: expected an integer that fits in `u8`, found `300` at field .0"), "{:?}", found);
+    assert!(found[1].contains("expected a boolean, found an integer at field .2"), "{:?}", found);
+
+    let found = errors(quote!(), quote!(const WEIGHTS: Weights = Weights { low: 1, high: 2 };), r#"{"low": "a", "high": 2.5, "mid": 1}"#);
+    assert_eq!(3, found.len(), "{:?}", found);
+    assert!(found[0].contains("expected an integer at `low`, found a string"), "{:?}", found);
+    assert!(found[1].contains("expected an integer at `high`, found a number"), "{:?}", found);
+    assert!(found[2].contains("unknown key `mid`"), "{:?}", found);
+}
+
+#[test]
+fn test_tuple() {
+    let env = TestEnv::builder()
+        .set("LIMITS", "(10, \"soft\", false)")
+        .build();
+    let result = from_env(quote!((no_doc)), quote!(const LIMITS: (u8, &str, bool) = (1, "hard", true);), env);
+    assert_eq!(format!("{}", quote!(const LIMITS: (u8, &str, bool) = (10, "soft", false);)), format!("{}", result));
+}
+
+#[test]
+fn test_float_range() {
+    let result = |item: TokenStream, value: &str| format!("{}", from_env(quote!((no_doc)), item, TestEnv::builder().set("GAIN", value).build()));
+    let out = result(quote!(const GAIN: f32 = 1.0;), "3.5e38");
+    assert!(out.contains("Failed to parse environment variable `GAIN` as a float: expected a float that fits in `f32`, found `3.5e38`"), "{}", out);
+    let out = result(quote!(const GAIN: f64 = 1.0;), "1e400");
+    assert!(out.contains("expected a float that fits in `f64`, found `1e400`"), "{}", out);
+    // Without a declared float type, the suffix of the default or of the value decides.
+    let out = result(quote!(const GAIN: Gain = 1.0f32;), "3.5e38");
+    assert!(out.contains("expected a float that fits in `f32`, found `3.5e38`"), "{}", out);
+    let out = result(quote!(const GAIN: Gain = 1.0;), "-3.5e38f32");
+    assert!(out.contains("expected a float that fits in `f32`, found `3.5e38`"), "{}", out);
+    let out = result(quote!(const GAIN: Gain = 1.0;), "3.5e38");
+    assert_eq!(out, format!("{}", quote!(const GAIN: Gain = 3.5e38;)));
+    let out = result(quote!(const GAIN: f32 = 1.0;), "0.1000000000000000001");
+    assert_eq!(out, format!("{}", quote!(const GAIN: f32 = 0.1000000000000000001;)));
+}
+
+#[test]
+fn test_strict_precision() {
+    let result = |item: TokenStream, value: &str| format!("{}", from_env(quote!((no_doc, strict_precision)), item, TestEnv::builder().set("GAIN", value).build()));
+    let out = result(quote!(const GAIN: f32 = 1.0;), "0.1000000000000000001");
+    assert!(out.contains("[CE0006] Environment variable `GAIN` is `0.1000000000000000001`, which has more digits than `f32` keeps and would round to `0.1`"), "{}", out);
+    let out = result(quote!(const GAIN: f64 = 1.0;), "-0.1000000000000000001");
+    assert!(out.contains("is `-0.1000000000000000001`, which has more digits than `f64` keeps and would round to `-0.1`"), "{}", out);
+    let out = result(quote!(const GAIN: f32 = 1.0;), "1e-50");
+    assert!(out.contains("would round to `0.0`"), "{}", out);
+    let out = result(quote!(const GAIN: [f32; 2] = [1.0, 2.0];), "[0.25, 0.30000001]");
+    assert!(out.contains("Element 1 of environment variable `GAIN` is `0.30000001`"), "{}", out);
+
+    // Values the type rounds back to as written are kept.
+    for value in ["0.1", "1.5e-3", "16777216.0", "3.4028235e38"] {
+        let out = result(quote!(const GAIN: f32 = 1.0;), value);
+        assert!(!out.contains("compile_error"), "{}", out);
+    }
+
+    let out = format!("{}", from_env(quote!((strict_precision, sensitive)), quote!(const GAIN: f32 = 1.0;), TestEnv::builder().set("GAIN", "0.1000000000000000001").build()));
+    assert!(out.contains("Environment variable `GAIN` has more digits than `f32` keeps") && !out.contains("0.1000000000000000001"), "{}", out);
+    let out = format!("{}", from_env(quote!((strict_precision)), quote!(const GAIN: u32 = 1;), TestEnv::builder().build()));
+    assert!(out.contains("`strict_precision` can only be used on items with a float type or default"), "{}", out);
+}
+
+#[test]
+fn test_ranges() {
+    let expand = |attr, item, value: &str| expand_var("CORES", value, attr, item);
+    let cases = [
+        (quote!((no_doc, ranges)), quote!(const CORES: &'static [u16] = &[0];), "0-3,8,10-11", quote!(const CORES: &'static [u16] = &[0, 1, 2, 3, 8, 10, 11];)),
+        (quote!((no_doc, ranges)), quote!(const CORES: [u8; 4] = [0; 4];), " 4 - 6 , 9 ", quote!(const CORES: [u8; 4] = [4, 5, 6, 9];)),
+        (quote!(("CORES", no_doc, ranges)), quote!(const OFFSETS: &[i8] = &[];), "-3--1,2", quote!(const OFFSETS: &[i8] = &[-3, -2, -1, 2];)),
+        (quote!((no_doc, ranges)), quote!(const CORES: &[u16] = &[0];), "", quote!(const CORES: &[u16] = &[];)),
+        (quote!((no_doc, ranges, dedup)), quote!(const CORES: &[u16] = &[0];), "0-3,2-5,1", quote!(const CORES: &[u16] = &[0, 1, 2, 3, 4, 5];)),
+        (quote!((no_doc, ranges, dedup)), quote!(const CORES: &[u16] = &[0];), "3-1", quote!(const CORES: &[u16] = &[3, 2, 1];)),
+        (quote!((no_doc, ranges, sort)), quote!(const CORES: &[u16] = &[0];), "8,0-1", quote!(const CORES: &[u16] = &[0, 1, 8];)),
+        (quote!((no_doc, ranges, ty = "[u32; 2]")), quote!(const CORES: Cores = [0u32, 1u32];), "4-5", quote!(const CORES: Cores = [4u32, 5u32];)),
+    ];
+    for (attr, item, value, expected) in cases {
+        assert_eq!(expand(attr, item, value), format!("{}", expected));
+    }
+
+    let result = |item: TokenStream, value: &str| expand(quote!((min = 1, max = 100, ranges)), item, value);
+    let out = result(quote!(const CORES: &[u16] = &[0];), "1-3,x,4-,5-6-7");
+    assert!(out.contains("[CE0002] Segment `x` of environment variable `CORES` is not an integer or an inclusive range such as `0-3`"), "{}", out);
+    assert!(out.contains("Segment `4-` of") && out.contains("Segment `5-6-7` of") && !out.contains("1-3,x"), "{}", out);
+    let out = result(quote!(const CORES: &[u8] = &[0];), "1,250-300");
+    assert!(out.contains("Segment `250-300` of environment variable `CORES` includes `300`, which does not fit in `u8`"), "{}", out);
+    let out = result(quote!(const CORES: &[u16] = &[1];), "5-2,1-3,3");
+    assert!(out.contains("[CE0006] Segment `5-2` of environment variable `CORES` is a reversed range; write it as `2-5`, or add `dedup` to allow it"), "{}", out);
+    assert!(out.contains("Segment `3` of environment variable `CORES` overlaps `1-3`; add `dedup` to allow this"), "{}", out);
+    let out = result(quote!(const CORES: &[u16] = &[1];), "0-2,99-101");
+    assert!(out.contains("Element 0 of environment variable `CORES` is `0`, which is below the minimum of 1"), "{}", out);
+    assert!(out.contains("Element 5 of environment variable `CORES` is `101`, which is above the maximum of 100"), "{}", out);
+    let out = result(quote!(const CORES: [u16; 4] = [1; 4];), "1-3,8,10");
+    assert!(out.contains("Environment variable `CORES` expands to 5 elements, but the array has 4; use a slice to allow any number"), "{}", out);
+    let out = result(quote!(const CORES: &[u32] = &[1];), "1-100000");
+    assert!(out.contains("expands to 100000 elements, more than the limit of 65536"), "{}", out);
+
+    let out = expand(quote!((ranges, sensitive)), quote!(const CORES: &[u16] = &[0];), "secret-1");
+    assert!(out.contains("A segment of environment variable `CORES` is not an integer") && !out.contains("secret"), "{}", out);
+    let out = expand(quote!((ranges)), quote!(const CORES: &[&str] = &["a"];), "1");
+    assert!(out.contains("`ranges` can only be used on items with an integer array or slice type or default"), "{}", out);
+    let out = expand(quote!((ranges, expr)), quote!(const CORES: &[u16] = &[0];), "1");
+    assert!(out.contains("`ranges` cannot be combined with `expr`"), "{}", out);
+}
+
+#[test]
+fn test_declared_type() {
+    let expand = |attr, value, item| expand_var("VALUE", value, attr, item);
+    let cases = [
+        (quote!(const VALUE: u32 = DEFAULT_VALUE;), "42", quote!(const VALUE: u32 = 42;)),
+        (quote!(const VALUE: i8 = i8::MIN + 1;), "-128", quote!(const VALUE: i8 = -128;)),
+        (quote!(const VALUE: f64 = core::f64::consts::PI;), "2.5", quote!(const VALUE: f64 = 2.5;)),
+        (quote!(const VALUE: bool = cfg!(unix);), "true", quote!(const VALUE: bool = true;)),
+        (quote!(const VALUE: char = DEFAULT;), "x", quote!(const VALUE: char = 'x';)),
+        (quote!(const VALUE: &str = concat!("a", "b");), "c d", quote!(const VALUE: &str = "c d";)),
+        (quote!(const VALUE: &[u8] = DEFAULT;), "key", quote!(const VALUE: &[u8] = b"key";)),
+        (quote!(const VALUE: &[u8] = &[1, 2];), "[3, 4, 5]", quote!(const VALUE: &[u8] = &[3, 4, 5];)),
+        (quote!(const VALUE: [u16; 2] = DEFAULTS;), "[80, 443]", quote!(const VALUE: [u16; 2] = [80, 443];)),
+        (quote!(const VALUE: &'static [&'static str] = &[];), r#"["a", "b"]"#, quote!(const VALUE: &'static [&'static str] = &["a", "b"];)),
+        (quote!(const VALUE: u8 = b'a';), "7", quote!(const VALUE: u8 = b'7';)),
+        (quote!(const VALUE: Port = DEFAULT;), "8080", quote!(const VALUE: Port = 8080;)),
+    ];
+    for (item, value, expected) in cases {
+        let attr = match item.to_string().contains("Port") {
+            true => quote!((ty = "u16", no_doc)),
+            false => quote!((no_doc)),
+        };
+        assert_eq!(format!("{}", expected), expand(attr, value, item));
+    }
+    let result = expand(quote!((no_doc)), "a\\tb", quote!(const VALUE: &core::ffi::CStr = DEFAULT;));
+    assert_eq!(format!("{}", quote!(const VALUE: &core::ffi::CStr = c"a\tb";)), result);
+
+    let mismatches = [
+        (quote!(const N: u32 = 0.5;), "[CE0020] The default of `N` does not match its declared type `u32`: expected an integer that fits in `u32`, found a float"),
+        (quote!(const N: u8 = 300;), "expected an integer that fits in `u8`, found `300`"),
+        (quote!(const N: u16 = -1;), "expected an integer that fits in `u16`, found `-1`"),
+        (quote!(const N: u16 = 5u8;), "expected an integer that fits in `u16`, found an integer of type `u8`"),
+        (quote!(const N: &str = b"x";), "expected a string, found a byte string"),
+        (quote!(const N: [u16; 2] = [1, 2, 3];), "expected an array of 2 elements, found an array of 3 elements"),
+        (quote!(const N: &[bool] = &[true, 1];), "expected a boolean, found an integer at index [1]"),
+        (quote!(const N: [u8; 4] = [0; 3];), "expected an array of 4 elements, found an array of 3 elements"),
+        (quote!(const N: Ratio = 0.5;), "declared type `u32`"),
+    ];
+    for (item, expected) in mismatches {
+        let attr = match item.to_string().contains("Ratio") {
+            true => quote!((ty = "u32")),
+            false => quote!(()),
+        };
+        let result = from_env(attr, item, TestEnv::builder().build()).to_string();
+        assert!(result.contains(expected), "{}", result);
+    }
+
+    let result = expand(quote!(()), "70000", quote!(const VALUE: u16 = 80;));
+    assert!(result.contains("[CE0002] Failed to parse environment variable `VALUE` as an integer: expected an integer that fits in `u16`, found `70000`"), "{}", result);
+    let result = expand(quote!(()), "[1, 2, 3]", quote!(const VALUE: [u8; 2] = [0, 0];));
+    assert!(result.contains("expected an array of 2 elements, found an array of 3 elements"), "{}", result);
+    let result = expand(quote!(()), "[1, 256]", quote!(const VALUE: &[u8] = &[];));
+    assert!(result.contains("found `256` at index [1]"), "{}", result);
+    let result = expand(quote!(()), "a\\0b", quote!(const VALUE: &CStr = DEFAULT;));
+    assert!(result.contains("as a C string: contains a NUL byte at offset 1"), "{}", result);
+    let result = expand(quote!((ty = "Port")), "1", quote!(const VALUE: Port = 1;));
+    assert!(result.contains("[CE0004] Expected `ty` to name a number"), "{}", result);
+}
+
+#[test]
+fn test_emit_runtime_getter() {
+    let env = || TestEnv::builder().set("APP_PORT", "8080").build();
+    let expected: TokenStream = quote! {
+        pub const DEBUG: bool = false;
+        pub fn debug_from_env() -> Option<bool> {
+            match ["DEBUG"].iter().any(|name| ::std::env::var_os(name).is_some()) {
+                true => Some(true),
+                false => None,
+            }
+        }
+    };
+    let result = from_env(quote!((exists, emit_runtime_getter, allow_system_var, no_doc)), quote!(pub const DEBUG: bool = false;), env());
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let result = format!("{}", from_env(quote!((emit_runtime_getter, max = 9000, no_doc)), quote!(const APP_PORT: u16 = 80;), env()));
+    let expected = format!("{}", quote! {
+        const APP_PORT: u16 = 8080;
+        fn app_port_from_env() -> Option<u16> {
+            let value = ["APP_PORT"].iter().find_map(|name| ::std::env::var(name).ok())?;
+        }
+    });
+    assert!(result.starts_with(expected.trim_end_matches('}')), "{}", result);
+    assert!(result.contains(&format!("{}", quote!(let value = <u16>::from_str_radix(&format!("{}{}", sign, digits), radix).ok()?;))), "{}", result);
+    assert!(result.contains(&format!("{}", quote!(if value > 9000 { return None; } Some(value)))), "{}", result);
+
+    for item in [quote!(const NAME: &str = "app";), quote!(const IDS: [u8; 2] = [1, 2];), quote!(const B: u8 = b'x';)] {
+        let result = format!("{}", from_env(quote!((emit_runtime_getter)), item, env()));
+        assert!(result.contains("[CE0005] `emit_runtime_getter`"), "{}", result);
+    }
+    let result = format!("{}", from_env(quote!((emit_runtime_getter, file)), quote!(const PORT: u16 = 80;), env()));
+    assert!(result.contains("`emit_runtime_getter` cannot be combined with `file`"), "{}", result);
+}
+
+/// A source whose lookups of one variable fail.
+struct FailingEnv;
+
+impl ReadEnv for FailingEnv {
+    fn read_env(&self, var_name: &str) -> Result<Option<Cow<'_, OsStr>>, EnvError> {
+        match var_name {
+            "PORT" => Err(EnvError::Lookup { name: var_name.to_string(), message: "connection refused".to_string() }),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[test]
+fn test_lookup_error_is_reported() {
+    let item: TokenStream = quote! {
+        const PORT: u16 = 80;
+    };
+    let result = format!("{}", from_env(quote!((no_doc)), item, FailingEnv));
+    assert!(result.contains("compile_error"), "{}", result);
+    assert!(result.contains("[CE0016] Failed to read environment variable `PORT`: connection refused"), "{}", result);
+
+    let item: TokenStream = quote! {
+        const HOST: &str = "localhost";
+    };
+    let result = from_env(quote!((allow_system_var, no_doc)), item.clone(), FailingEnv);
+    assert_eq!(format!("{}", item), format!("{}", result));
+}
+
+#[test]
+fn test_debug_tracing_does_not_change_expansion() {
+    let item: TokenStream = quote! {
+        const TRACED: u32 = 1;
+    };
+    for value in [None, Some("2")] {
+        let mut plain = TestEnv::builder();
+        let mut traced = TestEnv::builder().set("CONST_ENV_DEBUG", "1");
+        if let Some(value) = value {
+            plain = plain.set("TRACED", value);
+            traced = traced.set("TRACED", value);
+        }
+        let expected = from_env(TokenStream::new(), item.clone(), plain.build());
+        let result = from_env(TokenStream::new(), item.clone(), traced.build());
+        assert_eq!(format!("{}", expected), format!("{}", result));
+    }
+}
+
+#[test]
+fn test_list_records_every_item() {
+    let list_file = write_temp_file("inventory.txt", "");
+    let env = || TestEnv::builder()
+        .set("CONST_ENV_LIST", "1")
+        .set("CONST_ENV_LIST_FILE", list_file.to_str().unwrap())
+        .set("CARGO_CRATE_NAME", "app")
+        .set("LISTED_SET", "2");
+    let set_item: TokenStream = quote! {
+        const LISTED_SET: u32 = 1;
+    };
+    let unset_item: TokenStream = quote! {
+        static LISTED_UNSET: &str = "default";
+    };
+    let attr: TokenStream = quote! {
+        ("LISTED_NAME", debug_var = "LISTED_NAME_DEBUG")
+    };
+    from_env(TokenStream::new(), set_item, env().build());
+    from_env(attr, unset_item, env().build());
+
+    let contents = std::fs::read_to_string(&list_file).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines, [
+        "item=app::LISTED_SET vars=LISTED_SET type=u32 set=true",
+        "item=app::LISTED_UNSET vars=LISTED_NAME_DEBUG,LISTED_NAME type=& str set=false",
+    ]);
+}
+
+#[test]
+fn test_system_var_warning() {
+    let env = TestEnv::builder()
+        .set("PATH", "/usr/bin")
+        .build();
+    let result = from_env(quote!((no_doc)), quote!(const PATH: &str = "/data";), env);
+    let warning = "`PATH` reads `PATH`, a well-known system variable, and may pick up an unrelated value";
+    assert!(format!("{}", result).contains(warning), "{}", result);
+
+    for attr in [quote! { (allow_system_var, no_doc) }, quote! { (allow_warnings, no_doc) }] {
+        let env = TestEnv::builder()
+            .set("PATH", "/usr/bin")
+            .build();
+        let item: TokenStream = quote! {
+            const PATH: &str = "/data";
+        };
+        let expected: TokenStream = quote! {
+            const PATH: &str = "/usr/bin";
+        };
+        let result = from_env(attr, item, env);
+        assert_eq!(format!("{}", expected), format!("{}", result));
+    }
+}
+
+#[test]
+fn test_strict_mode_requires_every_item() {
+    let item: TokenStream = quote! {
+        const STRICT_PORT: u16 = 80;
+    };
+    let env = TestEnv::builder()
+        .set("CONST_ENV_STRICT", "1")
+        .build();
+    let result = format!("{}", from_env(quote!((no_doc)), item.clone(), env));
+    assert!(result.contains("Environment variable `STRICT_PORT` is required but not set"), "{}", result);
+
+    let env = TestEnv::builder()
+        .set("CONST_ENV_STRICT", "1")
+        .set("STRICT_PORT", "8080")
+        .build();
+    let expected: TokenStream = quote! {
+        const STRICT_PORT: u16 = 8080;
+    };
+    let result = from_env(quote!((no_doc)), item.clone(), env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let env = TestEnv::builder()
+        .set("CONST_ENV_STRICT", "0")
+        .build();
+    let result = from_env(quote!((no_doc)), item, env);
+    assert!(!format!("{}", result).contains("compile_error"), "{}", result);
+}
+
+#[test]
+fn test_optional_exempt_from_strict_mode() {
+    let attr: TokenStream = quote! {
+        (optional, no_doc)
+    };
+    let item: TokenStream = quote! {
+        const STRICT_PORT: u16 = 80;
+    };
+    let env = TestEnv::builder()
+        .set("CONST_ENV_STRICT", "1")
+        .build();
+    let result = from_env(attr, item.clone(), env);
+    assert_eq!(format!("{}", item), format!("{}", result));
+
+    let attr: TokenStream = quote! {
+        (required, optional, no_doc)
+    };
+    let result = format!("{}", from_env(attr, item, TestEnv::builder().build()));
+    assert!(result.contains("`optional` cannot be combined with `required`"), "{}", result);
+}
+
+#[test]
+fn test_required_suggests_similar_variable() {
+    let attr: TokenStream = quote! {
+        (required)
+    };
+    let item: TokenStream = quote! {
+        const APP_PORT: u16 = 8080;
+    };
+    let env = TestEnv::builder()
+        .set("APP_PRT", "9090")
+        .set("APP_HOST", "localhost")
+        .set("PATH", "/usr/bin")
+        .set("APPLE", "red")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("a similarly named variable `APP_PRT` is set; did you mean `APP_PORT`?"), "{}", result);
+}
+
+#[test]
+fn test_suggestion_prefers_closest() {
+    let attr: TokenStream = quote! {
+        ("DATABASE_URL", required)
+    };
+    let item: TokenStream = quote! {
+        const URL: &str = "";
+    };
+    let env = TestEnv::builder()
+        .set("DATABASE_URLS", "a")
+        .set("DATABSE_URL", "b")
+        .set("DATA", "c")
+        .build();
+    let result = format!("{}", from_env(attr.clone(), item.clone(), env));
+    assert!(result.contains("`DATABASE_URLS` is set"), "{}", result);
+
+    // Names that only share a prefix are other variables, not typos.
+    let env = TestEnv::builder()
+        .set("DATABASE_URL_PRIMARY", "a")
+        .set("DATABASE_USER", "b")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(!result.contains("did you mean"), "{}", result);
+}
+
+#[test]
+fn test_no_suggestion_of_own_names() {
+    let attr: TokenStream = quote! {
+        ("BUF", debug_var = "BUF_D", release_var = "BUF_R", no_doc)
+    };
+    let item: TokenStream = quote! {
+        const BUF: usize = 1;
+    };
+    let env = TestEnv::builder()
+        .set("BUF_D", "2")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(!result.contains("did you mean"), "{}", result);
+    assert!(result.contains("debug_assertions"), "{}", result);
+}
+
+#[test]
+fn test_no_suggestion_without_similar_variable() {
+    let attr: TokenStream = quote! {
+        (required)
+    };
+    let item: TokenStream = quote! {
+        const APP_PORT: u16 = 8080;
+    };
+    let env = TestEnv::builder()
+        .set("HOME", "/root")
+        .set("PORT_APP", "1")
+        .build();
+    let result = format!("{}", from_env(attr, item, env));
+    assert!(result.contains("is required but not set"), "{}", result);
+    assert!(!result.contains("did you mean"), "{}", result);
+}
+
+#[test]
+fn test_duplicate_variable_claims() {
+    let out_dir = std::env::temp_dir().join(format!("const_env_impl_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let env = || TestEnv::builder()
+        .set("OUT_DIR", out_dir.to_str().unwrap())
+        .set("CARGO_CRATE_NAME", "duplicate_claims")
+        .set("CONST_ENV_DUPLICATES", "error");
+    let first: TokenStream = quote! {
+        const RETRIES: u32 = 3;
+    };
+    let second: TokenStream = quote! {
+        const MAX_RETRIES: u32 = 5;
+    };
+    let attr: TokenStream = quote! {
+        ("RETRIES", no_doc)
+    };
+    let result = from_env(quote!((no_doc)), first.clone(), env().build());
+    assert_eq!(format!("{}", first), format!("{}", result));
+    // Expanding the same item again is not a conflict.
+    let result = from_env(quote!((no_doc)), first, env().build());
+    assert!(!format!("{}", result).contains("compile_error"), "{}", result);
+
+    let result = format!("{}", from_env(attr.clone(), second.clone(), env().build()));
+    assert!(result.contains("Environment variable `RETRIES` read by `MAX_RETRIES` is also read by `RETRIES`"), "{}", result);
+
+    // Claims of items whose source file has changed since, or is gone, are stale.
+    let claims = out_dir.join("const_env_claims_duplicate_claims.tsv");
+    let source = out_dir.join("source.rs");
+    std::fs::write(&source, "").unwrap();
+    std::fs::write(&claims, format!("gone.rs\t1\tOTHER\tRETRIES\t1\n{}\t1\tOTHER\tRETRIES\t1\n", source.display())).unwrap();
+    let result = from_env(attr.clone(), second.clone(), env().build());
+    assert!(!format!("{}", result).contains("compile_error"), "{}", result);
+
+    // Items of the same name in different places are different items. Outside of a
+    // macro, tokens are at line 0 of `<unknown>`.
+    std::fs::write(&claims, "<unknown>\t7\tMAX_RETRIES\tRETRIES\t-\n").unwrap();
+    let result = format!("{}", from_env(attr, second.clone(), env().build()));
+    assert!(result.contains("read by `MAX_RETRIES` is also read by `MAX_RETRIES` at <unknown>:7"), "{}", result);
+
+    let result = from_env(quote!((no_doc)), second, env().set("CONST_ENV_DUPLICATES", "off").build());
+    assert!(!format!("{}", result).contains("compile_error"), "{}", result);
+}
@@ -0,0 +1,147 @@
+#![cfg(feature = "cli")]
+
+use std::path::Path;
+
+use const_env_impl::scan_source;
+
+const SOURCE: &str = r#"
+use const_env::{env_item, from_env, ConstEnv, FromEnvDefaults};
+
+#[env_item]
+const PORT: u16 = 8080;
+
+#[const_env::env_item("APP_HOST", per_target)]
+pub static HOST: &str = "localhost";
+
+#[env_item(debug_var = "LOG_DEBUG", release_var = "LOG_RELEASE")]
+const LOG: &str = "info";
+
+#[env_item(format = "{HOST}:{PORT}")]
+const ADDR: &str = "";
+
+#[env_item(sensitive)]
+const TOKEN: &str = "dev";
+
+#[env_item(rename_all = "verbatim")]
+const workers: u32 = 4;
+
+mod nested {
+    fn limits() {
+        #[env_item]
+        const BURST: u32 = 8;
+    }
+}
+
+struct Pool;
+
+impl Pool {
+    #[env_item("POOL_SIZE")]
+    const SIZE: usize = 16;
+}
+
+#[derive(Debug, FromEnvDefaults)]
+#[from_env(prefix = "DB_")]
+struct Database {
+    #[from_env(default = 5432)]
+    port: u16,
+    #[from_env("DATABASE_URL", default = "")]
+    url: String,
+    pool: u32,
+}
+
+from_env! {
+    const BLOCK_A: u32 = 1;
+    #[from_env("BLOCK_CUSTOM")]
+    const BLOCK_B: bool = false;
+}
+"#;
+
+#[test]
+fn test_scan_items() {
+    let scan = scan_source(Path::new("src/config.rs"), SOURCE);
+    assert!(scan.unresolved.is_empty());
+    let found: Vec<(usize, &str, &str, Vec<&str>, &str)> = scan.items.iter()
+        .map(|item| (item.line, &*item.item, &*item.ty, item.vars.iter().map(String::as_str).collect(), &*item.default))
+        .collect();
+    assert_eq!(vec![
+        (5, "PORT", "u16", vec!["PORT"], "8080"),
+        (8, "HOST", "&str", vec!["APP_HOST_<target>", "APP_HOST"], "\"localhost\""),
+        (11, "LOG", "&str", vec!["LOG_DEBUG", "LOG_RELEASE", "LOG"], "\"info\""),
+        (14, "ADDR", "&str", vec!["HOST", "PORT"], "\"\""),
+        (17, "TOKEN", "&str", vec!["TOKEN"], "<redacted>"),
+        (20, "workers", "u32", vec!["workers"], "4"),
+        (25, "BURST", "u32", vec!["BURST"], "8"),
+        (33, "Pool::SIZE", "usize", vec!["POOL_SIZE"], "16"),
+        (40, "Database::port", "u16", vec!["DB_PORT"], "5432"),
+        (42, "Database::url", "String", vec!["DATABASE_URL"], "\"\""),
+        (47, "BLOCK_A", "u32", vec!["BLOCK_A"], "1"),
+        (49, "BLOCK_B", "bool", vec!["BLOCK_CUSTOM"], "false"),
+    ], found);
+
+    let scan = scan_source(Path::new("src/lib.rs"), "#[env_item(prefix_map = \"FEATURE_\")]\nstatic FEATURES: &[(&str, &str)] = &[];");
+    assert_eq!(vec!["FEATURE_*"], scan.items[0].vars);
+
+    let scan = scan_source(Path::new("src/lib.rs"), "#[env_item(split_into(HOST = 0, PORT = 1), split_sep = \":\")]\nmod endpoint {\n    const HOST: &str = \"\";\n    const PORT: u16 = 0;\n}");
+    let found: Vec<(&str, Vec<&str>)> = scan.items.iter().map(|item| (&*item.item, item.vars.iter().map(String::as_str).collect())).collect();
+    assert_eq!(vec![("endpoint::HOST", vec!["ENDPOINT"]), ("endpoint::PORT", vec!["ENDPOINT"])], found);
+}
+
+#[test]
+fn test_scan_output() {
+    let scan = scan_source(Path::new("src/lib.rs"), "#[env_item(\"APP_PORT\")]\nconst PORT: u16 = 8080;\n\n#[env_item]\nstatic NAME: &str = \"app\";\n");
+    assert_eq!(concat!(
+        "FILE          ITEM  TYPE  VARIABLE  DEFAULT\n",
+        "src/lib.rs:2  PORT  u16   APP_PORT  8080\n",
+        "src/lib.rs:5  NAME  &str  NAME      \"app\"\n",
+    ), scan.to_table());
+    let json: serde_json::Value = serde_json::from_str(&scan.to_json()).unwrap();
+    assert_eq!(serde_json::json!({
+        "items": [
+            {"file": "src/lib.rs", "line": 2, "item": "PORT", "type": "u16", "vars": ["APP_PORT"], "default": "8080"},
+            {"file": "src/lib.rs", "line": 5, "item": "NAME", "type": "&str", "vars": ["NAME"], "default": "\"app\""},
+        ],
+        "unresolved": [],
+    }), json);
+}
+
+#[test]
+fn test_scan_unresolved() {
+    let source = r#"
+#[env_item(case = "title")]
+const NAME: &str = "";
+
+#[env_item]
+fn port() -> u16 { 0 }
+
+#[cfg_attr(feature = "env", env_item)]
+const LEVEL: u8 = 1;
+
+#[env_item(format = "{HOST")]
+const ADDR: &str = "";
+
+#[derive(ConstEnv)]
+struct Limits {
+    #[from_env(default = 4)]
+    burst: u32,
+    rate: u32,
+    #[from_env("LIMITS_BURST")]
+    window: u32,
+}
+"#;
+    let scan = scan_source(Path::new("src/lib.rs"), source);
+    let unresolved: Vec<(usize, &str)> = scan.unresolved.iter().map(|unresolved| (unresolved.line, &*unresolved.message)).collect();
+    assert_eq!(vec![
+        (2, "Invalid arguments of `NAME`: Expected `case` to be \"lower\" or \"upper\""),
+        (5, "Only `const` and `static` items can be configured"),
+        (8, "`env_item` under `cfg_attr` depends on the build configuration"),
+        (12, "Cannot name the variables of `ADDR`: it reads none"),
+        (18, "`Limits::rate` has no `from_env` attribute"),
+        (19, "`Limits::window` has no `default = ...`"),
+    ], unresolved);
+    let names: Vec<&str> = scan.items.iter().map(|item| &*item.item).collect();
+    assert_eq!(vec!["Limits::burst"], names);
+
+    let scan = scan_source(Path::new("src/lib.rs"), "const PORT: u16 = ;");
+    assert_eq!(1, scan.unresolved.len());
+    assert!(scan.unresolved[0].message.starts_with("Failed to parse the file: "), "{}", scan.unresolved[0].message);
+}
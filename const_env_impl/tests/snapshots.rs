@@ -0,0 +1,72 @@
+use const_env_impl::{expand_to_string, FromEnvError, TestEnv};
+
+/// Expands `item` with `attr` while the variables in `vars` are set.
+fn expand(attr: &str, item: &str, vars: &[(&str, &str)]) -> String {
+    let env = TestEnv::builder().extend(vars.iter().copied()).build();
+    expand_to_string(attr, item, &env).unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[test]
+fn test_literal_kinds() {
+    let cases = [
+        ("const NAME: &str = \"app\";", "NAME", "hello world", "const NAME: &str = \"hello world\";"),
+        ("static NAME: &'static str = \"app\";", "NAME", "x", "static NAME: &'static str = \"x\";"),
+        ("const KEY: &[u8] = b\"\";", "KEY", "abc", "const KEY: &[u8] = b\"abc\";"),
+        ("const SEP: char = ',';", "SEP", ";", "const SEP: char = ';';"),
+        ("const SEP: u8 = b',';", "SEP", ";", "const SEP: u8 = b';';"),
+        ("const ON: bool = false;", "ON", "true", "const ON: bool = true;"),
+        ("const PORT: u16 = 80;", "PORT", "8080", "const PORT: u16 = 8080;"),
+        ("const PORT: u16 = 80u16;", "PORT", "8080u16", "const PORT: u16 = 8080u16;"),
+        ("const RATIO: f64 = 0.5;", "RATIO", "1.25", "const RATIO: f64 = 1.25;"),
+        ("const OFFSET: i32 = -1;", "OFFSET", "-42", "const OFFSET: i32 = -42;"),
+        ("const OFFSET: i32 = 1;", "OFFSET", "-42", "const OFFSET: i32 = -42;"),
+        ("const PORTS: [u16; 2] = [80, 443];", "PORTS", "[8080, 8443]", "const PORTS: [u16; 2] = [8080, 8443];"),
+        ("static HOSTS: &[&str] = &[];", "HOSTS", "[\"a\", \"b\"]", "static HOSTS: &[&str] = &[\"a\", \"b\"];"),
+        ("pub(crate) const LIMITS: [i8; 2] = [-1, 1];", "LIMITS", "[-2, 2]", "pub(crate) const LIMITS: [i8; 2] = [-2, 2];"),
+    ];
+    for (item, var, value, expected) in cases {
+        assert_eq!(expand("no_doc", item, &[(var, value)]), expected, "{}", item);
+    }
+}
+
+#[test]
+fn test_unset_items_are_unchanged() {
+    assert_eq!(expand("no_doc", "#[doc = \"The port.\"] pub static PORT: Option<u16> = None;", &[]),
+        "#[doc = \"The port.\"] pub static PORT: Option<u16> = None;");
+}
+
+#[test]
+fn test_generated_docs() {
+    assert_eq!(expand("", "#[doc = \" The port.\"] pub static PORT: Option<u16> = None;", &[]),
+        "#[doc = \" The port.\"] #[doc = \"\"] #[doc = \" *Configurable at build time via the `PORT` environment variable.*\"] \
+         pub static PORT: Option<u16> = None;");
+    assert_eq!(expand("per_target, doc_override", "const PORT: u16 = 80;", &[("TARGET", "wasm32"), ("PORT", "8080")]),
+        "#[doc = \" *Configurable at build time via the `PORT_wasm32` or `PORT` environment variables.*\"] #[doc = \"\"] \
+         #[doc = \" *This build overrides the default.*\"] const PORT: u16 = 8080;");
+    assert_eq!(expand("format = \"{HOST}:{PORT}\", doc_override", "const ADDR: &str = \"\";", &[]),
+        "#[doc = \" *Configurable at build time via the `HOST` or `PORT` environment variables.*\"] #[doc = \"\"] \
+         #[doc = \" *This build uses the default.*\"] const ADDR: &str = \"\";");
+    let err = expand_to_string("no_doc, doc_override", "const PORT: u16 = 80;", &TestEnv::builder().build()).unwrap_err();
+    assert!(err.to_string().contains("`doc_override` cannot be combined with `no_doc`"), "{}", err);
+}
+
+#[test]
+fn test_arguments() {
+    assert_eq!(expand("\"APP_PORT\", no_doc", "const PORT: u16 = 80;", &[("APP_PORT", "1")]), "const PORT: u16 = 1;");
+    assert_eq!(expand("expr, no_doc", "const TIMEOUT: u64 = 1;", &[("TIMEOUT", "60 * 1000")]), "const TIMEOUT: u64 = 60 * 1000;");
+    assert_eq!(expand("expr, no_doc", "const TEXT: &str = \"\";", &[("TEXT", "::core::concat!(\"a\", \"b\")")]),
+        "const TEXT: &str = ::core::concat!(\"a\", \"b\");");
+    assert_eq!(expand("debug_var = \"DEBUG_PORT\", no_doc", "const PORT: u16 = 80;", &[("DEBUG_PORT", "1")]),
+        "const PORT: u16 = if ::core::cfg!(debug_assertions) { 1 } else { 80 };");
+}
+
+#[test]
+fn test_errors() {
+    let env = TestEnv::builder().set("PORT", "eighty").build();
+    let err = expand_to_string("", "const PORT: u16 = 80;", &env).unwrap_err();
+    assert!(matches!(err, FromEnvError::ParseFailure { .. }), "{:?}", err);
+    let err = expand_to_string("(", "const PORT: u16 = 80;", &env).unwrap_err();
+    assert!(matches!(err, FromEnvError::BadAttribute(_)), "{:?}", err);
+    let err = expand_to_string("", "const PORT: u16 = \"80;", &env).unwrap_err();
+    assert_eq!(err.code(), "CE0003");
+}
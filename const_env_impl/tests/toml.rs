@@ -0,0 +1,83 @@
+use const_env_impl::{from_env, ReadEnv, TomlEnv, TomlError};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+fn value(env: &TomlEnv, name: &str) -> Option<String> {
+    env.read_env_utf8(name).unwrap()
+}
+
+#[test]
+fn test_flattens_tables() {
+    let env: TomlEnv = "\
+name = \"app\"
+
+[server]
+port = 8080
+ratio = 0.5
+verbose = true
+tls.enabled = false
+
+[server.limits]
+hosts = [\"a\", 'b']
+sizes = [1, 2, 3,]
+"
+    .parse()
+    .unwrap();
+    assert_eq!(value(&env, "NAME").as_deref(), Some("app"));
+    assert_eq!(value(&env, "SERVER_PORT").as_deref(), Some("8080"));
+    assert_eq!(value(&env, "SERVER_RATIO").as_deref(), Some("0.5"));
+    assert_eq!(value(&env, "SERVER_VERBOSE").as_deref(), Some("true"));
+    assert_eq!(value(&env, "SERVER_TLS_ENABLED").as_deref(), Some("false"));
+    assert_eq!(value(&env, "SERVER_LIMITS_HOSTS").as_deref(), Some("[\"a\", \"b\"]"));
+    assert_eq!(value(&env, "SERVER_LIMITS_SIZES").as_deref(), Some("[1, 2, 3]"));
+    assert_eq!(value(&env, "server_port"), None);
+}
+
+#[test]
+fn test_separator_and_case() {
+    let env = TomlEnv::builder()
+        .separator("__")
+        .preserve_case()
+        .parse("[server]\nport = 8080\n")
+        .unwrap();
+    assert_eq!(value(&env, "server__port").as_deref(), Some("8080"));
+    assert_eq!(env.var_names(), vec!["server__port".to_string()]);
+}
+
+#[test]
+fn test_parse_errors() {
+    for (source, expected) in [
+        ("[server]\nport = {}\n", "line 2, key `port`: inline tables are not supported"),
+        ("[[servers]]\n", "line 1: arrays of tables are not supported"),
+        ("hosts = [\"a\",\n\"b\"]\n", "line 1, key `hosts`: unterminated array, arrays must be written on a single line"),
+        ("a = 1\na = 2\n", "line 2: key `a` is defined more than once"),
+        ("a_b = 1\n[a]\nb = 2\n", "line 3, key `a.b`: becomes `A_B`, which is also the name of key `a_b`"),
+    ] {
+        match source.parse::<TomlEnv>() {
+            Err(err @ TomlError::Parse { .. }) => assert_eq!(err.to_string(), expected, "{:?}", source),
+            other => panic!("{:?}: {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn test_from_path() {
+    let dir = std::env::temp_dir().join(format!("const_env_impl_toml_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("deploy.toml");
+    std::fs::write(&path, "[server]\nports = [80, 443]\n").unwrap();
+    let env = TomlEnv::from_path(&path).unwrap();
+
+    let item: TokenStream = quote! {
+        const SERVER_PORTS: [u16; 2] = [8080, 8443];
+    };
+    let expected: TokenStream = quote! {
+        const SERVER_PORTS: [u16; 2] = [80, 443];
+    };
+    let result = from_env(quote!((no_doc)), item, env);
+    assert_eq!(format!("{}", expected), format!("{}", result));
+
+    let err = TomlEnv::from_path(dir.join("missing.toml")).unwrap_err();
+    assert!(matches!(err, TomlError::Io { .. }), "{:?}", err);
+}
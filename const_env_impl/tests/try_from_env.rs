@@ -0,0 +1,128 @@
+use const_env_impl::{from_env, try_from_env, try_from_env_with_substitutions, FromEnvError, Substitution, TestEnv};
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+
+/// Extracts the message of every `compile_error!` invocation in `output`.
+fn compile_errors(output: TokenStream) -> Vec<String> {
+    let mut messages = Vec::new();
+    for token in output {
+        if let TokenTree::Group(group) = token {
+            match syn::parse2::<syn::LitStr>(group.stream()) {
+                Ok(message) => messages.push(message.value()),
+                Err(_) => messages.extend(compile_errors(group.stream())),
+            }
+        }
+    }
+    messages
+}
+
+/// Expands the attribute with both entry points, checking that they agree, and returns
+/// the error.
+fn expand_err(attr: TokenStream, item: TokenStream, vars: &[(&str, &str)]) -> FromEnvError {
+    let env = || TestEnv::builder().extend(vars.iter().copied()).build();
+    let err = try_from_env(attr.clone(), item.clone(), env()).unwrap_err();
+    assert_eq!(compile_errors(from_env(attr, item, env())).join("\n"), err.to_string());
+    assert_eq!(compile_errors(err.to_compile_error()).join("\n"), err.to_string());
+    err
+}
+
+#[test]
+fn test_success() {
+    let env = TestEnv::builder().set("PORT", "8080").build();
+    let result = try_from_env(quote!((no_doc)), quote!(const PORT: u16 = 80;), env).unwrap();
+    assert_eq!(quote!(const PORT: u16 = 8080;).to_string(), result.to_string());
+
+    let result = try_from_env(quote!((no_doc)), quote!(const PORT: u16 = 80;), TestEnv::builder().build()).unwrap();
+    assert_eq!(quote!(const PORT: u16 = 80;).to_string(), result.to_string());
+}
+
+#[test]
+fn test_unsupported_item() {
+    let err = expand_err(quote!(), quote!(fn port() -> u16 { 80 }), &[]);
+    assert!(matches!(err, FromEnvError::UnsupportedItem { .. }), "{:?}", err);
+    assert_eq!(err.code(), "CE0003");
+    assert_eq!(err.to_string(), "[CE0003] `env_item` can only be applied to `const` and `static` items");
+}
+
+#[test]
+fn test_malformed_item_reports_the_parse_error() {
+    let err = expand_err(quote!(), quote!(const PORT: u16 = ;), &[]);
+    assert!(matches!(err, FromEnvError::Other(_)), "{:?}", err);
+    assert_eq!(err.code(), "CE0003");
+    assert!(err.to_string().starts_with("[CE0003] expected"), "{}", err);
+}
+
+#[test]
+fn test_bad_attribute() {
+    let err = expand_err(quote!((requried)), quote!(const PORT: u16 = 80;), &[]);
+    assert!(matches!(err, FromEnvError::BadAttribute(_)), "{:?}", err);
+    assert_eq!(err.code(), "CE0004");
+    assert!(err.to_string().starts_with("[CE0004] "), "{}", err);
+}
+
+#[test]
+fn test_missing_variable() {
+    let err = expand_err(quote!((required)), quote!(const PORT: u16 = 80;), &[("PORTS", "1")]);
+    match &err {
+        FromEnvError::MissingVariable { names, suggestion, .. } => {
+            assert_eq!(names, &["PORT"]);
+            assert!(suggestion.as_deref().unwrap().contains("`PORTS`"), "{:?}", suggestion);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(err.code(), "CE0001");
+    assert!(err.to_string().starts_with("[CE0001] Environment variable `PORT` is required but not set; "), "{}", err);
+}
+
+#[test]
+fn test_parse_failure() {
+    let err = expand_err(quote!(), quote!(const PORT: u16 = 80;), &[("PORT", "eighty")]);
+    match &err {
+        FromEnvError::ParseFailure { var, value, kind, .. } => {
+            assert_eq!(var, "PORT");
+            assert_eq!(value, "\"eighty\"");
+            assert_eq!(*kind, "an integer");
+        }
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(err.code(), "CE0002");
+
+    let err = expand_err(quote!((sensitive)), quote!(const TOKEN: u16 = 0;), &[("TOKEN", "secret")]);
+    match &err {
+        FromEnvError::ParseFailure { value, .. } => assert!(!value.contains("secret"), "{}", value),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_other() {
+    let err = expand_err(quote!((max = 100)), quote!(const PORT: u16 = 80;), &[("PORT", "8080")]);
+    assert!(matches!(err, FromEnvError::Other(_)), "{:?}", err);
+    assert_eq!(err.code(), "CE0006");
+}
+
+#[test]
+fn test_substitutions() {
+    let env = TestEnv::builder().set("APP_PORTS", "[8080]").build();
+    let item = quote!(static PORTS: &[u16] = &[80, 443];);
+    let (tokens, substitutions) = try_from_env_with_substitutions(quote!(("APP_PORTS", no_doc)), item.clone(), env).unwrap();
+    assert_eq!(quote!(static PORTS: &[u16] = &[8080];).to_string(), tokens.to_string());
+    assert_eq!(substitutions.len(), 1);
+    let Substitution { item: name, var_names, provided, original, replacement, .. } = &substitutions[0];
+    assert_eq!(name, "PORTS");
+    assert_eq!(var_names, &["APP_PORTS"]);
+    assert!(provided);
+    assert_eq!(original, "&[80, 443]");
+    assert_eq!(replacement.as_deref(), Some("&[8080]"));
+
+    let (tokens, substitutions) = try_from_env_with_substitutions(quote!((per_target, no_doc)), item.clone(), TestEnv::builder().set("TARGET", "wasm32").build()).unwrap();
+    assert_eq!(item.to_string(), tokens.to_string());
+    assert_eq!(substitutions[0].var_names, ["PORTS_wasm32", "PORTS"]);
+    assert!(!substitutions[0].provided);
+    assert_eq!(substitutions[0].replacement, None);
+
+    let env = TestEnv::builder().set("TOKEN", "secret").build();
+    let (_, substitutions) = try_from_env_with_substitutions(quote!((sensitive)), quote!(const TOKEN: &str = "";), env).unwrap();
+    assert_eq!(substitutions[0].replacement.as_deref(), Some("<redacted>"));
+}